@@ -0,0 +1,65 @@
+use std::io::{BufRead, ErrorKind, Write};
+use std::process::ExitCode;
+use chess_logic::logic::{Board, GameStatus, PieceColor};
+
+fn main() -> ExitCode {
+    match play(&mut std::io::stdout().lock()) {
+        Ok(()) => ExitCode::SUCCESS,
+        // Whoever reads our output has gone away, so there is nobody left to tell
+        Err(error) if error.kind() == ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("chess-cli: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn play(out: &mut impl Write) -> std::io::Result<()> {
+    let mut board = Board::new();
+    let mut movetext = String::new();
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    writeln!(out, "{}", board)?;
+    let status = loop {
+        let status = board.status();
+        if status != GameStatus::Ongoing { break status };
+
+        write!(out, "{} to move> ", board.on_move)?;
+        out.flush()?;
+        let Some(Ok(line)) = lines.next() else { break GameStatus::Ongoing };
+        if line.trim().is_empty() { continue };
+
+        let Some(candidate) = board.parse_move(&line) else {
+            writeln!(out, "could not parse move '{}'", line.trim())?;
+            continue;
+        };
+        let san = board.san(candidate);
+        let mover = board.on_move;
+        match board.try_move(candidate) {
+            Ok(_) => {
+                if mover == PieceColor::WHITE {
+                    movetext.push_str(&format!("{}. ", board.turn_number / 2 + 1));
+                }
+                movetext.push_str(&san);
+                movetext.push(' ');
+                writeln!(out, "{}", board)?;
+                if !board.checking_squares(board.on_move).is_empty() { writeln!(out, "check")? };
+            }
+            Err(error) => writeln!(out, "illegal move: {}", error)?
+        }
+    };
+
+    let result = status.result();
+    match status {
+        GameStatus::Checkmate { winner } => writeln!(out, "checkmate, {} wins", winner),
+        GameStatus::Resigned { winner } => writeln!(out, "{} resigns, {} wins", winner.opposite(), winner),
+        GameStatus::Stalemate => writeln!(out, "stalemate"),
+        GameStatus::TimeForfeit { winner } => writeln!(out, "{} ran out of time, {} wins", winner.opposite(), winner),
+        GameStatus::DrawAgreed => writeln!(out, "draw agreed"),
+        GameStatus::Draw { reason } => writeln!(out, "draw by {}", reason),
+        GameStatus::Ongoing => writeln!(out, "game abandoned")
+    }?;
+    writeln!(out, "{}{}", movetext, result)?;
+    out.flush()
+}
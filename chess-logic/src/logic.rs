@@ -0,0 +1,999 @@
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::Display;
+use std::hash::{Hash, Hasher};
+use std::iter::Iterator;
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum PieceKind {
+    PAWN,
+    ROOK,
+    KNIGHT,
+    BISHOP,
+    KING,
+    QUEEN
+}
+
+impl Display for PieceKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            PieceKind::PAWN => "pawn",
+            PieceKind::ROOK => "rook",
+            PieceKind::KNIGHT => "knight",
+            PieceKind::BISHOP => "bishop",
+            PieceKind::KING => "king",
+            PieceKind::QUEEN => "queen"
+        })
+    }
+}
+
+impl PieceKind {
+    pub const PROMOTION_TARGETS: [PieceKind; 4] = [PieceKind::QUEEN, PieceKind::ROOK, PieceKind::BISHOP, PieceKind::KNIGHT];
+
+    pub fn material_value(&self) -> i32 {
+        match self {
+            PieceKind::PAWN => 1,
+            PieceKind::KNIGHT | PieceKind::BISHOP => 3,
+            PieceKind::ROOK => 5,
+            PieceKind::QUEEN => 9,
+            PieceKind::KING => 0
+        }
+    }
+
+    pub fn to_fen_char(&self, color: PieceColor) -> char {
+        let character = match self {
+            PieceKind::PAWN => 'p',
+            PieceKind::ROOK => 'r',
+            PieceKind::KNIGHT => 'n',
+            PieceKind::BISHOP => 'b',
+            PieceKind::KING => 'k',
+            PieceKind::QUEEN => 'q'
+        };
+        if color == PieceColor::WHITE { character.to_ascii_uppercase() } else { character }
+    }
+
+    pub fn from_fen_char(character: char) -> Option<(PieceColor, PieceKind)> {
+        let kind = match character.to_ascii_lowercase() {
+            'p' => PieceKind::PAWN,
+            'r' => PieceKind::ROOK,
+            'n' => PieceKind::KNIGHT,
+            'b' => PieceKind::BISHOP,
+            'k' => PieceKind::KING,
+            'q' => PieceKind::QUEEN,
+            _ => return None
+        };
+        let color = if character.is_ascii_uppercase() { PieceColor::WHITE } else { PieceColor::BLACK };
+        Some((color, kind))
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum PieceColor {
+    WHITE,
+    BLACK
+}
+
+impl Display for PieceColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", match self {
+            PieceColor::WHITE => "white",
+            PieceColor::BLACK => "black"
+        })
+    }
+}
+
+impl PieceColor {
+    pub fn opposite(&self) -> PieceColor {
+        match self {
+            PieceColor::WHITE => PieceColor::BLACK,
+            PieceColor::BLACK => PieceColor::WHITE
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum DrawReason {
+    ThreefoldRepetition,
+    FivefoldRepetition,
+    FiftyMoveRule,
+    SeventyFiveMoveRule
+}
+
+impl Display for DrawReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DrawReason::ThreefoldRepetition => write!(f, "threefold repetition"),
+            DrawReason::FivefoldRepetition => write!(f, "fivefold repetition"),
+            DrawReason::FiftyMoveRule => write!(f, "fifty-move rule"),
+            DrawReason::SeventyFiveMoveRule => write!(f, "seventy-five-move rule")
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum GameStatus {
+    Ongoing,
+    Checkmate { winner: PieceColor },
+    Stalemate,
+    Resigned { winner: PieceColor },
+    TimeForfeit { winner: PieceColor },
+    DrawAgreed,
+    Draw { reason: DrawReason }
+}
+
+impl GameStatus {
+    pub fn result(&self) -> &'static str {
+        match self {
+            GameStatus::Checkmate { winner } | GameStatus::Resigned { winner } | GameStatus::TimeForfeit { winner } => match winner {
+                PieceColor::WHITE => "1-0",
+                PieceColor::BLACK => "0-1"
+            },
+            GameStatus::Stalemate | GameStatus::DrawAgreed | GameStatus::Draw { .. } => "1/2-1/2",
+            GameStatus::Ongoing => "*"
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct PositionStatus {
+    pub status: GameStatus,
+    pub check: bool
+}
+
+impl PositionStatus {
+    pub fn suffix(&self) -> &'static str {
+        match self.status {
+            GameStatus::Checkmate { .. } => "#",
+            _ if self.check => "+",
+            _ => ""
+        }
+    }
+}
+
+#[derive(Copy, Clone, Hash, PartialEq, Eq, Debug)]
+pub struct Coordinate(pub i8, pub i8);
+
+impl Coordinate {
+    pub fn file(&self) -> i8 {
+        self.0
+    }
+
+    pub fn rank(&self) -> i8 {
+        self.1
+    }
+
+    pub fn is_on_board(&self) -> bool {
+        (0..=7).contains(&self.0) && (0..=7).contains(&self.1)
+    }
+
+    pub fn offset(&self, dx: i8, dy: i8) -> Option<Coordinate> {
+        let moved = Coordinate(self.0 + dx, self.1 + dy);
+        if moved.is_on_board() { Some(moved) } else { None }
+    }
+
+    pub fn all() -> impl Iterator<Item=Coordinate> {
+        (0..8i8).flat_map(|rank| (0..8i8).map(move |file| Coordinate(file, rank)))
+    }
+
+    pub fn from_algebraic(text: &str) -> Option<Coordinate> {
+        let mut chars = text.chars();
+        let (Some(file), Some(rank), None) = (chars.next(), chars.next(), chars.next()) else { return None };
+        if !('a'..='h').contains(&file) || !('1'..='8').contains(&rank) { return None };
+        Some(Coordinate(file as i8 - 'a' as i8, rank as i8 - '1' as i8))
+    }
+
+    pub fn to_algebraic(&self) -> String {
+        format!("{}{}", (b'a' + self.0 as u8) as char, self.1 + 1)
+    }
+}
+
+impl Display for Coordinate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_algebraic())
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Piece {
+    pub kind: PieceKind,
+    pub color: PieceColor,
+    pub square: Coordinate,
+    pub moved: bool
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Move {
+    pub from: Coordinate,
+    pub to: Coordinate,
+    pub promotion: Option<PieceKind>
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.from, self.to)?;
+        match self.promotion {
+            Some(kind) => write!(f, "{}", kind.to_fen_char(PieceColor::BLACK)),
+            None => Ok(())
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct MoveRecord {
+    pub piece: Piece,
+    pub from: Coordinate,
+    pub to: Coordinate,
+    pub captured: Option<Piece>,
+    pub rook_move: Option<(Coordinate, Coordinate)>,
+    pub promotion: Option<PieceKind>,
+    pub previous_en_pessant_file: Option<i8>,
+    pub previous_halfmove_clock: u32
+}
+
+impl MoveRecord {
+    pub fn as_move(&self) -> Move {
+        Move {from: self.from, to: self.to, promotion: self.promotion}
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum IllegalMoveReason {
+    OccupiedByOwnPiece,
+    LeavesKingInCheck,
+    Unreachable
+}
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum MoveError {
+    NoPieceAtSource,
+    WrongColorToMove,
+    DestinationNotLegal { reason: IllegalMoveReason },
+    PromotionRequired,
+    InvalidPromotion(PieceKind)
+}
+
+impl Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MoveError::NoPieceAtSource => write!(f, "there is no piece on the source square"),
+            MoveError::WrongColorToMove => write!(f, "that piece belongs to the side not on move"),
+            MoveError::DestinationNotLegal { reason: IllegalMoveReason::OccupiedByOwnPiece } => write!(f, "the destination is occupied by your own piece"),
+            MoveError::DestinationNotLegal { reason: IllegalMoveReason::LeavesKingInCheck } => write!(f, "the move would leave your king in check"),
+            MoveError::DestinationNotLegal { reason: IllegalMoveReason::Unreachable } => write!(f, "the piece cannot reach that square"),
+            MoveError::PromotionRequired => write!(f, "a promotion piece must be chosen"),
+            MoveError::InvalidPromotion(kind) => write!(f, "cannot promote to a {}", kind)
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum FenError {
+    MissingField(&'static str),
+    InvalidPlacement(String),
+    InvalidSideToMove(String),
+    InvalidCastling(String),
+    InvalidEnPassant(String),
+    InvalidMoveNumber(String),
+    KingCount { color: PieceColor, count: usize },
+    PawnOnBackRank(Coordinate),
+    OpponentInCheck
+}
+
+impl Display for FenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FenError::MissingField(field) => write!(f, "the {} field is missing", field),
+            FenError::InvalidPlacement(reason) => write!(f, "invalid piece placement: {}", reason),
+            FenError::InvalidSideToMove(text) => write!(f, "side to move must be w or b, not {}", text),
+            FenError::InvalidCastling(text) => write!(f, "invalid castling rights {}", text),
+            FenError::InvalidEnPassant(text) => write!(f, "invalid en passant square {}", text),
+            FenError::InvalidMoveNumber(text) => write!(f, "invalid move number {}", text),
+            FenError::KingCount { color, count } => write!(f, "{} must have exactly one king, found {}", color, count),
+            FenError::PawnOnBackRank(square) => write!(f, "pawn on {} cannot stand on the first or last rank", square),
+            FenError::OpponentInCheck => write!(f, "the side not on move is in check")
+        }
+    }
+}
+
+/// FEN of the standard starting position, the one `Board::new` sets up
+pub const STANDARD_START: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Clone)]
+pub struct Board {
+    pub pieces: HashMap<Coordinate, Piece>,
+    pub on_move: PieceColor,
+    pub turn_number: u32,
+    pub en_pessant_file: Option<i8>,
+    pub halfmove_clock: u32,
+    pub position_keys: Vec<u64>
+}
+const ROOK_PATTERN: [(i8, i8); 4] = [(1, 0), (-1, 0), (0, 1), (0, -1)];
+const BISHOP_PATTERN: [(i8, i8); 4] = [(1, 1), (-1, 1), (1, -1), (-1, -1)];
+const KING_PATTERN: [(i8, i8); 8] = [(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (-1, 1), (1, -1), (-1, -1)];
+const KNIGHT_PATTERN: [(i8, i8); 8] = [(1, 2), (2, 1), (-1, 2), (2, -1), (1, -2), (-2, 1), (-1, -2), (-2, -1)];
+const WHITE_PAWN_CAPTURES: [(i8, i8); 2] = [(1, 1), (-1, 1)];
+const BLACK_PAWN_CAPTURES: [(i8, i8); 2] = [(1, -1), (-1, -1)];
+
+impl Default for Board {
+    fn default() -> Self {
+        Board::new()
+    }
+}
+
+impl Board {
+    pub fn new() -> Self {
+        let mut starting: HashMap<Coordinate, Piece> = HashMap::new();
+        for color in [PieceColor::WHITE, PieceColor::BLACK] {
+            for (index, kind) in [PieceKind::ROOK, PieceKind::KNIGHT, PieceKind::BISHOP, PieceKind::QUEEN, PieceKind::KING, PieceKind::BISHOP, PieceKind::KNIGHT, PieceKind::ROOK].iter().enumerate() {
+                let row = if color == PieceColor::WHITE { 0i8 } else { 7i8 };
+                let coordinate = Coordinate(index as i8, row);
+                starting.insert( coordinate, Piece{kind: *kind, color, square: coordinate, moved: false});
+            }
+            let row = if color == PieceColor::WHITE { 1i8 } else { 6i8 };
+            for col in 0..=7i8 {
+                let coordinate = Coordinate(col, row);
+                starting.insert(coordinate, Piece{kind: PieceKind::PAWN, color, square: coordinate, moved: false});
+            }
+        }
+        let mut board = Board {pieces: starting, on_move: PieceColor::WHITE, turn_number: 0, en_pessant_file: None, halfmove_clock: 0, position_keys: Vec::new()};
+        board.position_keys.push(board.position_key());
+        board
+    }
+
+    pub fn position_key(&self) -> u64 {
+        let mut key = self.pieces.values().fold(0u64, |key, piece| {
+            let mut hasher = DefaultHasher::new();
+            (piece.kind, piece.color, piece.square).hash(&mut hasher);
+            (piece.moved && matches!(piece.kind, PieceKind::KING | PieceKind::ROOK)).hash(&mut hasher);
+            key ^ hasher.finish()
+        });
+        let mut hasher = DefaultHasher::new();
+        (self.on_move, self.en_pessant_file).hash(&mut hasher);
+        key ^= hasher.finish();
+        key
+    }
+
+    pub fn repetition_count(&self) -> usize {
+        let Some(current) = self.position_keys.last() else { return 1 };
+        self.position_keys.iter().filter(|key| *key == current).count()
+    }
+
+    pub fn claimable_draw(&self) -> Option<DrawReason> {
+        if self.repetition_count() >= 3 { return Some(DrawReason::ThreefoldRepetition) };
+        if self.halfmove_clock >= 100 { return Some(DrawReason::FiftyMoveRule) };
+        None
+    }
+
+    pub fn pieces_of(&self, color: PieceColor) -> impl Iterator<Item=&Piece> {
+        self.pieces.values().filter(move |piece| piece.color == color)
+    }
+
+    pub fn find(&self, color: PieceColor, kind: PieceKind) -> Option<&Piece> {
+        self.pieces_of(color).find(|piece| piece.kind == kind)
+    }
+
+    pub fn count(&self, color: PieceColor, kind: PieceKind) -> usize {
+        self.pieces_of(color).filter(|piece| piece.kind == kind).count()
+    }
+
+    pub fn has_moves(&self, color: PieceColor) -> bool {
+        self.pieces_of(color).any(|piece| self.moves_for(piece).next().is_some())
+    }
+
+    pub fn looking_at(&self, piece: &Piece) -> Vec<Coordinate> {
+        self.targets(piece).collect()
+    }
+
+    pub fn targets(&self, piece: &Piece) -> impl Iterator<Item=Coordinate> + '_ {
+        let piece = *piece;
+        let pawn_direction = if piece.color == PieceColor::WHITE { 1i8 } else { -1i8 };
+        let (deltas, sliding): (&'static [(i8, i8)], bool) = match piece.kind {
+            PieceKind::KING => (&KING_PATTERN, false),
+            PieceKind::QUEEN => (&KING_PATTERN, true),
+            PieceKind::ROOK => (&ROOK_PATTERN, true),
+            PieceKind::BISHOP => (&BISHOP_PATTERN, true),
+            PieceKind::KNIGHT => (&KNIGHT_PATTERN, false),
+            PieceKind::PAWN => (if pawn_direction == 1 { &WHITE_PAWN_CAPTURES } else { &BLACK_PAWN_CAPTURES }, false)
+        };
+        deltas.iter().flat_map(move |&(dx, dy)| {
+            let mut check = Some(piece.square);
+            std::iter::from_fn(move || {
+                let next = check?.offset(dx, dy)?;
+                check = if sliding { Some(next) } else { None };
+                match self.pieces.get(&next) {
+                    Some(occupying) => {
+                        check = None;
+                        if occupying.color == piece.color { None } else { Some(next) }
+                    }
+                    None if piece.kind == PieceKind::PAWN => None,
+                    None => Some(next)
+                }
+            })
+        })
+    }
+
+    pub fn attacked_by(&self, piece: &Piece) -> Vec<Coordinate> {
+        let (deltas, sliding): (&'static [(i8, i8)], bool) = match piece.kind {
+            PieceKind::KING => (&KING_PATTERN, false),
+            PieceKind::QUEEN => (&KING_PATTERN, true),
+            PieceKind::ROOK => (&ROOK_PATTERN, true),
+            PieceKind::BISHOP => (&BISHOP_PATTERN, true),
+            PieceKind::KNIGHT => (&KNIGHT_PATTERN, false),
+            PieceKind::PAWN if piece.color == PieceColor::WHITE => (&WHITE_PAWN_CAPTURES, false),
+            PieceKind::PAWN => (&BLACK_PAWN_CAPTURES, false)
+        };
+        let mut attacked = Vec::new();
+        for &(dx, dy) in deltas {
+            let mut check = piece.square.offset(dx, dy);
+            while let Some(square) = check {
+                attacked.push(square);
+                check = if sliding && !self.pieces.contains_key(&square) { square.offset(dx, dy) } else { None };
+            }
+        }
+        attacked
+    }
+
+    pub fn attack_counts(&self, color: PieceColor) -> HashMap<Coordinate, u8> {
+        let mut counts = HashMap::new();
+        for piece in self.pieces_of(color) {
+            for square in self.attacked_by(piece) {
+                *counts.entry(square).or_insert(0) += 1;
+            }
+        }
+        counts
+    }
+
+    pub fn position_status(&self) -> PositionStatus {
+        PositionStatus {status: self.status(), check: !self.checking_squares(self.on_move).is_empty()}
+    }
+
+    pub fn status(&self) -> GameStatus {
+        if self.has_moves(self.on_move) {
+            return self.automatic_draw().map_or(GameStatus::Ongoing, |reason| GameStatus::Draw {reason});
+        }
+        if self.checking_squares(self.on_move).is_empty() { return GameStatus::Stalemate };
+        GameStatus::Checkmate { winner: self.on_move.opposite() }
+    }
+
+    fn automatic_draw(&self) -> Option<DrawReason> {
+        if self.repetition_count() >= 5 { return Some(DrawReason::FivefoldRepetition) };
+        if self.halfmove_clock >= 150 { return Some(DrawReason::SeventyFiveMoveRule) };
+        None
+    }
+
+    pub fn checking_squares(&self, color: PieceColor) -> Vec<Coordinate> {
+        let Some(king) = self.find(color, PieceKind::KING) else { return Vec::new() };
+        self.pieces_of(color.opposite())
+            .filter(|checking| self.targets(checking).any(|target| target == king.square))
+            .map(|checking| checking.square)
+            .collect()
+    }
+
+    pub fn is_checked(&self, piece: &Piece) -> bool {
+        self.pieces_of(piece.color.opposite()).any(|checking| self.targets(checking).any(|target| target == piece.square))
+    }
+
+    pub fn get_valid_moves(&self, piece: &Piece) -> Vec<Coordinate>{
+        self.moves_for(piece).map(|valid| valid.to).collect()
+    }
+
+    pub fn is_legal_move(&self, candidate: Move) -> bool {
+        let Some(piece) = self.pieces.get(&candidate.from) else { return false };
+        self.moves_for(piece).any(|valid| valid.to == candidate.to)
+    }
+
+    pub fn moves_for(&self, piece: &Piece) -> impl Iterator<Item=Move> + '_ {
+        let piece = *piece;
+        let from = piece.square;
+        let mut scratch: Option<Board> = None;
+        self.targets(&piece)
+            .chain(self.pawn_pushes(&piece))
+            .chain(std::iter::once(()).flat_map(move |_| self.en_passant_targets(&piece)))
+            .filter(move |to| scratch.get_or_insert_with(|| self.clone()).keeps_king_safe(&piece, *to))
+            .chain(std::iter::once(()).flat_map(move |_| self.castling_targets(&piece)))
+            .map(move |to| Move {from, to, promotion: None})
+    }
+
+    fn pawn_pushes(&self, piece: &Piece) -> impl Iterator<Item=Coordinate> + '_ {
+        let piece = *piece;
+        let direction = if piece.color == PieceColor::WHITE { 1i8 } else { -1i8 };
+        let steps = match (piece.kind, piece.moved) {
+            (PieceKind::PAWN, false) => 2,
+            (PieceKind::PAWN, true) => 1,
+            _ => 0
+        };
+        let mut following = piece.square;
+        (0..steps).map_while(move |_| {
+            following = following.offset(0, direction).filter(|square| !self.pieces.contains_key(square))?;
+            Some(following)
+        })
+    }
+
+    // Plays the move on a scratch board and takes it back, so checking every candidate costs no copies
+    fn keeps_king_safe(&mut self, piece: &Piece, to: Coordinate) -> bool {
+        let record = self.move_piece(&piece.square, &to);
+        let Some(king) = self.find(piece.color, PieceKind::KING) else { panic!("no king") };
+        let safe = !self.is_checked(king);
+        self.unmove_piece(&record);
+        safe
+    }
+
+    fn castling_targets(&self, piece: &Piece) -> Vec<Coordinate> {
+        let mut targets = Vec::new();
+        if piece.kind != PieceKind::KING || piece.moved || self.is_checked(piece) { return targets };
+        let rank = piece.square.1;
+        let mut scratch = self.clone();
+        for direction in [-1i8, 1] {
+            let passing = Coordinate(piece.square.0+direction, rank);
+            if !self.targets(piece).any(|target| target == passing) || !scratch.keeps_king_safe(piece, passing) { continue };
+            if self.pieces.contains_key(&passing) { continue };
+            if self.pieces.contains_key(&Coordinate(piece.square.0+direction*2, rank)) { continue };
+            if self.is_checked(&Piece{kind: PieceKind::KING, color: piece.color, square: Coordinate(piece.square.0+direction*2, rank), moved: false}) { continue };
+
+            let mut scan = Coordinate(piece.square.0+direction*2, rank);
+            while let Some(next) = scan.offset(direction, 0) {
+                scan = next;
+                let Some(occupying) = self.pieces.get(&scan) else { continue };
+                if occupying.kind == PieceKind::ROOK && occupying.color == piece.color && !occupying.moved {
+                    targets.push(Coordinate(piece.square.0+direction*2, rank));
+                }
+                break;
+            }
+        }
+        targets
+    }
+
+    fn en_passant_targets(&self, piece: &Piece) -> Vec<Coordinate> {
+        let mut targets = Vec::new();
+        let Some(file) = self.en_pessant_file else { return targets };
+        if piece.kind != PieceKind::PAWN || piece.square.1 != (if piece.color == PieceColor::WHITE { 4 } else { 3 }) { return targets };
+        let direction = if piece.color == PieceColor::WHITE { 1 } else { -1 };
+        for delta in [-1i8, 1] {
+            if file == piece.square.0+delta {
+                targets.push(Coordinate(piece.square.0 + delta, piece.square.1 + direction));
+            }
+        }
+        targets
+    }
+
+    pub fn move_piece(&mut self, from: &Coordinate, to: &Coordinate) -> MoveRecord {
+        let mut piece = self.pieces[from];
+        let mut record = MoveRecord {
+            piece,
+            from: *from,
+            to: *to,
+            captured: self.pieces.get(to).copied(),
+            rook_move: None,
+            promotion: None,
+            previous_en_pessant_file: self.en_pessant_file,
+            previous_halfmove_clock: self.halfmove_clock
+        };
+        piece.moved = true;
+        piece.square = *to;
+        self.pieces.insert(*to, piece);
+        self.pieces.remove(from);
+
+        let distance = to.0 - from.0;
+        if piece.kind == PieceKind::KING && distance.abs() > 1 {
+            let direction = distance.signum();
+            let mut position = to.0;
+            while (0..8).contains(&position) {
+                position += direction;
+                let coordinate = Coordinate(position, to.1);
+                if !self.pieces.contains_key(&coordinate) { continue };
+                let rook_destination = Coordinate(from.0+direction, from.1);
+                self.move_piece(&coordinate, &rook_destination);
+                record.rook_move = Some((coordinate, rook_destination));
+                break;
+            }
+        }
+        if piece.kind == PieceKind::PAWN && record.captured.is_none() && from.0 != to.0 {
+            record.captured = self.pieces.remove(&Coordinate(to.0, from.1));
+        }
+
+        self.en_pessant_file = None;
+        let vdistance = to.1 - from.1;
+        if piece.kind == PieceKind::PAWN && vdistance.abs() > 1 {
+            self.en_pessant_file = Some(piece.square.0);
+        }
+        self.halfmove_clock = if piece.kind == PieceKind::PAWN || record.captured.is_some() { 0 } else { record.previous_halfmove_clock + 1 };
+        record
+    }
+
+    pub fn unmake_move(&mut self, record: &MoveRecord) {
+        self.unmove_piece(record);
+        if self.position_keys.len() > 1 {
+            self.position_keys.pop();
+        }
+        self.turn_number = self.turn_number.saturating_sub(1);
+        self.on_move = record.piece.color;
+    }
+
+    // Undoes move_piece alone, leaving the side to move and the position history untouched
+    fn unmove_piece(&mut self, record: &MoveRecord) {
+        self.pieces.remove(&record.to);
+        if let Some((rook_from, rook_to)) = record.rook_move {
+            if let Some(mut rook) = self.pieces.remove(&rook_to) {
+                rook.square = rook_from;
+                rook.moved = false;
+                self.pieces.insert(rook_from, rook);
+            }
+        }
+        self.pieces.insert(record.from, record.piece);
+        if let Some(captured) = record.captured {
+            self.pieces.insert(captured.square, captured);
+        }
+        self.en_pessant_file = record.previous_en_pessant_file;
+        self.halfmove_clock = record.previous_halfmove_clock;
+    }
+
+    pub fn try_move(&mut self, candidate: Move) -> Result<MoveRecord, MoveError> {
+        let Some(piece) = self.pieces.get(&candidate.from).copied() else { return Err(MoveError::NoPieceAtSource) };
+        if piece.color != self.on_move { return Err(MoveError::WrongColorToMove) };
+        if !self.is_legal_move(candidate) {
+            let reason = if self.pieces.get(&candidate.to).is_some_and(|occupying| occupying.color == piece.color) {
+                IllegalMoveReason::OccupiedByOwnPiece
+            } else if self.targets(&piece).chain(self.pawn_pushes(&piece)).any(|target| target == candidate.to) {
+                IllegalMoveReason::LeavesKingInCheck
+            } else {
+                IllegalMoveReason::Unreachable
+            };
+            return Err(MoveError::DestinationNotLegal { reason });
+        }
+        let promotes = piece.kind == PieceKind::PAWN && (candidate.to.1 == 0 || candidate.to.1 == 7);
+        match (promotes, candidate.promotion) {
+            (true, None) => return Err(MoveError::PromotionRequired),
+            (true, Some(kind)) if !PieceKind::PROMOTION_TARGETS.contains(&kind) => return Err(MoveError::InvalidPromotion(kind)),
+            (false, Some(kind)) => return Err(MoveError::InvalidPromotion(kind)),
+            _ => {}
+        }
+
+        let mut record = self.move_piece(&candidate.from, &candidate.to);
+        if let Some(kind) = candidate.promotion {
+            self.pieces.get_mut(&candidate.to).unwrap().kind = kind;
+            record.promotion = Some(kind);
+        }
+        self.flip_on_move();
+        Ok(record)
+    }
+
+    pub fn san(&self, candidate: Move) -> String {
+        if !self.pieces.contains_key(&candidate.from) { return String::new() };
+        let mut after = self.clone();
+        after.move_piece(&candidate.from, &candidate.to);
+        if let Some(kind) = candidate.promotion {
+            after.pieces.get_mut(&candidate.to).unwrap().kind = kind;
+        }
+        after.flip_on_move();
+        self.san_with_status(candidate, after.position_status())
+    }
+
+    pub fn san_with_status(&self, candidate: Move, after: PositionStatus) -> String {
+        let Some(piece) = self.pieces.get(&candidate.from).copied() else { return String::new() };
+        let mut san = String::new();
+        if piece.kind == PieceKind::KING && (candidate.to.0 - candidate.from.0).abs() > 1 {
+            let rook_distance = if candidate.to.0 > candidate.from.0 { 7 - candidate.from.0 } else { candidate.from.0 };
+            san.push_str(if rook_distance == 3 { "O-O" } else { "O-O-O" });
+        } else {
+            let capture = self.pieces.contains_key(&candidate.to) || (piece.kind == PieceKind::PAWN && candidate.from.0 != candidate.to.0);
+            if piece.kind == PieceKind::PAWN {
+                if capture { san.push(candidate.from.to_algebraic().chars().next().unwrap()) };
+            } else {
+                san.push(piece.kind.to_fen_char(PieceColor::WHITE));
+                let rivals: Vec<Coordinate> = self.pieces_of(piece.color)
+                    .filter(|rival| rival.kind == piece.kind && rival.square != piece.square)
+                    .filter(|rival| self.moves_for(rival).any(|valid| valid.to == candidate.to))
+                    .map(|rival| rival.square)
+                    .collect();
+                let from = candidate.from.to_algebraic();
+                if !rivals.is_empty() {
+                    if rivals.iter().all(|rival| rival.0 != candidate.from.0) {
+                        san.push_str(&from[..1]);
+                    } else if rivals.iter().all(|rival| rival.1 != candidate.from.1) {
+                        san.push_str(&from[1..]);
+                    } else {
+                        san.push_str(&from);
+                    }
+                }
+            }
+            if capture { san.push('x') };
+            san.push_str(&candidate.to.to_algebraic());
+            if let Some(kind) = candidate.promotion {
+                san.push('=');
+                san.push(kind.to_fen_char(PieceColor::WHITE));
+            }
+        }
+
+        san.push_str(after.suffix());
+        san
+    }
+
+    pub fn san_line(&self, moves: &[Move]) -> String {
+        let mut board = self.clone();
+        let mut line = Vec::with_capacity(moves.len());
+        for (index, candidate) in moves.iter().enumerate() {
+            let number = board.turn_number / 2 + 1;
+            let mut san = match board.on_move {
+                PieceColor::WHITE => format!("{}. ", number),
+                PieceColor::BLACK if index == 0 => format!("{}... ", number),
+                PieceColor::BLACK => String::new()
+            };
+            san.push_str(&board.san(*candidate));
+            if board.try_move(*candidate).is_err() { break };
+            line.push(san);
+        }
+        line.join(" ")
+    }
+
+    pub fn legal_moves(&self) -> impl Iterator<Item=Move> + '_ {
+        self.pieces_of(self.on_move).flat_map(move |piece| self.moves_for(piece))
+    }
+
+    pub fn parse_move(&self, text: &str) -> Option<Move> {
+        let text = text.trim();
+        if text.len() >= 4 && text.is_ascii() {
+            if let (Some(from), Some(to)) = (Coordinate::from_algebraic(&text[0..2]), Coordinate::from_algebraic(&text[2..4])) {
+                let promotion = match text[4..].trim_start_matches('=').chars().next() {
+                    Some(character) => Some(PieceKind::from_fen_char(character)?.1),
+                    None => None
+                };
+                return Some(Move {from, to, promotion});
+            }
+        }
+
+        let wanted = text.trim_end_matches(['+', '#', '!', '?']).replace('0', "O");
+        self.legal_moves()
+            .flat_map(|legal| {
+                let promotes = self.pieces.get(&legal.from).is_some_and(|piece| piece.kind == PieceKind::PAWN) && (legal.to.1 == 0 || legal.to.1 == 7);
+                let options: Vec<Option<PieceKind>> = if promotes { PieceKind::PROMOTION_TARGETS.map(Some).to_vec() } else { vec![None] };
+                options.into_iter().map(move |promotion| Move {promotion, ..legal})
+            })
+            .find(|candidate| self.san(*candidate).trim_end_matches(['+', '#']) == wanted)
+    }
+
+    pub fn to_fen(&self) -> String {
+        let mut fen = String::new();
+        for rank in (0..8i8).rev() {
+            let mut empty = 0;
+            for file in 0..8i8 {
+                match self.pieces.get(&Coordinate(file, rank)) {
+                    Some(piece) => {
+                        if empty > 0 {
+                            fen.push_str(&empty.to_string());
+                            empty = 0;
+                        }
+                        fen.push(piece.kind.to_fen_char(piece.color));
+                    }
+                    None => empty += 1
+                }
+            }
+            if empty > 0 {
+                fen.push_str(&empty.to_string());
+            }
+            if rank > 0 {
+                fen.push('/');
+            }
+        }
+        fen.push_str(if self.on_move == PieceColor::WHITE { " w " } else { " b " });
+
+        let mut castling = String::new();
+        for color in [PieceColor::WHITE, PieceColor::BLACK] {
+            let Some(king) = self.find(color, PieceKind::KING).filter(|king| !king.moved) else { continue };
+            let mut rooks: Vec<&Piece> = self.pieces_of(color)
+                .filter(|rook| rook.kind == PieceKind::ROOK && !rook.moved && rook.square.1 == king.square.1)
+                .collect();
+            rooks.sort_by_key(|rook| -rook.square.0);
+            for rook in rooks {
+                let side = if rook.square.0 > king.square.0 { PieceKind::KING } else { PieceKind::QUEEN };
+                castling.push(side.to_fen_char(color));
+            }
+        }
+        fen.push_str(if castling.is_empty() { "-" } else { &castling });
+
+        match self.en_pessant_file {
+            Some(file) => {
+                let rank = if self.on_move == PieceColor::WHITE { 5 } else { 2 };
+                fen.push(' ');
+                fen.push_str(&Coordinate(file, rank).to_algebraic());
+            }
+            None => fen.push_str(" -")
+        }
+        fen.push_str(&format!(" {} {}", self.halfmove_clock, self.turn_number / 2 + 1));
+        fen
+    }
+
+    pub fn from_fen(fen: &str) -> Result<Board, FenError> {
+        let mut fields = fen.split_whitespace();
+        let placement = fields.next().ok_or(FenError::MissingField("piece placement"))?;
+        let side = fields.next().ok_or(FenError::MissingField("side to move"))?;
+        let castling = fields.next().unwrap_or("-");
+        let en_passant = fields.next().unwrap_or("-");
+        let halfmove_clock = fields.next().unwrap_or("0");
+        let fullmove = fields.next().unwrap_or("1");
+
+        let ranks: Vec<&str> = placement.split('/').collect();
+        if ranks.len() != 8 {
+            return Err(FenError::InvalidPlacement(format!("expected 8 ranks, found {}", ranks.len())));
+        }
+        let mut pieces = HashMap::new();
+        for (row, text) in ranks.iter().enumerate() {
+            let rank = 7 - row as i8;
+            let mut file = 0i8;
+            for character in text.chars() {
+                if let Some(empty) = character.to_digit(10).filter(|empty| (1..=8).contains(empty)) {
+                    file += empty as i8;
+                    if file > 8 {
+                        return Err(FenError::InvalidPlacement(format!("rank {} has more than 8 squares", rank + 1)));
+                    }
+                } else {
+                    let (color, kind) = PieceKind::from_fen_char(character)
+                        .ok_or_else(|| FenError::InvalidPlacement(format!("unknown piece {}", character)))?;
+                    if file > 7 {
+                        return Err(FenError::InvalidPlacement(format!("rank {} has more than 8 squares", rank + 1)));
+                    }
+                    let square = Coordinate(file, rank);
+                    let home_rank = if color == PieceColor::WHITE { 1 } else { 6 };
+                    pieces.insert(square, Piece {kind, color, square, moved: kind == PieceKind::PAWN && rank != home_rank || kind == PieceKind::KING || kind == PieceKind::ROOK});
+                    file += 1;
+                }
+            }
+            if file != 8 {
+                return Err(FenError::InvalidPlacement(format!("rank {} does not describe 8 squares", rank + 1)));
+            }
+        }
+
+        let on_move = match side {
+            "w" => PieceColor::WHITE,
+            "b" => PieceColor::BLACK,
+            _ => return Err(FenError::InvalidSideToMove(side.to_string()))
+        };
+        let mut board = Board {pieces, on_move, turn_number: 0, en_pessant_file: None, halfmove_clock: 0, position_keys: Vec::new()};
+
+        if castling != "-" {
+            for character in castling.chars() {
+                let invalid = || FenError::InvalidCastling(castling.to_string());
+                let (color, side) = PieceKind::from_fen_char(character).filter(|(_, kind)| matches!(kind, PieceKind::KING | PieceKind::QUEEN)).ok_or_else(invalid)?;
+                let king = *board.find(color, PieceKind::KING).filter(|king| king.square.1 == if color == PieceColor::WHITE { 0 } else { 7 }).ok_or_else(invalid)?;
+                let rook = board.pieces_of(color)
+                    .filter(|rook| rook.kind == PieceKind::ROOK && rook.square.1 == king.square.1)
+                    .filter(|rook| (rook.square.0 > king.square.0) == (side == PieceKind::KING))
+                    .max_by_key(|rook| (rook.square.0 - king.square.0).abs())
+                    .map(|rook| rook.square)
+                    .ok_or_else(invalid)?;
+                board.pieces.get_mut(&king.square).unwrap().moved = false;
+                board.pieces.get_mut(&rook).unwrap().moved = false;
+            }
+        }
+
+        if en_passant != "-" {
+            let expected_rank = if on_move == PieceColor::WHITE { 5 } else { 2 };
+            let square = Coordinate::from_algebraic(en_passant)
+                .filter(|square| square.1 == expected_rank)
+                .ok_or_else(|| FenError::InvalidEnPassant(en_passant.to_string()))?;
+            board.en_pessant_file = Some(square.0);
+        }
+
+        let fullmove: u32 = fullmove.parse().ok().filter(|number| *number >= 1)
+            .ok_or_else(|| FenError::InvalidMoveNumber(fullmove.to_string()))?;
+        board.turn_number = (fullmove - 1).checked_mul(2)
+            .and_then(|turn| turn.checked_add(if on_move == PieceColor::BLACK { 1 } else { 0 }))
+            .ok_or_else(|| FenError::InvalidMoveNumber(fullmove.to_string()))?;
+        board.halfmove_clock = halfmove_clock.parse()
+            .map_err(|_| FenError::InvalidMoveNumber(halfmove_clock.to_string()))?;
+        board.position_keys.push(board.position_key());
+
+        board.validate()?;
+        Ok(board)
+    }
+
+    pub fn validate(&self) -> Result<(), FenError> {
+        for color in [PieceColor::WHITE, PieceColor::BLACK] {
+            let count = self.count(color, PieceKind::KING);
+            if count != 1 {
+                return Err(FenError::KingCount {color, count});
+            }
+        }
+        if let Some(pawn) = self.pieces.values().find(|piece| piece.kind == PieceKind::PAWN && (piece.square.1 == 0 || piece.square.1 == 7)) {
+            return Err(FenError::PawnOnBackRank(pawn.square));
+        }
+        if !self.checking_squares(self.on_move.opposite()).is_empty() {
+            return Err(FenError::OpponentInCheck);
+        }
+        Ok(())
+    }
+
+    pub fn promote(&mut self, square: Coordinate, kind: PieceKind) -> Option<Piece> {
+        let piece = self.pieces.get_mut(&square)?;
+        piece.kind = kind;
+        let promoted = *piece;
+        let key = self.position_key();
+        if let Some(last) = self.position_keys.last_mut() {
+            *last = key;
+        }
+        Some(promoted)
+    }
+
+    pub fn flip_on_move(&mut self) {
+        self.turn_number += 1;
+        self.on_move = self.on_move.opposite();
+        self.position_keys.push(self.position_key());
+    }
+}
+
+impl Display for Board {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for rank in (0..8i8).rev() {
+            write!(f, "{} ", rank + 1)?;
+            for file in 0..8i8 {
+                let character = match self.pieces.get(&Coordinate(file, rank)) {
+                    Some(piece) => piece.kind.to_fen_char(piece.color),
+                    None => '.'
+                };
+                write!(f, " {}", character)?;
+            }
+            writeln!(f)?;
+        }
+        write!(f, "   a b c d e f g h")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_new_board_is_the_standard_start() {
+        assert_eq!(Board::new().to_fen(), STANDARD_START);
+    }
+
+    #[test]
+    fn san_of_an_empty_source_square_does_not_panic() {
+        let mut board = Board::new();
+        let candidate = board.parse_move("e3e4").unwrap();
+        assert_eq!(board.san(candidate), "");
+        assert!(matches!(board.try_move(candidate), Err(MoveError::NoPieceAtSource)));
+    }
+
+    #[test]
+    fn pinned_pieces_only_move_along_the_pin() {
+        let board = Board::from_fen("4k3/4r3/8/8/8/8/4R3/4K3 w - - 0 1").unwrap();
+        let rook = *board.pieces.get(&Coordinate(4, 1)).unwrap();
+        let fen = board.to_fen();
+        let targets = board.get_valid_moves(&rook);
+        assert_eq!(targets.len(), 5);
+        assert!(targets.iter().all(|target| target.0 == 4));
+        assert_eq!(board.to_fen(), fen);
+    }
+
+    #[test]
+    fn the_start_position_has_four_hundred_replies() {
+        let mut board = Board::from_fen(STANDARD_START).unwrap();
+        let fen = board.to_fen();
+        let mut total = 0;
+        for candidate in board.legal_moves().collect::<Vec<_>>() {
+            let record = board.try_move(candidate).unwrap();
+            total += board.legal_moves().count();
+            board.unmake_move(&record);
+        }
+        assert_eq!(total, 400);
+        assert_eq!(board.to_fen(), fen);
+    }
+
+    #[test]
+    fn en_passant_must_answer_check() {
+        let board = Board::from_fen("7k/8/8/8/2Pp4/8/8/4K2R b - c3 0 1").unwrap();
+        let pawn = *board.pieces.get(&Coordinate(3, 3)).unwrap();
+        assert!(board.get_valid_moves(&pawn).is_empty());
+        let pinned = Board::from_fen("8/8/8/8/k1Pp3R/8/8/4K3 b - c3 0 1").unwrap();
+        let pawn = *pinned.pieces.get(&Coordinate(3, 3)).unwrap();
+        assert!(!pinned.get_valid_moves(&pawn).contains(&Coordinate(2, 2)));
+    }
+
+    #[test]
+    fn oversized_fen_fields_are_rejected() {
+        let long_rank = Board::from_fen("8888888888888888/8/8/8/8/8/8/8 w - - 0 1");
+        assert!(matches!(long_rank, Err(FenError::InvalidPlacement(_))));
+        let late_move = Board::from_fen("4k3/8/8/8/8/8/8/4K3 b - - 0 4294967295");
+        assert!(matches!(late_move, Err(FenError::InvalidMoveNumber(_))));
+    }
+}
@@ -0,0 +1,192 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::logic::{Board, Move, PieceColor, PieceKind};
+
+pub const MATE_SCORE: i32 = 100_000;
+
+#[derive(Clone)]
+pub struct SearchLimits {
+    pub max_depth: u32,
+    pub stop: Arc<AtomicBool>
+}
+
+impl SearchLimits {
+    pub fn new(max_depth: u32) -> Self {
+        SearchLimits {max_depth, stop: Arc::new(AtomicBool::new(false))}
+    }
+
+    pub fn stop(&self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+
+    pub fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct SearchResult {
+    pub best_move: Move,
+    pub score: i32,
+    pub depth: u32,
+    pub pv: Vec<Move>
+}
+
+fn piece_value(kind: PieceKind) -> i32 {
+    match kind {
+        PieceKind::PAWN => 100,
+        PieceKind::KNIGHT => 320,
+        PieceKind::BISHOP => 330,
+        PieceKind::ROOK => 500,
+        PieceKind::QUEEN => 900,
+        PieceKind::KING => 0
+    }
+}
+
+pub fn evaluate(board: &Board) -> i32 {
+    board.pieces.values().map(|piece| {
+        let centrality = 3.5 - (piece.square.0 as f32 - 3.5).abs().max((piece.square.1 as f32 - 3.5).abs());
+        let positional = match piece.kind {
+            PieceKind::PAWN if piece.color == PieceColor::WHITE => piece.square.1 as i32 * 5,
+            PieceKind::PAWN => (7 - piece.square.1) as i32 * 5,
+            PieceKind::KNIGHT | PieceKind::BISHOP => (centrality * 10.0) as i32,
+            _ => 0
+        };
+        let value = piece_value(piece.kind) + positional;
+        if piece.color == board.on_move { value } else { -value }
+    }).sum()
+}
+
+pub fn candidate_moves(board: &Board) -> Vec<Move> {
+    let mut moves: Vec<Move> = board.legal_moves().flat_map(|candidate| {
+        let promotes = board.pieces[&candidate.from].kind == PieceKind::PAWN && (candidate.to.1 == 0 || candidate.to.1 == 7);
+        let promotions: Vec<Option<PieceKind>> = match promotes {
+            true => PieceKind::PROMOTION_TARGETS.into_iter().map(Some).collect(),
+            false => vec![None]
+        };
+        promotions.into_iter().map(move |promotion| Move {promotion, ..candidate})
+    }).collect();
+    // Captures first, then a fixed order so the search does not inherit the board's hash map order
+    moves.sort_by_key(|candidate| (
+        -board.pieces.get(&candidate.to).map_or(0, |captured| piece_value(captured.kind)),
+        (candidate.from.0, candidate.from.1, candidate.to.0, candidate.to.1),
+        candidate.promotion.map(|kind| kind as u8)
+    ));
+    moves
+}
+
+fn negamax(board: &mut Board, depth: u32, mut alpha: i32, beta: i32, ply: i32, limits: &SearchLimits, pv: &mut Vec<Move>) -> Option<i32> {
+    if limits.stopped() { return None };
+    let moves = candidate_moves(board);
+    if moves.is_empty() {
+        let checked = !board.checking_squares(board.on_move).is_empty();
+        return Some(if checked { -MATE_SCORE + ply } else { 0 });
+    }
+    if depth == 0 { return Some(evaluate(board)) };
+    let mut line = Vec::new();
+    for candidate in moves {
+        let mut record = board.move_piece(&candidate.from, &candidate.to);
+        if let Some(kind) = candidate.promotion {
+            board.pieces.get_mut(&candidate.to).unwrap().kind = kind;
+            record.promotion = Some(kind);
+        }
+        board.flip_on_move();
+        let score = negamax(board, depth - 1, -beta, -alpha, ply + 1, limits, &mut line).map(|score| -score);
+        board.unmake_move(&record);
+        let score = score?;
+        if score >= beta { return Some(beta) };
+        if score > alpha {
+            alpha = score;
+            pv.clear();
+            pv.push(candidate);
+            pv.append(&mut line);
+        }
+        line.clear();
+    }
+    Some(alpha)
+}
+
+pub fn search(board: &Board, limits: &SearchLimits) -> Option<SearchResult> {
+    search_lines(board, limits, 1, |_| {}).into_iter().next()
+}
+
+pub fn search_lines(board: &Board, limits: &SearchLimits, lines: usize, mut report: impl FnMut(&[SearchResult])) -> Vec<SearchResult> {
+    let mut board = board.clone();
+    let mut moves = candidate_moves(&board);
+    let mut best: Vec<SearchResult> = moves.iter().take(lines).map(|candidate| SearchResult {best_move: *candidate, score: 0, depth: 0, pv: vec![*candidate]}).collect();
+    for depth in 1..=limits.max_depth {
+        let mut iteration: Vec<SearchResult> = Vec::with_capacity(moves.len());
+        for candidate in moves.iter() {
+            let mut record = board.move_piece(&candidate.from, &candidate.to);
+            if let Some(kind) = candidate.promotion {
+                board.pieces.get_mut(&candidate.to).unwrap().kind = kind;
+                record.promotion = Some(kind);
+            }
+            board.flip_on_move();
+            let alpha = iteration.get(lines - 1).map_or(-MATE_SCORE - 1, |result| result.score);
+            let mut line = Vec::new();
+            let score = negamax(&mut board, depth - 1, -MATE_SCORE - 1, -alpha, 1, limits, &mut line).map(|score| -score);
+            line.insert(0, *candidate);
+            board.unmake_move(&record);
+            let Some(score) = score else { return best };
+            let index = iteration.partition_point(|result| result.score >= score);
+            iteration.insert(index, SearchResult {best_move: *candidate, score, depth, pv: line});
+        }
+        let Some(top) = iteration.first().map(|result| result.score) else { break };
+        moves = iteration.iter().map(|result| result.best_move).collect();
+        iteration.truncate(lines);
+        best = iteration;
+        report(&best);
+        if top.abs() >= MATE_SCORE - depth as i32 { break };
+    }
+    best
+}
+
+pub fn mate_in(score: i32) -> Option<i32> {
+    if score.abs() < MATE_SCORE - 1000 { return None };
+    let moves = (MATE_SCORE - score.abs() + 1) / 2;
+    Some(if score > 0 { moves } else { -moves })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn candidate_order_does_not_depend_on_the_board_instance() {
+        let fen = "r1bqkb1r/pppp1ppp/2n2n2/4p3/2B1P3/5N2/PPPP1PPP/RNBQK2R w KQkq - 4 4";
+        let first = candidate_moves(&Board::from_fen(fen).unwrap());
+        for _ in 0..8 {
+            assert_eq!(candidate_moves(&Board::from_fen(fen).unwrap()), first);
+        }
+        let best = search(&Board::from_fen(fen).unwrap(), &SearchLimits::new(3)).unwrap();
+        for _ in 0..4 {
+            assert_eq!(search(&Board::from_fen(fen).unwrap(), &SearchLimits::new(3)).unwrap(), best);
+        }
+    }
+    #[test]
+    fn mate_scores_count_whole_moves() {
+        assert_eq!(mate_in(MATE_SCORE - 1), Some(1));
+        assert_eq!(mate_in(MATE_SCORE - 2), Some(1));
+        assert_eq!(mate_in(MATE_SCORE - 3), Some(2));
+        assert_eq!(mate_in(-MATE_SCORE + 2), Some(-1));
+        assert_eq!(mate_in(-MATE_SCORE + 4), Some(-2));
+    }
+
+    #[test]
+    fn ordinary_scores_are_not_mates() {
+        assert_eq!(mate_in(0), None);
+        assert_eq!(mate_in(900), None);
+        assert_eq!(mate_in(-900), None);
+        assert_eq!(mate_in(MATE_SCORE - 1001), None);
+        assert_eq!(mate_in(MATE_SCORE - 1000), Some(500));
+    }
+
+    #[test]
+    fn searches_report_mates_for_both_sides() {
+        let mating = search(&Board::from_fen("k7/8/2K5/8/8/8/8/1R6 w - - 0 1").unwrap(), &SearchLimits::new(4)).unwrap();
+        assert_eq!(mate_in(mating.score), Some(2));
+        let mated = search(&Board::from_fen("k7/2K5/8/8/8/8/8/1R6 b - - 0 1").unwrap(), &SearchLimits::new(3)).unwrap();
+        assert_eq!(mate_in(mated.score), Some(-1));
+    }
+}
@@ -0,0 +1,135 @@
+use bevy::prelude::*;
+use chess_logic::logic::{Board, PieceColor};
+use crate::board::{BoardResource, GamePhase, GameStatusRes, LocalSide};
+use crate::clock::ChessClock;
+use crate::history::{HistoryView, MoveHistory, PlayedMove};
+use crate::piece::{BoardUpdate, NewGame, Premove, Selection};
+
+pub struct AnalysisSession {
+    board: Board,
+    history: Vec<PlayedMove>,
+    game_status: GameStatusRes,
+    phase: GamePhase,
+    local_side: Option<PieceColor>,
+    clock: ChessClock,
+    pub branch_ply: usize
+}
+
+#[derive(Resource, Default)]
+pub struct Analysis(pub Option<AnalysisSession>);
+
+impl Analysis {
+    pub fn is_active(&self) -> bool {
+        self.0.is_some()
+    }
+
+    pub fn branch_ply(&self) -> Option<usize> {
+        self.0.as_ref().map(|session| session.branch_ply)
+    }
+}
+
+pub fn analysing(analysis: Res<Analysis>) -> bool {
+    analysis.is_active()
+}
+
+#[derive(Event)]
+pub struct ToggleAnalysis;
+
+pub fn analysis_shortcut(keyboard: Res<ButtonInput<KeyCode>>, mut toggle_writer: EventWriter<ToggleAnalysis>) {
+    if keyboard.just_pressed(KeyCode::KeyX) {
+        toggle_writer.send(ToggleAnalysis);
+    }
+}
+
+pub fn toggle_analysis(
+    mut toggle_listener: EventReader<ToggleAnalysis>,
+    phase: Res<State<GamePhase>>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut analysis: ResMut<Analysis>,
+    mut board: ResMut<BoardResource>,
+    mut history: ResMut<MoveHistory>,
+    mut game_status: ResMut<GameStatusRes>,
+    mut local_side: ResMut<LocalSide>,
+    mut clock: ResMut<ChessClock>,
+    mut view: ResMut<HistoryView>,
+    mut selection: ResMut<Selection>,
+    mut premove: ResMut<Premove>,
+    mut board_update_writer: EventWriter<BoardUpdate>
+) {
+    if toggle_listener.read().count() == 0 { return };
+    if matches!(phase.get(), GamePhase::Loading | GamePhase::PromotionPending) { return };
+    match analysis.0.take() {
+        Some(session) => {
+            board.0 = session.board;
+            history.0 = session.history;
+            *game_status = session.game_status;
+            local_side.0 = session.local_side;
+            *clock = session.clock;
+            next_phase.set(session.phase);
+        }
+        None => {
+            let branch_ply = view.ply.unwrap_or(history.0.len());
+            let explored = view.displayed(&board.0).clone();
+            analysis.0 = Some(AnalysisSession {
+                board: std::mem::replace(&mut board.0, explored),
+                history: history.0.clone(),
+                game_status: game_status.clone(),
+                phase: *phase.get(),
+                local_side: local_side.0.take(),
+                clock: std::mem::take(&mut *clock),
+                branch_ply
+            });
+            history.0.truncate(branch_ply);
+            next_phase.set(GamePhase::Playing);
+        }
+    }
+    view.go_live();
+    selection.0 = None;
+    premove.0 = None;
+    board_update_writer.send(BoardUpdate::resync());
+}
+
+pub fn discard_analysis(mut new_game_listener: EventReader<NewGame>, mut analysis: ResMut<Analysis>, mut local_side: ResMut<LocalSide>) {
+    if new_game_listener.read().count() == 0 { return };
+    let Some(session) = analysis.0.take() else { return };
+    local_side.0 = session.local_side;
+}
+
+#[derive(Component)]
+pub struct AnalysisBanner;
+
+pub fn spawn_analysis_banner(mut commands: Commands) {
+    commands.spawn((ButtonBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(40.0),
+            left: Val::Percent(30.0),
+            padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+            display: Display::None,
+            ..default()
+        },
+        background_color: Color::rgba(0.35, 0.15, 0.5, 0.85).into(),
+        z_index: ZIndex::Global(15),
+        ..default()
+    }, AnalysisBanner)).with_children(|banner| {
+        banner.spawn(TextBundle::from_section(
+            "Analysis - moves are not part of the game. Back to game (X)",
+            TextStyle { font_size: 18.0, color: Color::WHITE, ..default() }
+        ));
+    });
+}
+
+pub fn update_analysis_banner(analysis: Res<Analysis>, mut banner_query: Query<&mut Style, With<AnalysisBanner>>) {
+    for mut style in banner_query.iter_mut() {
+        style.display = if analysis.is_active() { Display::Flex } else { Display::None };
+    }
+}
+
+pub fn analysis_banner_clicks(
+    banner_query: Query<&Interaction, (Changed<Interaction>, With<AnalysisBanner>)>,
+    mut toggle_writer: EventWriter<ToggleAnalysis>
+) {
+    if banner_query.iter().any(|interaction| *interaction == Interaction::Pressed) {
+        toggle_writer.send(ToggleAnalysis);
+    }
+}
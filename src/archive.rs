@@ -0,0 +1,98 @@
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use bevy::app::AppExit;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::tasks::IoTaskPool;
+use chess_logic::logic::GameStatus;
+use crate::board::{GameOverEvent, GameStatusRes};
+use crate::clock::ChessClock;
+use crate::history::{game_pgn, MoveHistory, StartingPosition};
+use crate::piece::MovePlayed;
+use crate::players::GameMetadata;
+use crate::settings::Settings;
+use crate::storage::FileStorage;
+
+const ARCHIVE_FILE: &str = "archive.pgn";
+const MAX_NAME_LENGTH: usize = 32;
+
+#[derive(Resource, Default)]
+pub struct GameArchive {
+    recorded: bool
+}
+
+#[derive(SystemParam)]
+pub struct ArchivedGame<'w> {
+    settings: Res<'w, Settings>,
+    history: Res<'w, MoveHistory>,
+    start: Res<'w, StartingPosition>,
+    metadata: Res<'w, GameMetadata>,
+    clock: Res<'w, ChessClock>,
+    archive: ResMut<'w, GameArchive>
+}
+
+impl ArchivedGame<'_> {
+    fn directory(&self) -> PathBuf {
+        self.settings.archive_directory.as_ref().map_or_else(|| FileStorage::default_root().join("archive"), PathBuf::from)
+    }
+
+    fn file_name(&self) -> String {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or_default();
+        format!("{}-{}-vs-{}.pgn", timestamp, sanitize_name(&self.metadata.white.name), sanitize_name(&self.metadata.black.name))
+    }
+
+    // Marks the game as recorded and hands back what to write, or None when there is nothing new to archive
+    fn take(&mut self, status: GameStatus) -> Option<(PathBuf, String, String)> {
+        if !self.settings.archive_games || self.archive.recorded || self.history.0.is_empty() { return None };
+        self.archive.recorded = true;
+        Some((self.directory(), self.file_name(), game_pgn(&self.history, &self.start, &self.metadata, status, &self.clock)))
+    }
+}
+
+fn sanitize_name(name: &str) -> String {
+    let sanitized: String = name.trim().chars()
+        .filter_map(|character| match character {
+            character if character.is_ascii_alphanumeric() || character == '-' || character == '_' => Some(character),
+            character if character.is_whitespace() => Some('_'),
+            _ => None
+        })
+        .take(MAX_NAME_LENGTH)
+        .collect();
+    if sanitized.is_empty() { String::from("player") } else { sanitized }
+}
+
+fn write_archive(directory: &Path, name: &str, pgn: &str) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(directory)?;
+    let path = directory.join(name);
+    fs::write(&path, pgn)?;
+    let mut archive = OpenOptions::new().create(true).append(true).open(directory.join(ARCHIVE_FILE))?;
+    write!(archive, "{}\n\n", pgn)?;
+    Ok(path)
+}
+
+fn save_game(directory: PathBuf, name: String, pgn: String) {
+    match write_archive(&directory, &name, &pgn) {
+        Ok(path) => info!("archived game to {}", path.display()),
+        Err(error) => warn!("failed to archive game to {}: {}", directory.display(), error)
+    }
+}
+
+pub fn reset_game_archive(mut move_played_listener: EventReader<MovePlayed>, mut archive: ResMut<GameArchive>) {
+    if move_played_listener.read().count() == 0 { return };
+    archive.recorded = false;
+}
+
+pub fn archive_finished_game(mut game_over_listener: EventReader<GameOverEvent>, mut game: ArchivedGame) {
+    let Some(game_over) = game_over_listener.read().last() else { return };
+    let Some((directory, name, pgn)) = game.take(game_over.result) else { return };
+    IoTaskPool::get().spawn(async move { save_game(directory, name, pgn) }).detach();
+}
+
+// The task pools are shutting down along with the app, so the last write happens on the spot
+pub fn archive_unfinished_game(mut exit_listener: EventReader<AppExit>, game_status: Res<GameStatusRes>, mut game: ArchivedGame) {
+    if exit_listener.read().count() == 0 || !matches!(game_status.status(), GameStatus::Ongoing) { return };
+    let Some((directory, name, pgn)) = game.take(GameStatus::Ongoing) else { return };
+    save_game(directory, name, pgn);
+}
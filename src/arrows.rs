@@ -0,0 +1,137 @@
+use bevy::ecs::system::SystemParam;
+use bevy::math::primitives::Triangle2d;
+use bevy::prelude::*;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+use chess_logic::logic::Coordinate;
+use crate::board::WorldCursor;
+use crate::config::ChessConfig;
+use crate::highlight::HighlightPalette;
+use crate::piece::MovePlayed;
+
+const ARROW_Z: f32 = 5.0;
+
+#[derive(Component)]
+pub struct Arrow;
+
+#[derive(Component)]
+pub struct AnnotationArrow;
+
+#[derive(Resource, Default)]
+pub struct Annotations {
+    pub arrows: Vec<(Coordinate, Coordinate)>
+}
+
+impl Annotations {
+    pub fn toggle_arrow(&mut self, from: Coordinate, to: Coordinate) {
+        match self.arrows.iter().position(|arrow| *arrow == (from, to)) {
+            Some(index) => { self.arrows.remove(index); }
+            None => self.arrows.push((from, to))
+        }
+    }
+}
+
+fn arrow_path(from: Coordinate, to: Coordinate) -> Vec<Coordinate> {
+    let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+    match (dx.abs(), dy.abs()) {
+        (1, 2) => vec![from, Coordinate(from.0, to.1), to],
+        (2, 1) => vec![from, Coordinate(to.0, from.1), to],
+        _ => vec![from, to]
+    }
+}
+
+#[derive(SystemParam)]
+pub struct ArrowPainter<'w, 's> {
+    commands: Commands<'w, 's>,
+    meshes: ResMut<'w, Assets<Mesh>>,
+    materials: ResMut<'w, Assets<ColorMaterial>>,
+    config: Res<'w, ChessConfig>,
+    palette: Res<'w, HighlightPalette>
+}
+
+impl<'w, 's> ArrowPainter<'w, 's> {
+    pub fn commands(&mut self) -> &mut Commands<'w, 's> {
+        &mut self.commands
+    }
+
+    pub fn palette(&self) -> HighlightPalette {
+        *self.palette
+    }
+
+    pub fn spawn_arrow(&mut self, from: Coordinate, to: Coordinate, color: Color) -> Entity {
+        self.spawn_sized_arrow(from, to, color, 1.0)
+    }
+
+    pub fn spawn_sized_arrow(&mut self, from: Coordinate, to: Coordinate, color: Color, scale: f32) -> Entity {
+        let width = self.config.square_size * 0.15 * scale;
+        let head_length = self.config.square_size * 0.35 * scale;
+        let head_width = self.config.square_size * 0.4 * scale;
+        let points: Vec<Vec2> = arrow_path(from, to).into_iter().map(|square| self.config.square_to_vector(square)).collect();
+
+        let head = self.meshes.add(Triangle2d::new(Vec2::new(0.0, head_width / 2.0), Vec2::new(0.0, -head_width / 2.0), Vec2::new(head_length, 0.0)));
+        let material = self.materials.add(ColorMaterial::from(color));
+        let segments = points.len() - 1;
+        self.commands.spawn((SpatialBundle::from_transform(Transform::from_xyz(0.0, 0.0, ARROW_Z)), Arrow)).with_children(|parent| {
+            for (index, segment) in points.windows(2).enumerate() {
+                let direction = (segment[1] - segment[0]).normalize_or_zero();
+                let start = if index == 0 { segment[0] } else { segment[0] - direction * width / 2.0 };
+                let end = if index + 1 == segments { segment[1] - direction * head_length } else { segment[1] };
+                let rotation = Quat::from_rotation_z(direction.y.atan2(direction.x));
+                parent.spawn(SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(Vec2::new(start.distance(end), width)),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(Vec3::from(((start + end) / 2.0, 0.0))).with_rotation(rotation),
+                    ..default()
+                });
+                if index + 1 == segments {
+                    parent.spawn(MaterialMesh2dBundle {
+                        mesh: Mesh2dHandle(head.clone()),
+                        material: material.clone(),
+                        transform: Transform::from_translation(Vec3::from((end, 0.0))).with_rotation(rotation),
+                        ..default()
+                    });
+                }
+            }
+        }).id()
+    }
+}
+
+pub fn annotate_with_arrows(
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    cursor: Option<Res<WorldCursor>>,
+    mut move_played_listener: EventReader<MovePlayed>,
+    mut pressed_square: Local<Option<Coordinate>>,
+    mut annotations: ResMut<Annotations>
+) {
+    let square = cursor.and_then(|cursor| cursor.square);
+    let moved = move_played_listener.read().count() > 0;
+    if (moved || mouse_button.just_pressed(MouseButton::Left)) && !annotations.arrows.is_empty() {
+        annotations.arrows.clear();
+    }
+    if mouse_button.just_pressed(MouseButton::Right) && !mouse_button.pressed(MouseButton::Left) {
+        *pressed_square = square;
+    }
+    if mouse_button.just_released(MouseButton::Right) {
+        if let (Some(from), Some(to)) = (pressed_square.take(), square) {
+            if from != to {
+                annotations.toggle_arrow(from, to);
+            }
+        }
+    }
+}
+
+pub fn update_annotation_arrows(
+    annotations: Res<Annotations>,
+    arrows: Query<Entity, With<AnnotationArrow>>,
+    mut painter: ArrowPainter
+) {
+    for entity in arrows.iter() {
+        painter.commands().entity(entity).despawn_recursive();
+    }
+    for (from, to) in annotations.arrows.iter() {
+        let arrow = painter.spawn_arrow(*from, *to, painter.palette().arrow());
+        painter.commands().entity(arrow).insert(AnnotationArrow);
+    }
+}
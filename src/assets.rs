@@ -0,0 +1,224 @@
+use std::collections::{HashMap, HashSet};
+use bevy::prelude::*;
+use bevy::asset::AssetLoadFailedEvent;
+use chess_logic::logic::{PieceColor, PieceKind};
+use crate::audio::SoundAssets;
+use crate::board::GamePhase;
+use crate::settings::Settings;
+
+pub const PIECE_KINDS: [PieceKind; 6] = [PieceKind::PAWN, PieceKind::ROOK, PieceKind::KNIGHT, PieceKind::BISHOP, PieceKind::KING, PieceKind::QUEEN];
+pub const SPRITE_SHEET_PATH: &str = "pieces.png";
+const SPRITE_SHEET_COLUMNS: [PieceKind; 6] = [PieceKind::KING, PieceKind::QUEEN, PieceKind::BISHOP, PieceKind::KNIGHT, PieceKind::ROOK, PieceKind::PAWN];
+pub const PIECE_SETS_DIR: &str = "pieces";
+pub const DEFAULT_PIECE_SET: &str = "default";
+
+#[derive(Clone, Debug)]
+pub struct PieceSetInfo {
+    pub name: String,
+    pub unavailable: Option<String>
+}
+
+#[derive(Resource, Default)]
+pub struct PieceSets(pub Vec<PieceSetInfo>);
+
+impl PieceSets {
+    pub fn discover() -> Self {
+        let mut sets = vec![PieceSetInfo {name: String::from(DEFAULT_PIECE_SET), unavailable: None}];
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let root = bevy::asset::io::file::FileAssetReader::get_base_path().join("assets").join(PIECE_SETS_DIR);
+            let mut found: Vec<PieceSetInfo> = std::fs::read_dir(root).into_iter().flatten().flatten()
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| {
+                    let missing: Vec<String> = [PieceColor::WHITE, PieceColor::BLACK].into_iter()
+                        .flat_map(|color| PIECE_KINDS.map(|kind| format!("{}_{}.png", color, kind)))
+                        .filter(|file| !entry.path().join(file).exists())
+                        .collect();
+                    let unavailable = match missing.len() {
+                        0 => None,
+                        12 => Some(String::from("no piece textures")),
+                        _ => Some(format!("missing {}", missing.join(", ")))
+                    };
+                    PieceSetInfo {name: entry.file_name().to_string_lossy().into_owned(), unavailable}
+                })
+                .collect();
+            found.sort_by(|a, b| a.name.cmp(&b.name));
+            sets.extend(found);
+        }
+        PieceSets(sets)
+    }
+
+    pub fn choose<'a>(&self, wanted: &'a str) -> &'a str {
+        match self.0.iter().find(|set| set.name == wanted) {
+            Some(PieceSetInfo {unavailable: None, ..}) => wanted,
+            Some(PieceSetInfo {unavailable: Some(reason), ..}) => {
+                warn!("piece set {} is unavailable ({}), using {}", wanted, reason, DEFAULT_PIECE_SET);
+                DEFAULT_PIECE_SET
+            }
+            None => {
+                warn!("unknown piece set {}, using {}", wanted, DEFAULT_PIECE_SET);
+                DEFAULT_PIECE_SET
+            }
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct PieceSetChanged;
+
+#[derive(Resource, Default)]
+pub struct PieceAssets {
+    pub set: String,
+    textures: HashMap<(PieceColor, PieceKind), Handle<Image>>,
+    sheet: Option<Handle<Image>>,
+    layout: Option<Handle<TextureAtlasLayout>>,
+    loaded: HashSet<AssetId<Image>>,
+    missing: HashSet<(PieceColor, PieceKind)>
+}
+
+impl PieceAssets {
+    pub fn load(set: &str, asset_server: &AssetServer) -> Self {
+        let mut piece_assets = PieceAssets {set: set.to_string(), ..default()};
+        if set == DEFAULT_PIECE_SET && sprite_sheet_exists() {
+            piece_assets.sheet = Some(asset_server.load(SPRITE_SHEET_PATH));
+        } else {
+            piece_assets.request_textures(asset_server);
+        }
+        piece_assets
+    }
+
+    pub fn texture_path(&self, color: PieceColor, kind: PieceKind) -> String {
+        match self.set.as_str() {
+            DEFAULT_PIECE_SET => format!("{}_{}.png", color, kind),
+            set => format!("{}/{}/{}_{}.png", PIECE_SETS_DIR, set, color, kind)
+        }
+    }
+
+    fn sheet_index(color: PieceColor, kind: PieceKind) -> usize {
+        let row = if color == PieceColor::WHITE { 0 } else { 1 };
+        row * SPRITE_SHEET_COLUMNS.len() + SPRITE_SHEET_COLUMNS.iter().position(|column| *column == kind).unwrap()
+    }
+
+    pub fn get(&self, color: PieceColor, kind: PieceKind) -> Handle<Image> {
+        match (&self.sheet, &self.layout) {
+            (Some(sheet), Some(_)) => sheet.clone(),
+            _ => self.textures.get(&(color, kind)).cloned().unwrap_or_default()
+        }
+    }
+
+    pub fn is_missing(&self, color: PieceColor, kind: PieceKind) -> bool {
+        self.layout.is_none() && self.missing.contains(&(color, kind))
+    }
+
+    pub fn atlas(&self, color: PieceColor, kind: PieceKind) -> Option<TextureAtlas> {
+        self.layout.as_ref().map(|layout| TextureAtlas {layout: layout.clone(), index: Self::sheet_index(color, kind)})
+    }
+
+    pub fn sprite_rect(&self, color: PieceColor, kind: PieceKind, layouts: &Assets<TextureAtlasLayout>) -> Option<Rect> {
+        let layout = layouts.get(self.layout.as_ref()?)?;
+        layout.textures.get(Self::sheet_index(color, kind)).copied()
+    }
+
+    pub fn apply(&self, color: PieceColor, kind: PieceKind, texture: &mut Handle<Image>, atlas: Option<Mut<TextureAtlas>>) {
+        *texture = self.get(color, kind);
+        if let Some(mut atlas) = atlas {
+            atlas.index = Self::sheet_index(color, kind);
+        }
+    }
+
+    fn request_textures(&mut self, asset_server: &AssetServer) {
+        for color in [PieceColor::WHITE, PieceColor::BLACK] {
+            for kind in PIECE_KINDS {
+                self.textures.insert((color, kind), asset_server.load(self.texture_path(color, kind)));
+            }
+        }
+    }
+}
+
+fn sprite_sheet_exists() -> bool {
+    #[cfg(not(target_arch = "wasm32"))]
+    return bevy::asset::io::file::FileAssetReader::get_base_path().join("assets").join(SPRITE_SHEET_PATH).exists();
+    #[cfg(target_arch = "wasm32")]
+    return false;
+}
+
+pub fn load_piece_assets(mut commands: Commands, asset_server: Res<AssetServer>, settings: Res<Settings>) {
+    let sets = PieceSets::discover();
+    commands.insert_resource(PieceAssets::load(sets.choose(&settings.piece_set), &asset_server));
+    commands.insert_resource(sets);
+}
+
+pub fn switch_piece_set(
+    settings: Res<Settings>,
+    sets: Res<PieceSets>,
+    asset_server: Res<AssetServer>,
+    mut piece_assets: ResMut<PieceAssets>,
+    mut set_changed_writer: EventWriter<PieceSetChanged>
+) {
+    let wanted = sets.choose(&settings.piece_set);
+    if piece_assets.set == wanted { return };
+    *piece_assets = PieceAssets::load(wanted, &asset_server);
+    set_changed_writer.send(PieceSetChanged);
+}
+
+pub fn track_piece_asset_failures(
+    mut failed_listener: EventReader<AssetLoadFailedEvent<Image>>,
+    asset_server: Res<AssetServer>,
+    mut piece_assets: ResMut<PieceAssets>
+) {
+    for failed in failed_listener.read() {
+        if piece_assets.sheet.as_ref().is_some_and(|sheet| sheet.id() == failed.id) {
+            warn!("failed to load piece sprite sheet assets/{}, falling back to individual textures", SPRITE_SHEET_PATH);
+            piece_assets.sheet = None;
+            piece_assets.request_textures(&asset_server);
+            continue;
+        }
+        let Some(key) = piece_assets.textures.iter()
+            .find(|(_, handle)| handle.id() == failed.id)
+            .map(|(key, _)| *key) else { continue };
+        warn!("missing piece texture, expected it at assets/{} ({})", piece_assets.texture_path(key.0, key.1), failed.error);
+        piece_assets.missing.insert(key);
+    }
+}
+
+pub fn track_piece_asset_loads(
+    mut loaded_listener: EventReader<AssetEvent<Image>>,
+    mut piece_assets: ResMut<PieceAssets>,
+    images: Res<Assets<Image>>,
+    mut layouts: ResMut<Assets<TextureAtlasLayout>>,
+    mut set_changed_writer: EventWriter<PieceSetChanged>
+) {
+    for event in loaded_listener.read() {
+        let AssetEvent::LoadedWithDependencies { id } = event else { continue };
+        if piece_assets.sheet.as_ref().is_some_and(|sheet| sheet.id() == *id) {
+            let size = images.get(*id).unwrap().size_f32();
+            let tile = Vec2::new(size.x / SPRITE_SHEET_COLUMNS.len() as f32, size.y / 2.0);
+            piece_assets.layout = Some(layouts.add(TextureAtlasLayout::from_grid(tile, SPRITE_SHEET_COLUMNS.len(), 2, None, None)));
+            set_changed_writer.send(PieceSetChanged);
+        }
+        piece_assets.loaded.insert(*id);
+    }
+}
+
+pub fn wait_for_assets(
+    piece_assets: Res<PieceAssets>,
+    sound_assets: Res<SoundAssets>,
+    asset_server: Res<AssetServer>,
+    mut next_phase: ResMut<NextState<GamePhase>>
+) {
+    if !sound_assets.settled(&asset_server) {
+        return;
+    }
+    if piece_assets.layout.is_some() {
+        next_phase.set(GamePhase::Playing);
+        return;
+    }
+    if piece_assets.textures.is_empty() {
+        return;
+    }
+    let settled = piece_assets.textures.iter()
+        .all(|(key, handle)| piece_assets.loaded.contains(&handle.id()) || piece_assets.missing.contains(key));
+    if settled {
+        next_phase.set(GamePhase::Playing);
+    }
+}
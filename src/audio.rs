@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use bevy::prelude::*;
+use bevy::asset::{AssetLoadFailedEvent, LoadState};
+use bevy::audio::Volume;
+use bevy::ui::RelativeCursorPosition;
+use crate::board::{BoardResource, GameOverEvent};
+use crate::clock::ChessClock;
+use crate::piece::MovePlayed;
+use crate::settings::Settings;
+
+const LOW_TIME_SECONDS: u64 = 10;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum SoundCategory {
+    Moves,
+    Ui,
+    Alerts
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum SoundKind {
+    Move,
+    Capture,
+    Castle,
+    Check,
+    Promotion,
+    GameEnd,
+    LowTime,
+    Click
+}
+
+impl SoundKind {
+    pub const ALL: [SoundKind; 8] = [SoundKind::Move, SoundKind::Capture, SoundKind::Castle, SoundKind::Check, SoundKind::Promotion, SoundKind::GameEnd, SoundKind::LowTime, SoundKind::Click];
+
+    pub fn path(&self) -> &'static str {
+        match self {
+            SoundKind::Move => "sounds/move.ogg",
+            SoundKind::Capture => "sounds/capture.ogg",
+            SoundKind::Castle => "sounds/castle.ogg",
+            SoundKind::Check => "sounds/check.ogg",
+            SoundKind::Promotion => "sounds/promotion.ogg",
+            SoundKind::GameEnd => "sounds/game_end.ogg",
+            SoundKind::LowTime => "sounds/low_time.ogg",
+            SoundKind::Click => "sounds/click.ogg"
+        }
+    }
+
+    pub fn category(&self) -> SoundCategory {
+        match self {
+            SoundKind::Move | SoundKind::Capture | SoundKind::Castle | SoundKind::Promotion => SoundCategory::Moves,
+            SoundKind::Check | SoundKind::GameEnd | SoundKind::LowTime => SoundCategory::Alerts,
+            SoundKind::Click => SoundCategory::Ui
+        }
+    }
+}
+
+pub fn channel_volume(settings: &Settings, channel: Option<SoundCategory>) -> f32 {
+    match channel {
+        None => settings.volume,
+        Some(SoundCategory::Moves) => settings.move_volume,
+        Some(SoundCategory::Ui) => settings.ui_volume,
+        Some(SoundCategory::Alerts) => settings.alert_volume
+    }
+}
+
+fn channel_volume_mut(settings: &mut Settings, channel: Option<SoundCategory>) -> &mut f32 {
+    match channel {
+        None => &mut settings.volume,
+        Some(SoundCategory::Moves) => &mut settings.move_volume,
+        Some(SoundCategory::Ui) => &mut settings.ui_volume,
+        Some(SoundCategory::Alerts) => &mut settings.alert_volume
+    }
+}
+
+pub fn playback_settings(settings: &Settings, kind: SoundKind) -> Option<PlaybackSettings> {
+    if settings.muted { return None };
+    let volume = (channel_volume(settings, None) * channel_volume(settings, Some(kind.category()))).clamp(0.0, 1.0);
+    if volume <= 0.0 { return None };
+    Some(PlaybackSettings::DESPAWN.with_volume(Volume::new(volume)))
+}
+
+#[derive(Resource, Default)]
+pub struct SoundAssets {
+    sounds: HashMap<SoundKind, Handle<AudioSource>>
+}
+
+impl SoundAssets {
+    pub fn settled(&self, asset_server: &AssetServer) -> bool {
+        self.sounds.values().all(|handle| matches!(asset_server.load_state(handle), LoadState::Loaded | LoadState::Failed))
+    }
+}
+
+#[derive(Event, Copy, Clone)]
+pub struct PlaySound(pub SoundKind);
+
+pub fn load_sound_assets(mut commands: Commands, asset_server: Res<AssetServer>) {
+    let sounds = SoundKind::ALL.into_iter().map(|kind| (kind, asset_server.load(kind.path()))).collect();
+    commands.insert_resource(SoundAssets {sounds});
+}
+
+pub fn track_sound_asset_failures(mut failed_listener: EventReader<AssetLoadFailedEvent<AudioSource>>, mut sound_assets: ResMut<SoundAssets>) {
+    for failed in failed_listener.read() {
+        let Some(kind) = sound_assets.sounds.iter()
+            .find(|(_, handle)| handle.id() == failed.id)
+            .map(|(kind, _)| *kind) else { continue };
+        warn!("missing sound, expected it at assets/{} ({})", kind.path(), failed.error);
+        sound_assets.sounds.remove(&kind);
+    }
+}
+
+pub fn queue_move_sounds(
+    mut move_played_listener: EventReader<MovePlayed>,
+    mut game_over_listener: EventReader<GameOverEvent>,
+    mut sound_writer: EventWriter<PlaySound>
+) {
+    let game_over = game_over_listener.read().count() > 0;
+    let Some(move_played) = move_played_listener.read().last() else {
+        if game_over {
+            sound_writer.send(PlaySound(SoundKind::GameEnd));
+        }
+        return;
+    };
+    let record = &move_played.record;
+    let kind = if game_over {
+        SoundKind::GameEnd
+    } else if move_played.after.check {
+        SoundKind::Check
+    } else if record.promotion.is_some() {
+        SoundKind::Promotion
+    } else if record.rook_move.is_some() {
+        SoundKind::Castle
+    } else if record.captured.is_some() {
+        SoundKind::Capture
+    } else {
+        SoundKind::Move
+    };
+    sound_writer.send(PlaySound(kind));
+}
+
+pub fn queue_low_time_tick(
+    clock: Res<ChessClock>,
+    board: Res<BoardResource>,
+    mut last_second: Local<Option<u64>>,
+    mut sound_writer: EventWriter<PlaySound>
+) {
+    if clock.control.is_none() || !clock.is_running() {
+        *last_second = None;
+        return;
+    }
+    let remaining = clock.remaining(board.0.on_move);
+    if remaining.is_zero() || remaining.as_secs() >= LOW_TIME_SECONDS {
+        *last_second = None;
+        return;
+    }
+    let second = remaining.as_secs();
+    if *last_second != Some(second) {
+        *last_second = Some(second);
+        sound_writer.send(PlaySound(SoundKind::LowTime));
+    }
+}
+
+pub fn play_menu_click(mut sound_writer: EventWriter<PlaySound>) {
+    sound_writer.send(PlaySound(SoundKind::Click));
+}
+
+pub fn play_sounds(
+    mut commands: Commands,
+    mut sound_listener: EventReader<PlaySound>,
+    sound_assets: Res<SoundAssets>,
+    settings: Res<Settings>
+) {
+    for PlaySound(kind) in sound_listener.read() {
+        let Some(source) = sound_assets.sounds.get(kind) else { continue };
+        let Some(playback) = playback_settings(&settings, *kind) else { continue };
+        commands.spawn(AudioBundle {source: source.clone(), settings: playback});
+    }
+}
+
+pub fn toggle_mute(keyboard: Res<ButtonInput<KeyCode>>, mut settings: ResMut<Settings>) {
+    if keyboard.just_pressed(KeyCode::KeyM) {
+        settings.muted = !settings.muted;
+    }
+}
+
+#[derive(Component)]
+pub struct VolumeSlider(pub Option<SoundCategory>);
+
+#[derive(Component)]
+pub struct VolumeSliderFill(pub Option<SoundCategory>);
+
+pub fn spawn_volume_sliders(parent: &mut ChildBuilder) {
+    let channels = [(None, "Volume"), (Some(SoundCategory::Moves), "Moves"), (Some(SoundCategory::Ui), "Interface"), (Some(SoundCategory::Alerts), "Alerts")];
+    for (channel, label) in channels {
+        parent.spawn(NodeBundle {
+            style: Style {
+                width: Val::Px(360.0),
+                margin: UiRect::top(Val::Px(8.0)),
+                align_items: AlignItems::Center,
+                column_gap: Val::Px(8.0),
+                ..default()
+            },
+            ..default()
+        }).with_children(|row| {
+            row.spawn(TextBundle::from_section(label, TextStyle { font_size: 20.0, color: Color::WHITE, ..default() })
+                .with_style(Style { width: Val::Px(100.0), ..default() }));
+            row.spawn((NodeBundle {
+                style: Style {
+                    flex_grow: 1.0,
+                    height: Val::Px(14.0),
+                    ..default()
+                },
+                background_color: Color::rgb(0.2, 0.2, 0.25).into(),
+                ..default()
+            }, Interaction::default(), RelativeCursorPosition::default(), VolumeSlider(channel))).with_children(|track| {
+                track.spawn((NodeBundle {
+                    style: Style {
+                        width: Val::Percent(0.0),
+                        height: Val::Percent(100.0),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.55, 0.6, 0.8).into(),
+                    ..default()
+                }, VolumeSliderFill(channel)));
+            });
+        });
+    }
+}
+
+pub fn drag_volume_sliders(slider_query: Query<(&Interaction, &RelativeCursorPosition, &VolumeSlider)>, mut settings: ResMut<Settings>) {
+    for (interaction, cursor, VolumeSlider(channel)) in slider_query.iter() {
+        if *interaction != Interaction::Pressed { continue };
+        let Some(position) = cursor.normalized else { continue };
+        let value = position.x.clamp(0.0, 1.0);
+        if (channel_volume(&settings, *channel) - value).abs() > 0.005 {
+            *channel_volume_mut(&mut settings, *channel) = value;
+        }
+    }
+}
+
+pub fn update_volume_sliders(settings: Res<Settings>, mut fill_query: Query<(&mut Style, &mut BackgroundColor, &VolumeSliderFill)>) {
+    for (mut style, mut background, VolumeSliderFill(channel)) in fill_query.iter_mut() {
+        style.width = Val::Percent(channel_volume(&settings, *channel) * 100.0);
+        *background = if settings.muted { Color::GRAY } else { Color::rgb(0.55, 0.6, 0.8) }.into();
+    }
+}
+
+#[derive(Component)]
+pub struct MuteIndicator;
+
+pub fn spawn_mute_indicator(mut commands: Commands) {
+    commands.spawn((TextBundle {
+        text: Text::from_section("muted (M)", TextStyle { font_size: 14.0, color: Color::rgba(1.0, 1.0, 1.0, 0.5), ..default() }),
+        style: Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(8.0),
+            top: Val::Px(8.0),
+            ..default()
+        },
+        visibility: Visibility::Hidden,
+        ..default()
+    }, MuteIndicator));
+}
+
+pub fn update_mute_indicator(settings: Res<Settings>, mut indicator_query: Query<&mut Visibility, With<MuteIndicator>>) {
+    for mut visibility in indicator_query.iter_mut() {
+        *visibility = if settings.muted { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
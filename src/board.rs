@@ -1,10 +1,16 @@
-use bevy::math::Vec2;
-use bevy::prelude::{Camera, Color, Commands, Component, default, EventWriter, GlobalTransform, Query, Res, Resource, Sprite, SpriteBundle, Transform, Window, With};
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::math::{Quat, Vec2, Vec3};
+use bevy::prelude::{ButtonInput, Camera, Camera2d, Color, Commands, Component, default, DetectChanges, Event, EventReader, EventWriter, GlobalTransform, Interaction, KeyCode, MouseButton, NextState, OrthographicProjection, Query, Res, ResMut, Resource, Sprite, SpriteBundle, State, States, Text, Text2dBundle, TextStyle, Transform, Visibility, Window, With, Without};
+use bevy::render::camera::ScalingMode;
 use bevy::window::PrimaryWindow;
-use crate::logic::{Board, Coordinate, PieceColor};
-use crate::piece::BoardUpdate;
-
-pub const SQUARE_SIZE: f32 = 64.0;
+use chess_logic::logic::{Board, Coordinate, GameStatus, PieceColor, PieceKind};
+use crate::analysis::Analysis;
+use crate::config::ChessConfig;
+use crate::highlight::TileHighlight;
+use crate::history::{HistoryView, MoveHistory};
+use crate::piece::{BoardUpdate, PromotionSquare};
+use crate::settings::Settings;
+use crate::theme::{BoardTheme, luminance};
 
 #[derive(Component)]
 pub struct BoardTile {
@@ -12,10 +18,10 @@ pub struct BoardTile {
 }
 
 impl BoardTile {
-    pub fn get_color(&self) -> Color {
+    pub fn get_color(&self, theme: &BoardTheme) -> Color {
         match (self.square.0 + self.square.1) % 2 {
-            0 => Color::WHITE,
-            1 => Color::BLACK,
+            0 => theme.dark,
+            1 => theme.light,
             _ => unreachable!()
         }
     }
@@ -23,83 +29,492 @@ impl BoardTile {
 #[derive(Resource)]
 pub struct BoardResource(pub Board);
 
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GamePhase {
+    #[default]
+    Loading,
+    Playing,
+    PromotionPending,
+    GameOver
+}
+
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MenuState {
+    #[default]
+    Closed,
+    Open
+}
+
+#[derive(Resource, Default)]
+pub struct LocalSide(pub Option<PieceColor>);
+
+impl LocalSide {
+    pub fn controls(&self, color: PieceColor) -> bool {
+        self.0.map_or(true, |side| side == color)
+    }
+}
+
+#[derive(Resource)]
+pub struct BoardOrientation(pub PieceColor);
+
+impl Default for BoardOrientation {
+    fn default() -> Self {
+        BoardOrientation(PieceColor::WHITE)
+    }
+}
+
+#[derive(Resource, Clone)]
+pub struct GameStatusRes {
+    status: GameStatus,
+    checked: Option<PieceColor>,
+    checking_squares: Vec<Coordinate>
+}
+
+impl Default for GameStatusRes {
+    fn default() -> Self {
+        GameStatusRes {status: GameStatus::Ongoing, checked: None, checking_squares: Vec::new()}
+    }
+}
+
+impl GameStatusRes {
+    pub fn in_check(&self, color: PieceColor) -> bool {
+        self.checked == Some(color)
+    }
+
+    pub fn status(&self) -> GameStatus {
+        self.status
+    }
+
+    pub fn checking_squares(&self) -> &[Coordinate] {
+        &self.checking_squares
+    }
+}
+
+pub fn game_ongoing(game_status: Res<GameStatusRes>) -> bool {
+    game_status.status == GameStatus::Ongoing
+}
+
+#[derive(Event)]
+pub struct CheckEvent {
+    pub color: PieceColor,
+    pub king_square: Coordinate,
+    pub checking_squares: Vec<Coordinate>
+}
+
+#[derive(Event)]
+pub struct GameOverEvent {
+    pub result: GameStatus
+}
+
+pub fn update_game_status(
+    board: Res<BoardResource>,
+    analysis: Res<Analysis>,
+    phase: Res<State<GamePhase>>,
+    promotion_square: Res<PromotionSquare>,
+    mut board_update_listener: EventReader<BoardUpdate>,
+    mut game_status: ResMut<GameStatusRes>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut check_writer: EventWriter<CheckEvent>,
+    mut game_over_writer: EventWriter<GameOverEvent>
+) {
+    if board_update_listener.read().count() == 0 { return };
+    if promotion_square.0.is_some() { return };
+    let position = board.0.position_status();
+    game_status.checked = if position.check { Some(board.0.on_move) } else { None };
+    game_status.checking_squares = board.0.checking_squares(board.0.on_move);
+    let upcoming = next_phase.0.unwrap_or(*phase.get());
+    if upcoming == GamePhase::GameOver && position.status == GameStatus::Ongoing { return };
+    game_status.status = position.status;
+    if let Some(king) = board.0.find(board.0.on_move, PieceKind::KING).filter(|_| !game_status.checking_squares.is_empty()) {
+        check_writer.send(CheckEvent {color: king.color, king_square: king.square, checking_squares: game_status.checking_squares.clone()});
+    }
+    if game_status.status != GameStatus::Ongoing && upcoming != GamePhase::GameOver && !analysis.is_active() {
+        next_phase.set(GamePhase::GameOver);
+        game_over_writer.send(GameOverEvent {result: game_status.status});
+    }
+}
+
+#[derive(Event)]
+pub struct ResignRequested(pub PieceColor);
+
+pub fn resign_game(
+    mut resign_listener: EventReader<ResignRequested>,
+    mut game_status: ResMut<GameStatusRes>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut game_over_writer: EventWriter<GameOverEvent>
+) {
+    let Some(ResignRequested(color)) = resign_listener.read().next() else { return };
+    if game_status.status != GameStatus::Ongoing { return };
+    game_status.status = GameStatus::Resigned {winner: color.opposite()};
+    next_phase.set(GamePhase::GameOver);
+    game_over_writer.send(GameOverEvent {result: game_status.status});
+}
+
+#[derive(Event)]
+pub struct DrawAccepted;
+
+pub fn accept_draw(
+    mut draw_accepted_listener: EventReader<DrawAccepted>,
+    mut game_status: ResMut<GameStatusRes>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut game_over_writer: EventWriter<GameOverEvent>
+) {
+    if draw_accepted_listener.read().count() == 0 { return };
+    if game_status.status != GameStatus::Ongoing { return };
+    game_status.status = GameStatus::DrawAgreed;
+    next_phase.set(GamePhase::GameOver);
+    game_over_writer.send(GameOverEvent {result: game_status.status});
+}
+
+#[derive(Event)]
+pub struct DrawClaimed;
+
+pub fn claim_draw(
+    mut draw_claimed_listener: EventReader<DrawClaimed>,
+    board: Res<BoardResource>,
+    mut game_status: ResMut<GameStatusRes>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut game_over_writer: EventWriter<GameOverEvent>
+) {
+    if draw_claimed_listener.read().count() == 0 { return };
+    if game_status.status != GameStatus::Ongoing { return };
+    let Some(reason) = board.0.claimable_draw() else { return };
+    game_status.status = GameStatus::Draw {reason};
+    next_phase.set(GamePhase::GameOver);
+    game_over_writer.send(GameOverEvent {result: game_status.status});
+}
+
+#[derive(Event)]
+pub struct FlagFell(pub PieceColor);
+
+pub fn flag_game(
+    mut flag_listener: EventReader<FlagFell>,
+    mut game_status: ResMut<GameStatusRes>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut game_over_writer: EventWriter<GameOverEvent>
+) {
+    let Some(FlagFell(color)) = flag_listener.read().next() else { return };
+    if game_status.status != GameStatus::Ongoing { return };
+    game_status.status = GameStatus::TimeForfeit {winner: color.opposite()};
+    next_phase.set(GamePhase::GameOver);
+    game_over_writer.send(GameOverEvent {result: game_status.status});
+}
+
 #[derive(Component)]
 pub struct BoardOutline;
 
-pub fn spawn_board(mut commands: Commands, mut board_update_writer: EventWriter<BoardUpdate>) {
-    commands.insert_resource(BoardResource(Board::new()));
+#[derive(Component)]
+pub enum CoordinateLabel {
+    File(i8),
+    Rank(i8)
+}
+
+impl CoordinateLabel {
+    fn text(&self) -> String {
+        match self {
+            CoordinateLabel::File(file) => Coordinate(*file, 0).to_algebraic()[..1].to_string(),
+            CoordinateLabel::Rank(rank) => (rank + 1).to_string()
+        }
+    }
+
+    fn position(&self, config: &ChessConfig, orientation: PieceColor) -> Vec2 {
+        let margin = if orientation == PieceColor::WHITE { -0.75 } else { 7.75 };
+        match self {
+            CoordinateLabel::File(file) => Vec2::new(*file as f32, margin) * config.square_size,
+            CoordinateLabel::Rank(rank) => Vec2::new(margin, *rank as f32) * config.square_size
+        }
+    }
+}
+
+pub fn spawn_board(mut commands: Commands, config: Res<ChessConfig>, theme: Res<BoardTheme>) {
     for col in 0..8i8 {
         for row in 0..8i8 {
             let tile = BoardTile{square: (col, row)};
             commands.spawn((SpriteBundle{
-                transform: Transform::from_xyz(
-                    f32::from(tile.square.0) * SQUARE_SIZE,
-                    f32::from(tile.square.1) * SQUARE_SIZE,
-                    0.0
-                ),
+                transform: Transform::from_translation(Vec3::from((config.square_to_vector(Coordinate(col, row)), 0.0))),
                 sprite: Sprite {
-                    color: tile.get_color(),
-                    custom_size: Some(Vec2::new(SQUARE_SIZE, SQUARE_SIZE)),
+                    color: tile.get_color(&theme),
+                    custom_size: Some(Vec2::splat(config.square_size)),
                     ..default()
                 },
                 ..default()
-            }, tile));
+            }, tile, TileHighlight::default()));
         }
     }
     commands.spawn((SpriteBundle{
-        transform: Transform::from_xyz(
-            SQUARE_SIZE * 3.5,
-            SQUARE_SIZE * 3.5,
-            -1.0
-        ),
+        transform: Transform::from_translation(Vec3::from((config.board_center(), -1.0))),
         sprite: Sprite {
             color: Color::WHITE,
-            custom_size: Some(Vec2::new(SQUARE_SIZE*9.0, SQUARE_SIZE*9.0)),
+            custom_size: Some(Vec2::splat(config.square_size * 9.0)),
             ..default()
         },
         ..default()
     }, BoardOutline));
-    board_update_writer.send(BoardUpdate{});
+    for label in (0..8i8).map(CoordinateLabel::File).chain((0..8i8).map(CoordinateLabel::Rank)) {
+        commands.spawn((Text2dBundle {
+            text: Text::from_section(label.text(), TextStyle { font_size: config.square_size * 0.3, color: Color::BLACK, ..default() }),
+            ..default()
+        }, label));
+    }
+}
+
+pub fn update_coordinate_labels(
+    config: Res<ChessConfig>,
+    orientation: Res<BoardOrientation>,
+    settings: Res<Settings>,
+    mut label_query: Query<(&CoordinateLabel, &mut Transform, &mut Text, &mut Visibility)>
+) {
+    let angle = if orientation.0 == PieceColor::WHITE { 0.0 } else { std::f32::consts::PI };
+    for (label, mut transform, mut text, mut visibility) in label_query.iter_mut() {
+        *transform = Transform::from_translation(Vec3::from((label.position(&config, orientation.0), 0.5)))
+            .with_rotation(Quat::from_rotation_z(angle));
+        for section in text.sections.iter_mut() {
+            section.style.font_size = config.square_size * 0.3;
+        }
+        *visibility = if settings.show_coordinates { Visibility::Inherited } else { Visibility::Hidden };
+    }
+}
+
+pub fn apply_chess_config(
+    config: Res<ChessConfig>,
+    mut orientation: ResMut<BoardOrientation>,
+    mut tile_query: Query<(&BoardTile, &mut Transform, &mut Sprite), Without<BoardOutline>>,
+    mut outline_query: Query<(&mut Transform, &mut Sprite), With<BoardOutline>>
+) {
+    if !config.is_changed() { return };
+    for (tile, mut transform, mut sprite) in tile_query.iter_mut() {
+        transform.translation = Vec3::from((config.square_to_vector(Coordinate(tile.square.0, tile.square.1)), 0.0));
+        sprite.custom_size = Some(Vec2::splat(config.square_size));
+    }
+    for (mut transform, mut sprite) in outline_query.iter_mut() {
+        transform.translation = Vec3::from((config.board_center(), -1.0));
+        sprite.custom_size = Some(Vec2::splat(config.square_size * 9.0));
+    }
+    if orientation.0 != config.orientation {
+        orientation.0 = config.orientation;
+    }
 }
-pub fn update_outline(board: Res<BoardResource>, mut outline_query: Query<&mut Sprite, With<BoardOutline>>) {
-    let mut outline = outline_query.single_mut();
-    if board.0.has_moves(board.0.on_move) {
-        outline.color = if board.0.on_move == PieceColor::WHITE { Color::WHITE } else { Color::BLACK };
-    } else {
-        outline.color = Color::GRAY;
+pub fn update_outline(
+    board: Res<BoardResource>,
+    theme: Res<BoardTheme>,
+    game_status: Res<GameStatusRes>,
+    mut outline_query: Query<&mut Sprite, With<BoardOutline>>,
+    mut label_query: Query<&mut Text, With<CoordinateLabel>>
+) {
+    let Ok(mut outline) = outline_query.get_single_mut() else { return };
+    outline.color = match game_status.status {
+        GameStatus::Ongoing if board.0.on_move == PieceColor::WHITE => theme.outline_light,
+        GameStatus::Ongoing => theme.outline_dark,
+        GameStatus::Checkmate { .. } => Color::rgb(0.6, 0.1, 0.1),
+        GameStatus::Stalemate => Color::rgb(0.3, 0.4, 0.6),
+        _ => Color::GRAY
+    };
+    let label_color = if luminance(outline.color) < 0.5 { Color::WHITE } else { Color::BLACK };
+    for mut text in label_query.iter_mut() {
+        for section in text.sections.iter_mut() {
+            section.style.color = label_color;
+        }
     }
 }
-fn vector_to_square(vec: Vec2) -> Coordinate {
-    Coordinate((vec.x / SQUARE_SIZE + 0.5) as i8, (vec.y / SQUARE_SIZE + 0.5) as i8)
+pub fn side_name(color: PieceColor) -> &'static str {
+    match color {
+        PieceColor::WHITE => "White",
+        PieceColor::BLACK => "Black"
+    }
 }
 
-pub fn square_to_vector(square: Coordinate) -> Vec2 {
-    Vec2::new(square.0 as f32 * SQUARE_SIZE, square.1 as f32 * SQUARE_SIZE)
+pub fn describe_status(status: GameStatus, on_move: PieceColor, in_check: bool) -> String {
+    match status {
+        GameStatus::Ongoing if in_check => format!("{} to move - check!", side_name(on_move)),
+        GameStatus::Ongoing => format!("{} to move", side_name(on_move)),
+        GameStatus::Checkmate { winner } => format!("Checkmate - {} wins", side_name(winner)),
+        GameStatus::Stalemate => String::from("Stalemate"),
+        GameStatus::Resigned { winner } => format!("{} resigned - {} wins", side_name(winner.opposite()), side_name(winner)),
+        GameStatus::TimeForfeit { winner } => format!("{} ran out of time - {} wins", side_name(winner.opposite()), side_name(winner)),
+        GameStatus::DrawAgreed => String::from("Draw agreed"),
+        GameStatus::Draw { reason } => format!("Draw by {}", reason)
+    }
 }
+
+#[derive(Component)]
+pub struct StatusText;
+
+pub fn spawn_status_text(mut commands: Commands, config: Res<ChessConfig>) {
+    commands.spawn((Text2dBundle {
+        text: Text::from_section("", TextStyle { font_size: config.square_size * 0.35, color: Color::WHITE, ..default() }),
+        ..default()
+    }, StatusText));
+}
+
+pub fn update_status_text(
+    config: Res<ChessConfig>,
+    board: Res<BoardResource>,
+    game_status: Res<GameStatusRes>,
+    orientation: Res<BoardOrientation>,
+    view: Res<HistoryView>,
+    history: Res<MoveHistory>,
+    mut status_query: Query<(&mut Text, &mut Transform), With<StatusText>>
+) {
+    let label = match view.ply {
+        None => describe_status(game_status.status, board.0.on_move, game_status.in_check(board.0.on_move)),
+        Some(ply) => {
+            let shown = view.displayed(&board.0);
+            let position = ply.checked_sub(1).map(|last| history.0[last].after).unwrap_or_else(|| shown.position_status());
+            describe_status(position.status, shown.on_move, position.check)
+        }
+    };
+    let (offset, angle) = if orientation.0 == PieceColor::WHITE { (8.25, 0.0) } else { (-1.25, std::f32::consts::PI) };
+    for (mut text, mut transform) in status_query.iter_mut() {
+        text.sections[0].value = label.clone();
+        text.sections[0].style.font_size = config.square_size * 0.35;
+        *transform = Transform::from_translation(Vec3::new(config.board_center().x, offset * config.square_size, 0.5))
+            .with_rotation(Quat::from_rotation_z(angle));
+    }
+}
+
 #[derive(Resource)]
 pub struct WorldCursor {
     pub position: Vec2,
-    pub square: Coordinate,
-    pub square_center: Vec2
+    pub square: Option<Coordinate>,
+    pub square_center: Option<Vec2>
 }
 
 impl WorldCursor {
-    fn from_position(position: Vec2) -> Self {
-        let square = vector_to_square(position);
-        WorldCursor {position, square, square_center: square_to_vector(square)}
+    pub fn from_position(position: Vec2, config: &ChessConfig) -> Self {
+        let square = config.vector_to_square(position);
+        WorldCursor {position, square, square_center: square.map(|square| config.square_to_vector(square))}
+    }
+}
+
+#[derive(Component)]
+pub struct BoardCamera;
+
+#[derive(Resource, Default)]
+pub struct UiPointerCapture(pub bool);
+
+fn pointer_over_ui(ui_query: &Query<&Interaction>, capture: &UiPointerCapture) -> bool {
+    capture.0 || ui_query.iter().any(|interaction| *interaction != Interaction::None)
+}
+
+pub fn fit_board_camera(config: Res<ChessConfig>, mut camera_query: Query<&mut OrthographicProjection, With<BoardCamera>>) {
+    let frame_size = config.frame_size();
+    for mut projection in camera_query.iter_mut() {
+        let fitted = matches!(projection.scaling_mode, ScalingMode::AutoMin {min_width, min_height} if min_width == frame_size && min_height == frame_size);
+        if fitted { continue };
+        projection.scaling_mode = ScalingMode::AutoMin {min_width: frame_size, min_height: frame_size};
+    }
+}
+
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 4.0;
+
+pub fn board_camera_controls(
+    config: Res<ChessConfig>,
+    mut wheel_listener: EventReader<MouseWheel>,
+    mut motion_listener: EventReader<MouseMotion>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    ui_query: Query<&Interaction>,
+    capture: Res<UiPointerCapture>,
+    mut camera_query: Query<(&Camera, &GlobalTransform, &mut Transform, &mut OrthographicProjection), With<BoardCamera>>
+) {
+    let Ok(window) = window_query.get_single() else { return };
+    let Ok((camera, camera_global_transform, mut camera_transform, mut projection)) = camera_query.get_single_mut() else { return };
+
+    if keyboard.just_pressed(KeyCode::Home) && keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        camera_transform.translation = Vec3::from((config.board_center(), camera_transform.translation.z));
+        projection.scale = 1.0;
+        return;
+    }
+
+    // The egui panel may dock beside the board, so sizes and cursor positions are taken within the viewport
+    let viewport = camera.logical_viewport_rect();
+    let viewport_width = viewport.map_or(window.width(), |viewport| viewport.width());
+    let world_per_pixel = projection.area.width() / viewport_width;
+    let pan = motion_listener.read().fold(Vec2::ZERO, |total, motion| total + motion.delta);
+    if mouse.pressed(MouseButton::Middle) {
+        let rotation = camera_transform.rotation;
+        camera_transform.translation += rotation * Vec3::new(-pan.x, pan.y, 0.0) * world_per_pixel;
+    }
+
+    let steps: f32 = wheel_listener.read().filter(|_| !pointer_over_ui(&ui_query, &capture)).map(|wheel| match wheel.unit {
+        MouseScrollUnit::Line => wheel.y,
+        MouseScrollUnit::Pixel => wheel.y / 100.0
+    }).sum();
+    if steps == 0.0 { return };
+    let old_scale = projection.scale;
+    let new_scale = (old_scale * 0.9f32.powf(steps)).clamp(MIN_ZOOM, MAX_ZOOM);
+    let viewport_origin = viewport.map_or(Vec2::ZERO, |viewport| viewport.min);
+    let Some(anchor) = window.cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_global_transform, cursor - viewport_origin)) else {
+        projection.scale = new_scale;
+        return
+    };
+    let new_translation = zoom_translation(camera_transform.translation.truncate(), anchor, old_scale, new_scale);
+    camera_transform.translation = Vec3::from((new_translation, camera_transform.translation.z));
+    projection.scale = new_scale;
+}
+
+// Moves the camera so the world point under the cursor stays under it once the scale changes
+fn zoom_translation(translation: Vec2, anchor: Vec2, old_scale: f32, new_scale: f32) -> Vec2 {
+    anchor + (translation - anchor) * (new_scale / old_scale)
+}
+
+pub fn apply_board_orientation(orientation: Res<BoardOrientation>, mut camera_query: Query<&mut Transform, With<BoardCamera>>) {
+    let angle = if orientation.0 == PieceColor::WHITE { 0.0 } else { std::f32::consts::PI };
+    for mut transform in camera_query.iter_mut() {
+        transform.rotation = Quat::from_rotation_z(angle);
     }
 }
 
 pub fn update_board_cursor(
+    config: Res<ChessConfig>,
     window_query: Query<&Window, With<PrimaryWindow>>,
-    camera_query: Query<(&Camera, &GlobalTransform)>,
+    board_camera_query: Query<(&Camera, &GlobalTransform), With<BoardCamera>>,
+    camera_query: Query<(&Camera, &GlobalTransform), With<Camera2d>>,
+    ui_query: Query<&Interaction>,
+    capture: Res<UiPointerCapture>,
     mut commands: Commands
 ) {
-    let (camera, camera_transform) = camera_query.single();
-    let position = window_query.single().cursor_position()
-        .and_then(|cursor| camera.viewport_to_world(camera_transform, cursor)
-            .map(|ray| ray.origin.truncate()));
+    // Without a primary window there is nothing to track, so a cursor placed by hand stays put
+    let Ok(window) = window_query.get_single() else { return };
+    if pointer_over_ui(&ui_query, &capture) { commands.remove_resource::<WorldCursor>(); return };
+    let Some((camera, camera_transform)) = board_camera_query.iter().next().or_else(|| camera_query.iter().next()) else {
+        commands.remove_resource::<WorldCursor>();
+        return
+    };
+    let viewport_origin = camera.logical_viewport_rect().map_or(Vec2::ZERO, |viewport| viewport.min);
+    let position = window.cursor_position()
+        .and_then(|cursor| camera.viewport_to_world_2d(camera_transform, cursor - viewport_origin));
     let Some(cursor_position) = position else { commands.remove_resource::<WorldCursor>(); return };
-    commands.insert_resource(WorldCursor::from_position(cursor_position));
+    commands.insert_resource(WorldCursor::from_position(cursor_position, &config));
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An orthographic camera maps a viewport offset to translation + rotation * offset * scale
+    fn world_under_cursor(translation: Vec2, rotation: f32, offset: Vec2, scale: f32) -> Vec2 {
+        translation + Vec2::from_angle(rotation).rotate(offset * scale)
+    }
+
+    #[test]
+    fn zooming_keeps_the_square_under_the_cursor() {
+        let config = ChessConfig::default();
+        let translation = config.board_center() + Vec2::new(40.0, -25.0);
+        for rotation in [0.0, std::f32::consts::PI] {
+            for offset in [Vec2::new(-310.0, 120.0), Vec2::new(95.0, 260.0), Vec2::new(5.0, -5.0)] {
+                for (old_scale, new_scale) in [(1.0, 0.9), (1.0, MAX_ZOOM), (2.5, MIN_ZOOM), (0.5, 0.45)] {
+                    let anchor = world_under_cursor(translation, rotation, offset, old_scale);
+                    let zoomed = zoom_translation(translation, anchor, old_scale, new_scale);
+                    let after = world_under_cursor(zoomed, rotation, offset, new_scale);
+                    assert!(after.distance(anchor) < 1e-3, "{} moved to {}", anchor, after);
+                    assert_eq!(config.vector_to_square(after), config.vector_to_square(anchor));
+                }
+            }
+        }
+    }
+}
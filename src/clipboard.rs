@@ -0,0 +1,205 @@
+use std::sync::{Arc, Mutex};
+use bevy::prelude::*;
+use crate::board::BoardResource;
+use crate::correspondence::ExportMoveRequested;
+use crate::fen::{FenInput, PositionLoader};
+use crate::history::CopyPgnRequested;
+use crate::shortcuts::TextInputFocused;
+use crate::toast::{ToastLevel, Toasts};
+
+pub enum ClipboardOutcome {
+    Pasted(String),
+    Copied,
+    Failed(String)
+}
+
+#[derive(Clone, Default)]
+pub struct ClipboardOutcomes(Arc<Mutex<Vec<ClipboardOutcome>>>);
+
+impl ClipboardOutcomes {
+    pub fn push(&self, outcome: ClipboardOutcome) {
+        self.0.lock().unwrap().push(outcome);
+    }
+
+    fn drain(&self) -> Vec<ClipboardOutcome> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+pub trait Clipboard: Send + Sync {
+    fn write(&self, text: &str, outcomes: ClipboardOutcomes);
+    fn read(&self, outcomes: ClipboardOutcomes);
+}
+
+#[derive(Resource)]
+pub struct ClipboardResource {
+    backend: Box<dyn Clipboard>,
+    outcomes: ClipboardOutcomes
+}
+
+impl Default for ClipboardResource {
+    fn default() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        return ClipboardResource {backend: Box::new(SystemClipboard::default()), outcomes: ClipboardOutcomes::default()};
+        #[cfg(target_arch = "wasm32")]
+        return ClipboardResource {backend: Box::new(WebClipboard), outcomes: ClipboardOutcomes::default()};
+    }
+}
+
+#[derive(Event)]
+pub struct ClipboardRequest(pub String);
+
+#[derive(Event)]
+pub struct PasteRequested;
+
+pub fn clipboard_shortcuts(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    text_input_focused: Res<TextInputFocused>,
+    board: Res<BoardResource>,
+    mut clipboard_writer: EventWriter<ClipboardRequest>,
+    mut copy_pgn_writer: EventWriter<CopyPgnRequested>,
+    mut paste_writer: EventWriter<PasteRequested>,
+    mut export_writer: EventWriter<ExportMoveRequested>
+) {
+    if text_input_focused.0 { return };
+    let control = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight, KeyCode::SuperLeft, KeyCode::SuperRight]);
+    if !control { return };
+    let shift = keyboard.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if keyboard.just_pressed(KeyCode::KeyC) && shift {
+        copy_pgn_writer.send(CopyPgnRequested);
+    } else if keyboard.just_pressed(KeyCode::KeyC) {
+        clipboard_writer.send(ClipboardRequest(board.0.to_fen()));
+    }
+    if keyboard.just_pressed(KeyCode::KeyV) {
+        paste_writer.send(PasteRequested);
+    }
+    if keyboard.just_pressed(KeyCode::KeyE) {
+        export_writer.send(ExportMoveRequested);
+    }
+}
+
+pub fn handle_clipboard_requests(
+    mut clipboard_listener: EventReader<ClipboardRequest>,
+    mut paste_listener: EventReader<PasteRequested>,
+    clipboard: Res<ClipboardResource>
+) {
+    for ClipboardRequest(text) in clipboard_listener.read() {
+        clipboard.backend.write(text, clipboard.outcomes.clone());
+    }
+    if paste_listener.read().count() > 0 {
+        clipboard.backend.read(clipboard.outcomes.clone());
+    }
+}
+
+pub fn apply_clipboard_outcomes(
+    clipboard: Res<ClipboardResource>,
+    mut fen_input: ResMut<FenInput>,
+    mut toasts: ResMut<Toasts>,
+    mut loader: PositionLoader
+) {
+    for outcome in clipboard.outcomes.drain() {
+        match outcome {
+            ClipboardOutcome::Pasted(text) if fen_input.editing => fen_input.text.push_str(text.trim()),
+            ClipboardOutcome::Pasted(text) => {
+                fen_input.submit(&text, &mut loader);
+                if let Some(error) = &fen_input.error {
+                    toasts.push(ToastLevel::Error, format!("Invalid FEN: {}", error));
+                }
+            }
+            ClipboardOutcome::Copied => toasts.push(ToastLevel::Success, "Copied to clipboard"),
+            ClipboardOutcome::Failed(reason) => {
+                warn!("clipboard unavailable: {}", reason);
+                toasts.push(ToastLevel::Error, format!("Clipboard unavailable: {}", reason));
+            }
+        }
+    }
+}
+
+// The arboard handle is kept alive because on X11 the copied text is only served while it exists.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Default)]
+pub struct SystemClipboard(Mutex<Option<arboard::Clipboard>>);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SystemClipboard {
+    fn with<T>(&self, action: impl FnOnce(&mut arboard::Clipboard) -> Result<T, arboard::Error>) -> Result<T, String> {
+        let mut handle = self.0.lock().unwrap();
+        if handle.is_none() {
+            *handle = Some(arboard::Clipboard::new().map_err(|error| error.to_string())?);
+        }
+        action(handle.as_mut().unwrap()).map_err(|error| error.to_string())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Clipboard for SystemClipboard {
+    fn write(&self, text: &str, outcomes: ClipboardOutcomes) {
+        outcomes.push(match self.with(|clipboard| clipboard.set_text(text)) {
+            Ok(()) => ClipboardOutcome::Copied,
+            Err(error) => ClipboardOutcome::Failed(error)
+        });
+    }
+
+    fn read(&self, outcomes: ClipboardOutcomes) {
+        outcomes.push(match self.with(|clipboard| clipboard.get_text()) {
+            Ok(text) => ClipboardOutcome::Pasted(text),
+            Err(error) => ClipboardOutcome::Failed(error)
+        });
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub struct WebClipboard;
+
+#[cfg(target_arch = "wasm32")]
+impl WebClipboard {
+    fn call(method: &str, args: &js_sys::Array) -> Result<js_sys::Promise, String> {
+        use wasm_bindgen::JsCast;
+        let window = web_sys::window().ok_or("no browser window")?;
+        let clipboard = js_sys::Reflect::get(&window.navigator(), &"clipboard".into())
+            .ok()
+            .filter(|clipboard| !clipboard.is_undefined())
+            .ok_or("navigator.clipboard is not available")?;
+        let function: js_sys::Function = js_sys::Reflect::get(&clipboard, &method.into())
+            .ok()
+            .and_then(|function| function.dyn_into().ok())
+            .ok_or("clipboard method is not available")?;
+        function.apply(&clipboard, args)
+            .map_err(|error| format!("{:?}", error))?
+            .dyn_into()
+            .map_err(|_| String::from("clipboard did not return a promise"))
+    }
+
+    fn settle(promise: js_sys::Promise, outcomes: ClipboardOutcomes, on_success: fn(wasm_bindgen::JsValue) -> Option<ClipboardOutcome>) {
+        use wasm_bindgen::closure::Closure;
+        let success_outcomes = outcomes.clone();
+        let resolve = Closure::once(move |value: wasm_bindgen::JsValue| {
+            if let Some(outcome) = on_success(value) {
+                success_outcomes.push(outcome);
+            }
+        });
+        let reject = Closure::once(move |error: wasm_bindgen::JsValue| {
+            outcomes.push(ClipboardOutcome::Failed(error.as_string().unwrap_or_else(|| String::from("permission denied"))));
+        });
+        let _ = promise.then2(&resolve, &reject);
+        resolve.forget();
+        reject.forget();
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Clipboard for WebClipboard {
+    fn write(&self, text: &str, outcomes: ClipboardOutcomes) {
+        match Self::call("writeText", &js_sys::Array::of1(&text.into())) {
+            Ok(promise) => Self::settle(promise, outcomes, |_| Some(ClipboardOutcome::Copied)),
+            Err(error) => outcomes.push(ClipboardOutcome::Failed(error))
+        }
+    }
+
+    fn read(&self, outcomes: ClipboardOutcomes) {
+        match Self::call("readText", &js_sys::Array::new()) {
+            Ok(promise) => Self::settle(promise, outcomes, |value| value.as_string().map(ClipboardOutcome::Pasted)),
+            Err(error) => outcomes.push(ClipboardOutcome::Failed(error))
+        }
+    }
+}
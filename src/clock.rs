@@ -0,0 +1,279 @@
+use std::time::Duration;
+use bevy::prelude::*;
+use chess_logic::logic::PieceColor;
+use crate::board::{BoardOrientation, BoardResource, FlagFell, GameOverEvent, GamePhase, MenuState};
+use crate::history::MoveHistory;
+use crate::piece::{MovePlayed, NewGame};
+#[cfg(not(feature = "egui"))]
+use crate::players::PlayerNameText;
+use crate::settings::{Settings, TimeControl};
+use crate::shortcuts::TextInputFocused;
+
+#[derive(Resource, Default, Clone)]
+pub struct ChessClock {
+    pub control: Option<TimeControl>,
+    remaining: [Duration; 2],
+    delay_left: Duration,
+    running: bool,
+    snapshots: Vec<[Duration; 2]>
+}
+
+fn side_index(color: PieceColor) -> usize {
+    match color {
+        PieceColor::WHITE => 0,
+        PieceColor::BLACK => 1
+    }
+}
+
+impl ChessClock {
+    pub fn new(control: Option<TimeControl>) -> Self {
+        let base = control.map_or(Duration::ZERO, |control| Duration::from_secs(control.base_seconds));
+        let delay_left = control.map_or(Duration::ZERO, |control| Duration::from_secs(control.delay_seconds));
+        ChessClock {control, remaining: [base; 2], delay_left, running: false, snapshots: Vec::new()}
+    }
+
+    pub fn remaining(&self, color: PieceColor) -> Duration {
+        self.remaining[side_index(color)]
+    }
+
+    pub fn remaining_after(&self, ply: usize, color: PieceColor) -> Option<Duration> {
+        self.control?;
+        self.snapshots.get(ply).map(|snapshot| snapshot[side_index(color)])
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn set_running(&mut self, running: bool) {
+        self.running = running;
+    }
+
+    pub fn set_remaining(&mut self, color: PieceColor, remaining: Duration) {
+        self.remaining[side_index(color)] = remaining;
+        if let Some(snapshot) = self.snapshots.last_mut() {
+            snapshot[side_index(color)] = remaining;
+        }
+    }
+
+    fn initial(&self) -> [Duration; 2] {
+        [self.control.map_or(Duration::ZERO, |control| Duration::from_secs(control.base_seconds)); 2]
+    }
+
+    fn reset_delay(&mut self) {
+        self.delay_left = self.control.map_or(Duration::ZERO, |control| Duration::from_secs(control.delay_seconds));
+    }
+}
+
+pub fn reset_clock(mut new_game_listener: EventReader<NewGame>, settings: Res<Settings>, mut clock: ResMut<ChessClock>) {
+    if new_game_listener.read().count() == 0 { return };
+    *clock = ChessClock::new(settings.time_control);
+}
+
+pub fn switch_clock(mut move_played_listener: EventReader<MovePlayed>, mut clock: ResMut<ChessClock>) {
+    for move_played in move_played_listener.read() {
+        let Some(control) = clock.control else { continue };
+        clock.remaining[side_index(move_played.by)] += Duration::from_secs(control.increment_seconds);
+        clock.reset_delay();
+        clock.running = true;
+        let snapshot = clock.remaining;
+        clock.snapshots.push(snapshot);
+    }
+}
+
+pub fn rewind_clock(history: Res<MoveHistory>, mut clock: ResMut<ChessClock>) {
+    if clock.snapshots.len() <= history.0.len() { return };
+    clock.snapshots.truncate(history.0.len());
+    clock.remaining = clock.snapshots.last().copied().unwrap_or_else(|| clock.initial());
+    clock.running = !clock.snapshots.is_empty();
+    clock.reset_delay();
+}
+
+pub fn stop_clock(mut game_over_listener: EventReader<GameOverEvent>, mut clock: ResMut<ChessClock>) {
+    if game_over_listener.read().count() == 0 { return };
+    clock.running = false;
+}
+
+pub fn tick_clock(
+    time: Res<Time>,
+    board: Res<BoardResource>,
+    phase: Res<State<GamePhase>>,
+    mut clock: ResMut<ChessClock>,
+    mut flag_writer: EventWriter<FlagFell>
+) {
+    if clock.control.is_none() || !clock.running || *phase.get() == GamePhase::GameOver { return };
+    let mut elapsed = time.delta();
+    let delayed = elapsed.min(clock.delay_left);
+    clock.delay_left -= delayed;
+    elapsed -= delayed;
+    let side = side_index(board.0.on_move);
+    clock.remaining[side] = clock.remaining[side].saturating_sub(elapsed);
+    if clock.remaining[side].is_zero() {
+        clock.running = false;
+        flag_writer.send(FlagFell(board.0.on_move));
+    }
+}
+
+pub fn choose_time_control(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    text_input_focused: Res<TextInputFocused>,
+    mut settings: ResMut<Settings>,
+    mut next_menu_state: ResMut<NextState<MenuState>>,
+    mut new_game_writer: EventWriter<NewGame>
+) {
+    if text_input_focused.0 { return };
+    let keys = [KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4, KeyCode::Digit5];
+    let Some(index) = keys.iter().position(|key| keyboard.just_pressed(*key)) else { return };
+    settings.time_control = TimeControl::PRESETS[index].1;
+    new_game_writer.send(NewGame::standard());
+    next_menu_state.set(MenuState::Closed);
+}
+
+#[derive(Component)]
+pub struct ClockText(pub PieceColor);
+
+#[derive(Component)]
+pub struct PlayerBar(pub PieceColor);
+
+#[cfg(not(feature = "egui"))]
+pub fn spawn_player_bars(mut commands: Commands) {
+    for color in [PieceColor::WHITE, PieceColor::BLACK] {
+        commands.spawn((NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(8.0),
+                column_gap: Val::Px(8.0),
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        }, PlayerBar(color))).with_children(|bar| {
+            bar.spawn((TextBundle::from_section("", TextStyle { font_size: 22.0, color: Color::WHITE, ..default() }), PlayerNameText(color)));
+            bar.spawn((TextBundle::from_section("", TextStyle { font_size: 28.0, color: Color::WHITE, ..default() })
+                .with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.6)), ClockText(color)));
+        });
+    }
+}
+
+pub fn position_player_bars(orientation: Res<BoardOrientation>, mut bar_query: Query<(&PlayerBar, &mut Style)>) {
+    for (PlayerBar(color), mut style) in bar_query.iter_mut() {
+        let at_bottom = *color == orientation.0;
+        style.top = if at_bottom { Val::Auto } else { Val::Px(36.0) };
+        style.bottom = if at_bottom { Val::Px(36.0) } else { Val::Auto };
+    }
+}
+
+pub fn format_clock(remaining: Duration) -> String {
+    let seconds = remaining.as_secs();
+    if seconds < 10 {
+        format!("{}:{:02}.{}", seconds / 60, seconds % 60, remaining.subsec_millis() / 100)
+    } else {
+        format!("{}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+pub fn update_clock_text(
+    clock: Res<ChessClock>,
+    board: Res<BoardResource>,
+    mut clock_texts: Query<(&ClockText, &mut Text, &mut Style)>
+) {
+    for (ClockText(color), mut text, mut style) in clock_texts.iter_mut() {
+        let display = if clock.control.is_some() { Display::Flex } else { Display::None };
+        if style.display != display {
+            style.display = display;
+        }
+        let label = format_clock(clock.remaining(*color));
+        let active = clock.is_running() && board.0.on_move == *color;
+        let color = if active { Color::WHITE } else { Color::GRAY };
+        if text.sections[0].value != label || text.sections[0].style.color != color {
+            text.sections[0].value = label;
+            text.sections[0].style.color = color;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+    use chess_logic::logic::Board;
+    use super::*;
+
+    fn clock_world(control: TimeControl) -> World {
+        let mut world = World::new();
+        world.insert_resource(ChessClock::new(Some(control)));
+        world.insert_resource(BoardResource(Board::new()));
+        world.insert_resource(State::new(GamePhase::Playing));
+        world.insert_resource(Time::<()>::default());
+        world.init_resource::<Events<MovePlayed>>();
+        world.init_resource::<Events<FlagFell>>();
+        world
+    }
+
+    fn play(world: &mut World, uci: &str) {
+        let mut board = world.remove_resource::<BoardResource>().unwrap();
+        let candidate = board.0.parse_move(uci).unwrap();
+        let by = board.0.on_move;
+        let record = board.0.try_move(candidate).unwrap();
+        let after = board.0.position_status();
+        world.insert_resource(board);
+        world.send_event(MovePlayed {record, san: String::new(), after, by});
+        world.run_system_once(switch_clock);
+        world.resource_mut::<Events<MovePlayed>>().clear();
+    }
+
+    fn tick(world: &mut World, seconds: u64) {
+        world.resource_mut::<Time>().advance_by(Duration::from_secs(seconds));
+        world.run_system_once(tick_clock);
+    }
+
+    fn remaining(world: &World) -> [u64; 2] {
+        let clock = world.resource::<ChessClock>();
+        [clock.remaining(PieceColor::WHITE).as_secs(), clock.remaining(PieceColor::BLACK).as_secs()]
+    }
+
+    #[test]
+    fn the_increment_goes_to_the_side_that_moved() {
+        let mut world = clock_world(TimeControl {base_seconds: 60, increment_seconds: 2, delay_seconds: 0});
+        assert!(!world.resource::<ChessClock>().is_running());
+        play(&mut world, "e2e4");
+        assert!(world.resource::<ChessClock>().is_running());
+        assert_eq!(remaining(&world), [62, 60]);
+        tick(&mut world, 5);
+        play(&mut world, "e7e5");
+        assert_eq!(remaining(&world), [62, 57]);
+        assert_eq!(world.resource::<ChessClock>().remaining_after(1, PieceColor::BLACK), Some(Duration::from_secs(57)));
+    }
+
+    #[test]
+    fn the_delay_only_absorbs_the_time_it_lasts() {
+        let mut world = clock_world(TimeControl {base_seconds: 60, increment_seconds: 0, delay_seconds: 3});
+        play(&mut world, "e2e4");
+        tick(&mut world, 2);
+        assert_eq!(remaining(&world), [60, 60]);
+        tick(&mut world, 2);
+        assert_eq!(remaining(&world), [60, 59]);
+        tick(&mut world, 4);
+        assert_eq!(remaining(&world), [60, 55]);
+        play(&mut world, "e7e5");
+        tick(&mut world, 5);
+        assert_eq!(remaining(&world), [58, 55]);
+    }
+
+    #[test]
+    fn the_flag_falls_at_zero() {
+        let mut world = clock_world(TimeControl {base_seconds: 1, increment_seconds: 0, delay_seconds: 0});
+        world.resource_mut::<ChessClock>().set_running(true);
+        tick(&mut world, 2);
+        assert_eq!(remaining(&world), [0, 1]);
+        assert!(!world.resource::<ChessClock>().is_running());
+        let flags: Vec<PieceColor> = world.resource_mut::<Events<FlagFell>>().drain().map(|FlagFell(color)| color).collect();
+        assert_eq!(flags, [PieceColor::WHITE]);
+    }
+
+    #[test]
+    fn formats_seconds_and_tenths() {
+        assert_eq!(format_clock(Duration::from_secs(125)), "2:05");
+        assert_eq!(format_clock(Duration::from_millis(9_950)), "0:09.9");
+        assert_eq!(format_clock(Duration::ZERO), "0:00.0");
+    }
+}
@@ -0,0 +1,91 @@
+use std::time::Duration;
+use bevy::prelude::*;
+use chess_logic::logic::{Coordinate, PieceColor};
+
+#[derive(Resource, Clone)]
+pub struct ChessConfig {
+    pub square_size: f32,
+    pub orientation: PieceColor,
+    pub spawn_camera: bool,
+    pub auto_queen: bool,
+    pub check_blink_interval: Duration,
+    pub blink_checked_king: bool,
+    pub drag_threshold: f32
+}
+
+impl Default for ChessConfig {
+    fn default() -> Self {
+        ChessConfig {
+            square_size: 64.0,
+            orientation: PieceColor::WHITE,
+            spawn_camera: true,
+            auto_queen: false,
+            check_blink_interval: Duration::from_millis(500),
+            blink_checked_king: false,
+            drag_threshold: 0.15
+        }
+    }
+}
+
+impl ChessConfig {
+    pub fn square_to_vector(&self, square: Coordinate) -> Vec2 {
+        Vec2::new(square.0 as f32 * self.square_size, square.1 as f32 * self.square_size)
+    }
+
+    pub fn vector_to_square(&self, vec: Vec2) -> Option<Coordinate> {
+        let file = (vec.x / self.square_size + 0.5).floor();
+        let rank = (vec.y / self.square_size + 0.5).floor();
+        if !(0.0..8.0).contains(&file) || !(0.0..8.0).contains(&rank) { return None };
+        Some(Coordinate(file as i8, rank as i8))
+    }
+
+    pub fn board_center(&self) -> Vec2 {
+        Vec2::splat(self.square_size * 3.5)
+    }
+
+    pub fn frame_size(&self) -> f32 {
+        self.square_size * 10.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn positions_just_outside_the_board_have_no_square() {
+        let config = ChessConfig::default();
+        let (low, high) = (-0.5 * config.square_size, 7.5 * config.square_size);
+        for along in [0.0, 3.5 * config.square_size, 7.0 * config.square_size] {
+            assert_eq!(config.vector_to_square(Vec2::new(low - 0.01, along)), None);
+            assert_eq!(config.vector_to_square(Vec2::new(high, along)), None);
+            assert_eq!(config.vector_to_square(Vec2::new(along, low - 0.01)), None);
+            assert_eq!(config.vector_to_square(Vec2::new(along, high)), None);
+        }
+        assert_eq!(config.vector_to_square(Vec2::new(low, low)), Some(Coordinate(0, 0)));
+        assert_eq!(config.vector_to_square(Vec2::new(high - 0.01, high - 0.01)), Some(Coordinate(7, 7)));
+    }
+
+    #[test]
+    fn square_boundaries_belong_to_the_higher_square() {
+        let config = ChessConfig::default();
+        for index in 0..7 {
+            let boundary = (index as f32 + 0.5) * config.square_size;
+            assert_eq!(config.vector_to_square(Vec2::new(boundary, 0.0)), Some(Coordinate(index + 1, 0)));
+            assert_eq!(config.vector_to_square(Vec2::new(boundary - 0.01, 0.0)), Some(Coordinate(index, 0)));
+            assert_eq!(config.vector_to_square(Vec2::new(0.0, boundary)), Some(Coordinate(0, index + 1)));
+            assert_eq!(config.vector_to_square(Vec2::new(0.0, boundary - 0.01)), Some(Coordinate(0, index)));
+        }
+    }
+
+    #[test]
+    fn square_centers_round_trip() {
+        let config = ChessConfig {square_size: 50.0, ..ChessConfig::default()};
+        for file in 0..8 {
+            for rank in 0..8 {
+                let square = Coordinate(file, rank);
+                assert_eq!(config.vector_to_square(config.square_to_vector(square)), Some(square));
+            }
+        }
+    }
+}
@@ -0,0 +1,200 @@
+use std::fmt;
+use bevy::prelude::*;
+use chess_logic::logic::Board;
+use crate::board::{BoardResource, GamePhase};
+use crate::clipboard::ClipboardRequest;
+use crate::history::{history_moves, MoveHistory, replay_game, StartingPosition};
+use crate::piece::MoveRequested;
+use crate::toast::{ToastLevel, Toasts};
+
+const PREFIX: &str = "cheess:";
+
+// FNV-1a over the FEN without its move counters, so both boards agree however the game was reached
+fn position_checksum(board: &Board) -> u32 {
+    let fen = board.to_fen();
+    let position = fen.split_whitespace().take(4).collect::<Vec<_>>().join(" ");
+    position.bytes().fold(0x811c9dc5, |hash, byte| (hash ^ byte as u32).wrapping_mul(0x01000193))
+}
+
+fn move_label(ply: usize) -> String {
+    format!("{}{}", (ply - 1) / 2 + 1, if ply % 2 == 1 { "." } else { "..." })
+}
+
+pub struct MoveString {
+    ply: usize,
+    uci: String,
+    checksum: u32
+}
+
+impl MoveString {
+    pub fn detect(text: &str) -> bool {
+        text.trim().starts_with(PREFIX)
+    }
+
+    fn parse(text: &str) -> Result<Self, String> {
+        let body = text.trim().strip_prefix(PREFIX).ok_or_else(|| format!("move strings start with {}", PREFIX))?;
+        let fields: Vec<&str> = body.split(':').collect();
+        let [ply, uci, checksum] = fields.as_slice() else { return Err(String::from("expected move number, move and checksum")) };
+        let ply = ply.parse().ok().filter(|ply| *ply > 0).ok_or_else(|| format!("bad move number {}", ply))?;
+        let checksum = u32::from_str_radix(checksum, 16).map_err(|_| format!("bad checksum {}", checksum))?;
+        Ok(MoveString {ply, uci: uci.to_string(), checksum})
+    }
+}
+
+impl fmt::Display for MoveString {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(formatter, "{}{}:{}:{:08x}", PREFIX, self.ply, self.uci, self.checksum)
+    }
+}
+
+#[derive(Event)]
+pub struct ExportMoveRequested;
+
+#[derive(Event)]
+pub struct ApplyMoveString(pub String);
+
+pub fn export_move(
+    mut export_listener: EventReader<ExportMoveRequested>,
+    history: Res<MoveHistory>,
+    start: Res<StartingPosition>,
+    mut toasts: ResMut<Toasts>,
+    mut clipboard_writer: EventWriter<ClipboardRequest>
+) {
+    if export_listener.read().count() == 0 { return };
+    let mut moves = history_moves(&history);
+    let Some(uci) = moves.pop() else {
+        toasts.push(ToastLevel::Warning, "No move to export yet");
+        return;
+    };
+    let start = start.0.as_ref().map(Board::to_fen);
+    let before = match replay_game(start.as_deref(), &moves) {
+        Ok((board, _, _)) => board,
+        Err(error) => { warn!("cannot rebuild the position before the last move: {}", error); return }
+    };
+    let exported = MoveString {ply: moves.len() + 1, uci, checksum: position_checksum(&before)};
+    clipboard_writer.send(ClipboardRequest(exported.to_string()));
+}
+
+pub fn apply_move_strings(
+    mut apply_listener: EventReader<ApplyMoveString>,
+    phase: Res<State<GamePhase>>,
+    board: Res<BoardResource>,
+    history: Res<MoveHistory>,
+    mut toasts: ResMut<Toasts>,
+    mut move_request_writer: EventWriter<MoveRequested>
+) {
+    for ApplyMoveString(text) in apply_listener.read() {
+        let received = match MoveString::parse(text) {
+            Ok(received) => received,
+            Err(error) => { toasts.push(ToastLevel::Error, format!("Unreadable move string: {}", error)); continue }
+        };
+        let expected = history.0.len() + 1;
+        let label = move_label(received.ply);
+        if received.ply < expected {
+            let played = history_moves(&history);
+            if played[received.ply - 1] == received.uci {
+                toasts.push(ToastLevel::Info, format!("{} {} is already on your board", label, received.uci));
+            } else {
+                toasts.push(ToastLevel::Error, format!(
+                    "Your friend is out of sync: their {} {} differs from yours. Send them your FEN (Ctrl+C) to paste instead",
+                    label, received.uci
+                ));
+            }
+            continue;
+        }
+        if received.ply > expected {
+            toasts.push(ToastLevel::Error, format!(
+                "You are out of sync: you are missing {} move(s) before {}. Ask for the earlier strings or paste their full FEN instead",
+                received.ply - expected, label
+            ));
+            continue;
+        }
+        if received.checksum != position_checksum(&board.0) {
+            toasts.push(ToastLevel::Error, format!(
+                "Your board differs from your friend's before {}; an earlier move went astray. Paste their full FEN instead",
+                label
+            ));
+            continue;
+        }
+        if *phase.get() != GamePhase::Playing {
+            toasts.push(ToastLevel::Warning, format!("Cannot apply {} {} now, the game is not in progress", label, received.uci));
+            continue;
+        }
+        let Some(candidate) = board.0.parse_move(&received.uci) else {
+            toasts.push(ToastLevel::Error, format!("Unreadable move {} in the move string", received.uci));
+            continue;
+        };
+        if let Err(error) = board.0.clone().try_move(candidate) {
+            toasts.push(ToastLevel::Error, format!("Illegal move {} {}: {}", label, received.uci, error));
+            continue;
+        }
+        move_request_writer.send(MoveRequested {from: candidate.from, to: candidate.to, promotion: candidate.promotion});
+        toasts.push(ToastLevel::Success, format!("Played {} {}", label, received.uci));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(moves: &[&str]) -> Board {
+        let mut board = Board::new();
+        for uci in moves {
+            let candidate = board.parse_move(uci).unwrap();
+            board.try_move(candidate).unwrap();
+        }
+        board
+    }
+
+    #[test]
+    fn move_strings_read_back() {
+        let written = MoveString {ply: 4, uci: String::from("e7e8q"), checksum: 0xbeef}.to_string();
+        assert_eq!(written, "cheess:4:e7e8q:0000beef");
+        assert!(MoveString::detect(&format!("  {}\n", written)));
+        let read = MoveString::parse(&format!("  {}\n", written)).unwrap();
+        assert_eq!((read.ply, read.uci.as_str(), read.checksum), (4, "e7e8q", 0xbeef));
+    }
+
+    #[test]
+    fn malformed_move_strings_are_rejected() {
+        for text in [
+            "",
+            "4:e2e4:0000beef",
+            "cheess:",
+            "cheess:1:e2e4",
+            "cheess:1:e2e4:0000beef:extra",
+            "cheess:0:e2e4:0000beef",
+            "cheess:-1:e2e4:0000beef",
+            "cheess:one:e2e4:0000beef",
+            "cheess:1:e2e4:nothex",
+            "cheess:1:e2e4:1ffffffff"
+        ] {
+            assert!(MoveString::parse(text).is_err(), "{text}");
+        }
+        assert!(!MoveString::detect("e2e4"));
+    }
+
+    #[test]
+    fn move_labels_number_both_sides() {
+        assert_eq!(move_label(1), "1.");
+        assert_eq!(move_label(2), "1...");
+        assert_eq!(move_label(3), "2.");
+    }
+
+    #[test]
+    fn checksum_ignores_how_the_position_was_reached() {
+        let direct = play(&["g1f3", "g8f6"]);
+        let roundabout = play(&["g1f3", "g8f6", "f3g1", "f6g8", "g1f3", "g8f6"]);
+        assert_ne!(direct.to_fen(), roundabout.to_fen());
+        assert_eq!(position_checksum(&direct), position_checksum(&roundabout));
+    }
+
+    #[test]
+    fn checksum_tells_positions_apart() {
+        let start = position_checksum(&Board::new());
+        assert_ne!(start, position_checksum(&play(&["e2e4"])));
+        assert_ne!(position_checksum(&play(&["g1f3"])), position_checksum(&play(&["g1f3", "g8f6", "f3g1"])));
+        let no_castling = Board::from_fen("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w - - 0 1").unwrap();
+        assert_ne!(start, position_checksum(&no_castling));
+    }
+}
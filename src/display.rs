@@ -0,0 +1,123 @@
+use bevy::prelude::*;
+use bevy::window::{PrimaryWindow, WindowMode};
+use crate::settings::{Settings, WindowModeSetting};
+
+fn set_window_mode(window: &mut Window, mode: WindowMode, windowed_size: &mut Option<Vec2>) {
+    if window.mode == mode { return };
+    match mode {
+        WindowMode::Windowed => {
+            window.mode = WindowMode::Windowed;
+            if let Some(size) = windowed_size.take() {
+                window.resolution.set(size.x, size.y);
+            }
+        }
+        _ => {
+            *windowed_size = Some(Vec2::new(window.width(), window.height()));
+            window.mode = mode;
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct WindowedSize(pub Option<Vec2>);
+
+pub fn apply_window_mode(
+    settings: Res<Settings>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+    mut windowed_size: ResMut<WindowedSize>
+) {
+    let Ok(mut window) = window_query.get_single_mut() else { return };
+    let mode = match settings.window_mode {
+        WindowModeSetting::Windowed => WindowMode::Windowed,
+        WindowModeSetting::Fullscreen => WindowMode::BorderlessFullscreen
+    };
+    set_window_mode(&mut window, mode, &mut windowed_size.0);
+}
+
+pub fn toggle_fullscreen(keyboard: Res<ButtonInput<KeyCode>>, mut settings: ResMut<Settings>) {
+    if !keyboard.just_pressed(KeyCode::F11) { return };
+    settings.window_mode = match settings.window_mode {
+        WindowModeSetting::Windowed => WindowModeSetting::Fullscreen,
+        WindowModeSetting::Fullscreen => WindowModeSetting::Windowed
+    };
+}
+
+pub const MIN_UI_SCALE: f32 = 0.75;
+pub const MAX_UI_SCALE: f32 = 2.0;
+const UI_SCALE_PRESETS: [f32; 6] = [0.75, 1.0, 1.25, 1.5, 1.75, 2.0];
+
+pub fn ui_scale(settings: &Settings, window: &Window) -> f32 {
+    settings.ui_scale.unwrap_or(window.scale_factor()).clamp(MIN_UI_SCALE, MAX_UI_SCALE)
+}
+
+pub fn detect_ui_scale(window_query: Query<&Window, With<PrimaryWindow>>, mut settings: ResMut<Settings>) {
+    if settings.ui_scale.is_some() { return };
+    let Ok(window) = window_query.get_single() else { return };
+    settings.ui_scale = Some(ui_scale(&settings, window));
+}
+
+pub fn apply_ui_scale(
+    settings: Res<Settings>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut scale: ResMut<UiScale>
+) {
+    let Ok(window) = window_query.get_single() else { return };
+    let relative = ui_scale(&settings, window) / window.scale_factor();
+    if scale.0 != relative {
+        scale.0 = relative;
+    }
+}
+
+#[derive(Component)]
+pub struct UiScaleButton(pub f32);
+
+pub fn spawn_ui_scale_buttons(parent: &mut ChildBuilder) {
+    parent.spawn(NodeBundle {
+        style: Style {
+            width: Val::Px(360.0),
+            margin: UiRect::top(Val::Px(8.0)),
+            align_items: AlignItems::Center,
+            column_gap: Val::Px(4.0),
+            ..default()
+        },
+        ..default()
+    }).with_children(|row| {
+        row.spawn(TextBundle::from_section("UI scale", TextStyle { font_size: 20.0, color: Color::WHITE, ..default() })
+            .with_style(Style { width: Val::Px(100.0), ..default() }));
+        for scale in UI_SCALE_PRESETS {
+            row.spawn((ButtonBundle {
+                style: Style {
+                    flex_grow: 1.0,
+                    padding: UiRect::all(Val::Px(4.0)),
+                    justify_content: JustifyContent::Center,
+                    border: UiRect::bottom(Val::Px(3.0)),
+                    ..default()
+                },
+                background_color: Color::rgb(0.25, 0.25, 0.3).into(),
+                ..default()
+            }, UiScaleButton(scale))).with_children(|button| {
+                button.spawn(TextBundle::from_section(format!("{}x", scale), TextStyle { font_size: 16.0, color: Color::WHITE, ..default() }));
+            });
+        }
+    });
+}
+
+pub fn choose_ui_scale(button_query: Query<(&Interaction, &UiScaleButton), Changed<Interaction>>, mut settings: ResMut<Settings>) {
+    for (interaction, UiScaleButton(scale)) in button_query.iter() {
+        if *interaction == Interaction::Pressed && settings.ui_scale != Some(*scale) {
+            settings.ui_scale = Some(*scale);
+        }
+    }
+}
+
+pub fn update_ui_scale_buttons(
+    settings: Res<Settings>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut button_query: Query<(&UiScaleButton, &mut BorderColor)>
+) {
+    let Ok(window) = window_query.get_single() else { return };
+    let current = ui_scale(&settings, window);
+    for (UiScaleButton(scale), mut border) in button_query.iter_mut() {
+        border.0 = if (*scale - current).abs() < 0.01 { Color::WHITE } else { Color::NONE };
+    }
+}
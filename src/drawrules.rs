@@ -0,0 +1,93 @@
+use bevy::prelude::*;
+use chess_logic::logic::{Board, DrawReason, GameStatus};
+use crate::board::{BoardResource, GameStatusRes};
+#[cfg(not(feature = "egui"))]
+use crate::board::DrawClaimed;
+use crate::toast::{ToastLevel, Toasts};
+
+pub fn fifty_move_label(board: &Board) -> String {
+    format!("Fifty-move rule: {}/100", board.halfmove_clock)
+}
+
+pub fn repetition_label(board: &Board) -> Option<String> {
+    let count = board.repetition_count();
+    (count > 1).then(|| format!("Position repeated {} times", count))
+}
+
+pub fn claimable_draw(board: &Board, status: GameStatus) -> Option<DrawReason> {
+    board.claimable_draw().filter(|_| status == GameStatus::Ongoing)
+}
+
+pub fn announce_claimable_draw(
+    board: Res<BoardResource>,
+    game_status: Res<GameStatusRes>,
+    mut announced: Local<Option<DrawReason>>,
+    mut toasts: ResMut<Toasts>
+) {
+    let claimable = claimable_draw(&board.0, game_status.status());
+    if claimable == *announced { return };
+    *announced = claimable;
+    let Some(reason) = claimable else { return };
+    toasts.push(ToastLevel::Warning, format!("Draw available by {}", reason));
+}
+
+#[cfg(not(feature = "egui"))]
+#[derive(Component)]
+pub struct DrawRuleText;
+
+#[cfg(not(feature = "egui"))]
+#[derive(Component)]
+pub struct ClaimDrawButton;
+
+#[cfg(not(feature = "egui"))]
+pub fn spawn_draw_rules(panel: &mut ChildBuilder) {
+    panel.spawn((TextBundle::from_section("", TextStyle { font_size: 14.0, color: Color::GRAY, ..default() })
+        .with_style(Style { margin: UiRect::top(Val::Px(8.0)), ..default() }), DrawRuleText));
+    panel.spawn((ButtonBundle {
+        style: Style {
+            margin: UiRect::top(Val::Px(4.0)),
+            padding: UiRect::all(Val::Px(4.0)),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        background_color: Color::rgb(0.18, 0.18, 0.2).into(),
+        ..default()
+    }, ClaimDrawButton)).with_children(|button| {
+        button.spawn(TextBundle::from_section("Claim draw", TextStyle { font_size: 16.0, color: Color::WHITE, ..default() }));
+    });
+}
+
+#[cfg(not(feature = "egui"))]
+pub fn update_draw_rules(
+    board: Res<BoardResource>,
+    game_status: Res<GameStatusRes>,
+    mut text_query: Query<&mut Text, With<DrawRuleText>>,
+    mut button_query: Query<&mut BackgroundColor, With<ClaimDrawButton>>
+) {
+    let claimable = claimable_draw(&board.0, game_status.status()).is_some();
+    let mut label = fifty_move_label(&board.0);
+    if let Some(repetitions) = repetition_label(&board.0) {
+        label.push('\n');
+        label.push_str(&repetitions);
+    }
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = label.clone();
+        text.sections[0].style.color = if claimable { Color::rgb(1.0, 0.85, 0.3) } else { Color::GRAY };
+    }
+    for mut background in button_query.iter_mut() {
+        *background = if claimable { Color::rgb(0.55, 0.45, 0.15) } else { Color::rgb(0.18, 0.18, 0.2) }.into();
+    }
+}
+
+#[cfg(not(feature = "egui"))]
+pub fn claim_draw_button(
+    button_query: Query<&Interaction, (Changed<Interaction>, With<ClaimDrawButton>)>,
+    board: Res<BoardResource>,
+    game_status: Res<GameStatusRes>,
+    mut draw_claimed_writer: EventWriter<DrawClaimed>
+) {
+    if !button_query.iter().any(|interaction| *interaction == Interaction::Pressed) { return };
+    if claimable_draw(&board.0, game_status.status()).is_some() {
+        draw_claimed_writer.send(DrawClaimed);
+    }
+}
@@ -0,0 +1,374 @@
+use bevy::prelude::*;
+use chess_logic::logic::{Board, Piece, PieceColor, PieceKind};
+use crate::analysis::Analysis;
+use crate::arrows::Annotations;
+use crate::assets::PieceAssets;
+use crate::board::{BoardResource, GamePhase, MenuState, WorldCursor};
+use crate::clock::ChessClock;
+use crate::history::{HistoryView, MoveHistory, PlayedMove};
+use crate::piece::{BoardUpdate, NewGame, Premove, Selection};
+
+const CASTLING: [(PieceColor, PieceKind); 4] = [
+    (PieceColor::WHITE, PieceKind::KING),
+    (PieceColor::WHITE, PieceKind::QUEEN),
+    (PieceColor::BLACK, PieceKind::KING),
+    (PieceColor::BLACK, PieceKind::QUEEN)
+];
+const PALETTE: [PieceKind; 6] = [PieceKind::KING, PieceKind::QUEEN, PieceKind::ROOK, PieceKind::BISHOP, PieceKind::KNIGHT, PieceKind::PAWN];
+
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum EditorState {
+    #[default]
+    Closed,
+    Open
+}
+
+struct EditorStash {
+    board: Board,
+    history: Vec<PlayedMove>,
+    phase: GamePhase,
+    clock: ChessClock
+}
+
+#[derive(Resource, Default)]
+pub struct BoardEditor {
+    pub brush: Option<(PieceColor, PieceKind)>,
+    pub castling: [bool; 4],
+    pub error: Option<String>,
+    stash: Option<EditorStash>
+}
+
+impl BoardEditor {
+    pub fn build(&self, edited: &Board) -> Result<Board, String> {
+        let mut board = edited.clone();
+        for piece in board.pieces.values_mut() {
+            let home_rank = if piece.color == PieceColor::WHITE { 1 } else { 6 };
+            piece.moved = match piece.kind {
+                PieceKind::PAWN => piece.square.1 != home_rank,
+                PieceKind::KING | PieceKind::ROOK => true,
+                _ => false
+            };
+        }
+        for (&(color, side), _) in CASTLING.iter().zip(self.castling).filter(|(_, allowed)| *allowed) {
+            let right = side.to_fen_char(color);
+            let rank = if color == PieceColor::WHITE { 0 } else { 7 };
+            // Same search as Board::from_fen, so the king may stand wherever the setup puts it
+            let Some(king) = board.find(color, PieceKind::KING).map(|king| king.square).filter(|king| king.1 == rank) else {
+                return Err(format!("castling {} needs a king on rank {}", right, rank + 1));
+            };
+            let rook = board.pieces_of(color)
+                .filter(|rook| rook.kind == PieceKind::ROOK && rook.square.1 == rank)
+                .filter(|rook| (rook.square.0 > king.0) == (side == PieceKind::KING))
+                .max_by_key(|rook| (rook.square.0 - king.0).abs())
+                .map(|rook| rook.square);
+            let Some(rook) = rook else {
+                return Err(format!("castling {} needs a rook on the {} side of the king", right, if side == PieceKind::KING { "h-file" } else { "a-file" }));
+            };
+            for square in [king, rook] {
+                if let Some(piece) = board.pieces.get_mut(&square) {
+                    piece.moved = false;
+                }
+            }
+        }
+        board.en_pessant_file = None;
+        board.halfmove_clock = 0;
+        board.turn_number = if board.on_move == PieceColor::BLACK { 1 } else { 0 };
+        board.position_keys = vec![board.position_key()];
+        board.validate().map_err(|error| error.to_string())?;
+        Ok(board)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum EditorAction {
+    Brush(Option<(PieceColor, PieceKind)>),
+    Clear,
+    StartPosition,
+    SideToMove,
+    Castling(usize),
+    Play,
+    Cancel
+}
+
+#[derive(Component)]
+pub struct EditorButton(pub EditorAction);
+
+#[derive(Component)]
+pub struct EditorPanel;
+
+#[derive(Component)]
+pub struct EditorError;
+
+#[derive(Component)]
+pub struct OpenEditorButton;
+
+pub fn spawn_open_editor_button(parent: &mut ChildBuilder) {
+    parent.spawn((ButtonBundle {
+        style: Style {
+            width: Val::Px(360.0),
+            margin: UiRect::top(Val::Px(8.0)),
+            padding: UiRect::all(Val::Px(4.0)),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        background_color: Color::rgb(0.25, 0.25, 0.3).into(),
+        ..default()
+    }, OpenEditorButton)).with_children(|button| {
+        button.spawn(TextBundle::from_section("Edit board", TextStyle { font_size: 16.0, color: Color::WHITE, ..default() }));
+    });
+}
+
+pub fn open_editor(
+    button_query: Query<&Interaction, (Changed<Interaction>, With<OpenEditorButton>)>,
+    phase: Res<State<GamePhase>>,
+    analysis: Res<Analysis>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+    mut next_editor: ResMut<NextState<EditorState>>
+) {
+    if !button_query.iter().any(|interaction| *interaction == Interaction::Pressed) { return };
+    if analysis.is_active() || matches!(phase.get(), GamePhase::Loading | GamePhase::PromotionPending) { return };
+    next_menu.set(MenuState::Closed);
+    next_editor.set(EditorState::Open);
+}
+
+pub fn enter_editor(
+    phase: Res<State<GamePhase>>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut editor: ResMut<BoardEditor>,
+    mut board: ResMut<BoardResource>,
+    mut history: ResMut<MoveHistory>,
+    mut clock: ResMut<ChessClock>,
+    mut view: ResMut<HistoryView>,
+    mut selection: ResMut<Selection>,
+    mut premove: ResMut<Premove>,
+    mut annotations: ResMut<Annotations>,
+    mut board_update_writer: EventWriter<BoardUpdate>
+) {
+    view.go_live();
+    let fen = board.0.to_fen();
+    let castling = fen.split_whitespace().nth(2).unwrap_or("-");
+    editor.castling = CASTLING.map(|(color, side)| castling.contains(side.to_fen_char(color)));
+    editor.error = None;
+    editor.stash = Some(EditorStash {
+        board: board.0.clone(),
+        history: std::mem::take(&mut history.0),
+        phase: *phase.get(),
+        clock: std::mem::take(&mut *clock)
+    });
+    board.0.en_pessant_file = None;
+    selection.0 = None;
+    premove.0 = None;
+    annotations.arrows.clear();
+    next_phase.set(GamePhase::Playing);
+    board_update_writer.send(BoardUpdate::resync());
+}
+
+fn editor_button<'a>(parent: &'a mut ChildBuilder, action: EditorAction, width: Val) -> bevy::ecs::system::EntityCommands<'a> {
+    parent.spawn((ButtonBundle {
+        style: Style {
+            width,
+            min_height: Val::Px(28.0),
+            padding: UiRect::all(Val::Px(2.0)),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            border: UiRect::all(Val::Px(2.0)),
+            ..default()
+        },
+        background_color: Color::rgb(0.25, 0.25, 0.3).into(),
+        ..default()
+    }, EditorButton(action)))
+}
+
+fn label(button: &mut ChildBuilder, text: &str) {
+    button.spawn(TextBundle::from_section(text, TextStyle { font_size: 16.0, color: Color::WHITE, ..default() }));
+}
+
+pub fn spawn_editor_panel(mut commands: Commands, piece_assets: Res<PieceAssets>) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(8.0),
+            top: Val::Px(80.0),
+            width: Val::Px(240.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(6.0),
+            ..default()
+        },
+        background_color: Color::rgba(0.1, 0.1, 0.12, 0.9).into(),
+        z_index: ZIndex::Global(12),
+        ..default()
+    }, Interaction::default(), EditorPanel)).with_children(|panel| {
+        label(panel, "Board editor");
+        for color in [PieceColor::WHITE, PieceColor::BLACK] {
+            panel.spawn(NodeBundle { style: Style { column_gap: Val::Px(4.0), ..default() }, ..default() }).with_children(|row| {
+                for kind in PALETTE {
+                    editor_button(row, EditorAction::Brush(Some((color, kind))), Val::Px(36.0)).with_children(|button| {
+                        if piece_assets.is_missing(color, kind) {
+                            label(button, &kind.to_fen_char(color).to_string());
+                            return;
+                        }
+                        let mut image = button.spawn(ImageBundle {
+                            style: Style { width: Val::Px(30.0), height: Val::Px(30.0), ..default() },
+                            image: UiImage::new(piece_assets.get(color, kind)),
+                            ..default()
+                        });
+                        if let Some(atlas) = piece_assets.atlas(color, kind) {
+                            image.insert(atlas);
+                        }
+                    });
+                }
+            });
+        }
+        editor_button(panel, EditorAction::Brush(None), Val::Auto).with_children(|button| label(button, "Eraser (or right click)"));
+        panel.spawn(NodeBundle { style: Style { column_gap: Val::Px(4.0), ..default() }, ..default() }).with_children(|row| {
+            editor_button(row, EditorAction::Clear, Val::Percent(50.0)).with_children(|button| label(button, "Clear"));
+            editor_button(row, EditorAction::StartPosition, Val::Percent(50.0)).with_children(|button| label(button, "Start position"));
+        });
+        editor_button(panel, EditorAction::SideToMove, Val::Auto).with_children(|button| label(button, "White to move"));
+        panel.spawn(NodeBundle { style: Style { column_gap: Val::Px(4.0), ..default() }, ..default() }).with_children(|row| {
+            for (index, (color, side)) in CASTLING.into_iter().enumerate() {
+                editor_button(row, EditorAction::Castling(index), Val::Percent(25.0)).with_children(|button| label(button, &side.to_fen_char(color).to_string()));
+            }
+        });
+        editor_button(panel, EditorAction::Play, Val::Auto).with_children(|button| label(button, "Play from here"));
+        editor_button(panel, EditorAction::Cancel, Val::Auto).with_children(|button| label(button, "Cancel"));
+        panel.spawn((TextBundle::from_section("", TextStyle { font_size: 14.0, color: Color::rgb(1.0, 0.5, 0.45), ..default() }), EditorError));
+    });
+}
+
+pub fn despawn_editor_panel(mut commands: Commands, panel_query: Query<Entity, With<EditorPanel>>) {
+    for entity in panel_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+pub fn editor_buttons(
+    button_query: Query<(&Interaction, &EditorButton), Changed<Interaction>>,
+    mut editor: ResMut<BoardEditor>,
+    mut board: ResMut<BoardResource>,
+    mut history: ResMut<MoveHistory>,
+    mut clock: ResMut<ChessClock>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut next_editor: ResMut<NextState<EditorState>>,
+    mut new_game_writer: EventWriter<NewGame>,
+    mut board_update_writer: EventWriter<BoardUpdate>
+) {
+    for (interaction, EditorButton(action)) in button_query.iter() {
+        if *interaction != Interaction::Pressed { continue };
+        editor.error = None;
+        match *action {
+            EditorAction::Brush(brush) => editor.brush = brush,
+            EditorAction::Clear => board.0.pieces.clear(),
+            EditorAction::StartPosition => {
+                board.0 = Board::new();
+                editor.castling = [true; 4];
+            }
+            EditorAction::SideToMove => board.0.on_move = board.0.on_move.opposite(),
+            EditorAction::Castling(index) => editor.castling[index] = !editor.castling[index],
+            EditorAction::Play => match editor.build(&board.0) {
+                Ok(position) => {
+                    new_game_writer.send(NewGame::from_position(position));
+                    continue;
+                }
+                Err(error) => editor.error = Some(error)
+            },
+            EditorAction::Cancel => {
+                let Some(stash) = editor.stash.take() else { continue };
+                board.0 = stash.board;
+                history.0 = stash.history;
+                *clock = stash.clock;
+                next_phase.set(stash.phase);
+                next_editor.set(EditorState::Closed);
+            }
+        }
+        board_update_writer.send(BoardUpdate::resync());
+    }
+}
+
+pub fn close_editor(
+    mut new_game_listener: EventReader<NewGame>,
+    editor_state: Res<State<EditorState>>,
+    mut editor: ResMut<BoardEditor>,
+    mut next_editor: ResMut<NextState<EditorState>>
+) {
+    if new_game_listener.read().count() == 0 || *editor_state.get() == EditorState::Closed { return };
+    editor.stash = None;
+    next_editor.set(EditorState::Closed);
+}
+
+pub fn paint_squares(
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    cursor: Option<Res<WorldCursor>>,
+    mut editor: ResMut<BoardEditor>,
+    mut board: ResMut<BoardResource>,
+    mut board_update_writer: EventWriter<BoardUpdate>,
+    mut stroke: Local<bool>
+) {
+    if mouse_button.any_just_pressed([MouseButton::Left, MouseButton::Right]) {
+        *stroke = cursor.as_ref().is_some_and(|cursor| cursor.square.is_some());
+    }
+    if !*stroke { return };
+    let Some(square) = cursor.and_then(|cursor| cursor.square) else { return };
+    let brush = match (mouse_button.pressed(MouseButton::Left), mouse_button.pressed(MouseButton::Right)) {
+        (_, true) => None,
+        (true, false) => editor.brush,
+        (false, false) => return
+    };
+    let wanted = brush.map(|(color, kind)| Piece {kind, color, square, moved: true});
+    let current = board.0.pieces.get(&square).map(|piece| (piece.color, piece.kind));
+    if current == brush { return };
+    match wanted {
+        Some(piece) => board.0.pieces.insert(square, piece),
+        None => board.0.pieces.remove(&square)
+    };
+    editor.error = None;
+    board_update_writer.send(BoardUpdate::resync());
+}
+
+pub fn update_editor_panel(
+    editor: Res<BoardEditor>,
+    board: Res<BoardResource>,
+    mut button_query: Query<(&EditorButton, &mut BorderColor, &Children)>,
+    mut text_query: Query<&mut Text, Without<EditorError>>,
+    mut error_query: Query<&mut Text, With<EditorError>>
+) {
+    for (EditorButton(action), mut border, children) in button_query.iter_mut() {
+        let active = match *action {
+            EditorAction::Brush(brush) => brush == editor.brush,
+            EditorAction::Castling(index) => editor.castling[index],
+            _ => false
+        };
+        border.0 = if active { Color::WHITE } else { Color::NONE };
+        if *action != EditorAction::SideToMove { continue };
+        let mut texts = text_query.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].value = format!("{} to move", if board.0.on_move == PieceColor::WHITE { "White" } else { "Black" });
+        }
+    }
+    for mut text in error_query.iter_mut() {
+        text.sections[0].value = editor.error.clone().unwrap_or_default();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chess_logic::logic::STANDARD_START;
+    use super::*;
+
+    #[test]
+    fn start_position_keeps_all_castling_rights() {
+        let editor = BoardEditor {castling: [true; 4], ..default()};
+        for start in [Board::new(), Board::from_fen(STANDARD_START).unwrap()] {
+            let board = editor.build(&start).unwrap();
+            assert_eq!(board.to_fen().split_whitespace().nth(2), Some("KQkq"));
+        }
+    }
+
+    #[test]
+    fn castling_without_a_rook_is_rejected() {
+        let editor = BoardEditor {castling: [true, false, false, false], ..default()};
+        let board = Board::from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 0 1").unwrap();
+        assert!(editor.build(&board).is_err());
+    }
+}
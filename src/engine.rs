@@ -0,0 +1,128 @@
+use std::sync::{Arc, Mutex};
+use bevy::prelude::*;
+use bevy::tasks::AsyncComputeTaskPool;
+use chess_logic::logic::Board;
+use chess_logic::search::{search_lines, SearchLimits, SearchResult};
+use crate::arrows::ArrowPainter;
+use crate::board::{BoardResource, GamePhase};
+use crate::piece::BoardUpdate;
+use crate::settings::Settings;
+
+const HINT_DEPTH: u32 = 4;
+const HINT_TIME_BUDGET: f32 = 0.5;
+
+pub struct EngineJob {
+    limits: SearchLimits,
+    result: Arc<Mutex<Option<Option<SearchResult>>>>
+}
+
+impl EngineJob {
+    pub fn spawn(board: Board, limits: SearchLimits) -> Self {
+        EngineJob::spawn_choosing(board, limits, 1, |lines| lines.into_iter().next())
+    }
+
+    pub fn spawn_choosing(
+        board: Board,
+        limits: SearchLimits,
+        lines: usize,
+        choose: impl FnOnce(Vec<SearchResult>) -> Option<SearchResult> + Send + 'static
+    ) -> Self {
+        let result = Arc::new(Mutex::new(None));
+        let (task_limits, task_result) = (limits.clone(), result.clone());
+        AsyncComputeTaskPool::get().spawn(async move {
+            let found = choose(search_lines(&board, &task_limits, lines, |_| {}));
+            *task_result.lock().unwrap() = Some(found);
+        }).detach();
+        EngineJob {limits, result}
+    }
+
+    pub fn stop(&self) {
+        self.limits.stop();
+    }
+
+    pub fn try_take(&self) -> Option<Option<SearchResult>> {
+        self.result.lock().unwrap().take()
+    }
+}
+
+pub struct LinesJob {
+    limits: SearchLimits,
+    lines: Arc<Mutex<Option<Vec<SearchResult>>>>
+}
+
+impl LinesJob {
+    pub fn spawn(board: Board, limits: SearchLimits, count: usize) -> Self {
+        let lines = Arc::new(Mutex::new(None));
+        let (task_limits, task_lines) = (limits.clone(), lines.clone());
+        AsyncComputeTaskPool::get().spawn(async move {
+            search_lines(&board, &task_limits, count, |found| *task_lines.lock().unwrap() = Some(found.to_vec()));
+        }).detach();
+        LinesJob {limits, lines}
+    }
+
+    pub fn take_update(&self) -> Option<Vec<SearchResult>> {
+        self.lines.lock().unwrap().take()
+    }
+}
+
+impl Drop for LinesJob {
+    fn drop(&mut self) {
+        self.limits.stop();
+    }
+}
+
+#[derive(Event)]
+pub struct HintRequested;
+
+#[derive(Component)]
+pub struct HintArrow;
+
+#[derive(Resource)]
+pub struct HintSearch {
+    job: EngineJob,
+    budget: Timer
+}
+
+pub fn start_hint_search(
+    mut hint_listener: EventReader<HintRequested>,
+    settings: Res<Settings>,
+    board: Res<BoardResource>,
+    phase: Res<State<GamePhase>>,
+    hint_search: Option<Res<HintSearch>>,
+    mut commands: Commands
+) {
+    if hint_listener.read().count() == 0 { return };
+    if !settings.allow_hints || hint_search.is_some() || *phase.get() != GamePhase::Playing { return };
+    commands.insert_resource(HintSearch {
+        job: EngineJob::spawn(board.0.clone(), SearchLimits::new(HINT_DEPTH)),
+        budget: Timer::from_seconds(HINT_TIME_BUDGET, TimerMode::Once)
+    });
+}
+
+pub fn poll_hint_search(
+    time: Res<Time>,
+    hint_search: Option<ResMut<HintSearch>>,
+    mut board_update_listener: EventReader<BoardUpdate>,
+    hint_arrows: Query<Entity, With<HintArrow>>,
+    mut painter: ArrowPainter
+) {
+    if board_update_listener.read().count() > 0 {
+        for entity in hint_arrows.iter() {
+            painter.commands().entity(entity).despawn_recursive();
+        }
+        if let Some(hint_search) = hint_search.as_ref() {
+            hint_search.job.stop();
+            painter.commands().remove_resource::<HintSearch>();
+        }
+        return;
+    }
+    let Some(mut hint_search) = hint_search else { return };
+    if hint_search.budget.tick(time.delta()).just_finished() {
+        hint_search.job.stop();
+    }
+    let Some(result) = hint_search.job.try_take() else { return };
+    painter.commands().remove_resource::<HintSearch>();
+    let Some(result) = result else { return };
+    let arrow = painter.spawn_arrow(result.best_move.from, result.best_move.to, painter.palette().hint_arrow());
+    painter.commands().entity(arrow).insert(HintArrow);
+}
@@ -0,0 +1,85 @@
+use std::time::Duration;
+use bevy::prelude::*;
+use chess_logic::logic::{Board, Move};
+use chess_logic::search::SearchResult;
+use crate::arrows::ArrowPainter;
+use crate::board::BoardResource;
+use crate::history::HistoryView;
+use crate::opponent::EngineDriver;
+use crate::piece::PieceComponent;
+use crate::settings::Settings;
+use crate::toast::{ToastLevel, Toasts};
+
+const ANALYSIS_LINES: usize = 3;
+const ARROW_REFRESH: Duration = Duration::from_millis(400);
+const SECONDARY_ARROW_SCALE: f32 = 0.6;
+const SECONDARY_ARROW_ALPHA: f32 = 0.4;
+
+#[derive(Resource, Default)]
+pub struct EngineLines {
+    pub position: Option<Board>,
+    pub lines: Vec<SearchResult>,
+    engine: Option<Box<dyn EngineDriver>>
+}
+
+#[derive(Component)]
+pub struct EngineArrow;
+
+pub fn analyse_position(
+    board: Res<BoardResource>,
+    view: Res<HistoryView>,
+    settings: Res<Settings>,
+    mut toasts: ResMut<Toasts>,
+    mut engine_lines: ResMut<EngineLines>
+) {
+    let shown = view.displayed(&board.0);
+    let fen = shown.to_fen();
+    if engine_lines.position.as_ref().map(Board::to_fen) == Some(fen) {
+        let Some(update) = engine_lines.engine.as_mut().and_then(|engine| engine.poll_lines()) else { return };
+        engine_lines.lines = update;
+        return;
+    }
+    engine_lines.position = Some(shown.clone());
+    engine_lines.lines.clear();
+    let engine = engine_lines.engine.take().map_or_else(|| settings.analysis_engine.open(0), Ok).or_else(|error| {
+        toasts.push(ToastLevel::Error, format!("Could not start the analysis engine: {}; using the built-in engine", error));
+        Settings::default().analysis_engine.open(0)
+    });
+    let Ok(mut engine) = engine else { return };
+    engine.analyse(shown, ANALYSIS_LINES);
+    engine_lines.engine = Some(engine);
+}
+
+pub fn stop_position_analysis(mut engine_lines: ResMut<EngineLines>) {
+    if engine_lines.position.is_some() {
+        *engine_lines = EngineLines::default();
+    }
+}
+
+pub fn update_engine_arrows(
+    time: Res<Time>,
+    engine_lines: Res<EngineLines>,
+    pieces: Query<&PieceComponent>,
+    arrows: Query<Entity, With<EngineArrow>>,
+    mut painter: ArrowPainter,
+    mut drawn: Local<Vec<Move>>,
+    mut since_drawn: Local<Duration>
+) {
+    *since_drawn += time.delta();
+    let dragging = pieces.iter().any(PieceComponent::is_dragged);
+    let wanted: Vec<Move> = if dragging { Vec::new() } else { engine_lines.lines.iter().map(|line| line.best_move).collect() };
+    if *drawn == wanted || (!wanted.is_empty() && !drawn.is_empty() && *since_drawn < ARROW_REFRESH) { return };
+    for entity in arrows.iter() {
+        painter.commands().entity(entity).despawn_recursive();
+    }
+    let color = painter.palette().engine_arrow();
+    for (rank, candidate) in wanted.iter().enumerate().rev() {
+        let arrow = match rank {
+            0 => painter.spawn_arrow(candidate.from, candidate.to, color),
+            _ => painter.spawn_sized_arrow(candidate.from, candidate.to, color.with_a(SECONDARY_ARROW_ALPHA), SECONDARY_ARROW_SCALE)
+        };
+        painter.commands().entity(arrow).insert(EngineArrow);
+    }
+    *drawn = wanted;
+    *since_drawn = Duration::ZERO;
+}
@@ -0,0 +1,331 @@
+use bevy::prelude::*;
+use chess_logic::logic::{PieceColor, PieceKind};
+use crate::board::{BoardResource, GameOverEvent, GamePhase, LocalSide, MenuState};
+use crate::clock::ChessClock;
+use crate::network::NetworkSession;
+use crate::opponent::{EngineDriver, EngineSpec};
+use crate::piece::{MoveRequested, NewGame};
+use crate::players::{GameMetadata, PlayerInfo};
+use crate::settings::Settings;
+use crate::shortcuts::TextInputFocused;
+use crate::spectator::GameReplacer;
+use crate::toast::{ToastLevel, Toasts};
+
+// The search in flight, tagged with the position it was asked about so that a result
+// arriving after the board changed underneath it is thrown away instead of played
+struct SearchTask {
+    color: PieceColor,
+    position: u64
+}
+
+// A side without an engine is played by hand
+struct MatchSession {
+    engines: [Option<Box<dyn EngineDriver>>; 2],
+    names: [String; 2],
+    thinking: Option<SearchTask>,
+    pause_requested: bool,
+    paused: Option<bool>,
+    ponder: bool,
+    result: Option<String>,
+    saved: (Option<PieceColor>, GameMetadata)
+}
+
+impl MatchSession {
+    fn engine(&mut self, color: PieceColor) -> Option<&mut Box<dyn EngineDriver>> {
+        match color {
+            PieceColor::WHITE => self.engines[0].as_mut(),
+            PieceColor::BLACK => self.engines[1].as_mut()
+        }
+    }
+
+    fn stop_thinking(&mut self) {
+        if let Some(engine) = self.thinking.take().and_then(|search| self.engine(search.color)) {
+            engine.stop();
+        }
+    }
+
+    fn stop_engines(&mut self) {
+        self.thinking = None;
+        for engine in self.engines.iter_mut().flatten() {
+            engine.stop();
+        }
+    }
+}
+
+// The --match and --seed command line options
+#[derive(Clone, Default)]
+pub struct MatchArgs {
+    engines: Option<[EngineSpec; 2]>,
+    seed: Option<u64>
+}
+
+impl MatchArgs {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Self {
+        let mut args = args.into_iter();
+        let mut match_args = MatchArgs::default();
+        while let Some(arg) = args.next() {
+            if arg == "--seed" {
+                match args.next().and_then(|seed| seed.parse().ok()) {
+                    Some(seed) => match_args.seed = Some(seed),
+                    None => warn!("--seed expects a number")
+                }
+                continue;
+            }
+            if arg != "--match" { continue };
+            match (args.next(), args.next()) {
+                (Some(white), Some(black)) => match_args.engines = Some([EngineSpec::parse(&white), EngineSpec::parse(&black)]),
+                _ => warn!("--match expects two players, each human, bot:<level> or a UCI engine path, optionally followed by @<elo>")
+            }
+        }
+        match_args
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct EngineMatch {
+    queued: Option<[EngineSpec; 2]>,
+    seed: Option<u64>,
+    session: Option<MatchSession>
+}
+
+impl EngineMatch {
+    pub fn new(args: MatchArgs) -> Self {
+        EngineMatch {queued: args.engines, seed: args.seed, session: None}
+    }
+
+    fn end(&mut self, local_side: &mut LocalSide, metadata: &mut GameMetadata) {
+        let Some(mut session) = self.session.take() else { return };
+        session.stop_engines();
+        (local_side.0, *metadata) = session.saved;
+    }
+}
+
+pub fn engine_match_idle(engine_match: Res<EngineMatch>) -> bool {
+    engine_match.session.is_none()
+}
+
+// Board input stays on while someone plays one side of the match by hand
+pub fn engine_match_takes_input(engine_match: Res<EngineMatch>) -> bool {
+    engine_match.session.as_ref().is_none_or(|session| session.engines.iter().any(Option::is_none))
+}
+
+pub fn engine_match_shortcuts(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    text_input_focused: Res<TextInputFocused>,
+    menu_state: Res<State<MenuState>>,
+    mut settings: ResMut<Settings>,
+    mut clock: ResMut<ChessClock>,
+    mut engine_match: ResMut<EngineMatch>,
+    mut next_menu_state: ResMut<NextState<MenuState>>
+) {
+    if text_input_focused.0 || keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight, KeyCode::SuperLeft, KeyCode::SuperRight]) { return };
+    if keyboard.just_pressed(KeyCode::KeyE) && *menu_state.get() == MenuState::Open {
+        engine_match.queued = Some(settings.match_engines.clone());
+        next_menu_state.set(MenuState::Closed);
+    }
+    let Some(session) = engine_match.session.as_mut().filter(|session| session.result.is_none()) else { return };
+    if keyboard.just_pressed(KeyCode::Space) {
+        match session.paused.take() {
+            Some(was_running) => clock.set_running(was_running),
+            None => session.pause_requested = !session.pause_requested
+        }
+    }
+    if keyboard.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter]) {
+        if let Some(engine) = session.thinking.as_ref().map(|search| search.color).and_then(|color| session.engine(color)) {
+            engine.move_now();
+        }
+    }
+    if keyboard.just_pressed(KeyCode::KeyO) {
+        session.ponder = !session.ponder;
+        settings.engine_ponder = session.ponder;
+        let thinking = session.thinking.as_ref().map(|search| search.color);
+        for color in [PieceColor::WHITE, PieceColor::BLACK] {
+            if session.ponder || thinking == Some(color) { continue };
+            if let Some(engine) = session.engine(color) {
+                engine.stop();
+            }
+        }
+    }
+}
+
+pub fn start_engine_match(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    network: Option<Res<NetworkSession>>,
+    mut engine_match: ResMut<EngineMatch>,
+    mut game: GameReplacer,
+    mut clock: ResMut<ChessClock>,
+    mut local_side: ResMut<LocalSide>,
+    mut metadata: ResMut<GameMetadata>,
+    mut toasts: ResMut<Toasts>
+) {
+    let Some(specs) = engine_match.queued.take() else { return };
+    if network.is_some() {
+        toasts.push(ToastLevel::Warning, "Engine matches are not available during a network game");
+        return;
+    }
+    if specs.iter().all(|spec| *spec == EngineSpec::Human) {
+        toasts.push(ToastLevel::Warning, "An engine match needs at least one engine");
+        return;
+    }
+    let seed = engine_match.seed.unwrap_or_else(|| time.elapsed().as_nanos() as u64);
+    let engines = specs.iter().enumerate().map(|(index, spec)| match spec {
+        EngineSpec::Human => Ok(None),
+        spec => spec.open(seed ^ index as u64).map(Some)
+    });
+    let engines = match engines.collect::<Result<Vec<_>, _>>() {
+        Ok(engines) => engines,
+        Err(error) => { toasts.push(ToastLevel::Error, format!("Could not start the engine match: {}", error)); return }
+    };
+    let Ok(engines) = <[Option<Box<dyn EngineDriver>>; 2]>::try_from(engines) else { return };
+    let human = [PieceColor::WHITE, PieceColor::BLACK].into_iter().zip(&engines).find(|(_, engine)| engine.is_none()).map(|(color, _)| color);
+    if let Err(error) = game.replace(None, &[]) {
+        warn!("failed to reset the board for the engine match: {}", error);
+        return;
+    }
+    engine_match.end(&mut local_side, &mut metadata);
+    let names = specs.clone().map(|spec| spec.name());
+    engine_match.session = Some(MatchSession {
+        engines,
+        names: names.clone(),
+        thinking: None,
+        pause_requested: false,
+        paused: None,
+        ponder: settings.engine_ponder,
+        result: None,
+        saved: (local_side.0, metadata.clone())
+    });
+    let [white, black] = specs;
+    metadata.white = PlayerInfo {name: white.name(), rating: white.elo()};
+    metadata.black = PlayerInfo {name: black.name(), rating: black.elo()};
+    local_side.0 = human;
+    *clock = ChessClock::new(settings.time_control);
+}
+
+pub fn end_engine_match(
+    mut new_game_listener: EventReader<NewGame>,
+    mut engine_match: ResMut<EngineMatch>,
+    mut local_side: ResMut<LocalSide>,
+    mut metadata: ResMut<GameMetadata>
+) {
+    if new_game_listener.read().count() == 0 { return };
+    engine_match.end(&mut local_side, &mut metadata);
+}
+
+pub fn drive_engine_match(
+    time: Res<Time>,
+    phase: Res<State<GamePhase>>,
+    board: Res<BoardResource>,
+    mut clock: ResMut<ChessClock>,
+    mut engine_match: ResMut<EngineMatch>,
+    mut toasts: ResMut<Toasts>,
+    mut move_request_writer: EventWriter<MoveRequested>
+) {
+    let Some(session) = engine_match.session.as_mut() else { return };
+    if *phase.get() != GamePhase::Playing {
+        session.stop_engines();
+        return;
+    }
+    let position = board.0.position_key();
+    if session.thinking.as_ref().is_some_and(|search| search.position != position) {
+        session.stop_thinking();
+    }
+    let Some(color) = session.thinking.as_ref().map(|search| search.color) else {
+        if session.paused.is_some() { return };
+        if session.pause_requested {
+            session.pause_requested = false;
+            session.paused = Some(clock.is_running());
+            clock.set_running(false);
+            return;
+        }
+        let on_move = board.0.on_move;
+        let Some(engine) = session.engine(on_move) else { return };
+        engine.think(&board.0, &clock);
+        session.thinking = Some(SearchTask {color: on_move, position});
+        if !session.ponder { return };
+        if let Some(opponent) = session.engine(on_move.opposite()) {
+            opponent.ponder(&board.0, &clock);
+        }
+        return;
+    };
+    let Some(outcome) = session.engine(color).and_then(|engine| engine.poll(time.delta())) else { return };
+    session.thinking = None;
+    let name = session.names[if color == PieceColor::WHITE { 0 } else { 1 }].clone();
+    let candidate = match outcome {
+        Ok(candidate) if board.0.clone().try_move(candidate).is_ok() => candidate,
+        Ok(candidate) => {
+            toasts.push(ToastLevel::Error, format!("{} played the illegal move {}; the match is paused", name, candidate));
+            session.paused = Some(clock.is_running());
+            clock.set_running(false);
+            return;
+        }
+        Err(error) => {
+            toasts.push(ToastLevel::Error, format!("{} failed: {}; the match is paused", name, error));
+            session.paused = Some(clock.is_running());
+            clock.set_running(false);
+            return;
+        }
+    };
+    move_request_writer.send(MoveRequested {from: candidate.from, to: candidate.to, promotion: candidate.promotion.or(Some(PieceKind::QUEEN))});
+}
+
+pub fn record_engine_match_result(
+    mut game_over_listener: EventReader<GameOverEvent>,
+    mut engine_match: ResMut<EngineMatch>,
+    mut toasts: ResMut<Toasts>
+) {
+    let Some(game_over) = game_over_listener.read().last() else { return };
+    let Some(session) = engine_match.session.as_mut() else { return };
+    session.stop_engines();
+    let [white, black] = &session.names;
+    let result = format!("{} {} {}", white, game_over.result.result(), black);
+    info!("engine match finished: {}", result);
+    toasts.push(ToastLevel::Info, format!("Engine match over: {}", result));
+    session.result = Some(result);
+}
+
+#[derive(Component)]
+pub struct EngineMatchBanner;
+
+pub fn spawn_engine_match_banner(mut commands: Commands) {
+    commands.spawn((TextBundle {
+        text: Text::from_section("", TextStyle { font_size: 18.0, color: Color::rgb(1.0, 0.85, 0.5), ..default() }),
+        style: Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(48.0),
+            left: Val::Px(12.0),
+            padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+            ..default()
+        },
+        background_color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+        visibility: Visibility::Hidden,
+        z_index: ZIndex::Global(15),
+        ..default()
+    }, EngineMatchBanner));
+}
+
+pub fn update_engine_match_banner(engine_match: Res<EngineMatch>, mut banner_query: Query<(&mut Text, &mut Visibility), With<EngineMatchBanner>>) {
+    let label = engine_match.session.as_ref().map(|session| {
+        let status = match (&session.result, session.paused, session.pause_requested) {
+            (Some(result), _, _) => format!("Finished: {} - N starts a new game", result),
+            (None, Some(_), _) => String::from("Paused - Space resumes"),
+            (None, None, true) => String::from("Pausing after this move - Space cancels"),
+            (None, None, false) => String::from("Space pauses after the current move")
+        };
+        let ponder = if session.ponder { "on" } else { "off" };
+        format!(
+            "Engine match - {} vs {}\n{}\nEnter - move now, O - pondering {} (uses CPU on the opponent's time)",
+            session.names[0], session.names[1], status, ponder
+        )
+    });
+    let wanted = if label.is_some() { Visibility::Inherited } else { Visibility::Hidden };
+    for (mut text, mut visibility) in banner_query.iter_mut() {
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+        if let Some(label) = label.as_ref().filter(|label| text.sections[0].value != **label) {
+            text.sections[0].value = label.clone();
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use bevy::prelude::*;
+use bevy::sprite::Anchor;
+use chess_logic::logic::{Board, GameStatus, PieceColor};
+use chess_logic::search::{evaluate, mate_in, SearchLimits};
+use crate::board::{BoardOrientation, BoardResource};
+use crate::config::ChessConfig;
+use crate::engine::EngineJob;
+use crate::history::HistoryView;
+use crate::settings::Settings;
+
+const EVAL_DEPTH: u32 = 5;
+const EVAL_TIME_BUDGET: f32 = 1.0;
+const EVAL_CLAMP: f32 = 1000.0;
+const EVAL_BAR_SPEED: f32 = 6.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Evaluation {
+    Centipawns(i32),
+    Mate { winner: PieceColor, moves: i32 }
+}
+
+impl Evaluation {
+    pub fn from_score(score: i32, on_move: PieceColor) -> Self {
+        let winner = |mating: bool| if mating { on_move } else { on_move.opposite() };
+        match mate_in(score) {
+            Some(moves) => Evaluation::Mate { winner: winner(moves > 0), moves: moves.abs() },
+            None if on_move == PieceColor::WHITE => Evaluation::Centipawns(score),
+            None => Evaluation::Centipawns(-score)
+        }
+    }
+
+    fn of_board(board: &Board) -> Self {
+        match board.status() {
+            GameStatus::Checkmate { winner } => Evaluation::Mate { winner, moves: 0 },
+            GameStatus::Ongoing => Evaluation::from_score(evaluate(board), board.on_move),
+            _ => Evaluation::Centipawns(0)
+        }
+    }
+
+    fn white_share(&self) -> f32 {
+        match *self {
+            Evaluation::Centipawns(score) => 0.5 + (score as f32).clamp(-EVAL_CLAMP, EVAL_CLAMP) / EVAL_CLAMP / 2.0,
+            Evaluation::Mate { winner: PieceColor::WHITE, .. } => 1.0,
+            Evaluation::Mate { .. } => 0.0
+        }
+    }
+
+    fn favors(&self) -> PieceColor {
+        match *self {
+            Evaluation::Centipawns(score) if score < 0 => PieceColor::BLACK,
+            Evaluation::Centipawns(_) => PieceColor::WHITE,
+            Evaluation::Mate { winner, .. } => winner
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match *self {
+            Evaluation::Centipawns(score) => format!("{:+.1}", score as f32 / 100.0),
+            Evaluation::Mate { moves: 0, .. } => String::from("#"),
+            Evaluation::Mate { moves, .. } => format!("M{}", moves)
+        }
+    }
+}
+
+#[derive(Resource)]
+pub struct EvalBar {
+    pub evaluation: Evaluation,
+    shown: f32,
+    search: Option<(EngineJob, PieceColor, Timer)>
+}
+
+impl Default for EvalBar {
+    fn default() -> Self {
+        EvalBar {evaluation: Evaluation::Centipawns(0), shown: 0.5, search: None}
+    }
+}
+
+#[derive(Component)]
+pub struct EvalBarRoot;
+
+#[derive(Component)]
+pub struct EvalBarFill;
+
+#[derive(Component)]
+pub struct EvalBarText;
+
+pub fn spawn_eval_bar(mut commands: Commands) {
+    commands.spawn((SpatialBundle::default(), EvalBarRoot)).with_children(|bar| {
+        bar.spawn(SpriteBundle {
+            sprite: Sprite { color: Color::rgb(0.15, 0.15, 0.15), anchor: Anchor::BottomCenter, ..default() },
+            ..default()
+        });
+        bar.spawn((SpriteBundle {
+            sprite: Sprite { color: Color::rgb(0.92, 0.92, 0.92), anchor: Anchor::BottomCenter, ..default() },
+            transform: Transform::from_xyz(0.0, 0.0, 0.1),
+            ..default()
+        }, EvalBarFill));
+    });
+    commands.spawn((Text2dBundle {
+        text: Text::from_section("", TextStyle { color: Color::WHITE, ..default() }),
+        ..default()
+    }, EvalBarText));
+}
+
+pub fn evaluate_position(board: Res<BoardResource>, view: Res<HistoryView>, settings: Res<Settings>, mut eval_bar: ResMut<EvalBar>) {
+    if let Some((job, _, _)) = eval_bar.search.take() {
+        job.stop();
+    }
+    let shown = view.displayed(&board.0);
+    eval_bar.evaluation = Evaluation::of_board(shown);
+    if settings.show_eval_bar && settings.engine_evaluation && shown.status() == GameStatus::Ongoing {
+        let on_move = shown.on_move;
+        let job = EngineJob::spawn(shown.clone(), SearchLimits::new(EVAL_DEPTH));
+        eval_bar.search = Some((job, on_move, Timer::from_seconds(EVAL_TIME_BUDGET, TimerMode::Once)));
+    }
+}
+
+pub fn poll_eval_search(time: Res<Time>, mut eval_bar: ResMut<EvalBar>) {
+    let Some((job, on_move, budget)) = eval_bar.search.as_mut() else { return };
+    if budget.tick(time.delta()).just_finished() {
+        job.stop();
+    }
+    let Some(result) = job.try_take() else { return };
+    let on_move = *on_move;
+    eval_bar.search = None;
+    if let Some(result) = result {
+        eval_bar.evaluation = Evaluation::from_score(result.score, on_move);
+    }
+}
+
+pub fn update_eval_bar(
+    time: Res<Time>,
+    config: Res<ChessConfig>,
+    settings: Res<Settings>,
+    orientation: Res<BoardOrientation>,
+    mut eval_bar: ResMut<EvalBar>,
+    mut root_query: Query<(&mut Transform, &mut Visibility, &Children), With<EvalBarRoot>>,
+    mut sprite_query: Query<(&mut Sprite, Has<EvalBarFill>)>,
+    mut text_query: Query<(&mut Text, &mut Transform, &mut Visibility), (With<EvalBarText>, Without<EvalBarRoot>)>
+) {
+    let target = eval_bar.evaluation.white_share();
+    eval_bar.shown = match settings.reduced_motion {
+        true => target,
+        false => eval_bar.shown + (target - eval_bar.shown) * (time.delta_seconds() * EVAL_BAR_SPEED).min(1.0)
+    };
+    let visibility = if settings.show_eval_bar { Visibility::Inherited } else { Visibility::Hidden };
+    let size = config.square_size;
+    let (x, angle) = if orientation.0 == PieceColor::WHITE { (-1.15, 0.0) } else { (8.15, std::f32::consts::PI) };
+    for (mut transform, mut root_visibility, children) in root_query.iter_mut() {
+        *transform = Transform::from_xyz(x * size, -0.5 * size, 0.5);
+        *root_visibility = visibility;
+        for child in children.iter() {
+            let Ok((mut sprite, fill)) = sprite_query.get_mut(*child) else { continue };
+            let height = if fill { eval_bar.shown * 8.0 } else { 8.0 };
+            sprite.custom_size = Some(Vec2::new(size * 0.2, size * height));
+        }
+    }
+    let y = if eval_bar.evaluation.favors() == PieceColor::WHITE { -0.8 } else { 7.8 };
+    let label = eval_bar.evaluation.label();
+    for (mut text, mut transform, mut text_visibility) in text_query.iter_mut() {
+        if text.sections[0].value != label {
+            text.sections[0].value = label.clone();
+        }
+        text.sections[0].style.font_size = size * 0.22;
+        *transform = Transform::from_xyz(x * size, y * size, 0.5).with_rotation(Quat::from_rotation_z(angle));
+        *text_visibility = visibility;
+    }
+}
@@ -0,0 +1,204 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use chess_logic::logic::Board;
+use crate::correspondence::{ApplyMoveString, MoveString};
+use crate::import::{GameUrl, ImportGameRequested};
+use crate::piece::NewGame;
+#[cfg(not(feature = "egui"))]
+use crate::board::BoardResource;
+#[cfg(not(feature = "egui"))]
+use crate::clipboard::{ClipboardRequest, PasteRequested};
+#[cfg(not(feature = "egui"))]
+use crate::correspondence::ExportMoveRequested;
+#[cfg(not(feature = "egui"))]
+use crate::history::HistoryView;
+#[cfg(not(feature = "egui"))]
+use crate::shortcuts::TextInputFocused;
+
+#[derive(Resource, Default)]
+pub struct FenInput {
+    pub text: String,
+    pub editing: bool,
+    pub error: Option<String>
+}
+
+#[derive(SystemParam)]
+pub struct PositionLoader<'w> {
+    new_game_writer: EventWriter<'w, NewGame>,
+    import_writer: EventWriter<'w, ImportGameRequested>,
+    move_string_writer: EventWriter<'w, ApplyMoveString>
+}
+
+impl FenInput {
+    pub fn submit(&mut self, text: &str, loader: &mut PositionLoader) {
+        self.error = None;
+        if MoveString::detect(text) {
+            loader.move_string_writer.send(ApplyMoveString(text.to_string()));
+            return;
+        }
+        if let Some(url) = GameUrl::parse(text) {
+            loader.import_writer.send(ImportGameRequested(url));
+            return;
+        }
+        match Board::from_fen(text.trim()) {
+            Ok(board) => { loader.new_game_writer.send(NewGame::from_position(board)); }
+            Err(error) => self.error = Some(error.to_string())
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct FenField;
+
+#[derive(Component)]
+pub struct FenFieldText;
+
+#[derive(Component)]
+pub struct FenErrorText;
+
+#[cfg(not(feature = "egui"))]
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum FenButton {
+    Copy,
+    Load,
+    ExportMove,
+    ApplyMove
+}
+
+#[cfg(not(feature = "egui"))]
+pub fn spawn_fen_field(panel: &mut ChildBuilder) {
+    panel.spawn((ButtonBundle {
+        style: Style {
+            margin: UiRect::top(Val::Px(8.0)),
+            padding: UiRect::all(Val::Px(4.0)),
+            ..default()
+        },
+        background_color: Color::rgb(0.18, 0.18, 0.2).into(),
+        ..default()
+    }, FenField)).with_children(|field| {
+        field.spawn((TextBundle::from_section("", TextStyle { font_size: 14.0, color: Color::WHITE, ..default() }), FenFieldText));
+    });
+    for buttons in [[(FenButton::Copy, "Copy"), (FenButton::Load, "Load")], [(FenButton::ExportMove, "Export move"), (FenButton::ApplyMove, "Apply move")]] {
+        panel.spawn(NodeBundle {
+            style: Style {
+                margin: UiRect::top(Val::Px(4.0)),
+                column_gap: Val::Px(4.0),
+                ..default()
+            },
+            ..default()
+        }).with_children(|row| {
+            for (button, label) in buttons {
+                row.spawn((ButtonBundle {
+                    style: Style {
+                        flex_grow: 1.0,
+                        padding: UiRect::all(Val::Px(4.0)),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.25, 0.25, 0.3).into(),
+                    ..default()
+                }, button)).with_children(|button| {
+                    button.spawn(TextBundle::from_section(label, TextStyle { font_size: 16.0, color: Color::WHITE, ..default() }));
+                });
+            }
+        });
+    }
+    panel.spawn((TextBundle::from_section("", TextStyle { font_size: 14.0, color: Color::rgb(1.0, 0.45, 0.4), ..default() }), FenErrorText));
+}
+
+#[cfg(not(feature = "egui"))]
+pub fn fen_field_input(
+    field_query: Query<&Interaction, With<FenField>>,
+    button_query: Query<(&Interaction, &FenButton), Changed<Interaction>>,
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    mut keyboard: ResMut<ButtonInput<KeyCode>>,
+    mut character_listener: EventReader<ReceivedCharacter>,
+    board: Res<BoardResource>,
+    view: Res<HistoryView>,
+    mut fen_input: ResMut<FenInput>,
+    mut text_input_focused: ResMut<TextInputFocused>,
+    mut clipboard_writer: EventWriter<ClipboardRequest>,
+    mut paste_writer: EventWriter<PasteRequested>,
+    mut export_writer: EventWriter<ExportMoveRequested>,
+    mut loader: PositionLoader
+) {
+    if mouse_button.just_pressed(MouseButton::Left) {
+        let on_field = field_query.iter().any(|interaction| *interaction == Interaction::Pressed);
+        if on_field && !fen_input.editing {
+            fen_input.editing = true;
+            fen_input.error = None;
+            text_input_focused.0 = true;
+        } else if !on_field && fen_input.editing {
+            fen_input.editing = false;
+            text_input_focused.0 = false;
+        }
+    }
+    for (interaction, button) in button_query.iter() {
+        if *interaction != Interaction::Pressed { continue };
+        match button {
+            FenButton::Copy => { clipboard_writer.send(ClipboardRequest(view.displayed(&board.0).to_fen())); }
+            FenButton::Load => {
+                let text = fen_input.text.clone();
+                fen_input.submit(&text, &mut loader);
+                fen_input.editing = false;
+                text_input_focused.0 = false;
+            }
+            FenButton::ExportMove => { export_writer.send(ExportMoveRequested); }
+            FenButton::ApplyMove => { paste_writer.send(PasteRequested); }
+        }
+    }
+    if !fen_input.editing {
+        character_listener.clear();
+        let fen = view.displayed(&board.0).to_fen();
+        if fen_input.text != fen {
+            fen_input.text = fen;
+        }
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::Escape) {
+        fen_input.editing = false;
+        fen_input.error = None;
+        text_input_focused.0 = false;
+        return;
+    }
+    if keyboard.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter]) {
+        keyboard.clear_just_pressed(KeyCode::Enter);
+        keyboard.clear_just_pressed(KeyCode::NumpadEnter);
+        let text = fen_input.text.clone();
+        fen_input.submit(&text, &mut loader);
+        fen_input.editing = false;
+        text_input_focused.0 = false;
+        return;
+    }
+    let control = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight, KeyCode::SuperLeft, KeyCode::SuperRight]);
+    if control && keyboard.just_pressed(KeyCode::KeyV) {
+        paste_writer.send(PasteRequested);
+    }
+    if keyboard.just_pressed(KeyCode::Delete) {
+        fen_input.text.clear();
+    }
+    if keyboard.just_pressed(KeyCode::Backspace) {
+        fen_input.text.pop();
+    }
+    for received in character_listener.read().filter(|_| !control) {
+        fen_input.text.extend(received.char.chars().filter(|character| !character.is_control()));
+    }
+}
+
+pub fn update_fen_field(
+    fen_input: Res<FenInput>,
+    mut field_text_query: Query<&mut Text, (With<FenFieldText>, Without<FenErrorText>)>,
+    mut error_text_query: Query<&mut Text, With<FenErrorText>>,
+    mut field_query: Query<&mut BackgroundColor, With<FenField>>
+) {
+    let cursor = if fen_input.editing { "_" } else { "" };
+    for mut text in field_text_query.iter_mut() {
+        text.sections[0].value = format!("{}{}", fen_input.text, cursor);
+    }
+    for mut text in error_text_query.iter_mut() {
+        text.sections[0].value = fen_input.error.clone().unwrap_or_default();
+    }
+    for mut background in field_query.iter_mut() {
+        *background = if fen_input.editing { Color::rgb(0.22, 0.22, 0.3) } else { Color::rgb(0.18, 0.18, 0.2) }.into();
+    }
+}
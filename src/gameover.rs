@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+use crate::analysis::ToggleAnalysis;
+use crate::board::{BoardOrientation, LocalSide};
+#[cfg(not(feature = "egui"))]
+use crate::board::{BoardResource, describe_status, GameStatusRes};
+use crate::history::{CopyPgnRequested, StartingPosition};
+use crate::players::GameMetadata;
+use crate::piece::NewGame;
+use crate::snapshot::SnapshotRequested;
+
+#[derive(Component)]
+pub struct GameOverOverlay;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum GameOverButton {
+    Rematch,
+    NewGame,
+    CopyPgn,
+    ExportImage,
+    Analyze,
+    Close
+}
+
+impl GameOverButton {
+    pub fn label(&self) -> &'static str {
+        match self {
+            GameOverButton::Rematch => "Rematch",
+            GameOverButton::NewGame => "New game",
+            GameOverButton::CopyPgn => "Copy PGN",
+            GameOverButton::ExportImage => "Save image",
+            GameOverButton::Analyze => "Analyze",
+            GameOverButton::Close => "Close"
+        }
+    }
+}
+
+#[cfg(not(feature = "egui"))]
+pub fn spawn_game_over_overlay(mut commands: Commands, board: Res<BoardResource>, game_status: Res<GameStatusRes>) {
+    let result = describe_status(game_status.status(), board.0.on_move, false);
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        z_index: ZIndex::Global(5),
+        ..default()
+    }, Interaction::default(), GameOverOverlay)).with_children(|overlay| {
+        overlay.spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                padding: UiRect::all(Val::Px(16.0)),
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.8).into(),
+            ..default()
+        }).with_children(|dialog| {
+            dialog.spawn(TextBundle::from_section(result, TextStyle { font_size: 32.0, color: Color::WHITE, ..default() }));
+            dialog.spawn(NodeBundle {
+                style: Style { column_gap: Val::Px(8.0), ..default() },
+                ..default()
+            }).with_children(|buttons| {
+                for button in [GameOverButton::Rematch, GameOverButton::NewGame, GameOverButton::CopyPgn, GameOverButton::ExportImage, GameOverButton::Analyze, GameOverButton::Close] {
+                    buttons.spawn((ButtonBundle {
+                        style: Style { padding: UiRect::axes(Val::Px(12.0), Val::Px(6.0)), ..default() },
+                        background_color: Color::rgb(0.25, 0.25, 0.3).into(),
+                        ..default()
+                    }, button)).with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(button.label(), TextStyle { font_size: 20.0, color: Color::WHITE, ..default() }));
+                    });
+                }
+            });
+        });
+    });
+}
+
+#[cfg(not(feature = "egui"))]
+pub fn despawn_game_over_overlay(mut commands: Commands, overlay_query: Query<Entity, With<GameOverOverlay>>) {
+    for entity in overlay_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+#[derive(Event)]
+pub struct GameOverAction(pub GameOverButton);
+
+#[derive(Resource, Default)]
+pub struct GameOverDismissed(pub bool);
+
+pub fn reset_game_over_dismissed(mut dismissed: ResMut<GameOverDismissed>) {
+    dismissed.0 = false;
+}
+
+pub fn game_over_buttons(button_query: Query<(&Interaction, &GameOverButton), Changed<Interaction>>, mut action_writer: EventWriter<GameOverAction>) {
+    for (interaction, button) in button_query.iter() {
+        if *interaction == Interaction::Pressed {
+            action_writer.send(GameOverAction(*button));
+        }
+    }
+}
+
+pub fn apply_game_over_actions(
+    mut action_listener: EventReader<GameOverAction>,
+    mut dismissed: ResMut<GameOverDismissed>,
+    overlay_query: Query<Entity, With<GameOverOverlay>>,
+    mut orientation: ResMut<BoardOrientation>,
+    mut local_side: ResMut<LocalSide>,
+    mut commands: Commands,
+    mut new_game_writer: EventWriter<NewGame>,
+    mut metadata: ResMut<GameMetadata>,
+    starting_position: Res<StartingPosition>,
+    mut copy_pgn_writer: EventWriter<CopyPgnRequested>,
+    mut snapshot_writer: EventWriter<SnapshotRequested>,
+    mut analysis_writer: EventWriter<ToggleAnalysis>
+) {
+    for GameOverAction(button) in action_listener.read() {
+        match button {
+            GameOverButton::Rematch => {
+                orientation.0 = orientation.0.opposite();
+                local_side.0 = local_side.0.map(|side| side.opposite());
+                let GameMetadata {white, black} = &mut *metadata;
+                std::mem::swap(white, black);
+                new_game_writer.send(NewGame {position: starting_position.0.clone()});
+            }
+            GameOverButton::NewGame => { new_game_writer.send(NewGame::standard()); }
+            GameOverButton::CopyPgn => {
+                copy_pgn_writer.send(CopyPgnRequested);
+            }
+            GameOverButton::ExportImage => { snapshot_writer.send(SnapshotRequested); }
+            GameOverButton::Analyze => { analysis_writer.send(ToggleAnalysis); }
+            GameOverButton::Close => {
+                dismissed.0 = true;
+                for entity in overlay_query.iter() {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,284 @@
+use std::collections::BTreeSet;
+use std::mem::discriminant;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use chess_logic::logic::{Coordinate, GameStatus, PieceKind};
+use crate::board::{BoardResource, BoardTile, GameStatusRes, LocalSide, WorldCursor};
+use crate::history::MoveHistory;
+use crate::piece::{Premove, Selection};
+use crate::config::ChessConfig;
+use crate::settings::Settings;
+use crate::theme::BoardTheme;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum HighlightKind {
+    Defended,
+    Attacked(u8),
+    Hover,
+    Grabbable,
+    LastMove,
+    Checker,
+    Selected,
+    Premove,
+    Check,
+    Checkmate
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum HighlightShape {
+    Frame,
+    CornerTriangles,
+    Ring,
+    DoubleRing
+}
+
+impl HighlightKind {
+    pub fn shape(&self) -> Option<HighlightShape> {
+        match self {
+            HighlightKind::LastMove | HighlightKind::Premove => Some(HighlightShape::CornerTriangles),
+            HighlightKind::Selected => Some(HighlightShape::Frame),
+            HighlightKind::Check | HighlightKind::Checker => Some(HighlightShape::Ring),
+            HighlightKind::Checkmate => Some(HighlightShape::DoubleRing),
+            _ => None
+        }
+    }
+}
+
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct HighlightPalette {
+    pub colorblind: bool
+}
+
+impl HighlightPalette {
+    pub fn color(&self, kind: HighlightKind) -> Color {
+        if self.colorblind {
+            return match kind {
+                HighlightKind::Defended => Color::rgba(0.0, 0.45, 0.7, 0.25),
+                HighlightKind::Attacked(attackers) => Color::rgba(0.84, 0.37, 0.0, (0.15 * attackers as f32).min(0.6)),
+                HighlightKind::Hover => Color::rgba(0.5, 0.5, 0.5, 0.25),
+                HighlightKind::Grabbable => Color::rgba(0.34, 0.71, 0.91, 0.3),
+                HighlightKind::LastMove => Color::rgba(0.94, 0.89, 0.26, 0.5),
+                HighlightKind::Selected => Color::rgba(0.34, 0.71, 0.91, 0.55),
+                HighlightKind::Checker => Color::rgba(0.8, 0.47, 0.65, 0.4),
+                HighlightKind::Premove => Color::rgba(0.8, 0.47, 0.65, 0.5),
+                HighlightKind::Check => Color::rgba(0.84, 0.37, 0.0, 0.65),
+                HighlightKind::Checkmate => Color::rgba(0.84, 0.37, 0.0, 0.9)
+            };
+        }
+        match kind {
+            HighlightKind::Defended => Color::rgba(0.2, 0.8, 0.2, 0.2),
+            HighlightKind::Attacked(attackers) => Color::rgba(0.9, 0.1, 0.1, (0.15 * attackers as f32).min(0.6)),
+            HighlightKind::Hover => Color::rgba(0.5, 0.5, 0.5, 0.25),
+            HighlightKind::Grabbable => Color::rgba(0.3, 0.8, 0.3, 0.3),
+            HighlightKind::LastMove => Color::rgba(0.95, 0.85, 0.3, 0.45),
+            HighlightKind::Selected => Color::rgba(1.0, 0.85, 0.0, 0.5),
+            HighlightKind::Checker => Color::rgba(0.9, 0.4, 0.1, 0.3),
+            HighlightKind::Premove => Color::rgba(0.9, 0.2, 0.2, 0.45),
+            HighlightKind::Check => Color::rgba(1.0, 0.1, 0.1, 0.6),
+            HighlightKind::Checkmate => Color::rgba(0.85, 0.0, 0.0, 0.9)
+        }
+    }
+
+    pub fn legal_move(&self) -> Color {
+        if self.colorblind { Color::rgba(0.0, 0.45, 0.7, 0.7) } else { Color::rgba(0.2, 0.6, 0.2, 0.6) }
+    }
+
+    pub fn capture(&self) -> Color {
+        if self.colorblind { Color::rgba(0.0, 0.45, 0.7, 0.9) } else { Color::rgba(0.2, 0.6, 0.2, 0.8) }
+    }
+
+    pub fn opponent_move(&self) -> Color {
+        if self.colorblind { Color::rgba(0.84, 0.37, 0.0, 0.5) } else { Color::rgba(0.7, 0.2, 0.2, 0.4) }
+    }
+
+    pub fn arrow(&self) -> Color {
+        if self.colorblind { Color::rgba(0.0, 0.45, 0.7, 0.8) } else { Color::rgba(0.1, 0.6, 0.1, 0.8) }
+    }
+
+    pub fn hint_arrow(&self) -> Color {
+        if self.colorblind { Color::rgba(0.34, 0.71, 0.91, 0.8) } else { Color::rgba(0.1, 0.7, 0.2, 0.8) }
+    }
+
+    pub fn engine_arrow(&self) -> Color {
+        if self.colorblind { Color::rgba(0.9, 0.6, 0.0, 0.85) } else { Color::rgba(0.95, 0.5, 0.1, 0.85) }
+    }
+
+    pub fn shape_color(&self, kind: HighlightKind) -> Color {
+        self.color(kind).with_a(0.95)
+    }
+}
+
+pub fn apply_highlight_palette(settings: Res<Settings>, mut palette: ResMut<HighlightPalette>) {
+    if palette.colorblind != settings.colorblind_palette {
+        palette.colorblind = settings.colorblind_palette;
+    }
+}
+
+#[derive(Component, Default)]
+pub struct TileHighlight {
+    layers: BTreeSet<HighlightKind>
+}
+
+impl TileHighlight {
+    pub fn layer_of(&self, kind: HighlightKind) -> Option<HighlightKind> {
+        self.layers.iter().find(|layer| discriminant(*layer) == discriminant(&kind)).copied()
+    }
+
+    pub fn resolve(&self, base: Color, palette: &HighlightPalette) -> Color {
+        self.layers.iter().fold(base, |color, kind| blend(color, palette.color(*kind)))
+    }
+}
+
+#[derive(SystemParam)]
+pub struct Highlights<'w, 's> {
+    tiles: Query<'w, 's, (&'static BoardTile, &'static mut TileHighlight)>
+}
+
+impl<'w, 's> Highlights<'w, 's> {
+    pub fn add(&mut self, square: Coordinate, kind: HighlightKind) {
+        for (tile, mut highlight) in self.tiles.iter_mut() {
+            if Coordinate(tile.square.0, tile.square.1) != square { continue };
+            replace_layer(&mut highlight, Some(kind), kind);
+        }
+    }
+
+    pub fn clear_kind(&mut self, kind: HighlightKind) {
+        for (_, mut highlight) in self.tiles.iter_mut() {
+            let Some(current) = highlight.layer_of(kind) else { continue };
+            highlight.layers.remove(&current);
+        }
+    }
+
+    pub fn set(&mut self, kind: HighlightKind, squares: &[Coordinate]) {
+        for (tile, mut highlight) in self.tiles.iter_mut() {
+            let wanted = squares.contains(&Coordinate(tile.square.0, tile.square.1)).then_some(kind);
+            replace_layer(&mut highlight, wanted, kind);
+        }
+    }
+}
+
+fn replace_layer(highlight: &mut Mut<TileHighlight>, wanted: Option<HighlightKind>, kind: HighlightKind) {
+    let current = highlight.layer_of(kind);
+    if current == wanted { return };
+    if let Some(current) = current { highlight.layers.remove(&current); }
+    if let Some(wanted) = wanted { highlight.layers.insert(wanted); }
+}
+
+pub fn blend(base: Color, overlay: Color) -> Color {
+    let alpha = overlay.a();
+    Color::rgba(
+        base.r() * (1.0 - alpha) + overlay.r() * alpha,
+        base.g() * (1.0 - alpha) + overlay.g() * alpha,
+        base.b() * (1.0 - alpha) + overlay.b() * alpha,
+        base.a()
+    )
+}
+
+pub fn update_hover_highlight(
+    cursor: Option<Res<WorldCursor>>,
+    board: Res<BoardResource>,
+    local_side: Res<LocalSide>,
+    mut highlights: Highlights
+) {
+    let square = cursor.and_then(|cursor| cursor.square);
+    let grabbable = square
+        .and_then(|square| board.0.pieces.get(&square))
+        .is_some_and(|piece| piece.color == board.0.on_move && local_side.controls(piece.color));
+    let hovered: Vec<Coordinate> = square.into_iter().collect();
+    highlights.set(HighlightKind::Hover, if grabbable { &[] } else { &hovered });
+    highlights.set(HighlightKind::Grabbable, if grabbable { &hovered } else { &[] });
+}
+
+pub fn update_selection_highlight(selection: Res<Selection>, mut highlights: Highlights) {
+    highlights.clear_kind(HighlightKind::Selected);
+    if let Some(square) = selection.0 {
+        highlights.add(square, HighlightKind::Selected);
+    }
+}
+
+pub fn update_premove_highlight(premove: Res<Premove>, mut highlights: Highlights) {
+    let squares: Vec<Coordinate> = premove.0.map(|(from, to)| vec![from, to]).unwrap_or_default();
+    highlights.set(HighlightKind::Premove, &squares);
+}
+
+pub fn resolve_tile_highlights(
+    theme: Res<BoardTheme>,
+    palette: Res<HighlightPalette>,
+    mut tiles: Query<(&BoardTile, Ref<TileHighlight>, &mut Sprite)>
+) {
+    for (tile, highlight, mut sprite) in tiles.iter_mut() {
+        if !theme.is_changed() && !palette.is_changed() && !highlight.is_changed() { continue };
+        sprite.color = highlight.resolve(tile.get_color(&theme), &palette);
+    }
+}
+
+pub fn draw_highlight_shapes(
+    mut gizmos: Gizmos,
+    config: Res<ChessConfig>,
+    palette: Res<HighlightPalette>,
+    tiles: Query<(&BoardTile, &TileHighlight)>
+) {
+    if !palette.colorblind { return };
+    let half = config.square_size / 2.0;
+    for (tile, highlight) in tiles.iter() {
+        let center = config.square_to_vector(Coordinate(tile.square.0, tile.square.1));
+        for kind in highlight.layers.iter() {
+            let Some(shape) = kind.shape() else { continue };
+            let color = palette.shape_color(*kind);
+            match shape {
+                HighlightShape::Frame => gizmos.rect_2d(center, 0.0, Vec2::splat(config.square_size * 0.92), color),
+                HighlightShape::Ring => { gizmos.circle_2d(center, half * 0.92, color); }
+                HighlightShape::DoubleRing => {
+                    gizmos.circle_2d(center, half * 0.92, color);
+                    gizmos.circle_2d(center, half * 0.78, color);
+                }
+                HighlightShape::CornerTriangles => {
+                    let leg = config.square_size * 0.22;
+                    for (x, y) in [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+                        let corner = center + Vec2::new(x * half, y * half);
+                        gizmos.linestrip_2d([corner, corner - Vec2::new(x * leg, 0.0), corner - Vec2::new(0.0, y * leg), corner], color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+pub fn update_last_move_highlight(history: Res<MoveHistory>, mut highlights: Highlights) {
+    let squares: Vec<Coordinate> = history.0.last().map(|played| vec![played.record.from, played.record.to]).unwrap_or_default();
+    highlights.set(HighlightKind::LastMove, &squares);
+}
+
+pub fn update_check_highlight(board: Res<BoardResource>, game_status: Res<GameStatusRes>, mut highlights: Highlights) {
+    let king: Vec<Coordinate> = board.0.find(board.0.on_move, PieceKind::KING)
+        .filter(|_| game_status.in_check(board.0.on_move))
+        .map(|king| king.square)
+        .into_iter()
+        .collect();
+    let mated = matches!(game_status.status(), GameStatus::Checkmate { .. });
+    highlights.set(HighlightKind::Check, if mated { &[] } else { &king });
+    highlights.set(HighlightKind::Checkmate, if mated { &king } else { &[] });
+    highlights.set(HighlightKind::Checker, game_status.checking_squares());
+}
+
+#[derive(Resource, Default)]
+pub struct AttackOverlay(pub bool);
+
+pub fn update_attack_overlay(
+    overlay: Res<AttackOverlay>,
+    settings: Res<Settings>,
+    board: Res<BoardResource>,
+    local_side: Res<LocalSide>,
+    mut highlights: Highlights
+) {
+    let side = local_side.0.unwrap_or(board.0.on_move);
+    let attacked = if overlay.0 { board.0.attack_counts(side.opposite()) } else { Default::default() };
+    let defended = if overlay.0 && settings.show_defended_squares { board.0.attack_counts(side) } else { Default::default() };
+    for (tile, mut highlight) in highlights.tiles.iter_mut() {
+        let square = Coordinate(tile.square.0, tile.square.1);
+        let attacked = attacked.get(&square).map(|attackers| HighlightKind::Attacked(*attackers));
+        replace_layer(&mut highlight, attacked, HighlightKind::Attacked(0));
+        let defended = defended.contains_key(&square).then_some(HighlightKind::Defended);
+        replace_layer(&mut highlight, defended, HighlightKind::Defended);
+    }
+}
@@ -0,0 +1,370 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use chess_logic::logic::{Board, GameStatus, MoveRecord, PositionStatus};
+use crate::analysis::Analysis;
+use crate::board::{side_name, BoardResource, GameOverEvent, GamePhase, GameStatusRes, LocalSide};
+use crate::clipboard::ClipboardRequest;
+use crate::clock::ChessClock;
+use crate::piece::{BoardUpdate, MovePlayed, NewGame, Premove, Selection};
+use crate::players::GameMetadata;
+use crate::settings::TimeControl;
+use crate::shortcuts::DrawOffer;
+use crate::storage::{Storage, StorageResource};
+
+const AUTOSAVE_KEY: &str = "autosave.ron";
+
+#[derive(Clone)]
+pub struct PlayedMove {
+    pub record: MoveRecord,
+    pub san: String,
+    pub after: PositionStatus
+}
+
+#[derive(Resource, Default)]
+pub struct MoveHistory(pub Vec<PlayedMove>);
+
+#[derive(Resource, Default)]
+pub struct StartingPosition(pub Option<Board>);
+
+#[derive(Resource, Default)]
+pub struct HistoryView {
+    pub ply: Option<usize>,
+    board: Option<Board>
+}
+
+impl HistoryView {
+    pub fn is_live(&self) -> bool {
+        self.ply.is_none()
+    }
+
+    pub fn displayed<'a>(&'a self, live: &'a Board) -> &'a Board {
+        self.board.as_ref().unwrap_or(live)
+    }
+
+    pub fn go_live(&mut self) {
+        if self.ply.is_some() {
+            self.ply = None;
+            self.board = None;
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct ViewPly(pub Option<usize>);
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum ReplayStep {
+    First,
+    Back,
+    Forward,
+    Last
+}
+
+impl ReplayStep {
+    pub const ALL: [ReplayStep; 4] = [ReplayStep::First, ReplayStep::Back, ReplayStep::Forward, ReplayStep::Last];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            ReplayStep::First => "|<",
+            ReplayStep::Back => "<",
+            ReplayStep::Forward => ">",
+            ReplayStep::Last => ">|"
+        }
+    }
+
+    pub fn target(&self, view: &HistoryView, history: &MoveHistory) -> Option<usize> {
+        let current = view.ply.unwrap_or(history.0.len());
+        match self {
+            ReplayStep::First => Some(0),
+            ReplayStep::Back => Some(current.saturating_sub(1)),
+            ReplayStep::Forward => Some(current + 1).filter(|ply| *ply < history.0.len()),
+            ReplayStep::Last => None
+        }
+    }
+}
+
+pub fn step_history(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    view: Res<HistoryView>,
+    history: Res<MoveHistory>,
+    button_query: Query<(&Interaction, &ReplayStep), Changed<Interaction>>,
+    mut view_writer: EventWriter<ViewPly>
+) {
+    let mut steps: Vec<ReplayStep> = button_query.iter()
+        .filter(|(interaction, _)| **interaction == Interaction::Pressed)
+        .map(|(_, step)| *step)
+        .collect();
+    let keys = [(KeyCode::Home, ReplayStep::First), (KeyCode::ArrowLeft, ReplayStep::Back), (KeyCode::ArrowRight, ReplayStep::Forward), (KeyCode::End, ReplayStep::Last)];
+    // During the live game the arrows move the keyboard cursor, and Ctrl+Home belongs to the camera
+    let arrows_step = !view.is_live();
+    if !keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        steps.extend(keys.into_iter()
+            .filter(|(key, step)| keyboard.just_pressed(*key) && (arrows_step || matches!(step, ReplayStep::First | ReplayStep::Last)))
+            .map(|(_, step)| step));
+    }
+    let Some(step) = steps.last() else { return };
+    let target = step.target(&view, &history);
+    if target != view.ply {
+        view_writer.send(ViewPly(target));
+    }
+}
+
+#[derive(Component)]
+pub struct HistoryBanner;
+
+pub fn spawn_history_banner(mut commands: Commands) {
+    commands.spawn((TextBundle {
+        text: Text::from_section("Viewing history - End returns to the game", TextStyle { font_size: 18.0, color: Color::rgb(1.0, 0.85, 0.4), ..default() }),
+        style: Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(8.0),
+            left: Val::Percent(30.0),
+            padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+            ..default()
+        },
+        background_color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+        visibility: Visibility::Hidden,
+        z_index: ZIndex::Global(15),
+        ..default()
+    }, HistoryBanner));
+}
+
+pub fn update_history_banner(view: Res<HistoryView>, mut banner_query: Query<&mut Visibility, With<HistoryBanner>>) {
+    for mut visibility in banner_query.iter_mut() {
+        *visibility = if view.is_live() { Visibility::Hidden } else { Visibility::Inherited };
+    }
+}
+
+pub fn viewing_live(view: Res<HistoryView>) -> bool {
+    view.is_live()
+}
+
+pub fn apply_history_view(
+    mut view_listener: EventReader<ViewPly>,
+    phase: Res<State<GamePhase>>,
+    board: Res<BoardResource>,
+    history: Res<MoveHistory>,
+    mut view: ResMut<HistoryView>,
+    mut board_update_writer: EventWriter<BoardUpdate>
+) {
+    let Some(ply) = view_listener.read().last().map(|ViewPly(ply)| *ply) else { return };
+    if *phase.get() == GamePhase::PromotionPending { return };
+    let ply = ply.filter(|ply| *ply < history.0.len());
+    if ply == view.ply { return };
+    view.board = ply.map(|ply| {
+        let mut shown = board.0.clone();
+        for played in history.0[ply..].iter().rev() {
+            shown.unmake_move(&played.record);
+        }
+        shown
+    });
+    view.ply = ply;
+    board_update_writer.send(BoardUpdate::resync());
+}
+
+pub fn record_move_history(mut move_played_listener: EventReader<MovePlayed>, mut history: ResMut<MoveHistory>) {
+    for move_played in move_played_listener.read() {
+        history.0.push(PlayedMove {record: move_played.record, san: move_played.san.clone(), after: move_played.after});
+    }
+}
+
+pub fn game_pgn(history: &MoveHistory, start: &StartingPosition, metadata: &GameMetadata, status: GameStatus, clock: &ChessClock) -> String {
+    let mut pgn = format!(
+        "[Event \"Casual game\"]\n[Site \"cheess\"]\n[White \"{}\"]\n[Black \"{}\"]\n[Result \"{}\"]\n",
+        metadata.white.name.replace('"', "'"),
+        metadata.black.name.replace('"', "'"),
+        status.result()
+    );
+    if let Some(rating) = metadata.white.rating {
+        pgn.push_str(&format!("[WhiteElo \"{}\"]\n", rating));
+    }
+    if let Some(rating) = metadata.black.rating {
+        pgn.push_str(&format!("[BlackElo \"{}\"]\n", rating));
+    }
+    pgn.push_str(&format!("[TimeControl \"{}\"]\n", TimeControl::pgn_tag(clock.control)));
+    let termination = match status {
+        GameStatus::Ongoing => "unterminated",
+        GameStatus::TimeForfeit { .. } => "time forfeit",
+        _ => "normal"
+    };
+    pgn.push_str(&format!("[Termination \"{}\"]\n", termination));
+    if let Some(board) = &start.0 {
+        pgn.push_str(&format!("[SetUp \"1\"]\n[FEN \"{}\"]\n", board.to_fen()));
+    }
+    pgn.push('\n');
+    let first_ply = start.0.as_ref().map_or(0, |board| board.turn_number as usize);
+    for (index, played) in history.0.iter().enumerate() {
+        let ply = first_ply + index;
+        if ply.is_multiple_of(2) {
+            pgn.push_str(&format!("{}. ", ply / 2 + 1));
+        } else if index == 0 {
+            pgn.push_str(&format!("{}... ", ply / 2 + 1));
+        }
+        pgn.push_str(&played.san);
+        pgn.push(' ');
+        if let Some(remaining) = clock.remaining_after(index, played.record.piece.color) {
+            let seconds = remaining.as_secs();
+            pgn.push_str(&format!("{{[%clk {}:{:02}:{:02}]}} ", seconds / 3600, seconds / 60 % 60, seconds % 60));
+        }
+    }
+    match status {
+        GameStatus::Resigned { winner } => pgn.push_str(&format!("{{{} resigns}} ", side_name(winner.opposite()))),
+        GameStatus::TimeForfeit { winner } => pgn.push_str(&format!("{{{} forfeits on time}} ", side_name(winner.opposite()))),
+        GameStatus::DrawAgreed => pgn.push_str("{Draw agreed} "),
+        GameStatus::Draw { reason } => pgn.push_str(&format!("{{Draw by {}}} ", reason)),
+        _ => {}
+    }
+    pgn.push_str(status.result());
+    pgn
+}
+
+#[derive(Event)]
+pub struct CopyPgnRequested;
+
+pub fn copy_pgn(
+    mut copy_pgn_listener: EventReader<CopyPgnRequested>,
+    history: Res<MoveHistory>,
+    start: Res<StartingPosition>,
+    metadata: Res<GameMetadata>,
+    game_status: Res<GameStatusRes>,
+    clock: Res<ChessClock>,
+    mut clipboard_writer: EventWriter<ClipboardRequest>
+) {
+    if copy_pgn_listener.read().count() == 0 { return };
+    clipboard_writer.send(ClipboardRequest(game_pgn(&history, &start, &metadata, game_status.status(), &clock)));
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct Autosave {
+    start: Option<String>,
+    moves: Vec<String>
+}
+
+pub fn replay_game(start: Option<&str>, moves: &[String]) -> Result<(Board, StartingPosition, MoveHistory), String> {
+    let start = StartingPosition(start.map(Board::from_fen).transpose().map_err(|error| format!("unreadable starting position: {}", error))?);
+    let mut board = start.0.clone().unwrap_or_else(Board::new);
+    let mut history = MoveHistory::default();
+    for text in moves {
+        let candidate = board.parse_move(text).ok_or_else(|| format!("unplayable move {}", text))?;
+        let before = board.clone();
+        let record = board.try_move(candidate).map_err(|error| format!("illegal move {}: {}", text, error))?;
+        let after = board.position_status();
+        history.0.push(PlayedMove {record, san: before.san_with_status(candidate, after), after});
+    }
+    Ok((board, start, history))
+}
+
+pub fn history_moves(history: &MoveHistory) -> Vec<String> {
+    history.0.iter().map(|played| played.record.as_move().to_string()).collect()
+}
+
+pub fn load_autosave(storage: &dyn Storage) -> Option<(Board, StartingPosition, MoveHistory)> {
+    let contents = storage.load(AUTOSAVE_KEY)?;
+    let autosave: Autosave = match ron::from_str(&contents) {
+        Ok(autosave) => autosave,
+        Err(error) => { warn!("ignoring unreadable {}: {}", AUTOSAVE_KEY, error); return None }
+    };
+    replay_game(autosave.start.as_deref(), &autosave.moves)
+        .map_err(|error| warn!("ignoring {}: {}", AUTOSAVE_KEY, error))
+        .ok()
+}
+
+pub fn autosave_game(history: Res<MoveHistory>, start: Res<StartingPosition>, storage: Res<StorageResource>) {
+    let autosave = Autosave {
+        start: start.0.as_ref().map(Board::to_fen),
+        moves: history_moves(&history)
+    };
+    let contents = match ron::to_string(&autosave) {
+        Ok(contents) => contents,
+        Err(error) => { warn!("failed to serialize autosave: {}", error); return }
+    };
+    if let Err(error) = storage.0.save(AUTOSAVE_KEY, &contents) {
+        warn!("failed to write autosave: {}", error);
+    }
+}
+
+pub fn clear_finished_autosave(mut game_over_listener: EventReader<GameOverEvent>, storage: Res<StorageResource>) {
+    if game_over_listener.read().count() == 0 { return };
+    if let Err(error) = storage.0.remove(AUTOSAVE_KEY) {
+        warn!("failed to remove autosave: {}", error);
+    }
+}
+
+pub fn reset_history(
+    mut new_game_listener: EventReader<NewGame>,
+    mut history: ResMut<MoveHistory>,
+    mut start: ResMut<StartingPosition>,
+    mut view: ResMut<HistoryView>,
+    storage: Res<StorageResource>
+) {
+    let Some(new_game) = new_game_listener.read().last() else { return };
+    history.0.clear();
+    start.0 = new_game.position.clone();
+    view.go_live();
+    if let Err(error) = storage.0.remove(AUTOSAVE_KEY) {
+        warn!("failed to remove autosave: {}", error);
+    }
+}
+
+#[derive(Event)]
+pub struct TakebackRequested;
+
+pub fn take_back_moves(
+    mut takeback_listener: EventReader<TakebackRequested>,
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    analysis: Res<Analysis>,
+    phase: Res<State<GamePhase>>,
+    local_side: Res<LocalSide>,
+    mut board: ResMut<BoardResource>,
+    mut history: ResMut<MoveHistory>,
+    mut view: ResMut<HistoryView>,
+    mut selection: ResMut<Selection>,
+    mut premove: ResMut<Premove>,
+    mut draw_offer: ResMut<DrawOffer>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut board_update_writer: EventWriter<BoardUpdate>
+) {
+    if takeback_listener.read().count() == 0 { return };
+    if mouse_button.pressed(MouseButton::Left) { return };
+    if matches!(phase.get(), GamePhase::Loading | GamePhase::PromotionPending) { return };
+    if analysis.branch_ply().is_some_and(|ply| history.0.len() <= ply) { return };
+    let Some(played) = history.0.pop() else { return };
+    view.go_live();
+    board.0.unmake_move(&played.record);
+    if let Some(side) = local_side.0 {
+        while board.0.on_move != side {
+            let Some(played) = history.0.pop() else { break };
+            board.0.unmake_move(&played.record);
+        }
+    }
+    selection.0 = None;
+    premove.0 = None;
+    draw_offer.0 = None;
+    next_phase.set(GamePhase::Playing);
+    board_update_writer.send(BoardUpdate::resync());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pgn::parse_pgn;
+
+    #[test]
+    fn pgn_of_a_default_game_reads_back_as_standard_chess() {
+        let mut board = Board::new();
+        let mut history = MoveHistory::default();
+        for text in ["e4", "e5", "Nf3", "Nc6", "Bc4", "Nf6", "O-O"] {
+            let candidate = board.parse_move(text).unwrap();
+            let san = board.san(candidate);
+            let record = board.try_move(candidate).unwrap();
+            history.0.push(PlayedMove {record, san, after: board.position_status()});
+        }
+        let pgn = game_pgn(&history, &StartingPosition::default(), &GameMetadata::default(), GameStatus::Ongoing, &ChessClock::default());
+        assert!(!pgn.contains("[FEN "));
+        assert!(pgn.contains("4. O-O"));
+        let game = parse_pgn(&pgn).unwrap();
+        assert_eq!(game.moves.len(), 7);
+        assert_eq!(game.moves[6].uci, "e1g1");
+    }
+}
@@ -0,0 +1,143 @@
+use std::time::Duration;
+use bevy::prelude::*;
+use chess_logic::logic::PieceColor;
+use crate::board::{BoardOrientation, GamePhase, LocalSide};
+use crate::history::HistoryView;
+use crate::piece::{MovePlayed, NewGame};
+use crate::settings::{HotseatFlipSetting, Settings};
+
+struct PendingFlip {
+    from: PieceColor,
+    to: PieceColor,
+    timer: Option<Timer>
+}
+
+#[derive(Resource, Default)]
+pub struct HotseatFlip {
+    pending: Option<PendingFlip>
+}
+
+#[derive(Component)]
+pub struct PassDeviceScreen;
+
+pub fn queue_hotseat_flip(
+    settings: Res<Settings>,
+    local_side: Res<LocalSide>,
+    orientation: Res<BoardOrientation>,
+    mut move_played_listener: EventReader<MovePlayed>,
+    mut new_game_listener: EventReader<NewGame>,
+    mut flip: ResMut<HotseatFlip>
+) {
+    if new_game_listener.read().count() > 0 {
+        flip.pending = None;
+    }
+    let Some(move_played) = move_played_listener.read().last() else { return };
+    if local_side.0.is_some() { return };
+    let timer = match settings.hotseat_flip {
+        HotseatFlipSetting::Off => return,
+        HotseatFlipSetting::Delayed => Some(Timer::new(Duration::from_millis(settings.hotseat_delay_millis), TimerMode::Once)),
+        HotseatFlipSetting::PassScreen => None
+    };
+    let to = move_played.by.opposite();
+    flip.pending = (orientation.0 != to).then_some(PendingFlip {from: orientation.0, to, timer});
+}
+
+pub fn apply_hotseat_flip(
+    time: Res<Time>,
+    phase: Res<State<GamePhase>>,
+    view: Res<HistoryView>,
+    screen_query: Query<&Interaction, With<PassDeviceScreen>>,
+    mut orientation: ResMut<BoardOrientation>,
+    mut flip: ResMut<HotseatFlip>
+) {
+    let Some(pending) = flip.pending.as_mut() else { return };
+    if orientation.0 != pending.from {
+        flip.pending = None;
+        return;
+    }
+    if *phase.get() == GamePhase::PromotionPending || !view.is_live() { return };
+    let skipped = screen_query.iter().any(|interaction| *interaction == Interaction::Pressed);
+    let elapsed = pending.timer.as_mut().is_some_and(|timer| timer.tick(time.delta()).finished());
+    if skipped || elapsed {
+        orientation.0 = pending.to;
+        flip.pending = None;
+    }
+}
+
+pub fn update_pass_device_screen(mut commands: Commands, flip: Res<HotseatFlip>, screen_query: Query<Entity, With<PassDeviceScreen>>) {
+    let Some(pending) = &flip.pending else {
+        for entity in screen_query.iter() {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    };
+    if !screen_query.is_empty() { return };
+    let hint = if pending.timer.is_some() { "click to continue now" } else { "click to continue" };
+    commands.spawn((ButtonBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        background_color: Color::rgb(0.08, 0.08, 0.1).into(),
+        z_index: ZIndex::Global(9),
+        ..default()
+    }, PassDeviceScreen)).with_children(|screen| {
+        screen.spawn(TextBundle::from_section(
+            format!("Pass the device to {}\n{}", pending.to, hint),
+            TextStyle { font_size: 36.0, color: Color::WHITE, ..default() }
+        ).with_text_justify(JustifyText::Center));
+    });
+}
+
+#[derive(Component)]
+pub struct HotseatToggle;
+
+fn hotseat_label(setting: HotseatFlipSetting, delay_millis: u64) -> String {
+    match setting {
+        HotseatFlipSetting::Off => String::from("Hotseat flip: off"),
+        HotseatFlipSetting::Delayed => format!("Hotseat flip: after {:.1} s", delay_millis as f32 / 1000.0),
+        HotseatFlipSetting::PassScreen => String::from("Hotseat flip: pass screen")
+    }
+}
+
+pub fn spawn_hotseat_toggle(parent: &mut ChildBuilder, settings: &Settings) {
+    parent.spawn((ButtonBundle {
+        style: Style {
+            width: Val::Px(360.0),
+            margin: UiRect::top(Val::Px(8.0)),
+            padding: UiRect::all(Val::Px(4.0)),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        background_color: Color::rgb(0.25, 0.25, 0.3).into(),
+        ..default()
+    }, HotseatToggle)).with_children(|button| {
+        button.spawn(TextBundle::from_section(
+            hotseat_label(settings.hotseat_flip, settings.hotseat_delay_millis),
+            TextStyle { font_size: 16.0, color: Color::WHITE, ..default() }
+        ));
+    });
+}
+
+pub fn toggle_hotseat_flip(
+    button_query: Query<(&Interaction, &Children), (Changed<Interaction>, With<HotseatToggle>)>,
+    mut text_query: Query<&mut Text>,
+    mut settings: ResMut<Settings>
+) {
+    for (interaction, children) in button_query.iter() {
+        if *interaction != Interaction::Pressed { continue };
+        settings.hotseat_flip = match settings.hotseat_flip {
+            HotseatFlipSetting::Off => HotseatFlipSetting::Delayed,
+            HotseatFlipSetting::Delayed => HotseatFlipSetting::PassScreen,
+            HotseatFlipSetting::PassScreen => HotseatFlipSetting::Off
+        };
+        let mut texts = text_query.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].value = hotseat_label(settings.hotseat_flip, settings.hotseat_delay_millis);
+        }
+    }
+}
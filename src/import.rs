@@ -0,0 +1,250 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use bevy::prelude::*;
+use chess_logic::logic::PieceColor;
+use crate::pgn::{parse_pgn, PgnGame};
+use crate::spectator::{MoveSource, Spectator, StreamEvent};
+use crate::storage::StorageResource;
+use crate::toast::{ToastLevel, Toasts};
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum GameSite {
+    Lichess,
+    ChessComLive,
+    ChessComDaily
+}
+
+#[derive(Clone)]
+pub struct GameUrl {
+    site: GameSite,
+    id: String
+}
+
+impl GameUrl {
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim();
+        let text = text.strip_prefix("https://").or_else(|| text.strip_prefix("http://")).unwrap_or(text);
+        let text = text.split(['?', '#']).next()?;
+        let mut segments = text.split('/').filter(|segment| !segment.is_empty());
+        let host = segments.next()?.to_ascii_lowercase();
+        let host = host.strip_prefix("www.").unwrap_or(&host);
+        let path: Vec<&str> = segments.collect();
+        match host {
+            "lichess.org" => {
+                let id = path.first()?;
+                let alphanumeric = id.chars().all(|character| character.is_ascii_alphanumeric());
+                if !alphanumeric || (id.len() != 8 && id.len() != 12) { return None };
+                Some(GameUrl {site: GameSite::Lichess, id: id[..8].to_string()})
+            }
+            "chess.com" => {
+                let (site, id) = match path.as_slice() {
+                    ["game", "daily", id, ..] => (GameSite::ChessComDaily, id),
+                    ["game", "live", id, ..] | ["live", "game", id, ..] | ["game", id, ..] => (GameSite::ChessComLive, id),
+                    _ => return None
+                };
+                if id.is_empty() || !id.chars().all(|character| character.is_ascii_digit()) { return None };
+                Some(GameUrl {site, id: id.to_string()})
+            }
+            _ => None
+        }
+    }
+
+    fn cache_key(&self) -> String {
+        match self.site {
+            GameSite::Lichess => format!("games/lichess-{}.pgn", self.id),
+            GameSite::ChessComLive => format!("games/chesscom-live-{}.pgn", self.id),
+            GameSite::ChessComDaily => format!("games/chesscom-daily-{}.pgn", self.id)
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self.site {
+            GameSite::Lichess => format!("lichess game {}", self.id),
+            GameSite::ChessComLive | GameSite::ChessComDaily => format!("chess.com game {}", self.id)
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct ImportGameRequested(pub GameUrl);
+
+struct Download {
+    url: GameUrl,
+    pgn: Result<String, String>,
+    cached: bool
+}
+
+#[derive(Clone, Default)]
+struct Downloads(Arc<Mutex<Vec<Download>>>);
+
+impl Downloads {
+    fn push(&self, url: GameUrl, pgn: Result<String, String>, cached: bool) {
+        self.0.lock().unwrap().push(Download {url, pgn, cached});
+    }
+
+    fn drain(&self) -> Vec<Download> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct GameImports {
+    downloads: Downloads
+}
+
+fn response_text(result: ehttp::Result<ehttp::Response>) -> Result<String, String> {
+    let response = result.map_err(|error| format!("network error: {}", error))?;
+    match response.status {
+        404 => return Err(String::from("the game does not exist or is private")),
+        429 => return Err(String::from("the site is rate limiting requests, try again in a minute")),
+        _ if !response.ok => return Err(format!("the site answered {} {}", response.status, response.status_text)),
+        _ => {}
+    }
+    response.text().map(str::to_string).ok_or_else(|| String::from("the site sent a response that is not text"))
+}
+
+fn response_json(result: ehttp::Result<ehttp::Response>) -> Result<serde_json::Value, String> {
+    let text = response_text(result)?;
+    serde_json::from_str(&text).map_err(|error| format!("unreadable answer from the site: {}", error))
+}
+
+fn fetch_lichess(url: GameUrl, downloads: Downloads) {
+    let request = ehttp::Request::get(format!("https://lichess.org/game/export/{}?clocks=true&evals=false", url.id));
+    ehttp::fetch(request, move |result| downloads.push(url, response_text(result), false));
+}
+
+// chess.com has no single-game PGN export, so the game page data names the players and month,
+// and the PGN comes from the white player's public monthly archive
+fn fetch_chess_com(url: GameUrl, downloads: Downloads) {
+    let kind = if url.site == GameSite::ChessComDaily { "daily" } else { "live" };
+    let request = ehttp::Request::get(format!("https://www.chess.com/callback/{}/game/{}", kind, url.id));
+    ehttp::fetch(request, move |result| {
+        let archive = response_json(result).and_then(|page| {
+            let headers = &page["game"]["pgnHeaders"];
+            let white = headers["White"].as_str().ok_or_else(|| String::from("the game is private or still in progress"))?;
+            let date = headers["Date"].as_str().unwrap_or_default();
+            let mut parts = date.split('.');
+            let (Some(year), Some(month)) = (parts.next(), parts.next()) else { return Err(format!("the game has no usable date ({})", date)) };
+            Ok(format!("https://api.chess.com/pub/player/{}/games/{}/{}", white.to_lowercase(), year, month))
+        });
+        let archive = match archive {
+            Ok(archive) => archive,
+            Err(error) => { downloads.push(url, Err(error), false); return }
+        };
+        ehttp::fetch(ehttp::Request::get(archive), move |result| {
+            let pgn = response_json(result).and_then(|archive| {
+                let suffix = format!("/{}", url.id);
+                archive["games"].as_array().into_iter().flatten()
+                    .find(|game| game["url"].as_str().is_some_and(|link| link.ends_with(&suffix)))
+                    .and_then(|game| game["pgn"].as_str().map(str::to_string))
+                    .ok_or_else(|| String::from("the game is not in the player's public archive"))
+            });
+            downloads.push(url, pgn, false);
+        });
+    });
+}
+
+struct ImportedGame(VecDeque<StreamEvent>);
+
+impl ImportedGame {
+    fn new(game: PgnGame) -> Self {
+        ImportedGame(VecDeque::from([
+            StreamEvent::Players {white: game.player(PieceColor::WHITE), black: game.player(PieceColor::BLACK), control: game.time_control()},
+            StreamEvent::Reset {start: game.start.clone(), moves: game.moves.iter().map(|played| played.uci.clone()).collect()},
+            StreamEvent::Finished {result: game.result.clone()}
+        ]))
+    }
+}
+
+impl MoveSource for ImportedGame {
+    fn poll(&mut self, _: Duration) -> Option<StreamEvent> {
+        self.0.pop_front()
+    }
+}
+
+pub fn request_game_imports(
+    mut request_listener: EventReader<ImportGameRequested>,
+    storage: Res<StorageResource>,
+    imports: Res<GameImports>,
+    mut toasts: ResMut<Toasts>
+) {
+    for ImportGameRequested(url) in request_listener.read() {
+        let downloads = imports.downloads.clone();
+        if let Some(pgn) = storage.0.load(&url.cache_key()) {
+            downloads.push(url.clone(), Ok(pgn), true);
+            continue;
+        }
+        toasts.push(ToastLevel::Info, format!("Downloading {}", url.describe()));
+        match url.site {
+            GameSite::Lichess => fetch_lichess(url.clone(), downloads),
+            GameSite::ChessComLive | GameSite::ChessComDaily => fetch_chess_com(url.clone(), downloads)
+        }
+    }
+}
+
+pub fn finish_game_imports(imports: Res<GameImports>, storage: Res<StorageResource>, mut spectator: ResMut<Spectator>, mut toasts: ResMut<Toasts>) {
+    for Download {url, pgn, cached} in imports.downloads.drain() {
+        let game = match pgn.and_then(|pgn| parse_pgn(&pgn).map(|game| (pgn, game))) {
+            Ok((pgn, game)) => {
+                if !cached {
+                    if let Err(error) = storage.0.save(&url.cache_key(), &pgn) {
+                        warn!("failed to cache {}: {}", url.describe(), error);
+                    }
+                }
+                game
+            }
+            Err(error) => {
+                toasts.push(ToastLevel::Error, format!("Could not import {}: {}", url.describe(), error));
+                continue;
+            }
+        };
+        let description = format!("{} vs {} ({})", game.player(PieceColor::WHITE).name, game.player(PieceColor::BLACK).name, url.describe());
+        spectator.start(Box::new(ImportedGame::new(game)), description);
+        toasts.push(ToastLevel::Success, format!("Imported {}", url.describe()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache_key(text: &str) -> Option<String> {
+        GameUrl::parse(text).map(|url| url.cache_key())
+    }
+
+    #[test]
+    fn reads_lichess_links() {
+        assert_eq!(cache_key("https://lichess.org/abcd1234").as_deref(), Some("games/lichess-abcd1234.pgn"));
+        assert_eq!(cache_key("  http://www.LiChess.org/abcd1234wxyz/black#12 ").as_deref(), Some("games/lichess-abcd1234.pgn"));
+        assert_eq!(cache_key("lichess.org/abcd1234?ref=share").as_deref(), Some("games/lichess-abcd1234.pgn"));
+    }
+
+    #[test]
+    fn reads_chess_com_links() {
+        assert_eq!(cache_key("https://www.chess.com/game/live/123456").as_deref(), Some("games/chesscom-live-123456.pgn"));
+        assert_eq!(cache_key("https://www.chess.com/live/game/123456").as_deref(), Some("games/chesscom-live-123456.pgn"));
+        assert_eq!(cache_key("chess.com/game/123456?move=3").as_deref(), Some("games/chesscom-live-123456.pgn"));
+        assert_eq!(cache_key("https://chess.com/game/daily/987").as_deref(), Some("games/chesscom-daily-987.pgn"));
+    }
+
+    #[test]
+    fn rejects_other_links() {
+        for text in [
+            "",
+            "https://",
+            "https://lichess.org/",
+            "https://lichess.org/abc",
+            "https://lichess.org/abcd12345",
+            "https://lichess.org/abcd-234",
+            "https://lichess.org/ábcd1234",
+            "https://chess.com/game/live/",
+            "https://chess.com/game/live/12a4",
+            "https://chess.com/member/someone",
+            "https://example.org/abcd1234",
+            "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1"
+        ] {
+            assert!(GameUrl::parse(text).is_none(), "{text}");
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use bevy::prelude::*;
+use chess_logic::logic::{Coordinate, PieceColor, PieceKind};
+use crate::board::{BoardOrientation, BoardResource, LocalSide};
+use crate::config::ChessConfig;
+use crate::history::HistoryView;
+use crate::piece::{MoveRequested, PromotionChosen, Selection};
+
+#[derive(Resource)]
+pub struct KeyboardCursor {
+    pub square: Coordinate,
+    pub active: bool
+}
+
+impl Default for KeyboardCursor {
+    fn default() -> Self {
+        KeyboardCursor {square: Coordinate(4, 1), active: false}
+    }
+}
+
+#[derive(Component)]
+pub struct KeyboardCursorMarker;
+
+pub fn spawn_keyboard_cursor(mut commands: Commands, config: Res<ChessConfig>) {
+    commands.spawn((SpriteBundle {
+        sprite: Sprite {
+            color: Color::rgba(0.1, 0.6, 1.0, 0.45),
+            custom_size: Some(Vec2::splat(config.square_size)),
+            ..default()
+        },
+        visibility: Visibility::Hidden,
+        ..default()
+    }, KeyboardCursorMarker));
+}
+
+pub fn move_keyboard_cursor(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut cursor_moved_listener: EventReader<CursorMoved>,
+    orientation: Res<BoardOrientation>,
+    board: Res<BoardResource>,
+    local_side: Res<LocalSide>,
+    view: Res<HistoryView>,
+    mut keyboard_cursor: ResMut<KeyboardCursor>
+) {
+    if cursor_moved_listener.read().count() > 0 && keyboard_cursor.active {
+        keyboard_cursor.active = false;
+    }
+    let mut delta = (0, 0);
+    // While history is on show, left and right step through it instead
+    if view.is_live() && keyboard.just_pressed(KeyCode::ArrowLeft) { delta.0 -= 1 };
+    if view.is_live() && keyboard.just_pressed(KeyCode::ArrowRight) { delta.0 += 1 };
+    if keyboard.just_pressed(KeyCode::ArrowDown) { delta.1 -= 1 };
+    if keyboard.just_pressed(KeyCode::ArrowUp) { delta.1 += 1 };
+    if orientation.0 == PieceColor::BLACK {
+        delta = (-delta.0, -delta.1);
+    }
+    if delta != (0, 0) {
+        let square = keyboard_cursor.square;
+        keyboard_cursor.square = Coordinate((square.0 + delta.0).clamp(0, 7), (square.1 + delta.1).clamp(0, 7));
+        keyboard_cursor.active = true;
+    }
+
+    if keyboard.just_pressed(KeyCode::Tab) && local_side.controls(board.0.on_move) {
+        let mut movable: Vec<Coordinate> = board.0.pieces_of(board.0.on_move)
+            .filter(|piece| board.0.moves_for(piece).next().is_some())
+            .map(|piece| piece.square)
+            .collect();
+        movable.sort_by_key(|square| (square.1, square.0));
+        let current = keyboard_cursor.square;
+        let next = movable.iter().find(|square| (square.1, square.0) > (current.1, current.0)).or(movable.first());
+        if let Some(next) = next {
+            keyboard_cursor.square = *next;
+            keyboard_cursor.active = true;
+        }
+    }
+}
+
+pub fn keyboard_select(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    keyboard_cursor: Res<KeyboardCursor>,
+    board: Res<BoardResource>,
+    local_side: Res<LocalSide>,
+    mut selection: ResMut<Selection>,
+    mut move_request_writer: EventWriter<MoveRequested>
+) {
+    if !keyboard_cursor.active { return };
+    if !keyboard.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter]) { return };
+    let square = keyboard_cursor.square;
+    let selectable = board.0.pieces.get(&square)
+        .is_some_and(|piece| piece.color == board.0.on_move && local_side.controls(piece.color));
+
+    if let Some(from) = selection.0.take() {
+        let legal = board.0.pieces.get(&from).is_some_and(|piece| board.0.get_valid_moves(piece).contains(&square));
+        if legal {
+            move_request_writer.send(MoveRequested::new(from, square));
+            return;
+        }
+        if from == square { return };
+    }
+    if selectable {
+        selection.0 = Some(square);
+    }
+}
+
+pub fn update_keyboard_cursor_marker(
+    config: Res<ChessConfig>,
+    keyboard_cursor: Res<KeyboardCursor>,
+    mut marker_query: Query<(&mut Transform, &mut Visibility), With<KeyboardCursorMarker>>
+) {
+    for (mut transform, mut visibility) in marker_query.iter_mut() {
+        transform.translation = Vec3::from((config.square_to_vector(keyboard_cursor.square), 0.6));
+        *visibility = if keyboard_cursor.active { Visibility::Visible } else { Visibility::Hidden };
+    }
+}
+
+pub fn keyboard_promotion(keyboard: Res<ButtonInput<KeyCode>>, mut promotion_chosen_writer: EventWriter<PromotionChosen>) {
+    let chosen = [
+        (KeyCode::KeyQ, PieceKind::QUEEN),
+        (KeyCode::KeyR, PieceKind::ROOK),
+        (KeyCode::KeyB, PieceKind::BISHOP),
+        (KeyCode::KeyN, PieceKind::KNIGHT),
+        (KeyCode::Enter, PieceKind::QUEEN),
+        (KeyCode::NumpadEnter, PieceKind::QUEEN)
+    ].into_iter().find(|(key, _)| keyboard.just_pressed(*key));
+    if let Some((_, kind)) = chosen {
+        promotion_chosen_writer.send(PromotionChosen(kind));
+    }
+}
@@ -1,32 +1,69 @@
+mod analysis;
+#[cfg(not(target_arch = "wasm32"))]
+mod archive;
+mod arrows;
+mod assets;
+mod audio;
 mod piece;
 mod board;
-mod logic;
+mod clipboard;
+mod clock;
+mod config;
+mod correspondence;
+mod display;
+mod drawrules;
+mod editor;
+mod engine;
+mod enginelines;
+mod enginematch;
+mod evalbar;
+mod fen;
+mod gameover;
+mod highlight;
+mod history;
+mod hotseat;
+mod import;
+mod keyboard;
+mod material;
+mod movelist;
+mod network;
+mod opponent;
+mod pgn;
+#[cfg(feature = "egui")]
+mod panel;
+mod players;
+mod plugin;
+mod puzzle;
+mod rush;
+mod settings;
+mod shortcuts;
+mod snapshot;
+mod spectator;
+mod stats;
+mod storage;
+#[cfg(test)]
+mod tests;
+mod theme;
+mod toast;
+mod transport;
 
-use std::time::Duration;
-use bevy::app::{App, Startup};
-use bevy::DefaultPlugins;
+use bevy::app::App;
+use bevy::asset::AssetMetaCheck;
 use bevy::prelude::*;
-use crate::board::{spawn_board, SQUARE_SIZE, update_board_cursor, update_outline};
-use crate::piece::{BoardUpdate, drag_piece, spawn_phantom_piece, update_board_pieces, AllowDrag, promotion_chooser, spawn_promotion_options, PromotionSquare, check_animation, CheckAnimationTimer};
+use bevy::DefaultPlugins;
+use crate::plugin::{ChessPlugin, LaunchArgs};
 
 fn main() {
     App::new()
-        .insert_resource(AllowDrag(true))
-        .insert_resource(PromotionSquare(None))
-        .insert_resource(CheckAnimationTimer(Timer::new(Duration::from_millis(500), TimerMode::Repeating)))
-        .add_event::<BoardUpdate>()
-        .add_plugins(DefaultPlugins)
-        .add_systems(Startup, (spawn_camera, spawn_board, spawn_phantom_piece, spawn_promotion_options))
-        .add_systems(Update, ((update_board_cursor, drag_piece, promotion_chooser, update_board_pieces).chain(), check_animation, update_outline))
+        .insert_resource(AssetMetaCheck::Never)
+        .add_plugins((DefaultPlugins.set(WindowPlugin {
+            primary_window: Some(Window {
+                title: String::from("cheess"),
+                canvas: Some(String::from("#cheess")),
+                prevent_default_event_handling: false,
+                ..default()
+            }),
+            ..default()
+        }), ChessPlugin::default().with_launch_args(LaunchArgs::from_environment())))
         .run();
 }
-
-
-pub fn spawn_camera(mut commands: Commands) {
-    commands.spawn(
-        Camera2dBundle {
-            transform: Transform::from_xyz(SQUARE_SIZE * 3.5, SQUARE_SIZE * 3.5, 0.0),
-            ..default()
-        }
-    );
-}
\ No newline at end of file
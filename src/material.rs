@@ -0,0 +1,84 @@
+use bevy::prelude::*;
+use chess_logic::logic::{Board, PieceColor, PieceKind};
+use crate::assets::PieceAssets;
+use crate::board::{BoardOrientation, BoardResource};
+use crate::history::{HistoryView, MoveHistory, PlayedMove};
+
+const TRAY_ICON_SIZE: f32 = 20.0;
+
+#[derive(Component)]
+pub struct CapturedTray(pub PieceColor);
+
+#[cfg(not(feature = "egui"))]
+pub fn spawn_captured_trays(mut commands: Commands) {
+    for color in [PieceColor::WHITE, PieceColor::BLACK] {
+        commands.spawn((NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                left: Val::Px(8.0),
+                max_width: Val::Percent(30.0),
+                flex_wrap: FlexWrap::Wrap,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        }, CapturedTray(color)));
+    }
+}
+
+pub fn captured_by(played: &[PlayedMove], color: PieceColor) -> Vec<PieceKind> {
+    let mut captured: Vec<PieceKind> = played.iter()
+        .filter(|played| played.record.piece.color == color)
+        .filter_map(|played| played.record.captured.map(|piece| piece.kind))
+        .collect();
+    captured.sort_by_key(|kind| (-kind.material_value(), *kind as u8));
+    captured
+}
+
+pub fn material_advantage(board: &Board, color: PieceColor) -> i32 {
+    let material = |color: PieceColor| -> i32 { board.pieces_of(color).map(|piece| piece.kind.material_value()).sum() };
+    material(color) - material(color.opposite())
+}
+
+// Captured pieces are shown as they stood on the board, so a captured promoted queen is a queen,
+// and the material difference is counted from the pieces currently on the board.
+pub fn update_captured_trays(
+    mut commands: Commands,
+    board: Res<BoardResource>,
+    history: Res<MoveHistory>,
+    view: Res<HistoryView>,
+    orientation: Res<BoardOrientation>,
+    piece_assets: Res<PieceAssets>,
+    mut trays: Query<(Entity, &CapturedTray, &mut Style)>
+) {
+    let shown = view.displayed(&board.0);
+    let played = &history.0[..view.ply.unwrap_or(history.0.len())];
+    for (entity, CapturedTray(color), mut style) in trays.iter_mut() {
+        let at_bottom = *color == orientation.0;
+        style.top = if at_bottom { Val::Auto } else { Val::Px(8.0) };
+        style.bottom = if at_bottom { Val::Px(8.0) } else { Val::Auto };
+
+        let captured = captured_by(played, *color);
+        let advantage = material_advantage(shown, *color);
+        commands.entity(entity).despawn_descendants().with_children(|tray| {
+            for kind in captured {
+                let mut icon = tray.spawn(ImageBundle {
+                    style: Style {
+                        width: Val::Px(TRAY_ICON_SIZE),
+                        height: Val::Px(TRAY_ICON_SIZE),
+                        ..default()
+                    },
+                    image: UiImage::new(piece_assets.get(color.opposite(), kind)),
+                    ..default()
+                });
+                if let Some(atlas) = piece_assets.atlas(color.opposite(), kind) {
+                    icon.insert(atlas);
+                }
+            }
+            if advantage > 0 {
+                tray.spawn(TextBundle::from_section(format!("+{}", advantage), TextStyle { font_size: 18.0, color: Color::WHITE, ..default() })
+                    .with_style(Style { margin: UiRect::left(Val::Px(4.0)), ..default() }));
+            }
+        });
+    }
+}
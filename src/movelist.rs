@@ -0,0 +1,204 @@
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::prelude::*;
+use bevy::ui::RelativeCursorPosition;
+#[cfg(not(feature = "egui"))]
+use crate::drawrules::spawn_draw_rules;
+#[cfg(not(feature = "egui"))]
+use crate::fen::spawn_fen_field;
+use crate::analysis::Analysis;
+use crate::history::{HistoryView, MoveHistory, ViewPly};
+#[cfg(not(feature = "egui"))]
+use crate::history::ReplayStep;
+
+#[cfg(not(feature = "egui"))]
+const PANEL_WIDTH: f32 = 220.0;
+const ROW_HEIGHT: f32 = 24.0;
+
+#[cfg(not(feature = "egui"))]
+#[derive(Component)]
+pub struct MoveListPanel;
+
+#[derive(Component)]
+pub struct MoveListScrollArea;
+
+#[derive(Component)]
+pub struct MoveListContent;
+
+#[derive(Component)]
+pub struct MoveListEntry(pub usize);
+
+#[derive(Component)]
+pub struct LivePositionButton;
+
+pub struct MoveListScroll {
+    offset: f32,
+    follow: bool
+}
+
+impl Default for MoveListScroll {
+    fn default() -> Self {
+        MoveListScroll {offset: 0.0, follow: true}
+    }
+}
+
+#[cfg(not(feature = "egui"))]
+pub fn spawn_move_list(mut commands: Commands) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(0.0),
+            top: Val::Px(0.0),
+            width: Val::Px(PANEL_WIDTH),
+            height: Val::Percent(100.0),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        },
+        background_color: Color::rgba(0.1, 0.1, 0.1, 0.85).into(),
+        ..default()
+    }, Interaction::default(), MoveListPanel)).with_children(|panel| {
+        panel.spawn((NodeBundle {
+            style: Style {
+                flex_grow: 1.0,
+                flex_direction: FlexDirection::Column,
+                overflow: Overflow::clip_y(),
+                ..default()
+            },
+            ..default()
+        }, RelativeCursorPosition::default(), MoveListScrollArea)).with_children(|area| {
+            area.spawn((NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
+                ..default()
+            }, MoveListContent));
+        });
+        panel.spawn(NodeBundle {
+            style: Style {
+                column_gap: Val::Px(4.0),
+                margin: UiRect::vertical(Val::Px(4.0)),
+                ..default()
+            },
+            ..default()
+        }).with_children(|row| {
+            for step in ReplayStep::ALL {
+                row.spawn((ButtonBundle {
+                    style: Style {
+                        flex_grow: 1.0,
+                        padding: UiRect::all(Val::Px(4.0)),
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.25, 0.25, 0.3).into(),
+                    ..default()
+                }, step)).with_children(|button| {
+                    button.spawn(TextBundle::from_section(step.label(), TextStyle { font_size: 18.0, color: Color::WHITE, ..default() }));
+                });
+            }
+        });
+        panel.spawn((ButtonBundle {
+            style: Style {
+                display: Display::None,
+                padding: UiRect::all(Val::Px(4.0)),
+                justify_content: JustifyContent::Center,
+                ..default()
+            },
+            background_color: Color::rgb(0.25, 0.35, 0.6).into(),
+            ..default()
+        }, LivePositionButton)).with_children(|button| {
+            button.spawn(TextBundle::from_section("Back to live (End)", TextStyle { font_size: 18.0, color: Color::WHITE, ..default() }));
+        });
+        spawn_draw_rules(panel);
+        spawn_fen_field(panel);
+    });
+}
+
+pub fn update_move_list(
+    mut commands: Commands,
+    history: Res<MoveHistory>,
+    view: Res<HistoryView>,
+    analysis: Res<Analysis>,
+    content_query: Query<Entity, With<MoveListContent>>,
+    mut live_button_query: Query<&mut Style, With<LivePositionButton>>
+) {
+    let Ok(content) = content_query.get_single() else { return };
+    let current = view.ply.unwrap_or(history.0.len());
+    commands.entity(content).despawn_descendants().with_children(|content| {
+        for (index, pair) in history.0.chunks(2).enumerate() {
+            content.spawn(NodeBundle {
+                style: Style {
+                    height: Val::Px(ROW_HEIGHT),
+                    flex_shrink: 0.0,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            }).with_children(|row| {
+                row.spawn(TextBundle::from_section(format!("{}.", index + 1), TextStyle { font_size: 18.0, color: Color::GRAY, ..default() })
+                    .with_style(Style { width: Val::Px(40.0), ..default() }));
+                for (offset, played) in pair.iter().enumerate() {
+                    let ply = index * 2 + offset;
+                    let background = if ply + 1 == current { Color::rgb(0.35, 0.35, 0.2) } else { Color::NONE };
+                    let explored = analysis.branch_ply().is_some_and(|branch| ply >= branch);
+                    let color = if explored { Color::rgb(0.75, 0.6, 1.0) } else { Color::WHITE };
+                    row.spawn((ButtonBundle {
+                        style: Style {
+                            width: Val::Px(80.0),
+                            padding: UiRect::horizontal(Val::Px(4.0)),
+                            ..default()
+                        },
+                        background_color: background.into(),
+                        ..default()
+                    }, MoveListEntry(ply))).with_children(|button| {
+                        button.spawn(TextBundle::from_section(played.san.clone(), TextStyle { font_size: 18.0, color, ..default() }));
+                    });
+                }
+            });
+        }
+    });
+    for mut style in live_button_query.iter_mut() {
+        style.display = if view.is_live() { Display::None } else { Display::Flex };
+    }
+}
+
+pub fn move_list_clicks(
+    entry_query: Query<(&Interaction, &MoveListEntry), Changed<Interaction>>,
+    live_button_query: Query<&Interaction, (Changed<Interaction>, With<LivePositionButton>)>,
+    mut view_writer: EventWriter<ViewPly>
+) {
+    for (interaction, entry) in entry_query.iter() {
+        if *interaction == Interaction::Pressed {
+            view_writer.send(ViewPly(Some(entry.0 + 1)));
+        }
+    }
+    if live_button_query.iter().any(|interaction| *interaction == Interaction::Pressed) {
+        view_writer.send(ViewPly(None));
+    }
+}
+
+pub fn scroll_move_list(
+    mut wheel_listener: EventReader<MouseWheel>,
+    mut scroll: Local<MoveListScroll>,
+    area_query: Query<(&Node, &RelativeCursorPosition), With<MoveListScrollArea>>,
+    mut content_query: Query<(&Node, &mut Style), With<MoveListContent>>
+) {
+    let Ok((area, cursor)) = area_query.get_single() else { return };
+    let Ok((content, mut style)) = content_query.get_single_mut() else { return };
+    let max_offset = (content.size().y - area.size().y).max(0.0);
+    let lines: f32 = wheel_listener.read().map(|wheel| match wheel.unit {
+        MouseScrollUnit::Line => wheel.y,
+        MouseScrollUnit::Pixel => wheel.y / ROW_HEIGHT
+    }).sum();
+    if lines != 0.0 && cursor.mouse_over() {
+        scroll.offset = (scroll.offset - lines * ROW_HEIGHT).clamp(0.0, max_offset);
+        scroll.follow = scroll.offset >= max_offset;
+    }
+    if scroll.follow {
+        scroll.offset = max_offset;
+    }
+    scroll.offset = scroll.offset.min(max_offset);
+    if style.top != Val::Px(-scroll.offset) {
+        style.top = Val::Px(-scroll.offset);
+    }
+}
@@ -0,0 +1,720 @@
+use std::collections::VecDeque;
+use std::io;
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::Mutex;
+use std::sync::mpsc::{channel, Receiver, Sender};
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Duration, Instant};
+use bevy::prelude::*;
+use chess_logic::logic::{Board, PieceColor};
+use serde::{Deserialize, Serialize};
+use crate::board::{BoardOrientation, BoardResource, GamePhase, LocalSide, ResignRequested};
+use crate::clock::ChessClock;
+use crate::history::{history_moves, HistoryView, MoveHistory, replay_game, StartingPosition};
+use crate::piece::{BoardUpdate, MovePlayed, MoveRequested};
+use crate::players::{GameMetadata, PlayerInfo};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::spectator::StreamEvent;
+use crate::toast::{ToastLevel, Toasts};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::transport::{TcpTransport, would_block};
+use crate::transport::{invalid, Transport, TransportEvent, WebSocketTransport};
+
+const PROTOCOL_VERSION: u32 = 3;
+#[cfg(not(target_arch = "wasm32"))]
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+#[cfg(not(target_arch = "wasm32"))]
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+#[cfg(not(target_arch = "wasm32"))]
+const RECONNECT_ATTEMPTS: u32 = 5;
+#[cfg(not(target_arch = "wasm32"))]
+const GREETING_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug)]
+pub enum NetworkMode {
+    Host(u16),
+    Join(String),
+    Relay {url: String, room: String}
+}
+
+#[derive(Clone, Debug)]
+pub struct NetworkArgs {
+    pub mode: NetworkMode,
+    pub color: Option<PieceColor>
+}
+
+impl NetworkArgs {
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        let mut args = args.into_iter();
+        let (mut mode, mut relay, mut room, mut color) = (None, None, None, None);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--host" => match args.next().and_then(|port| port.parse().ok()) {
+                    Some(port) => mode = Some(NetworkMode::Host(port)),
+                    None => warn!("--host expects a port number")
+                },
+                "--join" => match args.next() {
+                    Some(address) => mode = Some(NetworkMode::Join(address)),
+                    None => warn!("--join expects an address like 192.168.1.5:5000")
+                },
+                "--relay" => relay = args.next(),
+                "--room" => room = args.next(),
+                "--color" => match args.next().as_deref() {
+                    Some("white") => color = Some(PieceColor::WHITE),
+                    Some("black") => color = Some(PieceColor::BLACK),
+                    _ => warn!("--color expects white or black")
+                },
+                _ => {}
+            }
+        }
+        match (relay, room) {
+            (Some(url), Some(room)) => mode = Some(NetworkMode::Relay {url, room}),
+            (Some(_), None) | (None, Some(_)) => warn!("--relay and --room must be given together"),
+            (None, None) => {}
+        }
+        mode.map(|mode| NetworkArgs {mode, color})
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_query(query: &str) -> Option<Self> {
+        let args = query.trim_start_matches('?').split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .flat_map(|(key, value)| [format!("--{}", key), value.to_string()]);
+        Self::from_args(args)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub enum NetMessage {
+    Hello {version: u32, white: Option<bool>},
+    Start {joiner_white: bool},
+    Resync {start: Option<String>, moves: Vec<String>, fen: String},
+    Move {uci: String, san: String, fen: String},
+    Resign,
+    Desync {fen: String},
+    Watch
+}
+
+enum PeerEvent {
+    Connected {local: PieceColor, peer: String, host: bool},
+    Message(NetMessage),
+    PeerLeft(String),
+    Disconnected(String)
+}
+
+fn color_from_white(white: bool) -> PieceColor {
+    if white { PieceColor::WHITE } else { PieceColor::BLACK }
+}
+
+enum LinkState {
+    JoiningRoom,
+    WaitingForPeer,
+    AwaitingHello,
+    AwaitingStart,
+    Live
+}
+
+pub struct Link<T: Transport> {
+    transport: T,
+    host: bool,
+    preference: Option<bool>,
+    state: LinkState
+}
+
+impl<T: Transport> Link<T> {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn direct(transport: T, host: bool, preference: Option<bool>) -> io::Result<Self> {
+        let mut link = Link {transport, host, preference, state: LinkState::AwaitingHello};
+        link.greet()?;
+        Ok(link)
+    }
+
+    fn relayed(transport: T, preference: Option<bool>) -> Self {
+        Link {transport, host: false, preference, state: LinkState::JoiningRoom}
+    }
+
+    fn greet(&mut self) -> io::Result<()> {
+        self.transport.send(&NetMessage::Hello {version: PROTOCOL_VERSION, white: self.preference})?;
+        self.state = LinkState::AwaitingHello;
+        Ok(())
+    }
+
+    fn connected(&mut self, local: PieceColor, peer_events: &Sender<PeerEvent>) {
+        self.preference = Some(local == PieceColor::WHITE);
+        self.state = LinkState::Live;
+        let _ = peer_events.send(PeerEvent::Connected {local, peer: self.transport.peer(), host: self.host});
+    }
+
+    fn step(&mut self, peer_events: &Sender<PeerEvent>, outgoing: &Receiver<NetMessage>) -> io::Result<bool> {
+        let mut busy = false;
+        if matches!(self.state, LinkState::Live) {
+            while let Ok(message) = outgoing.try_recv() {
+                self.transport.send(&message)?;
+                busy = true;
+            }
+        }
+        let Some(event) = self.transport.poll()? else { return Ok(busy) };
+        self.handle(event, peer_events)?;
+        Ok(true)
+    }
+
+    fn handle(&mut self, event: TransportEvent, peer_events: &Sender<PeerEvent>) -> io::Result<()> {
+        match (&self.state, event) {
+            (_, TransportEvent::PeerLeft) => {
+                self.host = true;
+                self.state = LinkState::WaitingForPeer;
+                let _ = peer_events.send(PeerEvent::PeerLeft(String::from("opponent left the room")));
+            }
+            (LinkState::JoiningRoom, TransportEvent::RoomJoined {first}) => {
+                self.host = first;
+                if first {
+                    self.state = LinkState::WaitingForPeer;
+                } else {
+                    self.greet()?;
+                }
+            }
+            (LinkState::WaitingForPeer, TransportEvent::PeerJoined) => self.greet()?,
+            (LinkState::AwaitingHello, TransportEvent::Message(NetMessage::Hello {version, white})) => {
+                if version != PROTOCOL_VERSION {
+                    return Err(invalid(format!("peer speaks protocol {}, expected {}", version, PROTOCOL_VERSION)));
+                }
+                if self.host {
+                    let host_white = self.preference.or(white.map(|white| !white)).unwrap_or(true);
+                    self.transport.send(&NetMessage::Start {joiner_white: !host_white})?;
+                    self.connected(color_from_white(host_white), peer_events);
+                } else {
+                    self.state = LinkState::AwaitingStart;
+                }
+            }
+            (LinkState::AwaitingStart, TransportEvent::Message(NetMessage::Start {joiner_white})) => {
+                self.connected(color_from_white(joiner_white), peer_events);
+            }
+            (LinkState::Live, TransportEvent::Message(message)) => {
+                let _ = peer_events.send(PeerEvent::Message(message));
+            }
+            (_, event) => warn!("ignoring unexpected network event {:?}", event)
+        }
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn drive<T: Transport>(link: &mut Link<T>, peer_events: &Sender<PeerEvent>, outgoing: &Receiver<NetMessage>) -> io::Error {
+    loop {
+        match link.step(peer_events, outgoing) {
+            Ok(true) => {}
+            Ok(false) => thread::sleep(POLL_INTERVAL),
+            Err(error) => return error
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn keep_joining<T: Transport>(
+    peer_events: &Sender<PeerEvent>,
+    outgoing: &Receiver<NetMessage>,
+    mut preference: Option<bool>,
+    connect: impl Fn(Option<bool>) -> io::Result<Link<T>>
+) -> io::Result<()> {
+    let mut link = connect(preference)?;
+    loop {
+        let error = drive(&mut link, peer_events, outgoing);
+        preference = link.preference;
+        let _ = peer_events.send(PeerEvent::PeerLeft(format!("{}, reconnecting", error)));
+        let mut attempts = 0;
+        link = loop {
+            thread::sleep(RECONNECT_DELAY);
+            match connect(preference) {
+                Ok(link) => break link,
+                Err(error) if attempts + 1 >= RECONNECT_ATTEMPTS => return Err(error),
+                Err(_) => attempts += 1
+            }
+        };
+    }
+}
+
+// A connection to the host that has not yet said whether it came to play or to watch
+#[cfg(not(target_arch = "wasm32"))]
+struct Arrival {
+    transport: TcpTransport,
+    address: SocketAddr,
+    deadline: Instant
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Arrival {
+    fn greeting(&mut self) -> io::Result<Option<NetMessage>> {
+        match self.transport.poll()? {
+            Some(TransportEvent::Message(message)) => Ok(Some(message)),
+            Some(event) => Err(invalid(format!("unexpected greeting {:?}", event))),
+            None if Instant::now() >= self.deadline => Err(io::Error::new(io::ErrorKind::TimedOut, "no greeting received")),
+            None => Ok(None)
+        }
+    }
+}
+
+// Greetings are read one poll at a time, so a connection that stays silent cannot hold up the next one
+#[cfg(not(target_arch = "wasm32"))]
+fn accept_connections(listener: TcpListener, players: Sender<(TcpTransport, NetMessage)>, spectators: Sender<TcpTransport>) -> io::Result<()> {
+    listener.set_nonblocking(true)?;
+    let mut arrivals: Vec<Arrival> = Vec::new();
+    loop {
+        match listener.accept() {
+            Ok((stream, address)) => match stream.set_nonblocking(false).and_then(|_| TcpTransport::new(stream)) {
+                Ok(transport) => arrivals.push(Arrival {transport, address, deadline: Instant::now() + GREETING_TIMEOUT}),
+                Err(error) => warn!("dropping connection from {}: {}", address, error)
+            },
+            Err(error) if would_block(&error) => {}
+            Err(error) => return Err(error)
+        }
+        for mut arrival in std::mem::take(&mut arrivals) {
+            match arrival.greeting() {
+                Ok(None) => arrivals.push(arrival),
+                Ok(Some(NetMessage::Watch)) => {
+                    info!("spectator connected from {}", arrival.address);
+                    let _ = spectators.send(arrival.transport);
+                }
+                Ok(Some(hello)) => {
+                    if players.send((arrival.transport, hello)).is_err() { return Ok(()) };
+                }
+                Err(error) => warn!("dropping connection from {}: {}", arrival.address, error)
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn run_peer(args: NetworkArgs, peer_events: Sender<PeerEvent>, outgoing: Receiver<NetMessage>, spectators: Sender<TcpTransport>) -> io::Result<()> {
+    let mut preference = args.color.map(|color| color == PieceColor::WHITE);
+    match args.mode {
+        NetworkMode::Host(port) => {
+            let listener = TcpListener::bind(("0.0.0.0", port))?;
+            let (players, arrivals) = channel();
+            let acceptor = thread::spawn(move || accept_connections(listener, players, spectators));
+            for (transport, hello) in arrivals {
+                let mut link = Link::direct(transport, true, preference)?;
+                link.handle(TransportEvent::Message(hello), &peer_events)?;
+                let error = drive(&mut link, &peer_events, &outgoing);
+                preference = link.preference;
+                let _ = peer_events.send(PeerEvent::PeerLeft(error.to_string()));
+            }
+            acceptor.join().unwrap_or_else(|_| Err(io::Error::other("connection listener panicked")))
+        }
+        NetworkMode::Join(address) => keep_joining(&peer_events, &outgoing, preference, |preference| {
+            Link::direct(TcpTransport::new(TcpStream::connect(address.as_str())?)?, false, preference)
+        }),
+        NetworkMode::Relay {url, room} => keep_joining(&peer_events, &outgoing, preference, |preference| {
+            Ok(Link::relayed(WebSocketTransport::connect(&url, &room)?, preference))
+        })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn watch_game(address: &str, events: &Sender<StreamEvent>) -> io::Result<()> {
+    let mut transport = TcpTransport::new(TcpStream::connect(address)?)?;
+    transport.send(&NetMessage::Watch)?;
+    loop {
+        match transport.poll()? {
+            Some(TransportEvent::Message(NetMessage::Resync {start, moves, ..})) => {
+                if events.send(StreamEvent::Reset {start, moves}).is_err() { return Ok(()) };
+            }
+            Some(event) => warn!("ignoring unexpected spectator event {:?}", event),
+            None => thread::sleep(POLL_INTERVAL)
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+struct WebDriver {
+    link: Link<WebSocketTransport>,
+    peer_events: Sender<PeerEvent>,
+    outgoing: Mutex<Receiver<NetMessage>>
+}
+
+#[derive(Resource)]
+pub struct NetworkSession {
+    peer_events: Mutex<Receiver<PeerEvent>>,
+    outgoing: Sender<NetMessage>,
+    #[cfg(target_arch = "wasm32")]
+    driver: Option<WebDriver>,
+    #[cfg(not(target_arch = "wasm32"))]
+    spectator_arrivals: Mutex<Receiver<TcpTransport>>,
+    #[cfg(not(target_arch = "wasm32"))]
+    spectators: Vec<TcpTransport>,
+    pub description: String,
+    pub local: Option<PieceColor>,
+    pub peer: String,
+    pub synced: bool,
+    pub halted: Option<String>,
+    inbound: VecDeque<(String, String)>,
+    resync: Option<(Board, StartingPosition, MoveHistory)>
+}
+
+impl NetworkSession {
+    pub fn start(args: NetworkArgs) -> Self {
+        let (event_sender, peer_events) = channel();
+        let (outgoing, message_receiver) = channel();
+        let description = match &args.mode {
+            NetworkMode::Host(port) => format!("Waiting for an opponent on port {}", port),
+            NetworkMode::Join(address) => format!("Connecting to {}", address),
+            NetworkMode::Relay {room, ..} => format!("Waiting for an opponent in room {}", room)
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        let (spectator_sender, spectator_arrivals) = channel();
+        #[cfg(not(target_arch = "wasm32"))]
+        thread::spawn(move || {
+            if let Err(error) = run_peer(args, event_sender.clone(), message_receiver, spectator_sender) {
+                let _ = event_sender.send(PeerEvent::Disconnected(error.to_string()));
+            }
+        });
+        #[cfg(target_arch = "wasm32")]
+        let driver = match &args.mode {
+            NetworkMode::Relay {url, room} => match WebSocketTransport::connect(url, room) {
+                Ok(transport) => {
+                    let link = Link::relayed(transport, args.color.map(|color| color == PieceColor::WHITE));
+                    Some(WebDriver {link, peer_events: event_sender, outgoing: Mutex::new(message_receiver)})
+                }
+                Err(error) => {
+                    let _ = event_sender.send(PeerEvent::Disconnected(error.to_string()));
+                    None
+                }
+            },
+            _ => {
+                let _ = event_sender.send(PeerEvent::Disconnected(String::from("the web build can only play through a relay")));
+                None
+            }
+        };
+        NetworkSession {
+            peer_events: Mutex::new(peer_events),
+            outgoing,
+            #[cfg(target_arch = "wasm32")]
+            driver,
+            #[cfg(not(target_arch = "wasm32"))]
+            spectator_arrivals: Mutex::new(spectator_arrivals),
+            #[cfg(not(target_arch = "wasm32"))]
+            spectators: Vec::new(),
+            description,
+            local: None,
+            peer: String::new(),
+            synced: false,
+            halted: None,
+            inbound: VecDeque::new(),
+            resync: None
+        }
+    }
+
+    fn send(&self, message: NetMessage) {
+        let _ = self.outgoing.send(message);
+    }
+
+    fn halt(&mut self, level: ToastLevel, reason: String, toasts: &mut Toasts) {
+        warn!("network game paused: {}", reason);
+        toasts.push(level, reason.clone());
+        self.halted = Some(reason);
+    }
+
+    fn accept_resync(&mut self, start: Option<&str>, moves: &[String], fen: &str, toasts: &mut Toasts) {
+        match replay_game(start, moves) {
+            Ok((board, start, history)) if board.to_fen() == fen => {
+                self.resync = Some((board, start, history));
+                self.synced = true;
+            }
+            Ok((board, ..)) => {
+                self.send(NetMessage::Desync {fen: board.to_fen()});
+                self.halt(ToastLevel::Error, format!("Desync while resynchronising: expected {}, got {}", fen, board.to_fen()), toasts);
+            }
+            Err(error) => self.halt(ToastLevel::Error, format!("Could not resynchronise: {}", error), toasts)
+        }
+    }
+}
+
+pub fn network_live(session: Option<Res<NetworkSession>>) -> bool {
+    session.is_none_or(|session| session.local.is_some() && session.synced && session.halted.is_none())
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn drive_web_link(mut session: ResMut<NetworkSession>) {
+    let Some(driver) = session.driver.as_mut() else { return };
+    let result = {
+        let outgoing = driver.outgoing.lock().unwrap();
+        driver.link.step(&driver.peer_events, &outgoing)
+    };
+    if let Err(error) = result {
+        let _ = driver.peer_events.send(PeerEvent::Disconnected(error.to_string()));
+        session.driver = None;
+    }
+}
+
+pub fn poll_network(
+    board: Res<BoardResource>,
+    start: Res<StartingPosition>,
+    history: Res<MoveHistory>,
+    mut session: ResMut<NetworkSession>,
+    mut toasts: ResMut<Toasts>,
+    mut resign_writer: EventWriter<ResignRequested>
+) {
+    let events: Vec<PeerEvent> = session.peer_events.lock().unwrap().try_iter().collect();
+    for event in events {
+        match event {
+            PeerEvent::Connected {local, peer, host} => {
+                toasts.push(ToastLevel::Success, format!("Connected to {}, you play {}", peer, local));
+                session.local = Some(local);
+                session.peer = peer;
+                session.halted = None;
+                session.inbound.clear();
+                if !host {
+                    session.synced = false;
+                    continue;
+                }
+                let (start, moves, fen) = if session.synced {
+                    (start.0.as_ref().map(Board::to_fen), history_moves(&history), board.0.to_fen())
+                } else {
+                    (None, Vec::new(), Board::new().to_fen())
+                };
+                session.accept_resync(start.as_deref(), &moves, &fen, &mut toasts);
+                session.send(NetMessage::Resync {start, moves, fen});
+            }
+            PeerEvent::Message(NetMessage::Resync {start, moves, fen}) => session.accept_resync(start.as_deref(), &moves, &fen, &mut toasts),
+            PeerEvent::Message(NetMessage::Move {uci, fen, ..}) => session.inbound.push_back((uci, fen)),
+            PeerEvent::Message(NetMessage::Resign) => {
+                if let Some(local) = session.local {
+                    resign_writer.send(ResignRequested(local.opposite()));
+                }
+            }
+            PeerEvent::Message(NetMessage::Desync {fen}) => {
+                session.halt(ToastLevel::Error, format!("Opponent reports a desync, their position: {}", fen), &mut toasts);
+            }
+            PeerEvent::Message(message) => {
+                session.halt(ToastLevel::Error, format!("Unexpected message from opponent: {:?}", message), &mut toasts);
+            }
+            PeerEvent::PeerLeft(reason) => {
+                session.halt(ToastLevel::Warning, format!("Opponent disconnected ({}), waiting for them to rejoin", reason), &mut toasts);
+            }
+            PeerEvent::Disconnected(reason) => {
+                let context = if session.local.is_some() { "Connection lost" } else { "Could not connect" };
+                session.halt(ToastLevel::Error, format!("{}: {}", context, reason), &mut toasts);
+            }
+        }
+    }
+}
+
+pub fn apply_network_resync(
+    mut session: ResMut<NetworkSession>,
+    mut board: ResMut<BoardResource>,
+    mut start: ResMut<StartingPosition>,
+    mut history: ResMut<MoveHistory>,
+    mut view: ResMut<HistoryView>,
+    mut local_side: ResMut<LocalSide>,
+    mut orientation: ResMut<BoardOrientation>,
+    mut metadata: ResMut<GameMetadata>,
+    mut clock: ResMut<ChessClock>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut board_update_writer: EventWriter<BoardUpdate>
+) {
+    if session.resync.is_none() { return };
+    let (Some(local), Some((resynced, resynced_start, resynced_history))) = (session.local, session.resync.take()) else { return };
+    board.0 = resynced;
+    *start = resynced_start;
+    *history = resynced_history;
+    view.go_live();
+    local_side.0 = Some(local);
+    orientation.0 = local;
+    *metadata.player_mut(local.opposite()) = PlayerInfo {name: session.peer.clone(), rating: None};
+    *clock = ChessClock::new(None);
+    next_phase.set(GamePhase::Playing);
+    board_update_writer.send(BoardUpdate::resync());
+}
+
+pub fn apply_remote_moves(
+    board: Res<BoardResource>,
+    mut session: ResMut<NetworkSession>,
+    mut toasts: ResMut<Toasts>,
+    mut move_request_writer: EventWriter<MoveRequested>
+) {
+    let Some(local) = session.local else { return };
+    if !session.synced || session.halted.is_some() || board.0.on_move == local || session.inbound.is_empty() { return };
+    let Some((uci, fen)) = session.inbound.pop_front() else { return };
+    let Some(candidate) = board.0.parse_move(&uci) else {
+        session.halt(ToastLevel::Error, format!("Opponent sent an unreadable move: {}", uci), &mut toasts);
+        return;
+    };
+    let mut after = board.0.clone();
+    if let Err(error) = after.try_move(candidate) {
+        session.halt(ToastLevel::Error, format!("Opponent sent an illegal move {}: {}", uci, error), &mut toasts);
+        return;
+    }
+    let ours = after.to_fen();
+    if ours != fen {
+        session.send(NetMessage::Desync {fen: ours.clone()});
+        session.halt(ToastLevel::Error, format!("Desync after {}: expected {}, got {}", uci, fen, ours), &mut toasts);
+        return;
+    }
+    move_request_writer.send(MoveRequested {from: candidate.from, to: candidate.to, promotion: candidate.promotion});
+}
+
+pub fn send_local_moves(
+    board: Res<BoardResource>,
+    session: Res<NetworkSession>,
+    mut move_played_listener: EventReader<MovePlayed>,
+    mut resign_listener: EventReader<ResignRequested>
+) {
+    let Some(local) = session.local else { return };
+    for move_played in move_played_listener.read().filter(|move_played| move_played.by == local) {
+        session.send(NetMessage::Move {uci: move_played.record.as_move().to_string(), san: move_played.san.clone(), fen: board.0.to_fen()});
+    }
+    if resign_listener.read().any(|ResignRequested(color)| *color == local) {
+        session.send(NetMessage::Resign);
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn feed_spectators(board: Res<BoardResource>, start: Res<StartingPosition>, history: Res<MoveHistory>, mut session: ResMut<NetworkSession>) {
+    let arrived: Vec<TcpTransport> = session.spectator_arrivals.lock().unwrap().try_iter().collect();
+    let changed = history.is_changed() || start.is_changed();
+    if arrived.is_empty() && !changed { return };
+    let resync = NetMessage::Resync {start: start.0.as_ref().map(Board::to_fen), moves: history_moves(&history), fen: board.0.to_fen()};
+    let up_to_date = if changed { 0 } else { session.spectators.len() };
+    session.spectators.extend(arrived);
+    let mut index = 0;
+    session.spectators.retain_mut(|spectator| {
+        index += 1;
+        index <= up_to_date || spectator.send(&resync).map_err(|error| info!("spectator {} left: {}", spectator.peer(), error)).is_ok()
+    });
+}
+
+#[derive(Component)]
+pub struct NetworkBanner;
+
+pub fn update_network_banner(mut commands: Commands, session: Res<NetworkSession>, banner_query: Query<Entity, With<NetworkBanner>>) {
+    for entity in banner_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let text = match (&session.halted, session.local) {
+        (Some(reason), _) => format!("Network game paused\n{}", reason),
+        (None, None) => session.description.clone(),
+        (None, Some(_)) if !session.synced => String::from("Synchronising with opponent"),
+        (None, Some(_)) => return
+    };
+    commands.spawn((TextBundle::from_section(text, TextStyle { font_size: 24.0, color: Color::WHITE, ..default() })
+        .with_text_justify(JustifyText::Center)
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(48.0),
+            left: Val::Px(12.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        }).with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.7)), NetworkBanner));
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use std::io::Write;
+    use super::*;
+
+    // Keeps what the link sends; NetMessage is not Clone, so each one is stored as a decoded copy
+    #[derive(Default)]
+    struct RecordingTransport {
+        sent: Vec<NetMessage>
+    }
+
+    impl Transport for RecordingTransport {
+        fn send(&mut self, message: &NetMessage) -> io::Result<()> {
+            self.sent.push(ron::from_str(&ron::to_string(message).unwrap()).unwrap());
+            Ok(())
+        }
+
+        fn poll(&mut self) -> io::Result<Option<TransportEvent>> {
+            Ok(None)
+        }
+
+        fn peer(&self) -> String {
+            String::from("peer")
+        }
+    }
+
+    fn hello(white: Option<bool>) -> TransportEvent {
+        TransportEvent::Message(NetMessage::Hello {version: PROTOCOL_VERSION, white})
+    }
+
+    fn connected_as(peer_events: &Receiver<PeerEvent>) -> Option<(PieceColor, bool)> {
+        peer_events.try_iter().find_map(|event| match event {
+            PeerEvent::Connected {local, host, ..} => Some((local, host)),
+            _ => None
+        })
+    }
+
+    #[test]
+    fn the_host_honours_the_joiner_preference() {
+        let (events, peer_events) = channel();
+        let mut link = Link::direct(RecordingTransport::default(), true, None).unwrap();
+        assert!(matches!(link.transport.sent[..], [NetMessage::Hello {..}]));
+        link.handle(hello(Some(true)), &events).unwrap();
+        assert!(matches!(link.transport.sent[1], NetMessage::Start {joiner_white: true}));
+        assert_eq!(connected_as(&peer_events), Some((PieceColor::BLACK, true)));
+    }
+
+    #[test]
+    fn the_joiner_waits_for_start() {
+        let (events, peer_events) = channel();
+        let mut link = Link::direct(RecordingTransport::default(), false, Some(true)).unwrap();
+        link.handle(hello(None), &events).unwrap();
+        assert!(connected_as(&peer_events).is_none());
+        link.handle(TransportEvent::Message(NetMessage::Start {joiner_white: false}), &events).unwrap();
+        assert_eq!(connected_as(&peer_events), Some((PieceColor::BLACK, false)));
+        assert!(matches!(link.state, LinkState::Live));
+    }
+
+    #[test]
+    fn a_different_protocol_version_is_refused() {
+        let (events, _peer_events) = channel();
+        let mut link = Link::direct(RecordingTransport::default(), true, None).unwrap();
+        let old = TransportEvent::Message(NetMessage::Hello {version: PROTOCOL_VERSION - 1, white: None});
+        assert!(link.handle(old, &events).is_err());
+    }
+
+    #[test]
+    fn the_first_in_a_relay_room_hosts_once_a_peer_joins() {
+        let (events, peer_events) = channel();
+        let mut link = Link::relayed(RecordingTransport::default(), Some(false));
+        link.handle(TransportEvent::RoomJoined {first: true}, &events).unwrap();
+        assert!(link.transport.sent.is_empty());
+        link.handle(TransportEvent::PeerJoined, &events).unwrap();
+        assert!(matches!(link.transport.sent[..], [NetMessage::Hello {white: Some(false), ..}]));
+        link.handle(hello(None), &events).unwrap();
+        assert_eq!(connected_as(&peer_events), Some((PieceColor::BLACK, true)));
+    }
+
+    #[test]
+    fn moves_only_go_out_once_live() {
+        let (events, peer_events) = channel();
+        let (outgoing, queued) = channel();
+        let mut link = Link::direct(RecordingTransport::default(), false, None).unwrap();
+        outgoing.send(NetMessage::Resign).unwrap();
+        link.step(&events, &queued).unwrap();
+        assert_eq!(link.transport.sent.len(), 1);
+        link.handle(hello(None), &events).unwrap();
+        link.handle(TransportEvent::Message(NetMessage::Start {joiner_white: true}), &events).unwrap();
+        link.step(&events, &queued).unwrap();
+        assert!(matches!(link.transport.sent[1], NetMessage::Resign));
+        link.handle(TransportEvent::Message(NetMessage::Resign), &events).unwrap();
+        assert!(peer_events.try_iter().any(|event| matches!(event, PeerEvent::Message(NetMessage::Resign))));
+    }
+
+    #[test]
+    fn a_silent_connection_does_not_hold_up_the_next_player() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let (players, arrivals) = channel();
+        let (spectators, _watchers) = channel();
+        thread::spawn(move || accept_connections(listener, players, spectators));
+        let _silent = TcpStream::connect(address).unwrap();
+        let mut player = TcpStream::connect(address).unwrap();
+        writeln!(player, "{}", ron::to_string(&NetMessage::Hello {version: PROTOCOL_VERSION, white: None}).unwrap()).unwrap();
+        let (_, greeting) = arrivals.recv_timeout(GREETING_TIMEOUT / 2).expect("the player waited on the silent connection");
+        assert!(matches!(greeting, NetMessage::Hello {..}));
+    }
+}
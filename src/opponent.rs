@@ -0,0 +1,701 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::{BufRead, BufReader, Write};
+#[cfg(not(target_arch = "wasm32"))]
+use std::process::{Child, ChildStdin, Command, Stdio};
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Mutex;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::{channel, Receiver, TryRecvError};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+use std::time::Duration;
+use serde::{Deserialize, Serialize};
+use chess_logic::logic::{Board, Move};
+#[cfg(not(target_arch = "wasm32"))]
+use chess_logic::logic::PieceColor;
+#[cfg(not(target_arch = "wasm32"))]
+use chess_logic::search::MATE_SCORE;
+use chess_logic::search::{SearchLimits, SearchResult};
+use crate::clock::ChessClock;
+use crate::engine::{EngineJob, LinesJob};
+
+const MAX_BOT_LEVEL: u32 = 6;
+const MIN_MOVE_BUDGET: Duration = Duration::from_millis(50);
+#[cfg(not(target_arch = "wasm32"))]
+const UNTIMED_MOVE_MILLIS: u128 = 1000;
+#[cfg(not(target_arch = "wasm32"))]
+const UCI_HANDSHAKE: Duration = Duration::from_secs(2);
+const MIN_ELO: u32 = 100;
+const FULL_STRENGTH_ELO: u32 = 2800;
+const STRENGTH_LINES: usize = 6;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub enum EngineSpec {
+    Bot {level: u32, #[serde(default)] elo: Option<u32>},
+    Uci {path: String, #[serde(default)] elo: Option<u32>},
+    Human
+}
+
+impl EngineSpec {
+    // "human", or "bot:<level>" or a UCI engine path, either optionally followed by "@<elo>"
+    pub fn parse(text: &str) -> Self {
+        if text.eq_ignore_ascii_case("human") { return EngineSpec::Human };
+        let (text, elo) = match text.rsplit_once('@').and_then(|(engine, elo)| Some((engine, elo.parse::<u32>().ok()?))) {
+            Some((engine, elo)) => (engine, Some(elo.clamp(MIN_ELO, FULL_STRENGTH_ELO))),
+            None => (text, None)
+        };
+        match text.strip_prefix("bot") {
+            Some(level) => EngineSpec::Bot {level: level.trim_start_matches(':').parse().unwrap_or(3).clamp(1, MAX_BOT_LEVEL), elo},
+            None => EngineSpec::Uci {path: text.to_string(), elo}
+        }
+    }
+
+    pub fn name(&self) -> String {
+        match self {
+            EngineSpec::Bot {level, ..} => format!("cheess bot {}", level),
+            EngineSpec::Uci {path, ..} => std::path::Path::new(path).file_stem().map_or_else(|| path.clone(), |stem| stem.to_string_lossy().into_owned()),
+            EngineSpec::Human => String::from("You")
+        }
+    }
+
+    pub fn elo(&self) -> Option<u32> {
+        match self {
+            EngineSpec::Bot {elo, ..} | EngineSpec::Uci {elo, ..} => *elo,
+            EngineSpec::Human => None
+        }
+    }
+
+    pub fn open(&self, seed: u64) -> Result<Box<dyn EngineDriver>, String> {
+        let strength = self.elo().map(|elo| Strength::new(elo, seed));
+        match self {
+            EngineSpec::Bot {level, ..} => Ok(Box::new(BotDriver {
+                level: (*level).clamp(1, MAX_BOT_LEVEL),
+                strength,
+                search: None,
+                predicted: None,
+                pondering: None,
+                analysis: None
+            })),
+            #[cfg(not(target_arch = "wasm32"))]
+            EngineSpec::Uci {path, ..} => UciDriver::spawn(path, strength).map(|driver| Box::new(driver) as Box<dyn EngineDriver>),
+            #[cfg(target_arch = "wasm32")]
+            EngineSpec::Uci {..} => Err(String::from("UCI engines cannot run in the browser")),
+            EngineSpec::Human => Err(String::from("a human is not an engine"))
+        }
+    }
+}
+
+pub trait EngineDriver: Send + Sync {
+    fn think(&mut self, board: &Board, clock: &ChessClock);
+    fn poll(&mut self, delta: Duration) -> Option<Result<Move, String>>;
+    fn move_now(&mut self);
+    // Called with the position after this engine's own move, to think on the reply it expects
+    fn ponder(&mut self, board: &Board, clock: &ChessClock);
+    fn analyse(&mut self, board: &Board, lines: usize);
+    fn poll_lines(&mut self) -> Option<Vec<SearchResult>>;
+    fn stop(&mut self);
+}
+
+// A fortieth of the remaining time plus most of the increment, like a human who expects a long game
+fn move_budget(board: &Board, clock: &ChessClock) -> Option<Duration> {
+    let control = clock.control?;
+    let increment = Duration::from_secs(control.increment_seconds) * 3 / 4;
+    let remaining = clock.remaining(board.on_move);
+    Some((remaining / 40 + increment).min(remaining / 2).max(MIN_MOVE_BUDGET))
+}
+
+// Weakens an engine towards a target Elo: shallower, quicker searches, and now and then
+// a random pick among the moves scoring within a window of the best one
+#[derive(Clone, Copy)]
+struct Strength {
+    elo: u32,
+    state: u64
+}
+
+impl Strength {
+    fn new(elo: u32, seed: u64) -> Self {
+        Strength {elo: elo.clamp(MIN_ELO, FULL_STRENGTH_ELO), state: seed}
+    }
+
+    // splitmix64, which copes with any seed including zero
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut mixed = self.state;
+        mixed = (mixed ^ (mixed >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        mixed = (mixed ^ (mixed >> 27)).wrapping_mul(0x94d049bb133111eb);
+        mixed ^ (mixed >> 31)
+    }
+
+    fn fork(&mut self) -> Strength {
+        Strength {elo: self.elo, state: self.next()}
+    }
+
+    fn depth(&self) -> u32 {
+        (self.elo / 400).max(1)
+    }
+
+    fn move_time(&self) -> Duration {
+        Duration::from_millis(self.elo as u64 / 4).max(MIN_MOVE_BUDGET)
+    }
+
+    fn choose(&mut self, mut lines: Vec<SearchResult>) -> Option<SearchResult> {
+        let best = lines.first()?.score;
+        let deficit = FULL_STRENGTH_ELO - self.elo;
+        let mistakes_per_mille = (deficit * 1000 / (FULL_STRENGTH_ELO - MIN_ELO)).min(900);
+        if self.next() % 1000 >= mistakes_per_mille as u64 {
+            return lines.into_iter().next();
+        }
+        let window = (deficit / 3) as i32;
+        lines.retain(|line| best - line.score <= window);
+        let index = (self.next() % lines.len() as u64) as usize;
+        Some(lines.swap_remove(index))
+    }
+}
+
+pub struct BotDriver {
+    level: u32,
+    strength: Option<Strength>,
+    search: Option<(EngineJob, Option<Duration>)>,
+    predicted: Option<Move>,
+    pondering: Option<(u64, EngineJob)>,
+    analysis: Option<LinesJob>
+}
+
+impl BotDriver {
+    fn spawn_search(&mut self, board: &Board) -> EngineJob {
+        match self.strength.as_mut() {
+            Some(strength) => {
+                let mut picker = strength.fork();
+                let limits = SearchLimits::new(self.level.min(picker.depth()));
+                EngineJob::spawn_choosing(board.clone(), limits, STRENGTH_LINES, move |lines| picker.choose(lines))
+            }
+            None => EngineJob::spawn(board.clone(), SearchLimits::new(self.level))
+        }
+    }
+}
+
+impl EngineDriver for BotDriver {
+    fn think(&mut self, board: &Board, clock: &ChessClock) {
+        let pondering = self.pondering.take();
+        self.stop();
+        let budget = move_budget(board, clock);
+        let budget = match self.strength {
+            Some(strength) => Some(budget.map_or(strength.move_time(), |budget| budget.min(strength.move_time()))),
+            None => budget
+        };
+        let job = match pondering {
+            Some((position, job)) if position == board.position_key() => job,
+            Some((_, job)) => {
+                job.stop();
+                self.spawn_search(board)
+            }
+            None => self.spawn_search(board)
+        };
+        self.search = Some((job, budget));
+    }
+
+    fn poll(&mut self, delta: Duration) -> Option<Result<Move, String>> {
+        let (job, budget) = self.search.as_mut()?;
+        if let Some(left) = budget {
+            *left = left.saturating_sub(delta);
+            if left.is_zero() {
+                job.stop();
+            }
+        }
+        let result = job.try_take()?;
+        self.search = None;
+        self.predicted = result.as_ref().and_then(|result| result.pv.get(1).copied());
+        Some(result.map(|result| result.best_move).ok_or_else(|| String::from("the bot found no move")))
+    }
+
+    fn move_now(&mut self) {
+        if let Some((job, _)) = self.search.as_ref() {
+            job.stop();
+        }
+    }
+
+    fn ponder(&mut self, board: &Board, _: &ChessClock) {
+        let Some(reply) = self.predicted.take() else { return };
+        let mut expected = board.clone();
+        if expected.try_move(reply).is_err() { return };
+        if let Some((_, job)) = self.pondering.take() {
+            job.stop();
+        }
+        self.pondering = Some((expected.position_key(), self.spawn_search(&expected)));
+    }
+
+    fn analyse(&mut self, board: &Board, lines: usize) {
+        self.stop();
+        self.analysis = Some(LinesJob::spawn(board.clone(), SearchLimits::new(self.level), lines));
+    }
+
+    fn poll_lines(&mut self) -> Option<Vec<SearchResult>> {
+        self.analysis.as_ref()?.take_update()
+    }
+
+    fn stop(&mut self) {
+        if let Some((job, _)) = self.search.take() {
+            job.stop();
+        }
+        if let Some((_, job)) = self.pondering.take() {
+            job.stop();
+        }
+        self.analysis = None;
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub struct UciDriver {
+    child: Child,
+    input: ChildStdin,
+    lines: Mutex<Receiver<String>>,
+    board: Option<Board>,
+    stale: usize,
+    analysis: Vec<SearchResult>,
+    predicted: Option<Move>,
+    pondering: Option<u64>,
+    // Only kept when the engine has no UCI_Elo option and has to be weakened from outside
+    strength: Option<Strength>
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl UciDriver {
+    fn spawn(path: &str, strength: Option<Strength>) -> Result<UciEngine, String> {
+        let mut child = Command::new(path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|error| format!("cannot start {}: {}", path, error))?;
+        let (Some(input), Some(output)) = (child.stdin.take(), child.stdout.take()) else { return Err(format!("{} has no standard streams", path)) };
+        let (sender, receiver) = channel();
+        // Blocking pipe reads would pin one of the few compute pool threads for the engine's whole life,
+        // so the reader gets a thread of its own and the driver only ever polls the channel
+        std::thread::spawn(move || {
+            for line in BufReader::new(output).lines() {
+                let Ok(line) = line else { return };
+                if sender.send(line).is_err() { return };
+            }
+        });
+        let driver = UciDriver {child, input, lines: Mutex::new(receiver), board: None, stale: 0, analysis: Vec::new(), predicted: None, pondering: None, strength};
+        let (ready_sender, ready) = channel();
+        let path = path.to_string();
+        std::thread::spawn(move || {
+            let _ = ready_sender.send(driver.start(&path));
+        });
+        Ok(UciEngine {starting: Some(Mutex::new(ready)), driver: None, queued: None, failed: None})
+    }
+
+    fn start(mut self, path: &str) -> Result<Self, String> {
+        let deadline = Instant::now() + UCI_HANDSHAKE;
+        self.send("uci")?;
+        let elo_range = self.handshake(deadline).ok_or_else(|| format!("{} did not answer the UCI handshake", path))?;
+        if let (Some(strength), Some((min, max))) = (self.strength, elo_range) {
+            self.send(&format!("setoption name UCI_LimitStrength value true\nsetoption name UCI_Elo value {}", strength.elo.clamp(min, max)))?;
+            self.strength = None;
+        }
+        self.send("ucinewgame\nisready")?;
+        self.wait_for("readyok", deadline).ok_or_else(|| format!("{} did not report ready", path))?;
+        Ok(self)
+    }
+
+    fn next_line(&mut self, deadline: Instant) -> Option<String> {
+        self.lines.get_mut().unwrap().recv_timeout(deadline.saturating_duration_since(Instant::now())).ok()
+    }
+
+    // Waits for uciok, noting the UCI_Elo range if the engine offers one
+    fn handshake(&mut self, deadline: Instant) -> Option<Option<(u32, u32)>> {
+        let mut elo_range = None;
+        loop {
+            let line = self.next_line(deadline)?;
+            if line.trim() == "uciok" { return Some(elo_range) };
+            let words: Vec<&str> = line.split_whitespace().collect();
+            if words.get(..3) != Some(&["option", "name", "UCI_Elo"]) { continue };
+            let value = |key: &str| words.iter().position(|word| *word == key).and_then(|index| words.get(index + 1)?.parse().ok());
+            elo_range = Some((value("min").unwrap_or(MIN_ELO), value("max").unwrap_or(FULL_STRENGTH_ELO)));
+        }
+    }
+
+    fn wait_for(&mut self, reply: &str, deadline: Instant) -> Option<()> {
+        while self.next_line(deadline)?.trim() != reply {}
+        Some(())
+    }
+
+    fn send(&mut self, command: &str) -> Result<(), String> {
+        writeln!(self.input, "{}", command).map_err(|error| format!("the engine stopped listening: {}", error))
+    }
+
+    // The options to set before the search and the arguments for its go command
+    fn search_command(&self, board: &Board, clock: &ChessClock) -> (String, String) {
+        match (self.strength, clock.control) {
+            (Some(strength), _) => {
+                let move_time = move_budget(board, clock).map_or(strength.move_time(), |budget| budget.min(strength.move_time()));
+                (format!("setoption name MultiPV value {}\n", STRENGTH_LINES), format!("movetime {}", move_time.as_millis()))
+            }
+            (None, Some(control)) => {
+                let [white, black] = [PieceColor::WHITE, PieceColor::BLACK].map(|color| clock.remaining(color).as_millis());
+                let increment = control.increment_seconds * 1000;
+                (String::new(), format!("wtime {} btime {} winc {} binc {}", white, black, increment, increment))
+            }
+            (None, None) => (String::new(), format!("movetime {}", UNTIMED_MOVE_MILLIS))
+        }
+    }
+
+    fn record_info(&mut self, line: &str) -> bool {
+        let Some((index, result)) = self.board.as_ref().and_then(|board| parse_info(board, line)) else { return false };
+        match index.cmp(&self.analysis.len()) {
+            std::cmp::Ordering::Less => self.analysis[index] = result,
+            std::cmp::Ordering::Equal => self.analysis.push(result),
+            std::cmp::Ordering::Greater => return false
+        }
+        true
+    }
+}
+
+// Reads "info ... depth d ... multipv k score cp|mate n ... pv <moves>", skipping bound-only scores
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_info(board: &Board, line: &str) -> Option<(usize, SearchResult)> {
+    let mut words = line.split_whitespace();
+    if words.next() != Some("info") { return None };
+    let (mut depth, mut multipv, mut score, mut pv) = (0, 1, None, Vec::new());
+    while let Some(word) = words.next() {
+        match word {
+            "depth" => depth = words.next()?.parse().ok()?,
+            "multipv" => multipv = words.next()?.parse().ok()?,
+            "score" => score = match (words.next()?, words.next()?.parse::<i32>().ok()?) {
+                ("cp", centipawns) => Some(centipawns),
+                ("mate", moves) if moves > 0 => Some(MATE_SCORE - moves * 2 + 1),
+                ("mate", moves) => Some(-MATE_SCORE - moves * 2),
+                _ => return None
+            },
+            "lowerbound" | "upperbound" => return None,
+            "pv" => {
+                let mut position = board.clone();
+                for text in words.by_ref() {
+                    let Some(candidate) = position.parse_move(text) else { break };
+                    if position.try_move(candidate).is_err() { break };
+                    pv.push(candidate);
+                }
+            }
+            _ => {}
+        }
+    }
+    let best_move = *pv.first()?;
+    Some((multipv.max(1) - 1, SearchResult {best_move, score: score?, depth, pv}))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl EngineDriver for UciDriver {
+    fn think(&mut self, board: &Board, clock: &ChessClock) {
+        if self.board.is_some() && self.pondering.take() == Some(board.position_key()) && self.send("ponderhit").is_ok() { return };
+        self.stop();
+        self.analysis.clear();
+        let (setup, go) = self.search_command(board, clock);
+        if self.send(&format!("{}position fen {}\ngo {}", setup, board.to_fen(), go)).is_ok() {
+            self.board = Some(board.clone());
+        }
+    }
+
+    fn poll(&mut self, _: Duration) -> Option<Result<Move, String>> {
+        loop {
+            let line = match self.lines.get_mut().unwrap().try_recv() {
+                Ok(line) => line,
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => return Some(Err(String::from("the engine exited")))
+            };
+            let mut words = line.split_whitespace();
+            match words.next() {
+                Some("bestmove") if self.stale > 0 => self.stale -= 1,
+                Some("bestmove") => {
+                    let board = self.board.take()?;
+                    let lines = std::mem::take(&mut self.analysis);
+                    if let Some(chosen) = self.strength.as_mut().and_then(|strength| strength.choose(lines)) {
+                        self.predicted = chosen.pv.get(1).copied();
+                        return Some(Ok(chosen.best_move));
+                    }
+                    let text = words.next().unwrap_or_default();
+                    let Some(candidate) = board.parse_move(text) else { return Some(Err(format!("the engine sent an unreadable move {}", text))) };
+                    let reply = (words.next() == Some("ponder")).then(|| words.next()).flatten();
+                    self.predicted = reply.and_then(|reply| {
+                        let mut after = board.clone();
+                        after.try_move(candidate).ok()?;
+                        after.parse_move(reply)
+                    });
+                    return Some(Ok(candidate));
+                }
+                Some("info") if self.stale == 0 && self.strength.is_some() => { self.record_info(&line); }
+                _ => {}
+            }
+        }
+    }
+
+    fn move_now(&mut self) {
+        if self.board.is_some() && self.pondering.is_none() {
+            let _ = self.send("stop");
+        }
+    }
+
+    fn ponder(&mut self, board: &Board, clock: &ChessClock) {
+        let Some(reply) = self.predicted.take() else { return };
+        let mut expected = board.clone();
+        if expected.try_move(reply).is_err() { return };
+        self.stop();
+        self.analysis.clear();
+        let (setup, go) = self.search_command(&expected, clock);
+        let commands = format!("setoption name Ponder value true\n{}position fen {} moves {}\ngo ponder {}", setup, board.to_fen(), reply, go);
+        if self.send(&commands).is_ok() {
+            self.pondering = Some(expected.position_key());
+            self.board = Some(expected);
+        }
+    }
+
+    fn analyse(&mut self, board: &Board, lines: usize) {
+        self.stop();
+        self.analysis.clear();
+        let commands = format!("setoption name MultiPV value {}\nposition fen {}\ngo infinite", lines, board.to_fen());
+        if self.send(&commands).is_ok() {
+            self.board = Some(board.clone());
+        }
+    }
+
+    fn poll_lines(&mut self) -> Option<Vec<SearchResult>> {
+        let mut updated = false;
+        while let Ok(line) = self.lines.get_mut().unwrap().try_recv() {
+            if line.starts_with("bestmove") {
+                match self.stale {
+                    0 => self.board = None,
+                    _ => self.stale -= 1
+                }
+                continue;
+            }
+            if self.stale > 0 { continue };
+            updated |= self.record_info(&line);
+        }
+        updated.then(|| self.analysis.clone())
+    }
+
+    fn stop(&mut self) {
+        self.pondering = None;
+        if self.board.take().is_some() && self.send("stop").is_ok() {
+            self.stale += 1;
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+enum UciRequest {
+    Think(Board, ChessClock),
+    Analyse(Board, usize)
+}
+
+// The engine process starts on the spot, but its handshake runs on a thread of its own so a slow
+// engine does not hold up the frame; whatever is asked of it in the meantime waits for the handshake
+#[cfg(not(target_arch = "wasm32"))]
+pub struct UciEngine {
+    starting: Option<Mutex<Receiver<Result<UciDriver, String>>>>,
+    driver: Option<UciDriver>,
+    queued: Option<UciRequest>,
+    failed: Option<String>
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl UciEngine {
+    fn driver(&mut self) -> Option<&mut UciDriver> {
+        if let Some(starting) = self.starting.as_mut() {
+            match starting.get_mut().unwrap().try_recv() {
+                Err(TryRecvError::Empty) => return None,
+                Ok(Ok(driver)) => self.driver = Some(driver),
+                Ok(Err(error)) => self.failed = Some(error),
+                Err(TryRecvError::Disconnected) => self.failed = Some(String::from("the engine exited during its handshake"))
+            }
+            self.starting = None;
+            if let (Some(driver), Some(request)) = (self.driver.as_mut(), self.queued.take()) {
+                match request {
+                    UciRequest::Think(board, clock) => driver.think(&board, &clock),
+                    UciRequest::Analyse(board, lines) => driver.analyse(&board, lines)
+                }
+            }
+        }
+        self.driver.as_mut()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl EngineDriver for UciEngine {
+    fn think(&mut self, board: &Board, clock: &ChessClock) {
+        match self.driver() {
+            Some(driver) => driver.think(board, clock),
+            None => self.queued = Some(UciRequest::Think(board.clone(), clock.clone()))
+        }
+    }
+
+    fn poll(&mut self, delta: Duration) -> Option<Result<Move, String>> {
+        match self.driver() {
+            Some(driver) => driver.poll(delta),
+            None => self.failed.take().map(Err)
+        }
+    }
+
+    fn move_now(&mut self) {
+        if let Some(driver) = self.driver() {
+            driver.move_now();
+        }
+    }
+
+    fn ponder(&mut self, board: &Board, clock: &ChessClock) {
+        if let Some(driver) = self.driver() {
+            driver.ponder(board, clock);
+        }
+    }
+
+    fn analyse(&mut self, board: &Board, lines: usize) {
+        match self.driver() {
+            Some(driver) => driver.analyse(board, lines),
+            None => self.queued = Some(UciRequest::Analyse(board.clone(), lines))
+        }
+    }
+
+    fn poll_lines(&mut self) -> Option<Vec<SearchResult>> {
+        if let Some(driver) = self.driver() { return driver.poll_lines() };
+        if let Some(error) = self.failed.take() {
+            bevy::log::warn!("the analysis engine failed to start: {}", error);
+        }
+        None
+    }
+
+    fn stop(&mut self) {
+        self.queued = None;
+        if let Some(driver) = self.driver() {
+            driver.stop();
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Drop for UciDriver {
+    fn drop(&mut self) {
+        let _ = self.send("quit");
+        let _ = self.child.kill();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chess_logic::logic::{Coordinate, PieceColor, PieceKind, STANDARD_START};
+    use chess_logic::search::{candidate_moves, search_lines};
+    use super::*;
+
+    fn material(board: &Board, color: PieceColor) -> i32 {
+        board.pieces_of(color).map(|piece| piece.kind.material_value()).sum()
+    }
+
+    // Takes the most valuable piece it can, otherwise the first move it finds
+    fn greedy_move(board: &Board) -> Option<Move> {
+        candidate_moves(board).into_iter().max_by_key(|candidate| board.pieces.get(&candidate.to).map_or(0, |piece| piece.kind.material_value()))
+    }
+
+    // The same pick BotDriver makes, searched on the spot instead of on the task pool
+    fn weakened_move(board: &Board, level: u32, strength: &mut Strength) -> Option<Move> {
+        let mut picker = strength.fork();
+        let lines = search_lines(board, &SearchLimits::new(level.min(picker.depth())), STRENGTH_LINES, |_| {});
+        picker.choose(lines).map(|line| line.best_move)
+    }
+
+    // The move on which the bot has fallen three points behind, if it does within the given number of moves
+    fn blunder_within(moves: usize, seed: u64) -> Option<usize> {
+        let mut board = Board::new();
+        let mut strength = Strength::new(300, seed);
+        for number in 1..=moves {
+            board.try_move(weakened_move(&board, 3, &mut strength)?).unwrap();
+            board.try_move(greedy_move(&board)?).unwrap();
+            if material(&board, PieceColor::WHITE) + 3 <= material(&board, PieceColor::BLACK) { return Some(number) };
+        }
+        None
+    }
+
+    #[test]
+    fn a_300_elo_bot_blunders_against_a_greedy_opponent() {
+        let blunders: Vec<Option<usize>> = (0..8).map(|seed| blunder_within(40, seed)).collect();
+        assert_eq!(blunders, [8, 6, 14, 16, 10, 7, 6, 9].map(Some));
+    }
+
+    #[test]
+    fn the_same_seed_plays_the_same_moves() {
+        let board = Board::from_fen(STANDARD_START).unwrap();
+        let picks = |seed| {
+            let mut strength = Strength::new(300, seed);
+            (0..8).map(|_| weakened_move(&board, 3, &mut strength)).collect::<Vec<_>>()
+        };
+        assert_eq!(picks(42), picks(42));
+    }
+
+    // A stand-in UCI engine: a shell script that runs the given commands before uciok, only reports
+    // ready after ucinewgame and always plays the given move
+    #[cfg(unix)]
+    fn scripted_engine(name: &str, before_uciok: &str, best_move: &str) -> String {
+        use std::os::unix::fs::PermissionsExt;
+        let path = std::env::temp_dir().join(format!("cheess-{}-{}.sh", name, std::process::id()));
+        let script = format!(
+            "#!/bin/sh\nfresh=0\nwhile read -r command rest; do\n  case \"$command\" in\n    uci) {}echo uciok ;;\n    ucinewgame) fresh=1 ;;\n    isready) [ \"$fresh\" = 1 ] && echo readyok ;;\n    go) echo \"bestmove {}\" ;;\n    quit) exit 0 ;;\n  esac\ndone\n",
+            before_uciok, best_move
+        );
+        std::fs::write(&path, script).unwrap();
+        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    #[cfg(unix)]
+    fn wait_for_move(engine: &mut Box<dyn EngineDriver>) -> Result<Move, String> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Some(reply) = engine.poll(Duration::ZERO) { return reply };
+            assert!(Instant::now() < deadline, "the engine never answered");
+            std::thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_uci_engine_castles_from_the_standard_start() {
+        let mut board = Board::new();
+        for text in ["e4", "e5", "Nf3", "Nc6", "Bc4", "Nf6"] {
+            board.try_move(board.parse_move(text).unwrap()).unwrap();
+        }
+        let path = scripted_engine("castling", "", "e1g1");
+        let mut engine = EngineSpec::Uci {path: path.clone(), elo: None}.open(0).unwrap();
+        engine.think(&board, &ChessClock::default());
+        let reply = wait_for_move(&mut engine);
+        drop(engine);
+        let _ = std::fs::remove_file(path);
+        board.try_move(reply.unwrap()).unwrap();
+        assert_eq!(board.pieces[&Coordinate(6, 0)].kind, PieceKind::KING);
+        assert_eq!(board.pieces[&Coordinate(5, 0)].kind, PieceKind::ROOK);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_slow_handshake_does_not_block_opening_the_engine() {
+        let path = scripted_engine("slow", "sleep 1; ", "e2e4");
+        let opened = Instant::now();
+        let mut engine = EngineSpec::Uci {path: path.clone(), elo: None}.open(0).unwrap();
+        engine.think(&Board::new(), &ChessClock::default());
+        assert!(opened.elapsed() < Duration::from_millis(500));
+        assert!(engine.poll(Duration::ZERO).is_none());
+        let reply = wait_for_move(&mut engine);
+        drop(engine);
+        let _ = std::fs::remove_file(path);
+        assert_eq!(reply.unwrap().to_string(), "e2e4");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn a_silent_engine_fails_through_poll() {
+        let path = scripted_engine("silent", "sleep 5; ", "e2e4");
+        let mut engine = EngineSpec::Uci {path: path.clone(), elo: None}.open(0).unwrap();
+        engine.think(&Board::new(), &ChessClock::default());
+        let reply = wait_for_move(&mut engine);
+        drop(engine);
+        let _ = std::fs::remove_file(path);
+        assert!(reply.is_err());
+    }
+}
@@ -0,0 +1,300 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use bevy::window::PrimaryWindow;
+use bevy_egui::{egui, EguiContexts, EguiSettings};
+use chess_logic::logic::PieceColor;
+use crate::analysis::Analysis;
+use crate::board::{BoardCamera, BoardOrientation, BoardResource, describe_status, DrawClaimed, GameStatusRes, UiPointerCapture};
+use crate::clipboard::{ClipboardRequest, PasteRequested};
+use crate::clock::{ChessClock, format_clock};
+use crate::display::ui_scale;
+use crate::drawrules::{claimable_draw, fifty_move_label, repetition_label};
+use crate::enginelines::EngineLines;
+use crate::evalbar::{EvalBar, Evaluation};
+use crate::correspondence::ExportMoveRequested;
+use crate::fen::{FenInput, PositionLoader};
+use crate::gameover::{GameOverAction, GameOverButton, GameOverDismissed};
+use crate::history::{HistoryView, MoveHistory, ReplayStep, ViewPly};
+use crate::material::{captured_by, material_advantage};
+use crate::piece::{PromotionCancelConfirmed, PromotionCancelPrompt};
+use crate::players::GameMetadata;
+use crate::settings::Settings;
+use crate::shortcuts::TextInputFocused;
+
+const PANEL_WIDTH: f32 = 240.0;
+const PV_PLIES: usize = 8;
+
+#[derive(SystemParam)]
+pub struct PanelData<'w> {
+    clock: Res<'w, ChessClock>,
+    metadata: Res<'w, GameMetadata>,
+    orientation: Res<'w, BoardOrientation>,
+    board: Res<'w, BoardResource>,
+    history: Res<'w, MoveHistory>,
+    view: Res<'w, HistoryView>,
+    eval_bar: Res<'w, EvalBar>,
+    analysis: Res<'w, Analysis>,
+    engine_lines: Res<'w, EngineLines>,
+    game_status: Res<'w, GameStatusRes>
+}
+
+impl PanelData<'_> {
+    fn player_row(&self, ui: &mut egui::Ui, color: PieceColor) {
+        ui.horizontal(|ui| {
+            ui.label(egui::RichText::new(self.metadata.player(color).short_label()).strong());
+            if self.clock.control.is_none() { return };
+            let text = egui::RichText::new(format_clock(self.clock.remaining(color))).monospace().size(20.0);
+            let active = self.clock.is_running() && self.board.0.on_move == color;
+            ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                ui.label(if active { text.strong() } else { text.weak() });
+            });
+        });
+    }
+
+    fn captured_row(&self, ui: &mut egui::Ui, color: PieceColor) {
+        let played = &self.history.0[..self.view.ply.unwrap_or(self.history.0.len())];
+        let mut label: String = captured_by(played, color).iter().map(|kind| kind.to_fen_char(color.opposite())).collect();
+        let advantage = material_advantage(self.view.displayed(&self.board.0), color);
+        if advantage > 0 {
+            label.push_str(&format!(" +{}", advantage));
+        }
+        ui.label(label);
+    }
+
+    fn draw_rules(&self, ui: &mut egui::Ui, draw_claimed_writer: &mut EventWriter<DrawClaimed>) {
+        let board = &self.board.0;
+        let claimable = claimable_draw(board, self.game_status.status()).is_some();
+        let color = if claimable { egui::Color32::from_rgb(255, 215, 80) } else { ui.visuals().weak_text_color() };
+        if ui.add_enabled(claimable, egui::Button::new("Claim draw")).clicked() {
+            draw_claimed_writer.send(DrawClaimed);
+        }
+        if let Some(repetitions) = repetition_label(board) {
+            ui.colored_label(color, repetitions);
+        }
+        ui.colored_label(color, fifty_move_label(board));
+    }
+
+    fn engine_lines(&self, ui: &mut egui::Ui) {
+        let Some(position) = &self.engine_lines.position else { return };
+        if self.engine_lines.lines.is_empty() {
+            ui.weak("Engine thinking...");
+        }
+        // The panel lays out bottom up, so the best line goes in last to sit on top
+        for line in self.engine_lines.lines.iter().rev() {
+            let evaluation = Evaluation::from_score(line.score, position.on_move).label();
+            let pv = position.san_line(&line.pv[..line.pv.len().min(PV_PLIES)]);
+            ui.label(egui::RichText::new(format!("{} (depth {})  {}", evaluation, line.depth, pv)).small());
+        }
+        ui.label(egui::RichText::new("Engine lines").strong());
+        ui.separator();
+    }
+
+    fn replay_controls(&self, ui: &mut egui::Ui, view_writer: &mut EventWriter<ViewPly>) {
+        ui.horizontal(|ui| {
+            for step in ReplayStep::ALL {
+                let target = step.target(&self.view, &self.history);
+                if ui.add_enabled(target != self.view.ply, egui::Button::new(step.label())).clicked() {
+                    view_writer.send(ViewPly(target));
+                }
+            }
+        });
+    }
+
+    fn move_list(&self, ui: &mut egui::Ui, view_writer: &mut EventWriter<ViewPly>) {
+        let current = self.view.ply.unwrap_or(self.history.0.len());
+        egui::ScrollArea::vertical().auto_shrink([false, false]).stick_to_bottom(true).show(ui, |ui| {
+            egui::Grid::new("move_list").num_columns(3).striped(true).show(ui, |ui| {
+                for (index, pair) in self.history.0.chunks(2).enumerate() {
+                    ui.label(format!("{}.", index + 1));
+                    for (offset, played) in pair.iter().enumerate() {
+                        let ply = index * 2 + offset;
+                        let mut san = egui::RichText::new(&played.san);
+                        if self.analysis.branch_ply().is_some_and(|branch| ply >= branch) {
+                            san = san.italics().color(egui::Color32::from_rgb(190, 155, 255));
+                        }
+                        if ui.selectable_label(ply + 1 == current, san).clicked() {
+                            view_writer.send(ViewPly(Some(ply + 1)));
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+        });
+    }
+}
+
+pub fn side_panel(
+    mut contexts: EguiContexts,
+    data: PanelData,
+    mut fen_input: ResMut<FenInput>,
+    mut view_writer: EventWriter<ViewPly>,
+    mut clipboard_writer: EventWriter<ClipboardRequest>,
+    mut paste_writer: EventWriter<PasteRequested>,
+    mut export_writer: EventWriter<ExportMoveRequested>,
+    mut loader: PositionLoader,
+    mut draw_claimed_writer: EventWriter<DrawClaimed>,
+    mut mirrored_fen: Local<String>
+) {
+    let shown_fen = data.view.displayed(&data.board.0).to_fen();
+    if *mirrored_fen != shown_fen {
+        *mirrored_fen = shown_fen.clone();
+        if !fen_input.editing {
+            fen_input.text = shown_fen.clone();
+        }
+    }
+    let Some(ctx) = contexts.try_ctx_mut() else { return };
+    let top = data.orientation.0.opposite();
+    egui::SidePanel::right("side_panel").exact_width(PANEL_WIDTH).resizable(false).show(ctx, |ui| {
+        data.player_row(ui, top);
+        data.captured_row(ui, top);
+        ui.separator();
+        ui.with_layout(egui::Layout::bottom_up(egui::Align::Min), |ui| {
+            data.player_row(ui, top.opposite());
+            data.captured_row(ui, top.opposite());
+            ui.separator();
+            if let Some(error) = &fen_input.error {
+                ui.colored_label(egui::Color32::from_rgb(255, 115, 100), error);
+            }
+            let mut load = false;
+            ui.horizontal(|ui| {
+                if ui.button("Copy").clicked() {
+                    clipboard_writer.send(ClipboardRequest(shown_fen.clone()));
+                }
+                load = ui.button("Load").clicked();
+            });
+            let response = ui.add(egui::TextEdit::singleline(&mut fen_input.text).desired_width(f32::INFINITY));
+            if response.gained_focus() {
+                fen_input.editing = true;
+                fen_input.error = None;
+            }
+            if response.lost_focus() {
+                fen_input.editing = false;
+                load |= ui.input(|input| input.key_pressed(egui::Key::Enter));
+            }
+            if load {
+                let text = fen_input.text.clone();
+                fen_input.submit(&text, &mut loader);
+            }
+            ui.label("FEN or lichess / chess.com game link");
+            ui.horizontal(|ui| {
+                if ui.button("Export move").clicked() {
+                    export_writer.send(ExportMoveRequested);
+                }
+                if ui.button("Apply move").clicked() {
+                    paste_writer.send(PasteRequested);
+                }
+            });
+            ui.separator();
+            data.draw_rules(ui, &mut draw_claimed_writer);
+            ui.separator();
+            ui.label(format!("Evaluation: {}", data.eval_bar.evaluation.label()));
+            data.engine_lines(ui);
+            if !data.view.is_live() && ui.button("Back to live (End)").clicked() {
+                view_writer.send(ViewPly(None));
+            }
+            data.replay_controls(ui, &mut view_writer);
+            ui.with_layout(egui::Layout::top_down(egui::Align::Min), |ui| data.move_list(ui, &mut view_writer));
+        });
+    });
+}
+
+pub fn game_over_dialog(
+    mut contexts: EguiContexts,
+    board: Res<BoardResource>,
+    game_status: Res<GameStatusRes>,
+    dismissed: Res<GameOverDismissed>,
+    mut action_writer: EventWriter<GameOverAction>
+) {
+    if dismissed.0 { return };
+    let Some(ctx) = contexts.try_ctx_mut() else { return };
+    let result = describe_status(game_status.status(), board.0.on_move, false);
+    egui::Window::new("Game over")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.heading(result);
+            ui.horizontal(|ui| {
+                for button in [GameOverButton::Rematch, GameOverButton::NewGame, GameOverButton::CopyPgn, GameOverButton::ExportImage, GameOverButton::Analyze, GameOverButton::Close] {
+                    if ui.button(button.label()).clicked() {
+                        action_writer.send(GameOverAction(button));
+                    }
+                }
+            });
+        });
+}
+
+pub fn promotion_cancel_dialog(
+    mut contexts: EguiContexts,
+    mut prompt: ResMut<PromotionCancelPrompt>,
+    mut confirmed_writer: EventWriter<PromotionCancelConfirmed>
+) {
+    if !prompt.open { return };
+    let Some(ctx) = contexts.try_ctx_mut() else { return };
+    egui::Window::new("Cancel promotion?")
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            ui.label("Take the pawn back and play a different move?");
+            ui.horizontal(|ui| {
+                if ui.button("Yes").clicked() {
+                    confirmed_writer.send(PromotionCancelConfirmed);
+                }
+                if ui.button("No").clicked() {
+                    prompt.open = false;
+                }
+            });
+        });
+}
+
+pub fn sync_egui_input(
+    mut contexts: EguiContexts,
+    mut capture: ResMut<UiPointerCapture>,
+    mut text_input_focused: ResMut<TextInputFocused>,
+    mut keyboard_focus: Local<bool>
+) {
+    let Some(ctx) = contexts.try_ctx_mut() else { return };
+    let pointer = ctx.wants_pointer_input() || ctx.is_pointer_over_area();
+    if capture.0 != pointer {
+        capture.0 = pointer;
+    }
+    let focused = ctx.wants_keyboard_input();
+    if focused != *keyboard_focus {
+        *keyboard_focus = focused;
+        text_input_focused.0 = focused;
+    }
+}
+
+pub fn apply_egui_scale(
+    settings: Res<Settings>,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut egui_settings: ResMut<EguiSettings>
+) {
+    let Ok(window) = window_query.get_single() else { return };
+    let relative = ui_scale(&settings, window) / window.scale_factor();
+    if egui_settings.scale_factor != relative {
+        egui_settings.scale_factor = relative;
+    }
+}
+
+pub fn fit_board_viewport(
+    mut contexts: EguiContexts,
+    window_query: Query<&Window, With<PrimaryWindow>>,
+    mut camera_query: Query<&mut Camera, With<BoardCamera>>
+) {
+    let Ok(window) = window_query.get_single() else { return };
+    let Some(ctx) = contexts.try_ctx_mut() else { return };
+    let available = ctx.available_rect();
+    let scale = ctx.pixels_per_point();
+    let position = UVec2::new((available.min.x * scale) as u32, (available.min.y * scale) as u32);
+    let size = UVec2::new((available.width() * scale) as u32, (available.height() * scale) as u32)
+        .min(UVec2::new(window.physical_width(), window.physical_height()).saturating_sub(position));
+    if size.x == 0 || size.y == 0 { return };
+    for mut camera in camera_query.iter_mut() {
+        let unchanged = camera.viewport.as_ref().is_some_and(|viewport| viewport.physical_position == position && viewport.physical_size == size);
+        if unchanged { continue };
+        camera.viewport = Some(Viewport {physical_position: position, physical_size: size, ..default()});
+    }
+}
@@ -0,0 +1,197 @@
+use std::time::Duration;
+use chess_logic::logic::{Board, PieceColor, STANDARD_START};
+use crate::players::PlayerInfo;
+use crate::settings::TimeControl;
+
+const RESULTS: [&str; 4] = ["1-0", "0-1", "1/2-1/2", "*"];
+
+pub struct PgnMove {
+    pub uci: String,
+    pub clock: Option<Duration>
+}
+
+pub struct PgnGame {
+    pub tags: Vec<(String, String)>,
+    pub start: Option<String>,
+    pub moves: Vec<PgnMove>,
+    pub result: Option<String>
+}
+
+impl PgnGame {
+    pub fn tag(&self, name: &str) -> Option<&str> {
+        self.tags.iter().find(|(tag, _)| tag.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+
+    pub fn player(&self, color: PieceColor) -> PlayerInfo {
+        let (name_tag, elo_tag, fallback) = match color {
+            PieceColor::WHITE => ("White", "WhiteElo", "White"),
+            PieceColor::BLACK => ("Black", "BlackElo", "Black")
+        };
+        let name = self.tag(name_tag).filter(|name| !name.is_empty() && *name != "?").unwrap_or(fallback);
+        PlayerInfo {name: name.to_string(), rating: self.tag(elo_tag).and_then(|elo| elo.trim_end_matches('?').parse().ok())}
+    }
+
+    pub fn time_control(&self) -> Option<TimeControl> {
+        let tag = self.tag("TimeControl")?;
+        let (base, increment) = tag.split_once('+').unwrap_or((tag, "0"));
+        Some(TimeControl {base_seconds: base.parse().ok()?, increment_seconds: increment.parse().ok()?, delay_seconds: 0})
+    }
+}
+
+fn parse_tag(line: &str) -> Option<(String, String)> {
+    let inner = line.strip_prefix('[')?.strip_suffix(']')?;
+    let (name, value) = inner.split_once(char::is_whitespace)?;
+    let value = value.trim().strip_prefix('"')?.strip_suffix('"')?;
+    Some((name.to_string(), value.replace("\\\"", "\"").replace("\\\\", "\\")))
+}
+
+fn parse_clock(comment: &str) -> Option<Duration> {
+    let (_, rest) = comment.split_once("[%clk")?;
+    let (value, _) = rest.split_once(']')?;
+    let mut seconds = 0.0;
+    for part in value.trim().split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+    Duration::try_from_secs_f64(seconds).ok()
+}
+
+enum Token {
+    Word(String),
+    Comment(String),
+    Open,
+    Close
+}
+
+fn tokenize(movetext: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut characters = movetext.chars().peekable();
+    while let Some(character) = characters.next() {
+        match character {
+            '{' => tokens.push(Token::Comment(characters.by_ref().take_while(|character| *character != '}').collect())),
+            ';' => while characters.next_if(|character| *character != '\n').is_some() {},
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            character if character.is_whitespace() => {}
+            character => {
+                let mut word = String::from(character);
+                while let Some(next) = characters.next_if(|next| !next.is_whitespace() && !"{};()".contains(*next)) {
+                    word.push(next);
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+    tokens
+}
+
+fn is_move_number(token: &str) -> bool {
+    let digits = token.trim_end_matches('.');
+    digits.len() < token.len() && !digits.is_empty() && digits.chars().all(|character| character.is_ascii_digit())
+}
+
+fn read_moves(mut board: Board, tokens: &[Token]) -> Result<(Vec<PgnMove>, Option<String>), String> {
+    let mut moves: Vec<PgnMove> = Vec::new();
+    let mut depth = 0u32;
+    for token in tokens {
+        match token {
+            Token::Open => depth += 1,
+            Token::Close => depth = depth.saturating_sub(1),
+            Token::Comment(comment) if depth == 0 => {
+                if let (Some(last), Some(clock)) = (moves.last_mut(), parse_clock(comment)) {
+                    last.clock = Some(clock);
+                }
+            }
+            Token::Word(word) if depth == 0 => {
+                if RESULTS.contains(&word.as_str()) { return Ok((moves, Some(word.clone()))) };
+                if is_move_number(word) || word.starts_with('$') { continue };
+                let san = word.rsplit('.').next().unwrap_or(word).trim_end_matches(['!', '?']);
+                let candidate = board.parse_move(san).ok_or_else(|| format!("cannot read move {} after {} moves", san, moves.len()))?;
+                board.try_move(candidate).map_err(|error| format!("illegal move {}: {}", san, error))?;
+                moves.push(PgnMove {uci: candidate.to_string(), clock: None});
+            }
+            _ => {}
+        }
+    }
+    Ok((moves, None))
+}
+
+pub fn parse_pgn(text: &str) -> Result<PgnGame, String> {
+    let mut tags = Vec::new();
+    let mut movetext = String::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.starts_with('%') { continue };
+        if line.starts_with('[') && movetext.trim().is_empty() {
+            tags.extend(parse_tag(line));
+            continue;
+        }
+        if line.starts_with('[') { break };
+        movetext.push_str(line);
+        movetext.push('\n');
+    }
+    if let Some(variant) = tags.iter().find(|(tag, _)| tag.eq_ignore_ascii_case("Variant")).map(|(_, value)| value.as_str()) {
+        if !["Standard", "From Position"].iter().any(|supported| variant.eq_ignore_ascii_case(supported)) {
+            return Err(format!("{} games are not supported", variant));
+        }
+    }
+    let tokens = tokenize(&movetext);
+    let (start, (moves, result)) = match tags.iter().find(|(tag, _)| tag.eq_ignore_ascii_case("FEN")) {
+        Some((_, fen)) => {
+            let board = Board::from_fen(fen).map_err(|error| format!("unreadable FEN tag: {}", error))?;
+            (Some(fen.clone()), read_moves(board, &tokens)?)
+        }
+        None => {
+            let standard = Board::from_fen(STANDARD_START).map_err(|error| error.to_string())?;
+            (Some(STANDARD_START.to_string()), read_moves(standard, &tokens)?)
+        }
+    };
+    if moves.is_empty() && result.is_none() { return Err(String::from("no moves found")) };
+    Ok(PgnGame {tags, start, moves, result})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_clocks_from_move_comments() {
+        let game = parse_pgn("1. e4 {[%clk 0:05:00]} e5 {[%clk 0:04:58.5]} 2. Nf3 *").unwrap();
+        let clocks: Vec<Option<Duration>> = game.moves.iter().map(|played| played.clock).collect();
+        assert_eq!(clocks, [Some(Duration::from_secs(300)), Some(Duration::from_secs_f64(298.5)), None]);
+        assert_eq!(game.result.as_deref(), Some("*"));
+    }
+
+    #[test]
+    fn ignores_unreadable_clocks() {
+        let game = parse_pgn("1. e4 {[%clk -1:00:00]} e5 {[%clk 1e400]} 2. d4 {[%clk NaN]} 1-0").unwrap();
+        assert!(game.moves.iter().all(|played| played.clock.is_none()));
+    }
+
+    #[test]
+    fn skips_comments_variations_and_annotations() {
+        let text = "[White \"Anna\"]\n[WhiteElo \"1850\"]\n\n1. e4 {best by test} e5 (1... c5 2. Nf3 (2. c3)) 2. Nf3!? $1 ; a rest-of-line comment\nNc6 1/2-1/2";
+        let game = parse_pgn(text).unwrap();
+        let moves: Vec<&str> = game.moves.iter().map(|played| played.uci.as_str()).collect();
+        assert_eq!(moves, ["e2e4", "e7e5", "g1f3", "b8c6"]);
+        assert_eq!(game.result.as_deref(), Some("1/2-1/2"));
+        assert_eq!(game.player(PieceColor::WHITE).name, "Anna");
+        assert_eq!(game.player(PieceColor::WHITE).rating, Some(1850));
+        assert_eq!(game.player(PieceColor::BLACK).name, "Black");
+    }
+
+    #[test]
+    fn starts_from_the_fen_tag() {
+        let game = parse_pgn("[SetUp \"1\"]\n[FEN \"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\"]\n\n1. e4 *").unwrap();
+        assert_eq!(game.start.as_deref(), Some("4k3/8/8/8/8/8/4P3/4K3 w - - 0 1"));
+        assert_eq!(game.moves[0].uci, "e2e4");
+    }
+
+    #[test]
+    fn rejects_bad_input() {
+        assert!(parse_pgn("").is_err());
+        assert!(parse_pgn("1. e5 *").is_err());
+        assert!(parse_pgn("1. e4 e5 2. Ke3 *").is_err());
+        assert!(parse_pgn("[Variant \"Chess960\"]\n\n1. e4 *").is_err());
+        assert!(parse_pgn("[FEN \"not a position\"]\n\n1. e4 *").is_err());
+    }
+}
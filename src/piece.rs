@@ -1,10 +1,19 @@
+use std::collections::HashMap;
 use std::fmt::{Debug, Display};
 use std::ptr::null;
 use bevy::prelude::*;
 use bevy::prelude::Color::Rgba;
 
-use crate::board::{BoardResource, SQUARE_SIZE, square_to_vector, WorldCursor};
-use crate::logic::{Coordinate, Piece, PieceColor, PieceKind};
+use crate::arrows::Annotations;
+use crate::assets::PieceAssets;
+use bevy::window::{PrimaryWindow, WindowFocused};
+use crate::board::{BoardOrientation, BoardResource, CheckEvent, GameOverEvent, GamePhase, LocalSide, MenuState, WorldCursor};
+use crate::config::ChessConfig;
+use crate::highlight::HighlightPalette;
+use crate::history::HistoryView;
+use crate::settings::Settings;
+use crate::shortcuts::DrawOffer;
+use chess_logic::logic::{Board, Coordinate, GameStatus, MoveRecord, Piece, PieceColor, PositionStatus, PieceKind};
 
 #[derive(Component)]
 pub struct ShadowPiece {}
@@ -15,209 +24,989 @@ pub struct PhantomPiece {}
 #[derive(Component)]
 pub struct PieceComponent {
     piece: Piece,
-    dragged: bool
+    dragged: bool,
+    drag_started: bool,
+    grab_offset: Vec2
 }
 
 impl PieceComponent {
-    fn get_texture_name(&self) -> String {
-        format!("{}_{}", self.piece.color.to_string(), self.piece.kind.to_string())
+    pub fn is_dragged(&self) -> bool {
+        self.dragged
     }
 }
 
+#[derive(Clone, Copy)]
+pub enum BoardChange {
+    Moved(MoveRecord),
+    Removed(Coordinate),
+    Promoted(Piece)
+}
+
+#[derive(Event, Default)]
+pub struct BoardUpdate {
+    pub change: Option<BoardChange>
+}
+
+impl BoardUpdate {
+    pub fn resync() -> Self {
+        BoardUpdate {change: None}
+    }
+
+    pub fn changed(change: BoardChange) -> Self {
+        BoardUpdate {change: Some(change)}
+    }
+}
+
+#[derive(Event)]
+pub struct MovePlayed {
+    pub record: MoveRecord,
+    pub san: String,
+    pub after: PositionStatus,
+    pub by: PieceColor
+}
+
+#[derive(Event)]
+pub struct CaptureEvent {
+    pub piece: Piece,
+    pub square: Coordinate,
+    pub by: PieceColor
+}
+
+pub fn emit_capture_events(mut move_played_listener: EventReader<MovePlayed>, mut capture_writer: EventWriter<CaptureEvent>) {
+    for move_played in move_played_listener.read() {
+        let Some(captured) = move_played.record.captured else { continue };
+        capture_writer.send(CaptureEvent {piece: captured, square: captured.square, by: move_played.by});
+    }
+}
+
+#[derive(Resource)]
+pub struct PendingPromotion {
+    pub before: Board,
+    pub record: MoveRecord
+}
+
 #[derive(Event)]
-pub struct BoardUpdate {}
+pub struct NewGame {
+    pub position: Option<Board>
+}
+
+impl NewGame {
+    pub fn standard() -> Self {
+        NewGame {position: None}
+    }
+
+    pub fn from_position(board: Board) -> Self {
+        NewGame {position: Some(board)}
+    }
+}
+
+pub fn start_new_game(
+    mut new_game_listener: EventReader<NewGame>,
+    mut commands: Commands,
+    mut board: ResMut<BoardResource>,
+    mut promotion_square: ResMut<PromotionSquare>,
+    mut selection: ResMut<Selection>,
+    mut premove: ResMut<Premove>,
+    mut draw_offer: ResMut<DrawOffer>,
+    mut annotations: ResMut<Annotations>,
+    mut legal_targets: ResMut<LegalTargets>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut board_update_writer: EventWriter<BoardUpdate>,
+    mut overlays: Query<&mut Visibility, Or<(With<PromotionOption>, With<ShadowPiece>, With<PhantomPiece>)>>
+) {
+    let Some(new_game) = new_game_listener.read().last() else { return };
+    board.0 = new_game.position.clone().unwrap_or_else(Board::new);
+    selection.0 = None;
+    premove.0 = None;
+    draw_offer.0 = None;
+    annotations.arrows.clear();
+    legal_targets.clear();
+    promotion_square.0 = None;
+    commands.remove_resource::<PendingPromotion>();
+    for mut visibility in overlays.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+    next_phase.set(GamePhase::Playing);
+    board_update_writer.send(BoardUpdate::resync());
+}
+
+pub fn orient_piece_sprites(
+    orientation: Res<BoardOrientation>,
+    mut sprites: Query<&mut Transform, Or<(With<PieceComponent>, With<ShadowPiece>, With<PhantomPiece>)>>
+) {
+    let angle = if orientation.0 == PieceColor::WHITE { 0.0 } else { std::f32::consts::PI };
+    let rotation = Quat::from_rotation_z(angle);
+    for mut transform in sprites.iter_mut() {
+        if transform.rotation != rotation {
+            transform.rotation = rotation;
+        }
+    }
+}
+
+pub fn resync_board(mut board_update_writer: EventWriter<BoardUpdate>) {
+    board_update_writer.send(BoardUpdate::resync());
+}
+
+#[derive(Resource, Default)]
+pub struct PieceEntities(pub HashMap<Coordinate, Entity>);
+
+fn spawn_piece_sprite(commands: &mut Commands, piece_assets: &PieceAssets, config: &ChessConfig, piece: Piece) -> Entity {
+    let piece_component = PieceComponent{piece, dragged: false, drag_started: false, grab_offset: Vec2::ZERO};
+    let mut entity = commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(Vec2::new(config.square_size * 0.9, config.square_size * 0.9)),
+                ..default()
+            },
+            transform: Transform::from_translation(Vec3::from((config.square_to_vector(piece.square), 1.0))),
+            texture: piece_assets.get(piece.color, piece.kind),
+            ..default()
+        }, piece_component)
+    );
+    if let Some(atlas) = piece_assets.atlas(piece.color, piece.kind) {
+        entity.insert(atlas);
+    }
+    entity.id()
+}
+
+#[derive(Component)]
+pub struct FallbackGlyph;
+
+pub fn update_fallback_glyphs(
+    config: Res<ChessConfig>,
+    mut commands: Commands,
+    piece_assets: Res<PieceAssets>,
+    pieces_query: Query<(Entity, &PieceComponent, &Sprite, Option<&Children>), Changed<PieceComponent>>,
+    glyph_query: Query<(), With<FallbackGlyph>>
+) {
+    for (entity, piece_component, sprite, children) in pieces_query.iter() {
+        for child in children.into_iter().flatten() {
+            if glyph_query.contains(*child) {
+                commands.entity(*child).despawn_recursive();
+            }
+        }
+        let piece = piece_component.piece;
+        if !piece_assets.is_missing(piece.color, piece.kind) { continue };
+        let color = if piece.color == PieceColor::WHITE { Color::WHITE } else { Color::BLACK };
+        let size = sprite.custom_size.map_or(config.square_size, |size| size.x) * 0.7;
+        let glyph = commands.spawn((Text2dBundle {
+            text: Text::from_section(piece.kind.to_fen_char(PieceColor::WHITE).to_string(), TextStyle { font_size: size, color, ..default() }),
+            transform: Transform::from_xyz(0.0, 0.0, 0.1),
+            ..default()
+        }, FallbackGlyph)).id();
+        commands.entity(entity).add_child(glyph);
+    }
+}
 
 pub fn update_board_pieces(
+    config: Res<ChessConfig>,
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
+    piece_assets: Res<PieceAssets>,
     mut replace_event_listener: EventReader<BoardUpdate>,
-    mut pieces_query: Query<Entity, (With<PieceComponent>, Without<PromotionOption>)>,
+    mut pieces_query: Query<(Entity, &mut PieceComponent, &mut Transform, &mut Handle<Image>, Option<&mut TextureAtlas>), Without<PromotionOption>>,
+    mut piece_entities: ResMut<PieceEntities>,
+    tweening: Query<Entity, With<MoveTween>>,
+    captured_query: Query<Entity, With<Captured>>,
+    history_view: Res<HistoryView>,
     board: Res<BoardResource>
 ) {
-    for _ in replace_event_listener.read() {
-        for entity in pieces_query.iter() {
-            commands.entity(entity).despawn();
+    let changes: Vec<Option<BoardChange>> = replace_event_listener.read().map(|update| update.change).collect();
+    if changes.is_empty() { return };
+
+    if changes.iter().any(|change| change.is_none()) {
+        for (entity, _, _, _, _) in pieces_query.iter() {
+            commands.entity(entity).despawn_recursive();
         }
-        for (square, piece) in board.0.pieces.iter() {
-            let piece_component = PieceComponent{piece: piece.clone(), dragged: false};
-            commands.spawn((
-                SpriteBundle {
-                    sprite: Sprite {
-                        custom_size: Some(Vec2::new(SQUARE_SIZE * 0.9, SQUARE_SIZE * 0.9)),
-                        ..default()
-                    },
-                    transform: Transform::from_translation(Vec3::from((square_to_vector(square.clone()), 1.0))),
-                    texture: asset_server.load(piece_component.get_texture_name() + ".png"),
-                    ..default()
-                }, piece_component)
-            );
+        piece_entities.0 = history_view.displayed(&board.0).pieces.values()
+            .map(|piece| (piece.square, spawn_piece_sprite(&mut commands, &piece_assets, &config, *piece)))
+            .collect();
+        return;
+    }
+    if !history_view.is_live() { return };
+
+    for entity in tweening.iter() {
+        let Ok((_, piece_component, mut transform, _, _)) = pieces_query.get_mut(entity) else { continue };
+        transform.translation = Vec3::from((config.square_to_vector(piece_component.piece.square), 1.0));
+        commands.entity(entity).remove::<MoveTween>();
+    }
+    for entity in captured_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let entities = &mut piece_entities.0;
+    for change in changes.into_iter().flatten() {
+        match change {
+            BoardChange::Moved(record) => {
+                if let Some(captured) = record.captured {
+                    if let Some(entity) = entities.remove(&captured.square) {
+                        commands.entity(entity).insert(Captured(Timer::from_seconds(MOVE_DURATION, TimerMode::Once)));
+                    }
+                }
+                let mut relocations = vec![(record.from, record.to)];
+                relocations.extend(record.rook_move);
+                for (from, to) in relocations {
+                    let Some(entity) = entities.remove(&from) else { continue };
+                    let Ok((_, mut piece_component, mut transform, _, _)) = pieces_query.get_mut(entity) else { continue };
+                    piece_component.piece.square = to;
+                    piece_component.piece.moved = true;
+                    commands.entity(entity).insert(MoveTween::from(transform.translation.truncate()));
+                    entities.insert(to, entity);
+                }
+            }
+            BoardChange::Removed(square) => {
+                if let Some(entity) = entities.remove(&square) {
+                    commands.entity(entity).despawn_recursive();
+                }
+            }
+            BoardChange::Promoted(piece) => {
+                let existing = entities.get(&piece.square).and_then(|entity| pieces_query.get_mut(*entity).ok());
+                if let Some((_, mut piece_component, _, mut texture, atlas)) = existing {
+                    piece_component.piece = piece;
+                    piece_assets.apply(piece.color, piece.kind, &mut texture, atlas);
+                } else {
+                    let entity = spawn_piece_sprite(&mut commands, &piece_assets, &config, piece);
+                    entities.insert(piece.square, entity);
+                }
+            }
         }
-        break;
     }
+    debug_assert!(
+        entities.len() == board.0.pieces.len() && board.0.pieces.keys().all(|square| entities.contains_key(square)),
+        "piece sprite index out of sync with the board"
+    );
 }
 
-#[derive(Resource)]
-pub struct AllowDrag(pub bool);
+const MOVE_DURATION: f32 = 0.15;
+
+#[derive(Component)]
+pub struct MoveTween {
+    from: Vec2,
+    timer: Timer
+}
+
+impl From<Vec2> for MoveTween {
+    fn from(from: Vec2) -> Self {
+        MoveTween {from, timer: Timer::from_seconds(MOVE_DURATION, TimerMode::Once)}
+    }
+}
+
+#[derive(Component)]
+pub struct Captured(Timer);
+
+pub fn animate_piece_moves(
+    time: Res<Time>,
+    config: Res<ChessConfig>,
+    mut commands: Commands,
+    mut moving: Query<(Entity, &PieceComponent, &mut Transform, &mut MoveTween)>,
+    mut captured_query: Query<(Entity, &mut Captured)>
+) {
+    for (entity, piece_component, mut transform, mut tween) in moving.iter_mut() {
+        let destination = config.square_to_vector(piece_component.piece.square);
+        tween.timer.tick(time.delta());
+        if tween.timer.finished() {
+            transform.translation = Vec3::from((destination, 1.0));
+            commands.entity(entity).remove::<MoveTween>();
+            continue;
+        }
+        let eased = 1.0 - (1.0 - tween.timer.fraction()).powi(2);
+        transform.translation = Vec3::from((tween.from.lerp(destination, eased), 5.0));
+    }
+    for (entity, mut captured) in captured_query.iter_mut() {
+        if captured.0.tick(time.delta()).finished() {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+const CAPTURE_FADE_DURATION: f32 = 0.12;
+
+#[derive(Component)]
+pub struct CaptureFade(Timer);
+
+pub fn fade_captured_pieces(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    mut commands: Commands,
+    mut capture_listener: EventReader<CaptureEvent>,
+    captured_query: Query<(Entity, &PieceComponent), With<Captured>>,
+    mut fading: Query<(&mut Sprite, &mut CaptureFade)>
+) {
+    for capture in capture_listener.read() {
+        if settings.reduced_motion { continue };
+        let Some((entity, _)) = captured_query.iter().find(|(_, piece_component)| piece_component.piece.square == capture.square) else { continue };
+        commands.entity(entity).insert(CaptureFade(Timer::from_seconds(CAPTURE_FADE_DURATION, TimerMode::Once)));
+    }
+    for (mut sprite, mut fade) in fading.iter_mut() {
+        fade.0.tick(time.delta());
+        sprite.color.set_a(1.0 - fade.0.fraction());
+    }
+}
 
 #[derive(Component)]
 pub struct PromotionOption;
 
 #[derive(Resource)]
 pub struct PromotionSquare(pub Option<Coordinate>);
+#[derive(Event, Clone, Copy)]
+pub struct PromotionChosen(pub PieceKind);
+
 pub fn promotion_chooser(
+    promotion_square: Res<PromotionSquare>,
+    cursor_query: Option<Res<WorldCursor>>,
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    promotion_options: Query<(&Transform, &Visibility, &PieceComponent), With<PromotionOption>>,
+    mut promotion_chosen_writer: EventWriter<PromotionChosen>
+) {
+    let Some(square) = promotion_square.0 else { return };
+    let Some(cursor) = cursor_query else { return };
+    if cursor.square != Some(square) { return };
+    if !mouse_button.just_pressed(MouseButton::Left) { return };
+
+    let mut min_distance = f32::MAX;
+    let mut min_piece = None;
+    for (transform, visibility, sprite) in promotion_options.iter() {
+        if visibility == Visibility::Hidden { continue };
+        let distance = transform.translation.truncate().distance(cursor.position);
+        if distance < min_distance {
+            min_distance = distance;
+            min_piece = Some(sprite.piece);
+        }
+    }
+    let Some(piece) = min_piece else { return };
+    promotion_chosen_writer.send(PromotionChosen(piece.kind));
+}
+
+pub fn apply_promotion(
+    mut promotion_chosen_listener: EventReader<PromotionChosen>,
     mut board: ResMut<BoardResource>,
-    mut allow_drag: ResMut<AllowDrag>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
     mut promotion_square: ResMut<PromotionSquare>,
-    cursor_query: Option<Res<WorldCursor>>,
+    pending_promotion: Option<Res<PendingPromotion>>,
+    mut commands: Commands,
+    mut board_update_writer: EventWriter<BoardUpdate>,
+    mut move_played_writer: EventWriter<MovePlayed>,
+    mut promotion_options: Query<&mut Visibility, With<PromotionOption>>
+) {
+    let Some(PromotionChosen(kind)) = promotion_chosen_listener.read().next().copied() else { return };
+    let Some(pending) = pending_promotion else { return };
+    for mut visibility in promotion_options.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+    // detect_promotion lifted the pawn off the board while the options were shown
+    let pawn = Piece {square: pending.record.to, moved: true, ..pending.record.piece};
+    board.0.pieces.insert(pawn.square, pawn);
+    let Some(promoted) = board.0.promote(pawn.square, kind) else { return };
+    promotion_square.0 = None;
+    next_phase.set(GamePhase::Playing);
+    board_update_writer.send(BoardUpdate::changed(BoardChange::Promoted(promoted)));
+    let mut record = pending.record;
+    record.promotion = Some(promoted.kind);
+    let after = board.0.position_status();
+    let san = pending.before.san_with_status(record.as_move(), after);
+    move_played_writer.send(MovePlayed {record, san, after, by: promoted.color});
+    commands.remove_resource::<PendingPromotion>();
+}
+
+#[derive(Resource, Default)]
+pub struct PromotionCancelPrompt {
+    pub confirm: bool,
+    pub open: bool
+}
+
+#[derive(Event)]
+pub struct PromotionCancelConfirmed;
+
+pub fn cancel_promotion(
     mouse_button: Res<ButtonInput<MouseButton>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    cursor_query: Option<Res<WorldCursor>>,
+    mut prompt: ResMut<PromotionCancelPrompt>,
+    mut confirmed_listener: EventReader<PromotionCancelConfirmed>,
+    mut board: ResMut<BoardResource>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut promotion_square: ResMut<PromotionSquare>,
+    pending_promotion: Option<Res<PendingPromotion>>,
+    mut commands: Commands,
+    mut board_update_writer: EventWriter<BoardUpdate>,
+    mut promotion_options: Query<&mut Visibility, With<PromotionOption>>
+) {
+    let Some(pending) = pending_promotion else { return };
+    let clicked_outside = mouse_button.just_pressed(MouseButton::Left)
+        && cursor_query.is_some_and(|cursor| cursor.square != Some(pending.record.to));
+    let requested = clicked_outside || keyboard.just_pressed(KeyCode::Escape);
+    let confirmed = confirmed_listener.read().count() > 0;
+    if requested && prompt.confirm && !prompt.open {
+        prompt.open = true;
+        return;
+    }
+    if !confirmed && (!requested || prompt.confirm) { return };
+    prompt.open = false;
+    for mut visibility in promotion_options.iter_mut() {
+        *visibility = Visibility::Hidden;
+    }
+    board.0 = pending.before.clone();
+    promotion_square.0 = None;
+    next_phase.set(GamePhase::Playing);
+    board_update_writer.send(BoardUpdate::resync());
+    commands.remove_resource::<PendingPromotion>();
+}
+
+pub fn detect_promotion(
+    config: Res<ChessConfig>,
+    pending_promotion: Option<Res<PendingPromotion>>,
+    mut board: ResMut<BoardResource>,
+    mut next_phase: ResMut<NextState<GamePhase>>,
+    mut promotion_square: ResMut<PromotionSquare>,
     mut board_update_writer: EventWriter<BoardUpdate>,
     mut promotion_options: Query<(&mut Transform, &mut Visibility, &PieceComponent), With<PromotionOption>>
 ) {
-    if let Some(square) = promotion_square.0 {
-        let Some(cursor) = cursor_query else { return };
-        if cursor.square != square { return };
-        if !mouse_button.just_pressed(MouseButton::Left) { return };
-
-        let mut min_distance = f32::MAX;
-        let mut min_piece = None;
-        for (transform, visibility, sprite) in promotion_options.iter() {
-            if visibility == Visibility::Hidden { continue };
-            let distance = transform.translation.truncate().distance(cursor.position);
-            if distance < min_distance {
-                min_distance = distance;
-                min_piece = Some(sprite.piece);
-            }
-        }
-        for (_, mut visibility, _) in promotion_options.iter_mut() {
-            *visibility = Visibility::Hidden;
-        }
-        board.0.pieces.insert(square, Piece{kind: min_piece.unwrap().kind, color: min_piece.unwrap().color, square, moved: false});
-        allow_drag.0 = true;
-        promotion_square.0 = None;
-        board_update_writer.send(BoardUpdate{});
-
-    } else {
-        for rank in [7i8, 0] {
-            for file in 0i8..=7 {
-                let position = Coordinate(file, rank);
-                let Some(occupying) = board.0.pieces.get(&position) else { continue };
-                if occupying.kind == PieceKind::PAWN {
-                    allow_drag.0 = false;
-                    promotion_square.0 = Some(position);
-
-                    for (mut transform, mut visibility, sprite) in promotion_options.iter_mut() {
-                        if sprite.piece.color != occupying.color { continue };
-                        transform.translation = Vec3::from((square_to_vector(position), 21.37));
-                        match sprite.piece.kind {
-                            PieceKind::QUEEN => {
-                                transform.translation.x -= SQUARE_SIZE / 4.0;
-                                transform.translation.y += SQUARE_SIZE / 4.0;
-                            }
-                            PieceKind::ROOK => {
-                                transform.translation.x += SQUARE_SIZE / 4.0;
-                                transform.translation.y += SQUARE_SIZE / 4.0;
-                            }
-                            PieceKind::BISHOP => {
-                                transform.translation.x -= SQUARE_SIZE / 4.0;
-                                transform.translation.y -= SQUARE_SIZE / 4.0;
-                            }
-                            PieceKind::KNIGHT => {
-                                transform.translation.x += SQUARE_SIZE / 4.0;
-                                transform.translation.y -= SQUARE_SIZE / 4.0;
-                            }
-                            _ => {}
-                        }
-                        *visibility = Visibility::Visible;
-                    }
+    let Some(pending) = pending_promotion else { return };
+    if promotion_square.0.is_some() { return };
+    let occupying = Piece {square: pending.record.to, ..pending.record.piece};
+    promotion_square.0 = Some(occupying.square);
+    next_phase.set(GamePhase::PromotionPending);
 
-                    board.0.pieces.remove(&position);
-                    board_update_writer.send(BoardUpdate{});
-                    break;
-                }
+    for (mut transform, mut visibility, sprite) in promotion_options.iter_mut() {
+        if sprite.piece.color != occupying.color { continue };
+        transform.translation = Vec3::from((config.square_to_vector(occupying.square), 21.37));
+        match sprite.piece.kind {
+            PieceKind::QUEEN => {
+                transform.translation.x -= config.square_size / 4.0;
+                transform.translation.y += config.square_size / 4.0;
+            }
+            PieceKind::ROOK => {
+                transform.translation.x += config.square_size / 4.0;
+                transform.translation.y += config.square_size / 4.0;
             }
+            PieceKind::BISHOP => {
+                transform.translation.x -= config.square_size / 4.0;
+                transform.translation.y -= config.square_size / 4.0;
+            }
+            PieceKind::KNIGHT => {
+                transform.translation.x += config.square_size / 4.0;
+                transform.translation.y -= config.square_size / 4.0;
+            }
+            _ => {}
         }
+        *visibility = Visibility::Visible;
     }
+
+    board.0.pieces.remove(&occupying.square);
+    board_update_writer.send(BoardUpdate::changed(BoardChange::Removed(occupying.square)));
 }
 #[derive(Resource)]
 pub struct CheckAnimationTimer(pub Timer);
+#[derive(Component)]
+pub struct CheckBlink {
+    dimmed: bool,
+    mate: bool
+}
+
 pub fn check_animation(
     time: Res<Time>,
     mut animation_timer: ResMut<CheckAnimationTimer>,
-    board: Res<BoardResource>,
-    mut sprite_pieces: Query<(&mut Sprite, &PieceComponent), (Without<ShadowPiece>, Without<PhantomPiece>, Without<PromotionOption>)>,
+    mut commands: Commands,
+    mut board_update_listener: EventReader<BoardUpdate>,
+    mut check_listener: EventReader<CheckEvent>,
+    mut game_over_listener: EventReader<GameOverEvent>,
+    piece_entities: Res<PieceEntities>,
+    mut sprite_pieces: Query<(Entity, &PieceComponent, &mut Sprite, Option<&mut CheckBlink>), (Without<ShadowPiece>, Without<PhantomPiece>, Without<PromotionOption>)>,
 ) {
-    for (mut sprite, piece_component) in sprite_pieces.iter_mut() {
-        if piece_component.piece.kind != PieceKind::KING { continue };
-        if !board.0.is_checked(&piece_component.piece) { continue };
-        if !board.0.has_moves(piece_component.piece.color) {
+    let mate = game_over_listener.read().any(|game_over| matches!(game_over.result, GameStatus::Checkmate { .. }));
+    let updated = board_update_listener.read().count() > 0;
+    if updated {
+        for (entity, piece_component, mut sprite, blink) in sprite_pieces.iter_mut() {
+            if piece_component.piece.kind != PieceKind::KING { continue };
+            sprite.color.set_a(1.0);
+            if blink.is_some() {
+                commands.entity(entity).remove::<CheckBlink>();
+            }
+        }
+        animation_timer.0.reset();
+    }
+    for check in check_listener.read() {
+        let Some(entity) = piece_entities.0.get(&check.king_square) else { continue };
+        let Ok((_, _, mut sprite, _)) = sprite_pieces.get_mut(*entity) else { continue };
+        sprite.color.set_a(if mate { 0.5 } else { 1.0 });
+        commands.entity(*entity).insert(CheckBlink {dimmed: false, mate});
+    }
+    // Blinks being replaced this frame are only removed once commands apply, so leave them alone
+    if updated { return };
+
+    animation_timer.0.tick(time.delta());
+    for (_, _, mut sprite, blink) in sprite_pieces.iter_mut() {
+        let Some(mut blink) = blink else { continue };
+        blink.mate |= mate;
+        if blink.mate {
             sprite.color.set_a(0.5);
-            return;
+            continue;
         }
-        animation_timer.0.tick(time.delta());
         if animation_timer.0.just_finished() {
-            if sprite.color.a() == 1.0 {
-                sprite.color.set_a(0.75);
-            } else {
-                sprite.color.set_a(1.0);
-            }
+            blink.dimmed = !blink.dimmed;
+            sprite.color.set_a(if blink.dimmed { 0.75 } else { 1.0 });
+        }
+    }
+}
+#[derive(Event, Clone, Copy)]
+pub struct MoveRequested {
+    pub from: Coordinate,
+    pub to: Coordinate,
+    pub promotion: Option<PieceKind>
+}
+
+impl MoveRequested {
+    pub fn new(from: Coordinate, to: Coordinate) -> Self {
+        MoveRequested {from, to, promotion: None}
+    }
+}
+
+pub fn make_move(
+    config: Res<ChessConfig>,
+    mut move_request_listener: EventReader<MoveRequested>,
+    mut board: ResMut<BoardResource>,
+    mut selection: ResMut<Selection>,
+    mut commands: Commands,
+    mut board_update_writer: EventWriter<BoardUpdate>,
+    mut move_played_writer: EventWriter<MovePlayed>
+) {
+    for request in move_request_listener.read() {
+        let Some(piece) = board.0.pieces.get(&request.from) else { continue };
+        if piece.color != board.0.on_move || !board.0.get_valid_moves(piece).contains(&request.to) { continue };
+        selection.0 = None;
+        let before = board.0.clone();
+        let mut record = board.0.move_piece(&request.from, &request.to);
+        board.0.flip_on_move();
+        board_update_writer.send(BoardUpdate::changed(BoardChange::Moved(record)));
+        let promotes = record.piece.kind == PieceKind::PAWN && (record.to.1 == 0 || record.to.1 == 7);
+        let promotion = request.promotion.or(if config.auto_queen { Some(PieceKind::QUEEN) } else { None });
+        if promotes && promotion.is_none() {
+            commands.insert_resource(PendingPromotion {before, record});
+            break;
+        }
+        if let Some(kind) = promotion.filter(|_| promotes) {
+            let Some(promoted) = board.0.promote(record.to, kind) else { continue };
+            record.promotion = Some(kind);
+            board_update_writer.send(BoardUpdate::changed(BoardChange::Promoted(promoted)));
+        }
+        let after = board.0.position_status();
+        let san = before.san_with_status(record.as_move(), after);
+        move_played_writer.send(MovePlayed {record, san, after, by: record.piece.color});
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Premove(pub Option<(Coordinate, Coordinate)>);
+
+pub fn play_premove(
+    mut board_update_listener: EventReader<BoardUpdate>,
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    board: Res<BoardResource>,
+    local_side: Res<LocalSide>,
+    mut premove: ResMut<Premove>,
+    mut move_request_writer: EventWriter<MoveRequested>
+) {
+    if mouse_button.just_pressed(MouseButton::Right) {
+        premove.0 = None;
+    }
+    if board_update_listener.read().count() == 0 { return };
+    if local_side.0 != Some(board.0.on_move) { return };
+    let Some((from, to)) = premove.0.take() else { return };
+    let Some(piece) = board.0.pieces.get(&from).filter(|piece| piece.color == board.0.on_move) else { return };
+    if board.0.get_valid_moves(piece).contains(&to) {
+        move_request_writer.send(MoveRequested {from, to, promotion: Some(PieceKind::QUEEN)});
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Selection(pub Option<Coordinate>);
+
+pub fn select_piece(
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    cursor: Res<WorldCursor>,
+    board: Res<BoardResource>,
+    mut selection: ResMut<Selection>,
+    mut move_request_writer: EventWriter<MoveRequested>
+) {
+    if !mouse_button.just_pressed(MouseButton::Left) { return };
+    let Some(from) = selection.0 else { return };
+    selection.0 = None;
+    let Some(to) = cursor.square else { return };
+    let Some(piece) = board.0.pieces.get(&from) else { return };
+    if board.0.get_valid_moves(piece).contains(&to) {
+        move_request_writer.send(MoveRequested::new(from, to));
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct LegalTargets {
+    pub from: Option<Coordinate>,
+    pub targets: Vec<Coordinate>
+}
+
+impl LegalTargets {
+    fn show(&mut self, board: &Board, from: Coordinate) {
+        self.from = Some(from);
+        self.targets = board.pieces.get(&from).map(|piece| board.get_valid_moves(piece)).unwrap_or_default();
+    }
+
+    fn clear(&mut self) {
+        if self.from.is_some() {
+            self.from = None;
+            self.targets.clear();
+        }
+    }
+}
+
+pub fn update_selection_targets(
+    board: Res<BoardResource>,
+    selection: Res<Selection>,
+    pieces: Query<&PieceComponent>,
+    mut legal_targets: ResMut<LegalTargets>
+) {
+    match selection.0 {
+        Some(square) => legal_targets.show(&board.0, square),
+        None if pieces.iter().any(|piece| piece.dragged) => {}
+        None => legal_targets.clear()
+    }
+}
+
+#[derive(Component)]
+pub struct MoveMarker;
+
+pub fn update_move_markers(
+    mut commands: Commands,
+    config: Res<ChessConfig>,
+    settings: Res<Settings>,
+    palette: Res<HighlightPalette>,
+    board: Res<BoardResource>,
+    legal_targets: Res<LegalTargets>,
+    markers: Query<Entity, With<MoveMarker>>
+) {
+    for entity in markers.iter() {
+        commands.entity(entity).despawn();
+    }
+    if !settings.show_legal_moves { return };
+    for target in legal_targets.targets.iter().filter(|target| !board.0.pieces.contains_key(target)) {
+        let marker = spawn_marker_dot(&mut commands, &config, *target, palette.legal_move());
+        commands.entity(marker).insert(MoveMarker);
+    }
+}
+
+fn spawn_marker_dot(commands: &mut Commands, config: &ChessConfig, square: Coordinate, color: Color) -> Entity {
+    commands.spawn(SpriteBundle {
+        sprite: Sprite {
+            color,
+            custom_size: Some(Vec2::splat(config.square_size / 4.0)),
+            ..default()
+        },
+        transform: Transform::from_translation(Vec3::from((config.square_to_vector(square), 0.7))),
+        ..default()
+    }).id()
+}
+
+const OPPONENT_PREVIEW_DELAY: f32 = 0.4;
+
+#[derive(Component)]
+pub struct OpponentPreviewMarker;
+
+#[derive(Default)]
+pub struct OpponentPreview {
+    square: Option<Coordinate>,
+    hovered_for: f32,
+    shown: bool
+}
+
+pub fn preview_opponent_moves(
+    time: Res<Time>,
+    mut commands: Commands,
+    config: Res<ChessConfig>,
+    settings: Res<Settings>,
+    palette: Res<HighlightPalette>,
+    board: Res<BoardResource>,
+    local_side: Res<LocalSide>,
+    phase: Res<State<GamePhase>>,
+    cursor: Option<Res<WorldCursor>>,
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    pieces: Query<&PieceComponent>,
+    markers: Query<Entity, With<OpponentPreviewMarker>>,
+    mut preview: Local<OpponentPreview>
+) {
+    let idle = settings.preview_opponent_moves
+        && *phase.get() == GamePhase::Playing
+        && local_side.controls(board.0.on_move)
+        && !mouse_button.pressed(MouseButton::Left)
+        && !pieces.iter().any(|piece| piece.dragged);
+    let hovered = cursor.and_then(|cursor| cursor.square)
+        .filter(|square| idle && board.0.pieces.get(square).is_some_and(|piece| piece.color != board.0.on_move));
+    if hovered != preview.square || board.is_changed() {
+        for entity in markers.iter() {
+            commands.entity(entity).despawn();
         }
+        *preview = OpponentPreview {square: hovered, ..default()};
         return;
     }
-    animation_timer.0.reset();
+    let Some(square) = preview.square.filter(|_| !preview.shown) else { return };
+    preview.hovered_for += time.delta_seconds();
+    if preview.hovered_for < OPPONENT_PREVIEW_DELAY { return };
+    preview.shown = true;
+    let Some(piece) = board.0.pieces.get(&square) else { return };
+    for target in board.0.get_valid_moves(piece) {
+        let marker = spawn_marker_dot(&mut commands, &config, target, palette.opponent_move());
+        commands.entity(marker).insert(OpponentPreviewMarker);
+    }
+}
+
+pub fn draw_capture_rings(
+    mut gizmos: Gizmos,
+    config: Res<ChessConfig>,
+    settings: Res<Settings>,
+    palette: Res<HighlightPalette>,
+    board: Res<BoardResource>,
+    legal_targets: Res<LegalTargets>
+) {
+    if !settings.show_legal_moves { return };
+    for target in legal_targets.targets.iter().filter(|target| board.0.pieces.contains_key(target)) {
+        gizmos.circle_2d(config.square_to_vector(*target), config.square_size * 0.45, palette.capture());
+    }
 }
+
 pub fn drag_piece(
+    config: Res<ChessConfig>,
     mouse_button: Res<ButtonInput<MouseButton>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
     cursor_query: Option<Res<WorldCursor>>,
-    allow_drag: Res<AllowDrag>,
-    mut shadow_query: Query<(&mut Visibility, &mut Transform, &mut Handle<Image>), With<ShadowPiece>>,
-    mut phantom_query: Query<(&mut Visibility, &mut Transform, &mut Handle<Image>), (With<PhantomPiece>, Without<ShadowPiece>)>,
-    mut sprite_pieces: Query<(&mut PieceComponent, &mut Transform, &Handle<Image>), (Without<ShadowPiece>, Without<PhantomPiece>, Without<PromotionOption>)>,
-    mut board: ResMut<BoardResource>,
-    mut board_update_writer: EventWriter<BoardUpdate>
+    mut shadow_query: Query<(&mut Visibility, &mut Transform, &mut Handle<Image>, Option<&mut TextureAtlas>), With<ShadowPiece>>,
+    mut phantom_query: Query<(&mut Visibility, &mut Transform, &mut Handle<Image>, Option<&mut TextureAtlas>), (With<PhantomPiece>, Without<ShadowPiece>)>,
+    mut sprite_pieces: Query<(Entity, &mut PieceComponent, &mut Transform, Has<SnapBack>, Has<MoveTween>), (Without<ShadowPiece>, Without<PhantomPiece>, Without<PromotionOption>, Without<Captured>)>,
+    piece_entities: Res<PieceEntities>,
+    mut commands: Commands,
+    piece_assets: Res<PieceAssets>,
+    board: Res<BoardResource>,
+    mut selection: ResMut<Selection>,
+    local_side: Res<LocalSide>,
+    mut premove: ResMut<Premove>,
+    mut legal_targets: ResMut<LegalTargets>,
+    mut move_request_writer: EventWriter<MoveRequested>
 ) {
-    if (!allow_drag.0) { return };
     let Some(cursor) = cursor_query else { return };
 
-    let (mut shadow_visibility, mut shadow_transform, mut shadow_texture) = shadow_query.single_mut();
-    let (mut phantom_visibility, mut phantom_transform, mut phantom_texture) = phantom_query.single_mut();
-
-    for (mut sprite, mut transform, texture) in sprite_pieces.iter_mut() {
-        if sprite.piece.color != board.0.on_move { continue };
-        if sprite.piece.square == cursor.square && mouse_button.just_pressed(MouseButton::Left) {
-            sprite.dragged = true;
-            *shadow_texture = texture.clone();
-            *phantom_texture = texture.clone();
-            phantom_transform.translation = Vec3::from((square_to_vector(cursor.square), 1.0));
-            *phantom_visibility = Visibility::Visible;
+    let Ok((mut shadow_visibility, mut shadow_transform, mut shadow_texture, shadow_atlas)) = shadow_query.get_single_mut() else { return };
+    let Ok((mut phantom_visibility, mut phantom_transform, mut phantom_texture, phantom_atlas)) = phantom_query.get_single_mut() else { return };
+
+    if mouse_button.just_pressed(MouseButton::Left) {
+        let grabbed = cursor.square
+            .and_then(|square| piece_entities.0.get(&square))
+            .and_then(|entity| sprite_pieces.get_mut(*entity).ok());
+        if let Some((_, mut sprite, _, false, false)) = grabbed {
+            let own_turn = sprite.piece.color == board.0.on_move && local_side.controls(sprite.piece.color);
+            let can_premove = sprite.piece.color != board.0.on_move && local_side.0 == Some(sprite.piece.color) && premove.0.is_none();
+            if own_turn || can_premove {
+                sprite.dragged = true;
+                sprite.drag_started = false;
+                sprite.grab_offset = config.square_to_vector(sprite.piece.square) - cursor.position;
+                piece_assets.apply(sprite.piece.color, sprite.piece.kind, &mut shadow_texture, shadow_atlas);
+                piece_assets.apply(sprite.piece.color, sprite.piece.kind, &mut phantom_texture, phantom_atlas);
+                phantom_transform.translation = Vec3::from((config.square_to_vector(sprite.piece.square), 1.0));
+                if own_turn {
+                    legal_targets.show(&board.0, sprite.piece.square);
+                }
+            }
         }
+    }
+
+    for (entity, mut sprite, mut transform, _, _) in sprite_pieces.iter_mut() {
         if !sprite.dragged { continue };
-        let can_move = board.0.get_valid_moves(board.0.pieces.get(&sprite.piece.square).unwrap()).contains(&cursor.square);
+        let premoving = sprite.piece.color != board.0.on_move;
+        let target = match premoving {
+            true => cursor.square.filter(|square| *square != sprite.piece.square),
+            false => cursor.square.filter(|square| legal_targets.targets.contains(square))
+        };
 
+        if mouse_button.just_pressed(MouseButton::Right) || keyboard.just_pressed(KeyCode::Escape) {
+            sprite.dragged = false;
+            *shadow_visibility = Visibility::Hidden;
+            *phantom_visibility = Visibility::Hidden;
+            commands.entity(entity).insert(SnapBack::from(transform.translation.truncate()));
+            legal_targets.clear();
+            return;
+        }
+        if !sprite.drag_started {
+            let press_position = config.square_to_vector(sprite.piece.square) - sprite.grab_offset;
+            sprite.drag_started = cursor.position.distance(press_position) > config.drag_threshold * config.square_size;
+        }
+        if mouse_button.just_released(MouseButton::Left) && !sprite.drag_started {
+            sprite.dragged = false;
+            legal_targets.clear();
+            if !premoving {
+                selection.0 = Some(sprite.piece.square);
+            }
+            return;
+        }
+        if !sprite.drag_started { return };
         if mouse_button.just_released(MouseButton::Left) {
             sprite.dragged = false;
+            legal_targets.clear();
             *shadow_visibility = Visibility::Hidden;
             *phantom_visibility = Visibility::Hidden;
-            if can_move {
-                board.0.move_piece(&sprite.piece.square, &cursor.square);
-                board.0.flip_on_move();
-                board_update_writer.send(BoardUpdate{});
+            // The sprite keeps a copy of its piece, so make sure the board still agrees before acting on it
+            let current = board.0.pieces.get(&sprite.piece.square).is_some_and(|piece| piece.color == sprite.piece.color && piece.kind == sprite.piece.kind);
+            if !current {
+                commands.entity(entity).insert(SnapBack::from(transform.translation.truncate()));
+                return;
             }
-            transform.translation = Vec3::from((square_to_vector(sprite.piece.square), 1.0));
-
+            if let Some(to) = target.filter(|_| !premoving) {
+                move_request_writer.send(MoveRequested::new(sprite.piece.square, to));
+                return;
+            } else if let Some(to) = target.filter(|_| premoving) {
+                premove.0 = Some((sprite.piece.square, to));
+            }
+            commands.entity(entity).insert(SnapBack::from(transform.translation.truncate()));
             return;
         }
-        transform.translation = Vec3::from((cursor.position, 10.0));
-        shadow_transform.translation = Vec3::from((cursor.square_center, 2.0));
-        *shadow_visibility = if can_move { Visibility::Visible } else { Visibility::Hidden };
+        transform.translation = Vec3::from((cursor.position + sprite.grab_offset, 10.0));
+        *phantom_visibility = if cursor.square.is_some() { Visibility::Visible } else { Visibility::Hidden };
+        match target.and(cursor.square_center) {
+            Some(center) => {
+                shadow_transform.translation = Vec3::from((center, 2.0));
+                *shadow_visibility = Visibility::Visible;
+            }
+            None => *shadow_visibility = Visibility::Hidden
+        }
         return;
     }
 
 }
-pub fn spawn_phantom_piece(mut commands: Commands) {
-    commands.spawn((
+
+// A release outside the window never reaches drag_piece, so a drag has to end when the cursor
+// leaves, the window loses focus, or the button turns out to be up without a release being seen
+pub fn drag_interrupted(cursor: Option<Res<WorldCursor>>, mouse_button: Res<ButtonInput<MouseButton>>, mut focus_listener: EventReader<WindowFocused>) -> bool {
+    let focus_lost = focus_listener.read().any(|focus| !focus.focused);
+    focus_lost || cursor.is_none() || !(mouse_button.pressed(MouseButton::Left) || mouse_button.just_released(MouseButton::Left))
+}
+
+pub fn cancel_drags(
+    config: Res<ChessConfig>,
+    mut pieces: Query<(&mut PieceComponent, &mut Transform)>,
+    mut drag_sprites: Query<&mut Visibility, Or<(With<ShadowPiece>, With<PhantomPiece>)>>,
+    mut legal_targets: ResMut<LegalTargets>
+) {
+    for (mut sprite, mut transform) in pieces.iter_mut().filter(|(sprite, _)| sprite.dragged) {
+        sprite.dragged = false;
+        transform.translation = Vec3::from((config.square_to_vector(sprite.piece.square), 1.0));
+        legal_targets.clear();
+        for mut visibility in drag_sprites.iter_mut() {
+            *visibility = Visibility::Hidden;
+        }
+    }
+}
+
+pub fn update_cursor_icon(
+    cursor: Option<Res<WorldCursor>>,
+    board: Res<BoardResource>,
+    local_side: Res<LocalSide>,
+    promotion_square: Res<PromotionSquare>,
+    phase: Res<State<GamePhase>>,
+    menu_state: Res<State<MenuState>>,
+    pieces: Query<&PieceComponent>,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>
+) {
+    let Ok(mut window) = window_query.get_single_mut() else { return };
+    let square = cursor.and_then(|cursor| cursor.square);
+    let hovered_own_piece = square
+        .and_then(|square| board.0.pieces.get(&square))
+        .is_some_and(|piece| piece.color == board.0.on_move && local_side.controls(piece.color));
+    let icon = match (menu_state.get(), phase.get()) {
+        (MenuState::Open, _) => CursorIcon::Default,
+        _ if pieces.iter().any(|piece| piece.dragged) => CursorIcon::Grabbing,
+        (_, GamePhase::PromotionPending) if square.is_some() && square == promotion_square.0 => CursorIcon::Pointer,
+        (_, GamePhase::Playing) if hovered_own_piece => CursorIcon::Grab,
+        _ => CursorIcon::Default
+    };
+    if window.cursor.icon != icon {
+        window.cursor.icon = icon;
+    }
+}
+
+const SNAP_BACK_DURATION: f32 = 0.12;
+
+#[derive(Component)]
+pub struct SnapBack {
+    from: Vec2,
+    timer: Timer
+}
+
+impl From<Vec2> for SnapBack {
+    fn from(from: Vec2) -> Self {
+        SnapBack {from, timer: Timer::from_seconds(SNAP_BACK_DURATION, TimerMode::Once)}
+    }
+}
+
+pub fn animate_snap_back(
+    time: Res<Time>,
+    config: Res<ChessConfig>,
+    mut commands: Commands,
+    mut board_update_listener: EventReader<BoardUpdate>,
+    mut snapping: Query<(Entity, &PieceComponent, &mut Transform, &mut SnapBack)>
+) {
+    let interrupted = board_update_listener.read().count() > 0;
+    for (entity, piece_component, mut transform, mut snap_back) in snapping.iter_mut() {
+        let home = config.square_to_vector(piece_component.piece.square);
+        snap_back.timer.tick(time.delta());
+        if interrupted || snap_back.timer.finished() {
+            transform.translation = Vec3::from((home, 1.0));
+            commands.entity(entity).remove::<SnapBack>();
+            continue;
+        }
+        let eased = 1.0 - (1.0 - snap_back.timer.fraction()).powi(3);
+        transform.translation = Vec3::from((snap_back.from.lerp(home, eased), 10.0));
+    }
+}
+
+const SHAKE_DURATION: f32 = 0.45;
+
+#[derive(Component)]
+pub struct Shake {
+    from: Vec2,
+    timer: Timer
+}
+
+impl From<Vec2> for Shake {
+    fn from(from: Vec2) -> Self {
+        Shake {from, timer: Timer::from_seconds(SHAKE_DURATION, TimerMode::Once)}
+    }
+}
+
+pub fn animate_shakes(
+    time: Res<Time>,
+    config: Res<ChessConfig>,
+    settings: Res<Settings>,
+    mut commands: Commands,
+    mut shaking: Query<(Entity, &PieceComponent, &mut Transform, &mut Shake), Without<MoveTween>>
+) {
+    for (entity, piece_component, mut transform, mut shake) in shaking.iter_mut() {
+        let home = config.square_to_vector(piece_component.piece.square);
+        shake.timer.tick(time.delta());
+        if shake.timer.finished() {
+            transform.translation = Vec3::from((home, 1.0));
+            commands.entity(entity).remove::<Shake>();
+            continue;
+        }
+        let fraction = shake.timer.fraction();
+        let eased = 1.0 - (1.0 - (fraction / 0.3).min(1.0)).powi(3);
+        let wobble = match settings.reduced_motion {
+            true => 0.0,
+            false => (fraction * std::f32::consts::TAU * 4.0).sin() * (1.0 - fraction) * config.square_size * 0.1
+        };
+        transform.translation = Vec3::from((shake.from.lerp(home, eased) + Vec2::new(wobble, 0.0), 10.0));
+    }
+}
+
+pub fn spawn_phantom_piece(mut commands: Commands, piece_assets: Res<PieceAssets>, config: Res<ChessConfig>) {
+    let atlas = piece_assets.atlas(PieceColor::WHITE, PieceKind::PAWN);
+    let mut shadow = commands.spawn((
         SpriteBundle {
             sprite: Sprite {
-                custom_size: Some(Vec2::new(SQUARE_SIZE * 0.9, SQUARE_SIZE * 0.9)),
+                custom_size: Some(Vec2::new(config.square_size * 0.9, config.square_size * 0.9)),
                 color: Rgba {red: 1.0, green: 1.0, blue: 1.0, alpha: 0.5},
                 ..default()
             },
@@ -225,10 +1014,11 @@ pub fn spawn_phantom_piece(mut commands: Commands) {
             ..default()
         }, ShadowPiece{})
     );
-    commands.spawn((
+    if let Some(atlas) = atlas.clone() { shadow.insert(atlas); }
+    let mut phantom = commands.spawn((
         SpriteBundle {
             sprite: Sprite {
-                custom_size: Some(Vec2::new(SQUARE_SIZE * 0.9, SQUARE_SIZE * 0.9)),
+                custom_size: Some(Vec2::new(config.square_size * 0.9, config.square_size * 0.9)),
                 color: Rgba {red: 1.0, green: 1.0, blue: 1.0, alpha: 0.5},
                 ..default()
             },
@@ -236,24 +1026,51 @@ pub fn spawn_phantom_piece(mut commands: Commands) {
             ..default()
         }, PhantomPiece{})
     );
+    if let Some(atlas) = atlas { phantom.insert(atlas); }
 }
 
-pub fn spawn_promotion_options(mut commands: Commands, asset_server: Res<AssetServer>) {
+pub fn refresh_piece_sprites(
+    mut commands: Commands,
+    piece_assets: Res<PieceAssets>,
+    mut pieces_query: Query<(Entity, &mut PieceComponent, &mut Handle<Image>)>,
+    mut overlay_query: Query<(Entity, &mut Handle<Image>), (Or<(With<ShadowPiece>, With<PhantomPiece>)>, Without<PieceComponent>)>
+) {
+    let dragged = pieces_query.iter().find(|(_, component, _)| component.dragged).map(|(_, component, _)| component.piece);
+    let sprites = pieces_query.iter_mut().map(|(entity, mut component, texture)| {
+        component.set_changed();
+        (entity, component.piece, texture)
+    });
+    let overlays = overlay_query.iter_mut()
+        .map(|(entity, texture)| (entity, dragged.unwrap_or(Piece {kind: PieceKind::PAWN, color: PieceColor::WHITE, square: Coordinate(0, 0), moved: false}), texture));
+    for (entity, piece, mut texture) in sprites.chain(overlays) {
+        *texture = piece_assets.get(piece.color, piece.kind);
+        match piece_assets.atlas(piece.color, piece.kind) {
+            Some(atlas) => { commands.entity(entity).insert(atlas); }
+            None => { commands.entity(entity).remove::<TextureAtlas>(); }
+        }
+    }
+}
+
+pub fn spawn_promotion_options(mut commands: Commands, piece_assets: Res<PieceAssets>, config: Res<ChessConfig>) {
     for color in [PieceColor::WHITE, PieceColor::BLACK] {
-        for piece_kind in [PieceKind::QUEEN, PieceKind::ROOK, PieceKind::BISHOP, PieceKind::KNIGHT] {
-            let piece = PieceComponent { piece: Piece { kind: piece_kind, color, square: Coordinate(5, 5), moved: false }, dragged: false };
-            commands.spawn((
+        for piece_kind in PieceKind::PROMOTION_TARGETS {
+            let piece = PieceComponent { piece: Piece { kind: piece_kind, color, square: Coordinate(5, 5), moved: false }, dragged: false, drag_started: false, grab_offset: Vec2::ZERO };
+            let mut option = commands.spawn((
                 SpriteBundle {
                     sprite: Sprite {
-                        custom_size: Some(Vec2::new(SQUARE_SIZE * 0.9 * 0.5, SQUARE_SIZE * 0.9 * 0.5)),
+                        custom_size: Some(Vec2::new(config.square_size * 0.9 * 0.5, config.square_size * 0.9 * 0.5)),
                         color: Rgba { red: 1.0, green: 1.0, blue: 1.0, alpha: 1.0 },
                         ..default()
                     },
                     visibility: Visibility::Hidden,
-                    texture: asset_server.load(piece.get_texture_name() + ".png"),
+                    texture: piece_assets.get(color, piece_kind),
                     ..default()
                 }, PromotionOption {}, piece)
             );
+            if let Some(atlas) = piece_assets.atlas(color, piece_kind) { option.insert(atlas); }
         }
     }
-}
\ No newline at end of file
+}
+pub fn close_promotion_cancel_prompt(mut prompt: ResMut<PromotionCancelPrompt>) {
+    prompt.open = false;
+}
@@ -0,0 +1,166 @@
+use bevy::prelude::*;
+use chess_logic::logic::PieceColor;
+use crate::board::{LocalSide, side_name};
+use crate::shortcuts::TextInputFocused;
+
+const MAX_NAME_CHARS: usize = 20;
+pub const ENGINE_NAME: &str = "cheess engine";
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct PlayerInfo {
+    pub name: String,
+    pub rating: Option<u32>
+}
+
+impl PlayerInfo {
+    pub fn parse(text: &str) -> Self {
+        let text = text.trim();
+        let rating = text.strip_suffix(')')
+            .and_then(|rest| rest.rsplit_once('('))
+            .and_then(|(name, rating)| Some((name.trim(), rating.trim().parse::<u32>().ok()?)));
+        match rating {
+            Some((name, rating)) => PlayerInfo {name: name.to_string(), rating: Some(rating)},
+            None => PlayerInfo {name: text.to_string(), rating: None}
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self.rating {
+            Some(rating) => format!("{} ({})", self.name, rating),
+            None => self.name.clone()
+        }
+    }
+
+    pub fn short_label(&self) -> String {
+        let label = self.label();
+        if label.chars().count() <= MAX_NAME_CHARS { return label };
+        let mut truncated: String = label.chars().take(MAX_NAME_CHARS - 1).collect();
+        truncated.push('…');
+        truncated
+    }
+}
+
+#[derive(Resource, Clone)]
+pub struct GameMetadata {
+    pub white: PlayerInfo,
+    pub black: PlayerInfo
+}
+
+impl Default for GameMetadata {
+    fn default() -> Self {
+        GameMetadata {
+            white: PlayerInfo {name: String::from("White"), rating: None},
+            black: PlayerInfo {name: String::from("Black"), rating: None}
+        }
+    }
+}
+
+impl GameMetadata {
+    pub fn player(&self, color: PieceColor) -> &PlayerInfo {
+        match color {
+            PieceColor::WHITE => &self.white,
+            PieceColor::BLACK => &self.black
+        }
+    }
+
+    pub fn player_mut(&mut self, color: PieceColor) -> &mut PlayerInfo {
+        match color {
+            PieceColor::WHITE => &mut self.white,
+            PieceColor::BLACK => &mut self.black
+        }
+    }
+}
+
+pub fn fill_engine_player(local_side: Res<LocalSide>, mut metadata: ResMut<GameMetadata>) {
+    let Some(side) = local_side.0 else { return };
+    let engine = PlayerInfo {name: String::from(ENGINE_NAME), rating: None};
+    if *metadata.player(side.opposite()) != engine {
+        *metadata.player_mut(side.opposite()) = engine;
+    }
+    if metadata.player(side).name == ENGINE_NAME {
+        *metadata.player_mut(side) = PlayerInfo {name: side_name(side).to_string(), rating: None};
+    }
+}
+
+#[derive(Component)]
+pub struct PlayerNameText(pub PieceColor);
+
+pub fn update_player_names(metadata: Res<GameMetadata>, mut name_texts: Query<(&PlayerNameText, &mut Text)>) {
+    for (PlayerNameText(color), mut text) in name_texts.iter_mut() {
+        text.sections[0].value = metadata.player(*color).short_label();
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct EditingName(pub Option<PieceColor>);
+
+#[derive(Component)]
+pub struct NameField(pub PieceColor);
+
+pub fn spawn_name_fields(parent: &mut ChildBuilder) {
+    for color in [PieceColor::WHITE, PieceColor::BLACK] {
+        parent.spawn((ButtonBundle {
+            style: Style {
+                width: Val::Px(360.0),
+                padding: UiRect::all(Val::Px(6.0)),
+                margin: UiRect::top(Val::Px(8.0)),
+                ..default()
+            },
+            background_color: Color::rgb(0.2, 0.2, 0.25).into(),
+            ..default()
+        }, NameField(color))).with_children(|field| {
+            field.spawn(TextBundle::from_section("", TextStyle { font_size: 22.0, color: Color::WHITE, ..default() }));
+        });
+    }
+}
+
+pub fn edit_player_names(
+    field_query: Query<(&Interaction, &NameField), Changed<Interaction>>,
+    mut character_listener: EventReader<ReceivedCharacter>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut editing: ResMut<EditingName>,
+    mut text_input_focused: ResMut<TextInputFocused>,
+    mut metadata: ResMut<GameMetadata>,
+    mut buffer: Local<String>
+) {
+    for (interaction, NameField(color)) in field_query.iter() {
+        if *interaction != Interaction::Pressed { continue };
+        editing.0 = Some(*color);
+        text_input_focused.0 = true;
+        *buffer = metadata.player(*color).label();
+    }
+    let Some(color) = editing.0 else { character_listener.clear(); return };
+    if keyboard.any_just_pressed([KeyCode::Enter, KeyCode::NumpadEnter, KeyCode::Escape]) {
+        if !buffer.trim().is_empty() {
+            *metadata.player_mut(color) = PlayerInfo::parse(&buffer);
+        }
+        editing.0 = None;
+        text_input_focused.0 = false;
+        character_listener.clear();
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::Backspace) {
+        buffer.pop();
+    }
+    for received in character_listener.read() {
+        buffer.extend(received.char.chars().filter(|character| !character.is_control()));
+    }
+    *metadata.player_mut(color) = PlayerInfo {name: buffer.clone(), rating: None};
+}
+
+pub fn update_name_fields(
+    metadata: Res<GameMetadata>,
+    editing: Res<EditingName>,
+    field_query: Query<(&NameField, &Children)>,
+    mut text_query: Query<&mut Text>
+) {
+    for (NameField(color), children) in field_query.iter() {
+        let cursor = if editing.0 == Some(*color) { "_" } else { "" };
+        let label = format!("{}: {}{}", side_name(*color), metadata.player(*color).label(), cursor);
+        for child in children.iter() {
+            if let Ok(mut text) = text_query.get_mut(*child) {
+                text.sections[0].value = label.clone();
+            }
+        }
+    }
+}
@@ -0,0 +1,437 @@
+use bevy::app::{App, Plugin, Startup, Update};
+use bevy::prelude::*;
+use crate::engine::{HintRequested, poll_hint_search, start_hint_search};
+use crate::enginelines::{analyse_position, EngineLines, stop_position_analysis, update_engine_arrows};
+use crate::enginematch::{drive_engine_match, end_engine_match, engine_match_idle, engine_match_shortcuts, engine_match_takes_input, EngineMatch, MatchArgs, record_engine_match_result, spawn_engine_match_banner, start_engine_match, update_engine_match_banner};
+use crate::clock::{ChessClock, choose_time_control, reset_clock, rewind_clock, position_player_bars, stop_clock, switch_clock, tick_clock, update_clock_text};
+use crate::players::{edit_player_names, EditingName, fill_engine_player, GameMetadata, update_name_fields, update_player_names};
+use crate::evalbar::{EvalBar, evaluate_position, poll_eval_search, spawn_eval_bar, update_eval_bar};
+use crate::fen::{FenInput, update_fen_field};
+use crate::clipboard::{apply_clipboard_outcomes, clipboard_shortcuts, ClipboardRequest, ClipboardResource, handle_clipboard_requests, PasteRequested};
+use crate::drawrules::announce_claimable_draw;
+use crate::hotseat::{apply_hotseat_flip, HotseatFlip, queue_hotseat_flip, toggle_hotseat_flip, update_pass_device_screen};
+use crate::network::{apply_network_resync, apply_remote_moves, network_live, NetworkArgs, NetworkSession, poll_network, send_local_moves, update_network_banner};
+#[cfg(target_arch = "wasm32")]
+use crate::network::drive_web_link;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::network::feed_spectators;
+use crate::correspondence::{apply_move_strings, ApplyMoveString, export_move, ExportMoveRequested};
+use crate::import::{finish_game_imports, GameImports, ImportGameRequested, request_game_imports};
+use crate::spectator::{apply_stream_clocks, attract_playing, follow_move_stream, manage_spectating, spawn_spectator_banner, Spectator, SpectatorState, start_attract_mode, stop_attract_mode, update_spectator_banner};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::spectator::SpectatorArgs;
+use crate::toast::{spawn_toast_area, Toasts, update_toasts};
+use crate::snapshot::{export_snapshot, snapshot_shortcut, SnapshotRequested};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::archive::{archive_finished_game, archive_unfinished_game, GameArchive, reset_game_archive};
+use crate::analysis::{analysing, Analysis, analysis_banner_clicks, analysis_shortcut, discard_analysis, spawn_analysis_banner, toggle_analysis, ToggleAnalysis, update_analysis_banner};
+use crate::editor::{BoardEditor, close_editor, despawn_editor_panel, editor_buttons, EditorState, enter_editor, open_editor, paint_squares, spawn_editor_panel, update_editor_panel};
+use crate::rush::{PuzzleRush, rush_buttons, rush_menu_buttons, spawn_rush_banner, update_rush, update_rush_banner, update_rush_results};
+use crate::stats::Stats;
+use crate::puzzle::{check_puzzle_moves, load_dropped_puzzles, open_puzzles, play_puzzle_reply, puzzle_buttons, PuzzleOutcome, PuzzleSession, setup_puzzle_game, shake_rejected_piece, spawn_puzzle_panel, update_puzzle_panel};
+use crate::gameover::{apply_game_over_actions, game_over_buttons, GameOverAction, GameOverDismissed, reset_game_over_dismissed};
+#[cfg(feature = "egui")]
+use crate::panel::{apply_egui_scale, fit_board_viewport, game_over_dialog, promotion_cancel_dialog, side_panel, sync_egui_input};
+#[cfg(feature = "egui")]
+use bevy_egui::EguiPlugin;
+use crate::display::{apply_ui_scale, apply_window_mode, choose_ui_scale, detect_ui_scale, toggle_fullscreen, update_ui_scale_buttons, WindowedSize};
+use bevy::window::WindowScaleFactorChanged;
+use crate::keyboard::{keyboard_promotion, keyboard_select, KeyboardCursor, move_keyboard_cursor, spawn_keyboard_cursor, update_keyboard_cursor_marker};
+use crate::highlight::{apply_highlight_palette, AttackOverlay, draw_highlight_shapes, HighlightPalette, update_attack_overlay, resolve_tile_highlights, update_check_highlight, update_hover_highlight, update_last_move_highlight, update_premove_highlight, update_selection_highlight};
+use crate::material::update_captured_trays;
+use crate::movelist::{move_list_clicks, scroll_move_list, update_move_list};
+#[cfg(not(feature = "egui"))]
+use crate::clock::spawn_player_bars;
+#[cfg(not(feature = "egui"))]
+use crate::drawrules::{claim_draw_button, update_draw_rules};
+#[cfg(not(feature = "egui"))]
+use crate::fen::fen_field_input;
+#[cfg(not(feature = "egui"))]
+use crate::gameover::{despawn_game_over_overlay, spawn_game_over_overlay};
+#[cfg(not(feature = "egui"))]
+use crate::material::spawn_captured_trays;
+#[cfg(not(feature = "egui"))]
+use crate::movelist::spawn_move_list;
+use crate::history::{apply_history_view, spawn_history_banner, step_history, update_history_banner, HistoryView, viewing_live, ViewPly, autosave_game, clear_finished_autosave, load_autosave, MoveHistory, record_move_history, reset_history, copy_pgn, CopyPgnRequested, StartingPosition, take_back_moves, TakebackRequested};
+use crate::config::ChessConfig;
+use crate::settings::{apply_check_blink_interval, apply_settings, save_settings, Settings};
+use crate::theme::{apply_board_theme, BoardTheme, choose_board_theme, choose_piece_set, toggle_colorblind_palette, update_palette_toggle, update_piece_set_buttons, update_theme_buttons};
+use crate::shortcuts::{answer_draw_offer, clear_declined_draw_offer, draw_offer_shortcut, DrawOffer, update_draw_offer_prompt, resign_shortcut, ResignPrompt, update_resign_prompt, despawn_pause_menu, keyboard_shortcuts, spawn_pause_menu, text_input_idle, TextInputFocused};
+use crate::storage::StorageResource;
+use crate::arrows::{annotate_with_arrows, Annotations, update_annotation_arrows};
+use crate::assets::{load_piece_assets, PieceSetChanged, switch_piece_set, track_piece_asset_failures, track_piece_asset_loads, wait_for_assets};
+use crate::audio::{drag_volume_sliders, load_sound_assets, play_menu_click, play_sounds, PlaySound, queue_low_time_tick, queue_move_sounds, spawn_mute_indicator, toggle_mute, track_sound_asset_failures, update_mute_indicator, update_volume_sliders};
+use crate::board::{spawn_status_text, update_status_text, flag_game, FlagFell, accept_draw, DrawAccepted, claim_draw, DrawClaimed, resign_game, ResignRequested, apply_board_orientation, LocalSide, UiPointerCapture, apply_chess_config, board_camera_controls, BoardCamera, BoardOrientation, MenuState, fit_board_camera, BoardResource, CheckEvent, game_ongoing, GameOverEvent, GamePhase, GameStatusRes, spawn_board, update_board_cursor, update_coordinate_labels, update_game_status, update_outline, WorldCursor};
+use chess_logic::logic::Board;
+use crate::piece::{animate_shakes, close_promotion_cancel_prompt, PromotionCancelConfirmed, PromotionCancelPrompt, preview_opponent_moves, update_cursor_icon, animate_piece_moves, fade_captured_pieces, animate_snap_back, cancel_drags, cancel_promotion, drag_interrupted, BoardUpdate, CaptureEvent, check_animation, emit_capture_events, CheckAnimationTimer, detect_promotion, apply_promotion, drag_piece, make_move, PromotionChosen, play_premove, Premove, MovePlayed, MoveRequested, select_piece, Selection, draw_capture_rings, LegalTargets, update_move_markers, update_selection_targets, NewGame, orient_piece_sprites, start_new_game, PieceEntities, promotion_chooser, PromotionSquare, resync_board, spawn_phantom_piece, spawn_promotion_options, update_board_pieces, update_fallback_glyphs, refresh_piece_sprites};
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InputSet;
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RulesSet;
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SyncSet;
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PresentationSet;
+
+// What the command line, or the page's query string in the browser, asks for at startup.
+// It is read once in main, so the plugin does not change with whatever binary hosts it
+#[derive(Default, Clone)]
+pub struct LaunchArgs {
+    pub network: Option<NetworkArgs>,
+    #[cfg(not(target_arch = "wasm32"))]
+    pub spectator: Option<SpectatorArgs>,
+    pub engine_match: MatchArgs
+}
+
+impl LaunchArgs {
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn from_environment() -> Self {
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        LaunchArgs {
+            network: NetworkArgs::from_args(args.clone()),
+            spectator: SpectatorArgs::from_args(args.clone()),
+            engine_match: MatchArgs::from_args(args)
+        }
+    }
+
+    #[cfg(target_arch = "wasm32")]
+    pub fn from_environment() -> Self {
+        let network = web_sys::window().and_then(|window| window.location().search().ok()).and_then(|query| NetworkArgs::from_query(&query));
+        LaunchArgs {network, engine_match: MatchArgs::default()}
+    }
+}
+
+#[derive(Default)]
+pub struct ChessPlugin {
+    pub config: ChessConfig,
+    pub launch: LaunchArgs
+}
+
+impl ChessPlugin {
+    pub fn new(config: ChessConfig) -> Self {
+        ChessPlugin {config, launch: LaunchArgs::default()}
+    }
+
+    pub fn with_launch_args(self, launch: LaunchArgs) -> Self {
+        ChessPlugin {launch, ..self}
+    }
+}
+
+impl Plugin for ChessPlugin {
+    fn build(&self, app: &mut App) {
+        // A storage inserted before the plugin wins, which keeps headless runs off the real save files
+        let storage = app.world.remove_resource::<StorageResource>().unwrap_or_default();
+        let settings = Settings::load(storage.0.as_ref());
+        let (board, start, history) = load_autosave(storage.0.as_ref()).unwrap_or_else(|| (Board::new(), StartingPosition::default(), MoveHistory::default()));
+        let mut config = self.config.clone();
+        settings.apply_to(&mut config);
+        #[cfg(not(target_arch = "wasm32"))]
+        let spectator = self.launch.spectator.clone().map_or_else(Spectator::default, SpectatorArgs::into_spectator);
+        #[cfg(target_arch = "wasm32")]
+        let spectator = Spectator::default();
+        let engine_match = EngineMatch::new(self.launch.engine_match.clone());
+        app
+            .insert_resource(CheckAnimationTimer(Timer::new(config.check_blink_interval, TimerMode::Repeating)))
+            .insert_resource(BoardOrientation(config.orientation))
+            .insert_resource(config)
+            .insert_resource(ChessClock::new(settings.time_control))
+            .insert_resource(BoardTheme::named(&settings.board_theme).unwrap_or_default())
+            .insert_resource(HighlightPalette {colorblind: settings.colorblind_palette})
+            .insert_resource(Stats::load(storage.0.as_ref()))
+            .insert_resource(settings)
+            .insert_resource(storage)
+            .insert_resource(BoardResource(board))
+            .insert_resource(start)
+            .insert_resource(history)
+            .insert_resource(PromotionSquare(None))
+            .init_resource::<WindowedSize>()
+            .init_resource::<TextInputFocused>()
+            .init_resource::<Selection>()
+            .init_resource::<Annotations>()
+            .init_resource::<ResignPrompt>()
+            .init_resource::<DrawOffer>()
+            .init_resource::<AttackOverlay>()
+            .init_resource::<HistoryView>()
+            .init_resource::<GameMetadata>()
+            .init_resource::<EditingName>()
+            .init_resource::<EvalBar>()
+            .init_resource::<FenInput>()
+            .init_resource::<ClipboardResource>()
+            .init_resource::<GameImports>()
+            .init_resource::<Toasts>()
+            .init_resource::<HotseatFlip>()
+            .init_resource::<Analysis>()
+            .init_resource::<EngineLines>()
+            .init_resource::<BoardEditor>()
+            .init_resource::<PuzzleSession>()
+            .init_resource::<PuzzleRush>()
+            .init_resource::<UiPointerCapture>()
+            .init_resource::<GameOverDismissed>()
+            .init_resource::<PromotionCancelPrompt>()
+            .init_resource::<LegalTargets>()
+            .init_resource::<KeyboardCursor>()
+            .init_resource::<Premove>()
+            .init_resource::<LocalSide>()
+            .insert_resource(spectator)
+            .insert_resource(engine_match)
+            .insert_resource(PieceEntities::default())
+            .insert_resource(GameStatusRes::default())
+            .init_state::<GamePhase>()
+            .init_state::<MenuState>()
+            .init_state::<EditorState>()
+            .init_state::<SpectatorState>()
+            .add_event::<BoardUpdate>()
+            .add_event::<SnapshotRequested>()
+            .add_event::<ToggleAnalysis>()
+            .add_event::<PuzzleOutcome>()
+            .add_event::<MovePlayed>()
+            .add_event::<CaptureEvent>()
+            .add_event::<CheckEvent>()
+            .add_event::<GameOverEvent>()
+            .add_event::<NewGame>()
+            .add_event::<TakebackRequested>()
+            .add_event::<ViewPly>()
+            .add_event::<HintRequested>()
+            .add_event::<ResignRequested>()
+            .add_event::<DrawAccepted>()
+            .add_event::<DrawClaimed>()
+            .add_event::<PlaySound>()
+            .add_event::<PieceSetChanged>()
+            .add_event::<FlagFell>()
+            .add_event::<MoveRequested>()
+            .add_event::<PromotionChosen>()
+            .add_event::<ClipboardRequest>()
+            .add_event::<PasteRequested>()
+            .add_event::<ImportGameRequested>()
+            .add_event::<ExportMoveRequested>()
+            .add_event::<ApplyMoveString>()
+            .add_event::<CopyPgnRequested>()
+            .add_event::<GameOverAction>()
+            .add_event::<PromotionCancelConfirmed>()
+            .add_systems(Startup, (load_piece_assets, load_sound_assets, spawn_board, spawn_status_text, spawn_eval_bar, spawn_toast_area, spawn_mute_indicator, spawn_history_banner, spawn_analysis_banner, spawn_puzzle_panel, spawn_rush_banner, spawn_spectator_banner, spawn_engine_match_banner))
+            .add_systems(OnExit(GamePhase::Loading), (spawn_phantom_piece, spawn_promotion_options, spawn_keyboard_cursor, resync_board, update_captured_trays))
+            .add_systems(Update, ((track_piece_asset_failures, track_piece_asset_loads, track_sound_asset_failures), wait_for_assets.run_if(in_state(GamePhase::Loading))).chain())
+            .add_systems(OnEnter(GamePhase::GameOver), reset_game_over_dismissed)
+            .add_systems(OnExit(GamePhase::PromotionPending), close_promotion_cancel_prompt)
+            .add_systems(OnEnter(MenuState::Open), (spawn_pause_menu, play_menu_click, start_attract_mode.run_if(engine_match_idle)))
+            .add_systems(OnExit(MenuState::Open), (despawn_pause_menu, play_menu_click, stop_attract_mode))
+            .add_systems(OnEnter(EditorState::Open), (enter_editor, spawn_editor_panel))
+            .add_systems(OnExit(EditorState::Open), despawn_editor_panel)
+            .configure_sets(Update, (InputSet, RulesSet, SyncSet, PresentationSet).chain())
+            .configure_sets(Update, InputSet.run_if(in_state(MenuState::Closed).and_then(network_live).and_then(in_state(SpectatorState::Off)).and_then(engine_match_takes_input)))
+            .add_systems(Update, (
+                keyboard_shortcuts.run_if(not(in_state(GamePhase::Loading))),
+                clipboard_shortcuts.run_if(not(in_state(GamePhase::Loading))),
+                snapshot_shortcut.run_if(text_input_idle.and_then(not(in_state(GamePhase::Loading)))),
+                (analysis_shortcut.run_if(text_input_idle.and_then(not(in_state(GamePhase::Loading)))), resign_shortcut, draw_offer_shortcut).run_if(in_state(EditorState::Closed).and_then(in_state(SpectatorState::Off)).and_then(engine_match_idle)),
+                edit_player_names.run_if(in_state(MenuState::Open)),
+                choose_time_control.run_if(in_state(MenuState::Open)),
+                (start_new_game, discard_analysis, close_editor).chain(),
+                reset_clock,
+                reset_history,
+                fill_engine_player.run_if(resource_changed::<LocalSide>.or_else(on_event::<NewGame>())).run_if(not(resource_exists::<NetworkSession>)).run_if(engine_match_idle),
+                (take_back_moves.run_if(in_state(SpectatorState::Off).and_then(engine_match_idle)), move_list_clicks, analysis_banner_clicks),
+                step_history.run_if(text_input_idle.and_then(in_state(MenuState::Closed)).and_then(not(in_state(GamePhase::Loading)))),
+                game_over_buttons.run_if(in_state(GamePhase::GameOver)),
+                (apply_game_over_actions, toggle_analysis.run_if(in_state(EditorState::Closed))).chain(),
+                apply_history_view,
+                copy_pgn,
+                handle_clipboard_requests,
+                apply_clipboard_outcomes
+            ).chain().before(InputSet))
+            .add_systems(Update, (
+                fit_board_camera,
+                board_camera_controls,
+                toggle_fullscreen,
+                update_board_cursor,
+                (
+                    (move_keyboard_cursor, keyboard_select.run_if(in_state(GamePhase::Playing).and_then(game_ongoing).and_then(viewing_live))).chain().run_if(text_input_idle),
+                    select_piece.run_if(in_state(GamePhase::Playing).and_then(game_ongoing).and_then(resource_exists::<WorldCursor>).and_then(viewing_live)),
+                    drag_piece.run_if(in_state(GamePhase::Playing).and_then(game_ongoing).and_then(resource_exists::<WorldCursor>).and_then(viewing_live)),
+                    (cancel_promotion, promotion_chooser, keyboard_promotion.run_if(text_input_idle), apply_promotion).chain().run_if(in_state(GamePhase::PromotionPending))
+                ).chain().run_if(in_state(EditorState::Closed)),
+                paint_squares.run_if(in_state(EditorState::Open))
+            ).chain().in_set(InputSet))
+            .add_systems(Update, (make_move.run_if(in_state(GamePhase::Playing)), play_premove, detect_promotion.run_if(in_state(GamePhase::Playing)))
+                .chain()
+                .run_if(in_state(EditorState::Closed))
+                .after(InputSet)
+                .before(RulesSet))
+            .add_systems(Update, cancel_drags.run_if(drag_interrupted).after(drag_piece).before(RulesSet))
+            .add_systems(Update, annotate_with_arrows.after(apply_promotion).run_if(in_state(EditorState::Closed)).in_set(InputSet))
+            .add_systems(Update, (open_editor.run_if(in_state(MenuState::Open)), editor_buttons.run_if(in_state(EditorState::Open))).before(start_new_game))
+            .add_systems(Update, (load_dropped_puzzles, open_puzzles.run_if(in_state(MenuState::Open)), puzzle_buttons).before(start_new_game))
+            .add_systems(Update, (rush_menu_buttons.run_if(in_state(MenuState::Open)), rush_buttons).before(start_new_game))
+            .add_systems(Update, (setup_puzzle_game, play_puzzle_reply.run_if(in_state(EditorState::Closed))).chain().after(reset_clock).before(InputSet))
+            .add_systems(Update, (export_move, apply_move_strings.run_if(in_state(SpectatorState::Off))).after(apply_clipboard_outcomes).before(InputSet))
+            .add_systems(Update, (request_game_imports, finish_game_imports).chain().after(apply_clipboard_outcomes).before(manage_spectating))
+            .add_systems(Update, (manage_spectating, follow_move_stream.run_if(in_state(SpectatorState::Watching))).chain().after(reset_clock).after(reset_history).before(InputSet))
+            .add_systems(Update, apply_stream_clocks.after(switch_clock).in_set(RulesSet))
+            .add_systems(Update, (
+                engine_match_shortcuts.run_if(not(in_state(GamePhase::Loading))),
+                end_engine_match,
+                start_engine_match.run_if(not(in_state(GamePhase::Loading)).and_then(in_state(SpectatorState::Off))),
+                drive_engine_match.run_if(in_state(EditorState::Closed))
+            ).chain().after(reset_clock).after(reset_history).after(manage_spectating).before(InputSet))
+            .add_systems(Update, (record_engine_match_result, update_engine_match_banner.run_if(resource_changed::<EngineMatch>)).chain().in_set(PresentationSet))
+            .add_systems(Update, update_spectator_banner.run_if(resource_changed::<Spectator>).in_set(PresentationSet))
+            .add_systems(Update, (analyse_position.run_if(analysing), stop_position_analysis.run_if(not(analysing)), update_engine_arrows).chain().in_set(PresentationSet))
+            .add_systems(Update, (start_hint_search, poll_hint_search).chain().run_if(in_state(EditorState::Closed)).in_set(PresentationSet))
+            .add_systems(Update, (
+                evaluate_position.run_if(in_state(EditorState::Closed).and_then(
+                    on_event::<BoardUpdate>().or_else(resource_changed::<HistoryView>).or_else(resource_changed::<Settings>)
+                )),
+                poll_eval_search,
+                update_eval_bar
+            ).chain().in_set(PresentationSet))
+            .add_systems(Update, (
+                update_resign_prompt.run_if(resource_changed::<ResignPrompt>),
+                update_draw_offer_prompt.run_if(resource_changed::<DrawOffer>),
+                update_move_list.run_if(resource_changed::<MoveHistory>.or_else(resource_changed::<HistoryView>).or_else(resource_changed::<Analysis>)),
+                update_history_banner.run_if(resource_changed::<HistoryView>),
+                update_analysis_banner.run_if(resource_changed::<Analysis>),
+                update_puzzle_panel.run_if(resource_changed::<PuzzleSession>),
+                (update_rush_banner, update_rush_results).run_if(resource_changed::<PuzzleRush>),
+                scroll_move_list,
+                update_clock_text,
+                update_fen_field.run_if(resource_changed::<FenInput>),
+                (
+                    announce_claimable_draw.run_if(resource_changed::<BoardResource>.or_else(resource_changed::<GameStatusRes>)),
+                    update_toasts
+                ).chain(),
+                position_player_bars.run_if(resource_changed::<BoardOrientation>),
+                update_player_names.run_if(resource_changed::<GameMetadata>),
+                update_name_fields.run_if(in_state(MenuState::Open)),
+                update_status_text.run_if(
+                    resource_changed::<GameStatusRes>.or_else(resource_changed::<BoardResource>)
+                        .or_else(resource_changed::<BoardOrientation>).or_else(resource_changed::<ChessConfig>)
+                        .or_else(resource_changed::<HistoryView>)
+                ),
+                update_captured_trays.run_if(not(in_state(GamePhase::Loading)).and_then(
+                    resource_changed::<MoveHistory>.or_else(resource_changed::<HistoryView>).or_else(resource_changed::<BoardOrientation>)
+                        .or_else(on_event::<PieceSetChanged>())
+                )),
+                update_attack_overlay.run_if(on_event::<BoardUpdate>().or_else(resource_changed::<AttackOverlay>).or_else(resource_changed::<Settings>))
+            ).in_set(PresentationSet))
+            .add_systems(Update, (
+                (resign_game, clear_declined_draw_offer, answer_draw_offer.run_if(resource_changed::<DrawOffer>), accept_draw, claim_draw).chain().run_if(not(analysing).and_then(in_state(EditorState::Closed))),
+                tick_clock,
+                flag_game,
+                update_game_status.run_if(in_state(EditorState::Closed)),
+                emit_capture_events,
+                record_move_history,
+                (check_puzzle_moves, update_rush).chain(),
+                switch_clock,
+                rewind_clock.run_if(resource_changed::<MoveHistory>),
+                stop_clock,
+                autosave_game.run_if(on_event::<MovePlayed>().or_else(on_event::<TakebackRequested>()).and_then(not(analysing)).and_then(in_state(SpectatorState::Off)).and_then(engine_match_idle)),
+                clear_finished_autosave.run_if(in_state(SpectatorState::Off))
+            ).chain().in_set(RulesSet))
+            .add_systems(Update, (apply_chess_config, cancel_drags.before(update_board_pieces), update_board_pieces).run_if(on_event::<BoardUpdate>()).in_set(SyncSet))
+            .add_systems(Update, (queue_hotseat_flip.run_if(in_state(SpectatorState::Off)), apply_hotseat_flip, update_pass_device_screen.run_if(resource_changed::<HotseatFlip>)).chain().in_set(SyncSet))
+            .add_systems(Update, (
+                check_animation.run_if(|config: Res<ChessConfig>| config.blink_checked_king),
+                update_check_highlight.run_if(resource_changed::<GameStatusRes>),
+                apply_board_orientation.run_if(resource_changed::<BoardOrientation>),
+                (apply_window_mode, apply_settings, apply_board_theme, apply_highlight_palette, switch_piece_set).run_if(resource_changed::<Settings>),
+                refresh_piece_sprites.run_if(on_event::<PieceSetChanged>()),
+                apply_check_blink_interval.run_if(resource_changed::<ChessConfig>),
+                save_settings,
+                orient_piece_sprites,
+                update_keyboard_cursor_marker.run_if(resource_changed::<KeyboardCursor>),
+                update_hover_highlight,
+                update_last_move_highlight.run_if(resource_changed::<MoveHistory>),
+                update_selection_highlight.run_if(resource_changed::<Selection>),
+                update_premove_highlight.run_if(resource_changed::<Premove>),
+                (
+                    update_selection_targets.run_if(resource_changed::<Selection>),
+                    update_move_markers.run_if(resource_changed::<LegalTargets>.or_else(resource_changed::<Settings>).or_else(resource_changed::<HighlightPalette>))
+                ).chain(),
+                draw_capture_rings,
+                update_annotation_arrows.run_if(resource_changed::<Annotations>.or_else(resource_changed::<HighlightPalette>)),
+                update_fallback_glyphs,
+                update_coordinate_labels.run_if(resource_changed::<ChessConfig>.or_else(resource_changed::<BoardOrientation>).or_else(resource_changed::<Settings>)),
+                update_outline.run_if(resource_changed::<BoardResource>.or_else(resource_changed::<GameStatusRes>).or_else(resource_changed::<BoardTheme>)),
+                (update_theme_buttons, update_piece_set_buttons, update_palette_toggle, update_ui_scale_buttons).run_if(in_state(MenuState::Open))
+            ).in_set(PresentationSet))
+            .add_systems(Update, ((animate_snap_back, animate_piece_moves, fade_captured_pieces).chain(), update_cursor_icon, preview_opponent_moves, (shake_rejected_piece, animate_shakes).chain()).in_set(PresentationSet))
+            .add_systems(Update, (toggle_mute.run_if(text_input_idle), (drag_volume_sliders, choose_board_theme, choose_piece_set, toggle_colorblind_palette, toggle_hotseat_flip, choose_ui_scale).run_if(in_state(MenuState::Open))).before(InputSet))
+            .add_systems(Update, (queue_move_sounds.run_if(not(attract_playing)), queue_low_time_tick, play_sounds).chain().in_set(PresentationSet))
+            .add_systems(Update, (
+                update_volume_sliders.run_if(in_state(MenuState::Open)),
+                update_mute_indicator.run_if(resource_changed::<Settings>)
+            ).in_set(PresentationSet))
+            .add_systems(Update, update_editor_panel
+                .run_if(in_state(EditorState::Open).and_then(resource_changed::<BoardEditor>.or_else(resource_changed::<BoardResource>)))
+                .in_set(PresentationSet))
+            .add_systems(Update, (
+                poll_network.before(start_new_game),
+                apply_network_resync.after(reset_clock).after(reset_history).after(fill_engine_player).before(InputSet),
+                apply_remote_moves.run_if(in_state(MenuState::Closed).and_then(in_state(GamePhase::Playing))).after(start_new_game).before(InputSet),
+                send_local_moves.after(record_move_history).in_set(RulesSet),
+                update_network_banner.run_if(resource_exists_and_changed::<NetworkSession>).in_set(PresentationSet)
+            ).run_if(resource_exists::<NetworkSession>))
+            .add_systems(Update, export_snapshot.run_if(on_event::<SnapshotRequested>()).in_set(PresentationSet))
+            .add_systems(Update, (detect_ui_scale, apply_ui_scale).chain()
+                .run_if(resource_changed::<Settings>.or_else(on_event::<WindowScaleFactorChanged>()))
+                .in_set(PresentationSet))
+            .add_systems(Update, (resolve_tile_highlights, draw_highlight_shapes).after(PresentationSet));
+        #[cfg(feature = "egui")]
+        app
+            .add_plugins(EguiPlugin)
+            .insert_resource(PromotionCancelPrompt {confirm: true, open: false})
+            .add_systems(Update, (
+                side_panel,
+                game_over_dialog.run_if(in_state(GamePhase::GameOver)),
+                promotion_cancel_dialog.run_if(in_state(GamePhase::PromotionPending)),
+                sync_egui_input,
+                fit_board_viewport
+            ).chain().before(keyboard_shortcuts))
+            .add_systems(Update, apply_egui_scale.run_if(resource_changed::<Settings>.or_else(on_event::<WindowScaleFactorChanged>())));
+        #[cfg(not(feature = "egui"))]
+        app
+            .add_systems(Startup, (spawn_move_list, spawn_captured_trays, spawn_player_bars))
+            .add_systems(OnEnter(GamePhase::GameOver), spawn_game_over_overlay)
+            .add_systems(OnExit(GamePhase::GameOver), despawn_game_over_overlay)
+            .add_systems(Update, fen_field_input
+                .run_if(in_state(MenuState::Closed).and_then(not(in_state(GamePhase::Loading))))
+                .after(edit_player_names)
+                .before(choose_time_control))
+            .add_systems(Update, claim_draw_button.before(RulesSet))
+            .add_systems(Update, update_draw_rules
+                .run_if(resource_changed::<BoardResource>.or_else(resource_changed::<GameStatusRes>))
+                .in_set(PresentationSet));
+        #[cfg(not(target_arch = "wasm32"))]
+        app
+            .init_resource::<GameArchive>()
+            .add_systems(Update, (
+                reset_game_archive,
+                archive_finished_game.run_if(not(analysing).and_then(in_state(SpectatorState::Off)))
+            ).chain().after(RulesSet))
+            .add_systems(Last, archive_unfinished_game.run_if(not(analysing).and_then(in_state(SpectatorState::Off))));
+        if let Some(args) = self.launch.network.clone() {
+            app.insert_resource(NetworkSession::start(args));
+            #[cfg(target_arch = "wasm32")]
+            app.add_systems(Update, drive_web_link.before(poll_network));
+            #[cfg(not(target_arch = "wasm32"))]
+            app.add_systems(Update, feed_spectators.in_set(PresentationSet));
+        }
+        if self.config.spawn_camera {
+            app.add_systems(Startup, spawn_camera);
+        }
+    }
+}
+
+pub fn spawn_camera(mut commands: Commands, config: Res<ChessConfig>) {
+    commands.spawn((
+        Camera2dBundle {
+            transform: Transform::from_translation(Vec3::from((config.board_center(), 0.0))),
+            ..default()
+        },
+        BoardCamera
+    ));
+}
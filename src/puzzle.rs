@@ -0,0 +1,523 @@
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use chess_logic::logic::{Board, Coordinate, GameStatus, Move, MoveRecord, PieceColor, PositionStatus};
+use chess_logic::search::{mate_in, SearchLimits, SearchResult};
+use crate::board::{BoardOrientation, BoardResource, GamePhase, GameStatusRes, LocalSide, MenuState};
+use crate::clock::ChessClock;
+use crate::config::ChessConfig;
+use crate::engine::EngineJob;
+use crate::history::MoveHistory;
+use crate::piece::{BoardUpdate, MovePlayed, MoveRequested, NewGame, PieceEntities, Shake};
+use crate::settings::Settings;
+use crate::toast::{ToastLevel, Toasts};
+
+const REPLY_DELAY: f32 = 0.6;
+const MAX_ALTERNATE_MATE_PLIES: usize = 6;
+
+pub struct Puzzle {
+    pub id: String,
+    pub rating: Option<u32>,
+    pub start: Board,
+    pub moves: Vec<Move>,
+    pub mate: bool
+}
+
+impl Puzzle {
+    fn parse(line: &str) -> Result<Option<Puzzle>, String> {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with("PuzzleId") { return Ok(None) };
+        let (id, fen, moves, rating) = match line.split_once(';') {
+            Some((fen, moves)) => (None, fen, moves, None),
+            None => {
+                let fields: Vec<&str> = line.split(',').collect();
+                if fields.len() < 3 { return Err(String::from("expected \"FEN; moves\" or a lichess puzzle row")) };
+                (Some(fields[0].to_string()), fields[1], fields[2], fields.get(3).and_then(|rating| rating.trim().parse().ok()))
+            }
+        };
+        let start = Board::from_fen(fen.trim()).map_err(|error| error.to_string())?;
+        let mut board = start.clone();
+        let mut parsed = Vec::new();
+        for text in moves.split_whitespace() {
+            let candidate = board.parse_move(text).ok_or_else(|| format!("cannot read move {}", text))?;
+            board.try_move(candidate).map_err(|error| format!("illegal move {}: {}", text, error))?;
+            parsed.push(candidate);
+        }
+        if parsed.len() < 2 { return Err(String::from("needs the opponent's move and at least one reply")) };
+        let mate = matches!(board.position_status().status, GameStatus::Checkmate { .. });
+        Ok(Some(Puzzle {id: id.unwrap_or_default(), rating, start, moves: parsed, mate}))
+    }
+
+    pub fn solver(&self) -> PieceColor {
+        self.start.on_move.opposite()
+    }
+}
+
+pub fn parse_puzzles(text: &str) -> Result<Vec<Puzzle>, String> {
+    let mut puzzles = Vec::new();
+    for (index, line) in text.lines().enumerate() {
+        match Puzzle::parse(line) {
+            Ok(Some(mut puzzle)) => {
+                if puzzle.id.is_empty() {
+                    puzzle.id = format!("#{}", puzzles.len() + 1);
+                }
+                puzzles.push(puzzle);
+            }
+            Ok(None) => {}
+            Err(error) => warn!("skipping puzzle on line {}: {}", index + 1, error)
+        }
+    }
+    if puzzles.is_empty() { return Err(String::from("no playable puzzles found")) };
+    Ok(puzzles)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Default)]
+pub enum PuzzleFeedback {
+    #[default]
+    FindMove,
+    Correct,
+    Wrong,
+    Solved,
+    Completed
+}
+
+#[derive(Event, Clone, Copy, PartialEq, Eq)]
+pub enum PuzzleOutcome {
+    Mistake,
+    Solved,
+    Completed
+}
+
+#[derive(Resource, Default)]
+pub struct PuzzleSession {
+    pub puzzles: Vec<Puzzle>,
+    pub current: Option<usize>,
+    pub progress: usize,
+    pub mistakes: u32,
+    pub solved: u32,
+    pub failed: u32,
+    pub feedback: PuzzleFeedback,
+    pub timed: bool,
+    off_script: bool,
+    starting: bool,
+    reply: Option<(Move, Timer)>,
+    rejected: Option<(Coordinate, Coordinate)>,
+    checking: Option<AlternateCheck>,
+    saved_local_side: Option<Option<PieceColor>>
+}
+
+impl PuzzleSession {
+    pub fn puzzle(&self) -> Option<&Puzzle> {
+        self.current.and_then(|index| self.puzzles.get(index))
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.current.is_some()
+    }
+
+    pub fn finished(&self) -> bool {
+        matches!(self.feedback, PuzzleFeedback::Solved | PuzzleFeedback::Completed)
+    }
+
+    pub fn start(&mut self, index: usize, new_game_writer: &mut EventWriter<NewGame>) {
+        let Some(puzzle) = self.puzzles.get(index) else { return };
+        new_game_writer.send(NewGame::from_position(puzzle.start.clone()));
+        self.current = Some(index);
+        self.progress = 0;
+        self.mistakes = 0;
+        self.feedback = PuzzleFeedback::FindMove;
+        self.off_script = false;
+        self.starting = true;
+        self.reply = Some((puzzle.moves[0], Timer::from_seconds(REPLY_DELAY, TimerMode::Once)));
+        self.rejected = None;
+        self.cancel_check();
+    }
+
+    fn cancel_check(&mut self) {
+        if let Some(check) = self.checking.take() {
+            check.job.stop();
+        }
+    }
+
+    fn finish(&mut self) -> PuzzleOutcome {
+        self.reply = None;
+        if self.mistakes == 0 {
+            self.feedback = PuzzleFeedback::Solved;
+            self.solved += 1;
+            PuzzleOutcome::Solved
+        } else {
+            self.feedback = PuzzleFeedback::Completed;
+            self.failed += 1;
+            PuzzleOutcome::Completed
+        }
+    }
+
+    pub fn stop(&mut self, local_side: &mut LocalSide) {
+        self.current = None;
+        self.reply = None;
+        self.timed = false;
+        self.cancel_check();
+        if let Some(saved) = self.saved_local_side.take() {
+            local_side.0 = saved;
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        format!("{} solved, {} failed", self.solved, self.failed)
+    }
+}
+
+pub fn read_puzzle_file(path: &str) -> Result<Vec<Puzzle>, String> {
+    std::fs::read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|text| parse_puzzles(&text))
+}
+
+fn load_puzzle_file(path: &str, session: &mut PuzzleSession, toasts: &mut Toasts, new_game_writer: &mut EventWriter<NewGame>) -> bool {
+    match read_puzzle_file(path) {
+        Ok(puzzles) => {
+            toasts.push(ToastLevel::Success, format!("Loaded {} puzzles", puzzles.len()));
+            session.puzzles = puzzles;
+            session.solved = 0;
+            session.failed = 0;
+            session.timed = false;
+            session.start(0, new_game_writer);
+            true
+        }
+        Err(error) => {
+            warn!("failed to load puzzles from {}: {}", path, error);
+            toasts.push(ToastLevel::Error, format!("Could not load puzzles: {}", error));
+            false
+        }
+    }
+}
+
+pub fn load_dropped_puzzles(
+    mut drop_listener: EventReader<FileDragAndDrop>,
+    mut session: ResMut<PuzzleSession>,
+    mut settings: ResMut<Settings>,
+    mut toasts: ResMut<Toasts>,
+    mut new_game_writer: EventWriter<NewGame>
+) {
+    for event in drop_listener.read() {
+        let FileDragAndDrop::DroppedFile { path_buf, .. } = event else { continue };
+        let path = path_buf.display().to_string();
+        if load_puzzle_file(&path, &mut session, &mut toasts, &mut new_game_writer) {
+            settings.puzzle_file = Some(path);
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct PuzzleMenuButton;
+
+pub fn spawn_puzzle_button(parent: &mut ChildBuilder) {
+    parent.spawn((ButtonBundle {
+        style: Style {
+            width: Val::Px(360.0),
+            margin: UiRect::top(Val::Px(8.0)),
+            padding: UiRect::all(Val::Px(4.0)),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        background_color: Color::rgb(0.25, 0.25, 0.3).into(),
+        ..default()
+    }, PuzzleMenuButton)).with_children(|button| {
+        button.spawn(TextBundle::from_section("Puzzles (or drop a puzzle file on the window)", TextStyle { font_size: 16.0, color: Color::WHITE, ..default() }));
+    });
+}
+
+pub fn open_puzzles(
+    button_query: Query<&Interaction, (Changed<Interaction>, With<PuzzleMenuButton>)>,
+    settings: Res<Settings>,
+    mut session: ResMut<PuzzleSession>,
+    mut toasts: ResMut<Toasts>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+    mut new_game_writer: EventWriter<NewGame>
+) {
+    if !button_query.iter().any(|interaction| *interaction == Interaction::Pressed) { return };
+    let Some(path) = settings.puzzle_file.clone() else {
+        toasts.push(ToastLevel::Info, "Drop a puzzle file (lichess CSV or \"FEN; moves\" lines) on the window");
+        return;
+    };
+    if load_puzzle_file(&path, &mut session, &mut toasts, &mut new_game_writer) {
+        next_menu.set(MenuState::Closed);
+    }
+}
+
+pub fn setup_puzzle_game(
+    mut new_game_listener: EventReader<NewGame>,
+    mut session: ResMut<PuzzleSession>,
+    mut local_side: ResMut<LocalSide>,
+    mut orientation: ResMut<BoardOrientation>,
+    mut clock: ResMut<ChessClock>
+) {
+    if new_game_listener.read().count() == 0 { return };
+    if !std::mem::take(&mut session.starting) {
+        session.stop(&mut local_side);
+        return;
+    }
+    let Some(solver) = session.puzzle().map(Puzzle::solver) else { return };
+    session.saved_local_side.get_or_insert(local_side.0);
+    local_side.0 = Some(solver);
+    orientation.0 = solver;
+    *clock = ChessClock::new(None);
+}
+
+pub fn play_puzzle_reply(
+    time: Res<Time>,
+    phase: Res<State<GamePhase>>,
+    mut session: ResMut<PuzzleSession>,
+    mut move_request_writer: EventWriter<MoveRequested>,
+    mut outcome_writer: EventWriter<PuzzleOutcome>
+) {
+    if session.reply.is_none() || *phase.get() != GamePhase::Playing { return };
+    let Some((reply, timer)) = session.reply.as_mut() else { return };
+    if !timer.tick(time.delta()).finished() { return };
+    move_request_writer.send(MoveRequested {from: reply.from, to: reply.to, promotion: reply.promotion});
+    session.reply = None;
+    session.progress += 1;
+    let length = session.puzzle().map_or(0, |puzzle| puzzle.moves.len());
+    if !session.off_script && session.progress >= length {
+        outcome_writer.send(session.finish());
+    }
+}
+
+// Off-script solver moves in mate puzzles are searched on the task pool to see whether they still mate in time
+struct AlternateCheck {
+    job: EngineJob,
+    record: MoveRecord,
+    after: PositionStatus,
+    solver_moves_left: usize
+}
+
+fn spawn_alternate_check(board: &Board, solver_moves_left: usize) -> Option<EngineJob> {
+    if solver_moves_left == 0 || solver_moves_left * 2 > MAX_ALTERNATE_MATE_PLIES { return None };
+    Some(EngineJob::spawn(board.clone(), SearchLimits::new(solver_moves_left as u32 * 2)))
+}
+
+fn forced_mate_reply(result: Option<SearchResult>, solver_moves_left: usize) -> Option<Move> {
+    let result = result?;
+    mate_in(result.score).filter(|moves| *moves < 0 && -*moves as usize <= solver_moves_left)?;
+    Some(result.best_move)
+}
+
+#[derive(SystemParam)]
+pub struct PuzzleMoves<'w> {
+    session: ResMut<'w, PuzzleSession>,
+    board: ResMut<'w, BoardResource>,
+    history: ResMut<'w, MoveHistory>,
+    game_status: ResMut<'w, GameStatusRes>,
+    next_phase: ResMut<'w, NextState<GamePhase>>,
+    board_update_writer: EventWriter<'w, BoardUpdate>,
+    outcome_writer: EventWriter<'w, PuzzleOutcome>
+}
+
+impl PuzzleMoves<'_> {
+    fn accept(&mut self, scripted: bool, mates: bool, alternate: Option<Move>) {
+        let session = &mut *self.session;
+        let scripted_reply = session.puzzle().and_then(|puzzle| puzzle.moves.get(session.progress + 1).copied());
+        session.progress += 1;
+        session.off_script |= !scripted;
+        session.feedback = PuzzleFeedback::Correct;
+        let reply = if session.off_script { alternate } else { scripted_reply };
+        match reply.filter(|_| !mates) {
+            Some(reply) => session.reply = Some((reply, Timer::from_seconds(REPLY_DELAY, TimerMode::Once))),
+            None => { self.outcome_writer.send(session.finish()); }
+        }
+    }
+
+    fn reject(&mut self, record: MoveRecord, after: PositionStatus) {
+        self.session.mistakes += 1;
+        self.session.feedback = PuzzleFeedback::Wrong;
+        self.session.rejected = Some((record.from, record.to));
+        self.board.0.unmake_move(&record);
+        self.history.0.pop();
+        if after.status != GameStatus::Ongoing {
+            *self.game_status = GameStatusRes::default();
+            self.next_phase.set(GamePhase::Playing);
+        }
+        self.board_update_writer.send(BoardUpdate::resync());
+        self.outcome_writer.send(PuzzleOutcome::Mistake);
+    }
+
+    fn finish_alternate_check(&mut self) {
+        let Some(result) = self.session.checking.as_ref().and_then(|check| check.job.try_take()) else { return };
+        let Some(check) = self.session.checking.take() else { return };
+        match forced_mate_reply(result, check.solver_moves_left) {
+            Some(reply) => self.accept(false, false, Some(reply)),
+            None => self.reject(check.record, check.after)
+        }
+    }
+}
+
+pub fn check_puzzle_moves(mut move_played_listener: EventReader<MovePlayed>, mut moves: PuzzleMoves) {
+    moves.finish_alternate_check();
+    for move_played in move_played_listener.read() {
+        let session = &moves.session;
+        let Some(puzzle) = session.puzzle() else { return };
+        if move_played.by != puzzle.solver() || session.finished() || session.reply.is_some() || session.checking.is_some() { continue };
+        let played = move_played.record.as_move();
+        let mates = matches!(move_played.after.status, GameStatus::Checkmate { .. });
+        let scripted = !session.off_script && puzzle.moves.get(session.progress) == Some(&played);
+        let solver_moves_left = puzzle.moves.len().saturating_sub(session.progress).div_ceil(2).saturating_sub(1);
+        if scripted || mates {
+            moves.accept(scripted, mates, None);
+            continue;
+        }
+        let job = puzzle.mate.then(|| spawn_alternate_check(&moves.board.0, solver_moves_left)).flatten();
+        match job {
+            Some(job) => moves.session.checking = Some(AlternateCheck {job, record: move_played.record, after: move_played.after, solver_moves_left}),
+            None => moves.reject(move_played.record, move_played.after)
+        }
+    }
+}
+
+pub fn shake_rejected_piece(
+    config: Res<ChessConfig>,
+    piece_entities: Res<PieceEntities>,
+    mut session: ResMut<PuzzleSession>,
+    mut commands: Commands
+) {
+    if session.rejected.is_none() { return };
+    let Some((from, to)) = session.rejected.take() else { return };
+    let Some(entity) = piece_entities.0.get(&from) else { return };
+    commands.entity(*entity).insert(Shake::from(config.square_to_vector(to)));
+}
+
+#[derive(Component, Clone, Copy, PartialEq)]
+pub enum PuzzleButton {
+    Next,
+    Quit
+}
+
+#[derive(Component)]
+pub struct PuzzlePanel;
+
+#[derive(Component)]
+pub struct PuzzleText;
+
+pub fn spawn_puzzle_panel(mut commands: Commands) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(72.0),
+            left: Val::Percent(30.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(6.0),
+            display: Display::None,
+            ..default()
+        },
+        background_color: Color::rgba(0.1, 0.1, 0.12, 0.85).into(),
+        z_index: ZIndex::Global(15),
+        ..default()
+    }, Interaction::default(), PuzzlePanel)).with_children(|panel| {
+        panel.spawn((TextBundle::from_section("", TextStyle { font_size: 18.0, color: Color::WHITE, ..default() }), PuzzleText));
+        panel.spawn(NodeBundle { style: Style { column_gap: Val::Px(6.0), ..default() }, ..default() }).with_children(|row| {
+            for (button, label) in [(PuzzleButton::Next, "Next puzzle"), (PuzzleButton::Quit, "Quit puzzles")] {
+                row.spawn((ButtonBundle {
+                    style: Style { padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)), ..default() },
+                    background_color: Color::rgb(0.25, 0.25, 0.3).into(),
+                    ..default()
+                }, button)).with_children(|button| {
+                    button.spawn(TextBundle::from_section(label, TextStyle { font_size: 16.0, color: Color::WHITE, ..default() }));
+                });
+            }
+        });
+    });
+}
+
+pub fn puzzle_buttons(
+    button_query: Query<(&Interaction, &PuzzleButton), Changed<Interaction>>,
+    mut session: ResMut<PuzzleSession>,
+    mut local_side: ResMut<LocalSide>,
+    mut toasts: ResMut<Toasts>,
+    mut new_game_writer: EventWriter<NewGame>
+) {
+    let Some(button) = button_query.iter().find(|(interaction, _)| **interaction == Interaction::Pressed).map(|(_, button)| *button) else { return };
+    let Some(index) = session.current.filter(|_| !session.timed) else { return };
+    if !session.finished() {
+        session.failed += 1;
+    }
+    let next = index + 1;
+    if button == PuzzleButton::Next && next < session.puzzles.len() {
+        session.start(next, &mut new_game_writer);
+        return;
+    }
+    toasts.push(ToastLevel::Info, format!("Puzzle session over: {}", session.summary()));
+    session.stop(&mut local_side);
+}
+
+pub fn update_puzzle_panel(
+    session: Res<PuzzleSession>,
+    mut panel_query: Query<&mut Style, With<PuzzlePanel>>,
+    mut text_query: Query<&mut Text, With<PuzzleText>>
+) {
+    for mut style in panel_query.iter_mut() {
+        style.display = if session.is_active() && !session.timed { Display::Flex } else { Display::None };
+    }
+    let Some(puzzle) = session.puzzle() else { return };
+    let side = if puzzle.solver() == PieceColor::WHITE { "White" } else { "Black" };
+    let feedback = match session.feedback {
+        PuzzleFeedback::FindMove => format!("Find the best move for {}", side),
+        PuzzleFeedback::Correct => String::from("Correct, keep going"),
+        PuzzleFeedback::Wrong => format!("Wrong move, try again ({} mistakes)", session.mistakes),
+        PuzzleFeedback::Solved => String::from("Solved!"),
+        PuzzleFeedback::Completed => format!("Completed with {} mistakes", session.mistakes)
+    };
+    let rating = puzzle.rating.map(|rating| format!(", rated {}", rating)).unwrap_or_default();
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = format!(
+            "Puzzle {} of {} ({}{})\n{}\nSession: {}",
+            session.current.unwrap_or_default() + 1, session.puzzles.len(), puzzle.id, rating, feedback, session.summary()
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chess_logic::search::search;
+    use super::*;
+
+    #[test]
+    fn reads_lichess_rows_and_plain_lines() {
+        let row = "00sHx,q3k1nr/1pp1nQpp/3p4/1P2p3/4P3/B1PP1b2/B5PP/5K2 b k - 0 17,e8d7 a2e6 d7d8 f7f8,1760";
+        let puzzle = Puzzle::parse(row).unwrap().unwrap();
+        assert_eq!((puzzle.id.as_str(), puzzle.rating), ("00sHx", Some(1760)));
+        assert_eq!(puzzle.moves.len(), 4);
+        assert!(puzzle.mate);
+        assert_eq!(puzzle.solver(), PieceColor::WHITE);
+
+        let line = Puzzle::parse("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1; e8d7 e2e4").unwrap().unwrap();
+        assert_eq!((line.id.as_str(), line.rating), ("", None));
+        assert!(!line.mate);
+    }
+
+    #[test]
+    fn skips_headers_comments_and_blank_lines() {
+        for line in ["", "   ", "# a comment", "PuzzleId,FEN,Moves,Rating"] {
+            assert!(Puzzle::parse(line).unwrap().is_none());
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_puzzles() {
+        assert!(Puzzle::parse("just one field").is_err());
+        assert!(Puzzle::parse("not a fen; e2e4 e7e5").is_err());
+        assert!(Puzzle::parse("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1; e8d7").is_err());
+        assert!(Puzzle::parse("4k3/8/8/8/8/8/4P3/4K3 b - - 0 1; e8d7 e2e5").is_err());
+        assert!(parse_puzzles("# nothing here\n").is_err());
+        let puzzles = parse_puzzles("bad line\n4k3/8/8/8/8/8/4P3/4K3 b - - 0 1; e8d7 e2e4\n").unwrap();
+        assert_eq!(puzzles[0].id, "#1");
+    }
+
+    #[test]
+    fn accepts_any_move_that_still_mates_in_time() {
+        // Black's only move is Ka7, after which Qb7 mates
+        let board = Board::from_fen("k7/8/2K5/8/8/8/8/1Q6 b - - 0 1").unwrap();
+        assert!(forced_mate_reply(search(&board, &SearchLimits::new(2)), 1).is_some());
+        assert!(forced_mate_reply(search(&board, &SearchLimits::new(2)), 0).is_none());
+        let board = Board::from_fen("k7/8/8/8/8/8/8/K6R b - - 0 1").unwrap();
+        assert!(forced_mate_reply(search(&board, &SearchLimits::new(2)), 1).is_none());
+    }
+}
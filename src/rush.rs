@@ -0,0 +1,344 @@
+use std::time::Duration;
+use bevy::prelude::*;
+use crate::board::{LocalSide, MenuState};
+use crate::clock::format_clock;
+use crate::piece::NewGame;
+use crate::puzzle::{PuzzleOutcome, PuzzleSession, read_puzzle_file};
+use crate::settings::Settings;
+use crate::stats::Stats;
+use crate::storage::StorageResource;
+use crate::toast::{ToastLevel, Toasts};
+
+pub const RUSH_MINUTES: [u64; 2] = [3, 5];
+pub const RUSH_STRIKES: [u32; 2] = [1, 3];
+
+pub struct RushResult {
+    pub score: u32,
+    pub best: u32,
+    pub new_best: bool,
+    pub missed: Vec<String>
+}
+
+#[derive(Resource, Default)]
+pub struct PuzzleRush {
+    pub queue: Vec<usize>,
+    pub next: usize,
+    pub minutes: u64,
+    pub remaining: Duration,
+    pub strikes_left: u32,
+    pub score: u32,
+    pub missed: Vec<usize>,
+    pub running: bool,
+    pub result: Option<RushResult>
+}
+
+impl PuzzleRush {
+    fn serve_next(&mut self, session: &mut PuzzleSession, new_game_writer: &mut EventWriter<NewGame>) -> bool {
+        let Some(&index) = self.queue.get(self.next) else { return false };
+        self.next += 1;
+        session.start(index, new_game_writer);
+        true
+    }
+
+    fn end(&mut self, session: &mut PuzzleSession, local_side: &mut LocalSide, stats: &mut Stats, storage: &StorageResource) {
+        self.running = false;
+        let new_best = self.score > stats.best_rush_streak;
+        if new_best {
+            stats.best_rush_streak = self.score;
+            stats.save(storage.0.as_ref());
+        }
+        let missed = self.missed.iter().filter_map(|index| session.puzzles.get(*index)).map(|puzzle| puzzle.id.clone()).collect();
+        self.result = Some(RushResult {score: self.score, best: stats.best_rush_streak, new_best, missed});
+        session.stop(local_side);
+    }
+}
+
+fn begin_rush(
+    minutes: u64,
+    settings: &Settings,
+    rush: &mut PuzzleRush,
+    session: &mut PuzzleSession,
+    toasts: &mut Toasts,
+    new_game_writer: &mut EventWriter<NewGame>
+) -> bool {
+    if session.puzzles.is_empty() {
+        let Some(path) = settings.puzzle_file.as_deref() else {
+            toasts.push(ToastLevel::Info, "Drop a puzzle file on the window before starting a rush");
+            return false;
+        };
+        match read_puzzle_file(path) {
+            Ok(puzzles) => session.puzzles = puzzles,
+            Err(error) => {
+                toasts.push(ToastLevel::Error, format!("Could not load puzzles: {}", error));
+                return false;
+            }
+        }
+    }
+    let mut queue: Vec<usize> = (0..session.puzzles.len()).collect();
+    queue.sort_by_key(|index| session.puzzles[*index].rating.unwrap_or_default());
+    *rush = PuzzleRush {
+        queue,
+        minutes,
+        remaining: Duration::from_secs(minutes * 60),
+        strikes_left: settings.rush_strikes.max(1),
+        running: true,
+        ..default()
+    };
+    session.solved = 0;
+    session.failed = 0;
+    session.timed = true;
+    rush.serve_next(session, new_game_writer)
+}
+
+#[derive(Component)]
+pub enum RushMenuButton {
+    Start(u64),
+    Strikes
+}
+
+fn strikes_label(strikes: u32) -> String {
+    format!("Rush strikes: {}", strikes)
+}
+
+pub fn spawn_rush_buttons(parent: &mut ChildBuilder, settings: &Settings) {
+    parent.spawn(NodeBundle {
+        style: Style {
+            width: Val::Px(360.0),
+            margin: UiRect::top(Val::Px(8.0)),
+            column_gap: Val::Px(4.0),
+            ..default()
+        },
+        ..default()
+    }).with_children(|row| {
+        let buttons = RUSH_MINUTES.map(|minutes| (RushMenuButton::Start(minutes), format!("{} min rush", minutes)));
+        for (button, label) in buttons.into_iter().chain([(RushMenuButton::Strikes, strikes_label(settings.rush_strikes))]) {
+            row.spawn((ButtonBundle {
+                style: Style {
+                    flex_grow: 1.0,
+                    padding: UiRect::all(Val::Px(4.0)),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                background_color: Color::rgb(0.25, 0.25, 0.3).into(),
+                ..default()
+            }, button)).with_children(|button| {
+                button.spawn(TextBundle::from_section(label, TextStyle { font_size: 16.0, color: Color::WHITE, ..default() }));
+            });
+        }
+    });
+}
+
+pub fn rush_menu_buttons(
+    button_query: Query<(&Interaction, &RushMenuButton, &Children), Changed<Interaction>>,
+    mut text_query: Query<&mut Text>,
+    mut settings: ResMut<Settings>,
+    mut rush: ResMut<PuzzleRush>,
+    mut session: ResMut<PuzzleSession>,
+    mut toasts: ResMut<Toasts>,
+    mut next_menu: ResMut<NextState<MenuState>>,
+    mut new_game_writer: EventWriter<NewGame>
+) {
+    for (interaction, button, children) in button_query.iter() {
+        if *interaction != Interaction::Pressed { continue };
+        match button {
+            RushMenuButton::Start(minutes) => {
+                if begin_rush(*minutes, &settings, &mut rush, &mut session, &mut toasts, &mut new_game_writer) {
+                    next_menu.set(MenuState::Closed);
+                }
+            }
+            RushMenuButton::Strikes => {
+                let current = RUSH_STRIKES.iter().position(|strikes| *strikes == settings.rush_strikes).unwrap_or_default();
+                settings.rush_strikes = RUSH_STRIKES[(current + 1) % RUSH_STRIKES.len()];
+                let mut texts = text_query.iter_many_mut(children);
+                while let Some(mut text) = texts.fetch_next() {
+                    text.sections[0].value = strikes_label(settings.rush_strikes);
+                }
+            }
+        }
+    }
+}
+
+pub fn update_rush(
+    time: Res<Time>,
+    menu: Res<State<MenuState>>,
+    mut outcome_listener: EventReader<PuzzleOutcome>,
+    mut rush: ResMut<PuzzleRush>,
+    mut session: ResMut<PuzzleSession>,
+    mut local_side: ResMut<LocalSide>,
+    mut stats: ResMut<Stats>,
+    storage: Res<StorageResource>,
+    mut new_game_writer: EventWriter<NewGame>
+) {
+    if !rush.running { return };
+    if !session.timed {
+        rush.running = false;
+        return;
+    }
+    for outcome in outcome_listener.read() {
+        match outcome {
+            PuzzleOutcome::Mistake => {
+                if let Some(index) = session.current {
+                    rush.missed.push(index);
+                }
+                rush.strikes_left = rush.strikes_left.saturating_sub(1);
+            }
+            PuzzleOutcome::Solved | PuzzleOutcome::Completed => rush.score += 1
+        }
+        if rush.strikes_left == 0 || !rush.serve_next(&mut session, &mut new_game_writer) {
+            rush.end(&mut session, &mut local_side, &mut stats, &storage);
+            return;
+        }
+    }
+    if *menu.get() == MenuState::Open { return };
+    rush.remaining = rush.remaining.saturating_sub(time.delta());
+    if rush.remaining.is_zero() {
+        rush.end(&mut session, &mut local_side, &mut stats, &storage);
+    }
+}
+
+#[derive(Component)]
+pub struct RushBanner;
+
+#[derive(Component)]
+pub struct RushBannerText;
+
+#[derive(Component, Clone, Copy, PartialEq, Eq)]
+pub enum RushButton {
+    Stop,
+    Again,
+    Close
+}
+
+impl RushButton {
+    fn label(&self) -> &'static str {
+        match self {
+            RushButton::Stop => "Stop",
+            RushButton::Again => "Play again",
+            RushButton::Close => "Close"
+        }
+    }
+}
+
+fn spawn_rush_button(parent: &mut ChildBuilder, button: RushButton) {
+    parent.spawn((ButtonBundle {
+        style: Style { padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)), ..default() },
+        background_color: Color::rgb(0.25, 0.25, 0.3).into(),
+        ..default()
+    }, button)).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(button.label(), TextStyle { font_size: 16.0, color: Color::WHITE, ..default() }));
+    });
+}
+
+pub fn spawn_rush_banner(mut commands: Commands) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(72.0),
+            left: Val::Percent(30.0),
+            padding: UiRect::all(Val::Px(8.0)),
+            column_gap: Val::Px(12.0),
+            align_items: AlignItems::Center,
+            display: Display::None,
+            ..default()
+        },
+        background_color: Color::rgba(0.1, 0.1, 0.12, 0.85).into(),
+        z_index: ZIndex::Global(15),
+        ..default()
+    }, Interaction::default(), RushBanner)).with_children(|banner| {
+        banner.spawn((TextBundle::from_section("", TextStyle { font_size: 20.0, color: Color::WHITE, ..default() }), RushBannerText));
+        spawn_rush_button(banner, RushButton::Stop);
+    });
+}
+
+pub fn update_rush_banner(
+    rush: Res<PuzzleRush>,
+    session: Res<PuzzleSession>,
+    mut banner_query: Query<&mut Style, With<RushBanner>>,
+    mut text_query: Query<&mut Text, With<RushBannerText>>
+) {
+    for mut style in banner_query.iter_mut() {
+        let display = if rush.running { Display::Flex } else { Display::None };
+        if style.display != display {
+            style.display = display;
+        }
+    }
+    if !rush.running { return };
+    let side = match session.puzzle().map(|puzzle| puzzle.solver()) {
+        Some(chess_logic::logic::PieceColor::BLACK) => "Black",
+        _ => "White"
+    };
+    let label = format!("{}   Score {}   Strikes left {}   {} to move", format_clock(rush.remaining), rush.score, rush.strikes_left, side);
+    for mut text in text_query.iter_mut() {
+        if text.sections[0].value != label {
+            text.sections[0].value = label.clone();
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct RushResults;
+
+pub fn update_rush_results(mut commands: Commands, rush: Res<PuzzleRush>, results_query: Query<Entity, With<RushResults>>) {
+    for entity in results_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some(result) = &rush.result else { return };
+    let mut summary = format!("Puzzle rush over\n\nStreak: {}\nBest: {}{}", result.score, result.best, if result.new_best { " (new best!)" } else { "" });
+    if !result.missed.is_empty() {
+        summary.push_str(&format!("\nMissed: {}", result.missed.join(", ")));
+    }
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        z_index: ZIndex::Global(16),
+        ..default()
+    }, Interaction::default(), RushResults)).with_children(|overlay| {
+        overlay.spawn(NodeBundle {
+            style: Style {
+                flex_direction: FlexDirection::Column,
+                align_items: AlignItems::Center,
+                padding: UiRect::all(Val::Px(16.0)),
+                row_gap: Val::Px(8.0),
+                ..default()
+            },
+            background_color: Color::rgba(0.0, 0.0, 0.0, 0.85).into(),
+            ..default()
+        }).with_children(|dialog| {
+            dialog.spawn(TextBundle::from_section(summary, TextStyle { font_size: 24.0, color: Color::WHITE, ..default() })
+                .with_text_justify(JustifyText::Center));
+            dialog.spawn(NodeBundle { style: Style { column_gap: Val::Px(8.0), ..default() }, ..default() }).with_children(|buttons| {
+                spawn_rush_button(buttons, RushButton::Again);
+                spawn_rush_button(buttons, RushButton::Close);
+            });
+        });
+    });
+}
+
+pub fn rush_buttons(
+    button_query: Query<(&Interaction, &RushButton), Changed<Interaction>>,
+    settings: Res<Settings>,
+    mut rush: ResMut<PuzzleRush>,
+    mut session: ResMut<PuzzleSession>,
+    mut local_side: ResMut<LocalSide>,
+    mut stats: ResMut<Stats>,
+    storage: Res<StorageResource>,
+    mut toasts: ResMut<Toasts>,
+    mut new_game_writer: EventWriter<NewGame>
+) {
+    let Some(button) = button_query.iter().find(|(interaction, _)| **interaction == Interaction::Pressed).map(|(_, button)| *button) else { return };
+    match button {
+        RushButton::Stop if rush.running => rush.end(&mut session, &mut local_side, &mut stats, &storage),
+        RushButton::Again => {
+            let minutes = rush.minutes;
+            begin_rush(minutes, &settings, &mut rush, &mut session, &mut toasts, &mut new_game_writer);
+        }
+        RushButton::Close => rush.result = None,
+        RushButton::Stop => {}
+    }
+}
@@ -0,0 +1,190 @@
+use std::time::Duration;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::config::ChessConfig;
+use crate::opponent::EngineSpec;
+use crate::piece::CheckAnimationTimer;
+use crate::storage::{Storage, StorageResource};
+
+const SETTINGS_KEY: &str = "settings.ron";
+const SAVE_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(default)]
+pub struct TimeControl {
+    pub base_seconds: u64,
+    pub increment_seconds: u64,
+    pub delay_seconds: u64
+}
+
+impl TimeControl {
+    pub const PRESETS: [(&'static str, Option<TimeControl>); 5] = [
+        ("1+0", Some(TimeControl {base_seconds: 60, increment_seconds: 0, delay_seconds: 0})),
+        ("3+2", Some(TimeControl {base_seconds: 180, increment_seconds: 2, delay_seconds: 0})),
+        ("5+0", Some(TimeControl {base_seconds: 300, increment_seconds: 0, delay_seconds: 0})),
+        ("10+5", Some(TimeControl {base_seconds: 600, increment_seconds: 5, delay_seconds: 0})),
+        ("unlimited", None)
+    ];
+
+    pub fn pgn_tag(control: Option<TimeControl>) -> String {
+        match control {
+            Some(control) if control.increment_seconds > 0 => format!("{}+{}", control.base_seconds, control.increment_seconds),
+            Some(control) => control.base_seconds.to_string(),
+            None => String::from("-")
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum WindowModeSetting {
+    #[default]
+    Windowed,
+    Fullscreen
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum HotseatFlipSetting {
+    #[default]
+    Off,
+    Delayed,
+    PassScreen
+}
+
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct Settings {
+    pub board_theme: String,
+    pub piece_set: String,
+    pub volume: f32,
+    pub move_volume: f32,
+    pub ui_volume: f32,
+    pub alert_volume: f32,
+    pub muted: bool,
+    pub auto_queen: Option<bool>,
+    pub engine_path: Option<String>,
+    pub time_control: Option<TimeControl>,
+    pub window_mode: WindowModeSetting,
+    pub ui_scale: Option<f32>,
+    pub snapshot_size: u32,
+    pub puzzle_file: Option<String>,
+    pub rush_strikes: u32,
+    pub hotseat_flip: HotseatFlipSetting,
+    pub hotseat_delay_millis: u64,
+    pub check_blink_millis: Option<u64>,
+    pub show_legal_moves: bool,
+    pub show_coordinates: bool,
+    pub reduced_motion: bool,
+    pub colorblind_palette: bool,
+    pub preview_opponent_moves: bool,
+    pub allow_hints: bool,
+    pub draw_accept_threshold: i32,
+    pub show_defended_squares: bool,
+    pub show_eval_bar: bool,
+    pub engine_evaluation: bool,
+    pub attract_mode: bool,
+    pub match_engines: [EngineSpec; 2],
+    pub engine_ponder: bool,
+    pub analysis_engine: EngineSpec,
+    pub archive_games: bool,
+    pub archive_directory: Option<String>
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            board_theme: String::from("classic"),
+            piece_set: String::from("default"),
+            volume: 1.0,
+            move_volume: 1.0,
+            ui_volume: 1.0,
+            alert_volume: 1.0,
+            muted: false,
+            auto_queen: None,
+            engine_path: None,
+            time_control: None,
+            window_mode: WindowModeSetting::Windowed,
+            ui_scale: None,
+            snapshot_size: 1024,
+            puzzle_file: None,
+            rush_strikes: 1,
+            hotseat_flip: HotseatFlipSetting::Off,
+            hotseat_delay_millis: 1000,
+            check_blink_millis: None,
+            show_legal_moves: true,
+            show_coordinates: true,
+            reduced_motion: false,
+            colorblind_palette: false,
+            preview_opponent_moves: false,
+            allow_hints: true,
+            draw_accept_threshold: 50,
+            show_defended_squares: false,
+            show_eval_bar: true,
+            engine_evaluation: true,
+            attract_mode: true,
+            match_engines: [EngineSpec::Bot {level: 2, elo: None}, EngineSpec::Bot {level: 4, elo: None}],
+            engine_ponder: false,
+            analysis_engine: EngineSpec::Bot {level: 6, elo: None},
+            archive_games: true,
+            archive_directory: None
+        }
+    }
+}
+
+impl Settings {
+    pub fn load(storage: &dyn Storage) -> Self {
+        let Some(contents) = storage.load(SETTINGS_KEY) else { return Settings::default() };
+        ron::from_str(&contents).unwrap_or_else(|error| {
+            warn!("failed to parse {}, using default settings: {}", SETTINGS_KEY, error);
+            Settings::default()
+        })
+    }
+
+    pub fn save(&self, storage: &dyn Storage) {
+        let contents = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => contents,
+            Err(error) => { warn!("failed to serialize settings: {}", error); return }
+        };
+        if let Err(error) = storage.save(SETTINGS_KEY, &contents) {
+            warn!("failed to save settings: {}", error);
+        }
+    }
+
+    pub fn apply_to(&self, config: &mut ChessConfig) {
+        if let Some(auto_queen) = self.auto_queen {
+            config.auto_queen = auto_queen;
+        }
+        if let Some(millis) = self.check_blink_millis {
+            config.check_blink_interval = Duration::from_millis(millis.max(1));
+        }
+    }
+}
+
+pub fn save_settings(
+    time: Res<Time>,
+    settings: Res<Settings>,
+    storage: Res<StorageResource>,
+    mut debounce: Local<Option<Timer>>
+) {
+    if settings.is_changed() && !settings.is_added() {
+        *debounce = Some(Timer::new(SAVE_DELAY, TimerMode::Once));
+    }
+    let Some(timer) = debounce.as_mut() else { return };
+    if timer.tick(time.delta()).finished() {
+        settings.save(storage.0.as_ref());
+        *debounce = None;
+    }
+}
+
+pub fn apply_settings(settings: Res<Settings>, mut config: ResMut<ChessConfig>) {
+    let mut updated = config.clone();
+    settings.apply_to(&mut updated);
+    if updated.auto_queen != config.auto_queen || updated.check_blink_interval != config.check_blink_interval {
+        *config = updated;
+    }
+}
+
+pub fn apply_check_blink_interval(config: Res<ChessConfig>, mut check_animation_timer: ResMut<CheckAnimationTimer>) {
+    if check_animation_timer.0.duration() != config.check_blink_interval {
+        check_animation_timer.0.set_duration(config.check_blink_interval);
+    }
+}
@@ -0,0 +1,235 @@
+use bevy::prelude::*;
+use chess_logic::logic::PieceColor;
+use chess_logic::search::{search, SearchLimits};
+use crate::assets::PieceSets;
+use crate::audio::spawn_volume_sliders;
+use crate::display::spawn_ui_scale_buttons;
+use crate::editor::spawn_open_editor_button;
+use crate::board::{BoardOrientation, BoardResource, DrawAccepted, GamePhase, LocalSide, MenuState, ResignRequested};
+use crate::engine::HintRequested;
+use crate::highlight::AttackOverlay;
+use crate::hotseat::spawn_hotseat_toggle;
+use crate::history::TakebackRequested;
+use crate::piece::{MovePlayed, NewGame, Selection};
+use crate::players::spawn_name_fields;
+use crate::puzzle::spawn_puzzle_button;
+use crate::rush::spawn_rush_buttons;
+use crate::settings::Settings;
+use crate::theme::{spawn_palette_toggle, spawn_piece_set_buttons, spawn_theme_buttons};
+use crate::toast::{ToastLevel, Toasts};
+
+#[derive(Resource, Default)]
+pub struct TextInputFocused(pub bool);
+
+pub fn text_input_idle(text_input_focused: Res<TextInputFocused>) -> bool {
+    !text_input_focused.0
+}
+
+
+pub fn keyboard_shortcuts(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    text_input_focused: Res<TextInputFocused>,
+    mut orientation: ResMut<BoardOrientation>,
+    mut attack_overlay: ResMut<AttackOverlay>,
+    mut selection: ResMut<Selection>,
+    resign_prompt: Res<ResignPrompt>,
+    menu_state: Res<State<MenuState>>,
+    phase: Res<State<GamePhase>>,
+    mut next_menu_state: ResMut<NextState<MenuState>>,
+    mut new_game_writer: EventWriter<NewGame>,
+    mut takeback_writer: EventWriter<TakebackRequested>,
+    mut hint_writer: EventWriter<HintRequested>
+) {
+    if text_input_focused.0 { return };
+    let control = keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight, KeyCode::SuperLeft, KeyCode::SuperRight]);
+    let dragging = mouse_button.pressed(MouseButton::Left);
+
+    if control { return };
+    if keyboard.just_pressed(KeyCode::Escape) && selection.0.is_some() {
+        selection.0 = None;
+    } else if keyboard.just_pressed(KeyCode::Escape) && !dragging && *phase.get() != GamePhase::PromotionPending && resign_prompt.0.is_none() {
+        next_menu_state.set(match menu_state.get() {
+            MenuState::Closed => MenuState::Open,
+            MenuState::Open => MenuState::Closed
+        });
+    }
+    if keyboard.just_pressed(KeyCode::KeyN) && *phase.get() != GamePhase::PromotionPending {
+        new_game_writer.send(NewGame::standard());
+        next_menu_state.set(MenuState::Closed);
+    }
+    if keyboard.just_pressed(KeyCode::Backspace) && !dragging {
+        takeback_writer.send(TakebackRequested);
+        next_menu_state.set(MenuState::Closed);
+    }
+    if keyboard.just_pressed(KeyCode::KeyH) {
+        hint_writer.send(HintRequested);
+    }
+    if keyboard.just_pressed(KeyCode::KeyT) {
+        attack_overlay.0 = !attack_overlay.0;
+    }
+    if keyboard.just_pressed(KeyCode::KeyF) && !dragging {
+        orientation.0 = orientation.0.opposite();
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct ResignPrompt(pub Option<PieceColor>);
+
+#[derive(Component)]
+pub struct ResignPromptText;
+
+pub fn resign_shortcut(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    text_input_focused: Res<TextInputFocused>,
+    board: Res<BoardResource>,
+    local_side: Res<LocalSide>,
+    phase: Res<State<GamePhase>>,
+    mut resign_prompt: ResMut<ResignPrompt>,
+    mut resign_writer: EventWriter<ResignRequested>
+) {
+    if *phase.get() != GamePhase::Playing {
+        if resign_prompt.0.is_some() { resign_prompt.0 = None };
+        return;
+    }
+    if text_input_focused.0 { return };
+    if resign_prompt.0.is_some() && keyboard.just_pressed(KeyCode::Escape) {
+        resign_prompt.0 = None;
+        return;
+    }
+    if !keyboard.just_pressed(KeyCode::KeyR) { return };
+    match resign_prompt.0.take() {
+        Some(color) => { resign_writer.send(ResignRequested(color)); }
+        None => resign_prompt.0 = Some(local_side.0.unwrap_or(board.0.on_move))
+    }
+}
+
+pub fn update_resign_prompt(mut commands: Commands, resign_prompt: Res<ResignPrompt>, prompt_query: Query<Entity, With<ResignPromptText>>) {
+    for entity in prompt_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some(color) = resign_prompt.0 else { return };
+    commands.spawn((TextBundle::from_section(
+        format!("Resign as {}? R - confirm, Esc - cancel", color),
+        TextStyle { font_size: 24.0, color: Color::WHITE, ..default() }
+    ).with_style(Style {
+        position_type: PositionType::Absolute,
+        top: Val::Px(12.0),
+        left: Val::Px(12.0),
+        ..default()
+    }).with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.7)), ResignPromptText));
+}
+
+#[derive(Resource, Default)]
+pub struct DrawOffer(pub Option<PieceColor>);
+
+#[derive(Component)]
+pub struct DrawOfferText;
+
+pub fn draw_offer_shortcut(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    text_input_focused: Res<TextInputFocused>,
+    board: Res<BoardResource>,
+    local_side: Res<LocalSide>,
+    phase: Res<State<GamePhase>>,
+    mut draw_offer: ResMut<DrawOffer>,
+    mut toasts: ResMut<Toasts>,
+    mut draw_accepted_writer: EventWriter<DrawAccepted>
+) {
+    if *phase.get() == GamePhase::GameOver && draw_offer.0.is_some() {
+        draw_offer.0 = None;
+    }
+    if text_input_focused.0 || *phase.get() != GamePhase::Playing { return };
+    if keyboard.just_pressed(KeyCode::KeyD) && draw_offer.0.is_none() && local_side.controls(board.0.on_move) {
+        draw_offer.0 = Some(board.0.on_move);
+        toasts.push(ToastLevel::Info, "Draw offered");
+    }
+    let Some(offered_by) = draw_offer.0 else { return };
+    if keyboard.just_pressed(KeyCode::KeyA) && board.0.on_move == offered_by.opposite() && local_side.controls(board.0.on_move) {
+        draw_accepted_writer.send(DrawAccepted);
+        draw_offer.0 = None;
+    }
+}
+
+pub fn clear_declined_draw_offer(mut move_played_listener: EventReader<MovePlayed>, mut draw_offer: ResMut<DrawOffer>) {
+    for move_played in move_played_listener.read() {
+        if draw_offer.0.is_some_and(|offered_by| offered_by != move_played.by) {
+            draw_offer.0 = None;
+        }
+    }
+}
+
+pub fn answer_draw_offer(
+    settings: Res<Settings>,
+    board: Res<BoardResource>,
+    local_side: Res<LocalSide>,
+    mut draw_offer: ResMut<DrawOffer>,
+    mut draw_accepted_writer: EventWriter<DrawAccepted>
+) {
+    let Some(offered_by) = draw_offer.0 else { return };
+    if local_side.0 != Some(offered_by) { return };
+    let Some(result) = search(&board.0, &SearchLimits::new(2)) else { return };
+    let score = if board.0.on_move == offered_by { -result.score } else { result.score };
+    if score.abs() <= settings.draw_accept_threshold {
+        draw_accepted_writer.send(DrawAccepted);
+    } else {
+        info!("draw offer declined, evaluation {}", score);
+    }
+    draw_offer.0 = None;
+}
+
+pub fn update_draw_offer_prompt(mut commands: Commands, draw_offer: Res<DrawOffer>, local_side: Res<LocalSide>, prompt_query: Query<Entity, With<DrawOfferText>>) {
+    for entity in prompt_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+    let Some(offered_by) = draw_offer.0.filter(|offered_by| local_side.controls(offered_by.opposite())) else { return };
+    commands.spawn((TextBundle::from_section(
+        format!("{} offers a draw. A - accept, or move to decline", offered_by),
+        TextStyle { font_size: 24.0, color: Color::WHITE, ..default() }
+    ).with_style(Style {
+        position_type: PositionType::Absolute,
+        top: Val::Px(12.0),
+        right: Val::Px(12.0),
+        ..default()
+    }).with_background_color(Color::rgba(0.0, 0.0, 0.0, 0.7)), DrawOfferText));
+}
+
+#[derive(Component)]
+pub struct PauseMenu;
+
+pub fn spawn_pause_menu(mut commands: Commands, piece_sets: Res<PieceSets>, settings: Res<Settings>) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            width: Val::Percent(100.0),
+            height: Val::Percent(100.0),
+            justify_content: JustifyContent::Center,
+            align_items: AlignItems::Center,
+            flex_direction: FlexDirection::Column,
+            ..default()
+        },
+        background_color: Color::rgba(0.0, 0.0, 0.0, 0.6).into(),
+        z_index: ZIndex::Global(10),
+        ..default()
+    }, PauseMenu)).with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            "Paused\n\nEsc - resume\nN - new game\nBackspace - take back\nH - hint\nR - resign\nD - offer draw\nF - flip board\nT - attacked squares\nCtrl+C - copy FEN\nCtrl+Shift+C - copy PGN\nCtrl+V - paste FEN\nP - save board image\nX - analysis mode\nE - engine vs engine match\nM - mute\nHome/End - first move/back to the game\nLeft/Right - step through earlier moves\nCtrl+Home - reset board zoom\n\nNew game: 1 - 1+0, 2 - 3+2, 3 - 5+0, 4 - 10+5, 5 - unlimited",
+            TextStyle { font_size: 28.0, color: Color::WHITE, ..default() }
+        ).with_text_justify(JustifyText::Center));
+        spawn_name_fields(parent);
+        spawn_theme_buttons(parent);
+        spawn_palette_toggle(parent, &settings);
+        spawn_hotseat_toggle(parent, &settings);
+        spawn_piece_set_buttons(parent, &piece_sets);
+        spawn_ui_scale_buttons(parent);
+        spawn_open_editor_button(parent);
+        spawn_puzzle_button(parent);
+        spawn_rush_buttons(parent, &settings);
+        spawn_volume_sliders(parent);
+    });
+}
+
+pub fn despawn_pause_menu(mut commands: Commands, menu_query: Query<Entity, With<PauseMenu>>) {
+    for entity in menu_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
@@ -0,0 +1,199 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+use ab_glyph::{Font as _, FontArc, PxScale, ScaleFont as _};
+use bevy::prelude::*;
+use chess_logic::logic::{Board, Coordinate, PieceColor, PieceKind};
+use image::{imageops, Rgba, RgbaImage};
+use crate::assets::PieceAssets;
+use crate::board::{BoardOrientation, BoardResource};
+use crate::highlight::{blend, HighlightKind, HighlightPalette};
+use crate::history::{HistoryView, MoveHistory};
+use crate::settings::Settings;
+use crate::theme::BoardTheme;
+use crate::toast::{ToastLevel, Toasts};
+
+const MIN_SNAPSHOT_SIZE: u32 = 128;
+const MAX_SNAPSHOT_SIZE: u32 = 4096;
+
+#[derive(Event)]
+pub struct SnapshotRequested;
+
+pub fn snapshot_shortcut(keyboard: Res<ButtonInput<KeyCode>>, mut snapshot_writer: EventWriter<SnapshotRequested>) {
+    if keyboard.just_pressed(KeyCode::KeyP) {
+        snapshot_writer.send(SnapshotRequested);
+    }
+}
+
+fn pixel(color: Color) -> Rgba<u8> {
+    Rgba(color.as_rgba_u8())
+}
+
+fn draw_text(canvas: &mut RgbaImage, font: &FontArc, text: &str, size: f32, origin: Vec2, color: Color) {
+    let scale = PxScale::from(size);
+    let scaled = font.as_scaled(scale);
+    let Rgba([red, green, blue, _]) = pixel(color);
+    let mut caret = origin.x;
+    for character in text.chars() {
+        let glyph = font.glyph_id(character).with_scale_and_position(scale, ab_glyph::point(caret, origin.y + scaled.ascent()));
+        caret += scaled.h_advance(glyph.id);
+        let Some(outlined) = font.outline_glyph(glyph) else { continue };
+        let bounds = outlined.px_bounds();
+        outlined.draw(|x, y, coverage| {
+            let (x, y) = (bounds.min.x as i64 + x as i64, bounds.min.y as i64 + y as i64);
+            if x < 0 || y < 0 || x >= canvas.width() as i64 || y >= canvas.height() as i64 { return };
+            let target = canvas.get_pixel_mut(x as u32, y as u32);
+            let alpha = coverage.clamp(0.0, 1.0);
+            for (channel, value) in target.0.iter_mut().zip([red, green, blue]) {
+                *channel = (*channel as f32 * (1.0 - alpha) + value as f32 * alpha).round() as u8;
+            }
+        });
+    }
+}
+
+fn piece_image(
+    piece_assets: &PieceAssets,
+    images: &Assets<Image>,
+    layouts: &Assets<TextureAtlasLayout>,
+    color: PieceColor,
+    kind: PieceKind,
+    size: u32
+) -> Option<RgbaImage> {
+    if piece_assets.is_missing(color, kind) { return None };
+    let source = images.get(piece_assets.get(color, kind))?.clone().try_into_dynamic().ok()?.to_rgba8();
+    let source = match piece_assets.sprite_rect(color, kind, layouts) {
+        Some(rect) => imageops::crop_imm(&source, rect.min.x as u32, rect.min.y as u32, rect.width() as u32, rect.height() as u32).to_image(),
+        None => source
+    };
+    Some(imageops::resize(&source, size, size, imageops::FilterType::Triangle))
+}
+
+pub fn render_board_image(
+    board: &Board,
+    last_move: Option<(Coordinate, Coordinate)>,
+    orientation: PieceColor,
+    theme: &BoardTheme,
+    palette: &HighlightPalette,
+    piece_assets: &PieceAssets,
+    images: &Assets<Image>,
+    layouts: &Assets<TextureAtlasLayout>,
+    font: Option<&FontArc>,
+    size: u32
+) -> RgbaImage {
+    let square = size / 8;
+    let mut canvas = RgbaImage::new(square * 8, square * 8);
+    let to_screen = |coordinate: Coordinate| match orientation {
+        PieceColor::WHITE => (coordinate.0 as u32, 7 - coordinate.1 as u32),
+        PieceColor::BLACK => (7 - coordinate.0 as u32, coordinate.1 as u32)
+    };
+    for file in 0..8 {
+        for rank in 0..8 {
+            let coordinate = Coordinate(file, rank);
+            let base = if (file + rank) % 2 == 0 { theme.dark } else { theme.light };
+            let highlighted = last_move.is_some_and(|(from, to)| from == coordinate || to == coordinate);
+            let color = if highlighted { blend(base, palette.color(HighlightKind::LastMove)) } else { base };
+            let (column, row) = to_screen(coordinate);
+            imageops::replace(&mut canvas, &RgbaImage::from_pixel(square, square, pixel(color)), (column * square) as i64, (row * square) as i64);
+            let Some(font) = font else { continue };
+            let label_color = if (file + rank) % 2 == 0 { theme.light } else { theme.dark };
+            let label_size = square as f32 * 0.22;
+            let corner = Vec2::new((column * square) as f32, (row * square) as f32);
+            if row == 7 {
+                let label = ((b'a' + file as u8) as char).to_string();
+                draw_text(&mut canvas, font, &label, label_size, corner + Vec2::new(square as f32 - label_size * 0.75, square as f32 - label_size * 1.1), label_color);
+            }
+            if column == 0 {
+                draw_text(&mut canvas, font, &(rank + 1).to_string(), label_size, corner + Vec2::splat(label_size * 0.2), label_color);
+            }
+        }
+    }
+    for piece in board.pieces.values() {
+        let (column, row) = to_screen(piece.square);
+        let (x, y) = ((column * square) as i64, (row * square) as i64);
+        match piece_image(piece_assets, images, layouts, piece.color, piece.kind, square) {
+            Some(sprite) => imageops::overlay(&mut canvas, &sprite, x, y),
+            None => {
+                let Some(font) = font else { continue };
+                let glyph = piece.kind.to_fen_char(piece.color).to_string();
+                let color = if piece.color == PieceColor::WHITE { Color::WHITE } else { Color::BLACK };
+                draw_text(&mut canvas, font, &glyph, square as f32 * 0.7, Vec2::new(x as f32 + square as f32 * 0.3, y as f32 + square as f32 * 0.1), color);
+            }
+        }
+    }
+    canvas
+}
+
+fn snapshot_name(board: &Board) -> String {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|elapsed| elapsed.as_secs()).unwrap_or_default();
+    format!("chess-{}-move{}.png", timestamp, board.turn_number / 2 + 1)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn snapshot_dir() -> PathBuf {
+    let home = std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")).map(PathBuf::from);
+    std::env::var_os("XDG_PICTURES_DIR").map(PathBuf::from)
+        .into_iter()
+        .chain(home.iter().flat_map(|home| [home.join("Pictures"), home.join("Downloads")]))
+        .find(|dir| dir.is_dir())
+        .unwrap_or_else(|| PathBuf::from("."))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn save_snapshot(canvas: &RgbaImage, name: &str) -> Result<String, String> {
+    let path = snapshot_dir().join(name);
+    canvas.save(&path).map_err(|error| error.to_string())?;
+    Ok(path.display().to_string())
+}
+
+#[cfg(target_arch = "wasm32")]
+fn save_snapshot(canvas: &RgbaImage, name: &str) -> Result<String, String> {
+    use wasm_bindgen::JsCast;
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    canvas.write_to(&mut bytes, image::ImageOutputFormat::Png).map_err(|error| error.to_string())?;
+    let array = js_sys::Uint8Array::from(bytes.get_ref().as_slice());
+    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(
+        &js_sys::Array::of1(&array),
+        web_sys::BlobPropertyBag::new().type_("image/png")
+    ).map_err(|error| format!("{:?}", error))?;
+    let url = web_sys::Url::create_object_url_with_blob(&blob).map_err(|error| format!("{:?}", error))?;
+    let document = web_sys::window().and_then(|window| window.document()).ok_or("no browser document")?;
+    let anchor: web_sys::HtmlAnchorElement = document.create_element("a")
+        .map_err(|error| format!("{:?}", error))?
+        .dyn_into()
+        .map_err(|_| String::from("could not create a download link"))?;
+    anchor.set_href(&url);
+    anchor.set_download(name);
+    anchor.click();
+    let _ = web_sys::Url::revoke_object_url(&url);
+    Ok(name.to_string())
+}
+
+pub fn export_snapshot(
+    mut snapshot_listener: EventReader<SnapshotRequested>,
+    settings: Res<Settings>,
+    board: Res<BoardResource>,
+    view: Res<HistoryView>,
+    history: Res<MoveHistory>,
+    orientation: Res<BoardOrientation>,
+    theme: Res<BoardTheme>,
+    palette: Res<HighlightPalette>,
+    piece_assets: Res<PieceAssets>,
+    images: Res<Assets<Image>>,
+    layouts: Res<Assets<TextureAtlasLayout>>,
+    fonts: Res<Assets<Font>>,
+    mut toasts: ResMut<Toasts>
+) {
+    if snapshot_listener.read().count() == 0 { return };
+    let shown = view.displayed(&board.0);
+    let last_move = history.0[..view.ply.unwrap_or(history.0.len())].last().map(|played| (played.record.from, played.record.to));
+    let font = fonts.get(Handle::<Font>::default()).map(|font| &font.font);
+    let size = settings.snapshot_size.clamp(MIN_SNAPSHOT_SIZE, MAX_SNAPSHOT_SIZE);
+    let canvas = render_board_image(shown, last_move, orientation.0, &theme, &palette, &piece_assets, &images, &layouts, font, size);
+    match save_snapshot(&canvas, &snapshot_name(shown)) {
+        Ok(path) => toasts.push(ToastLevel::Success, format!("Saved image to {}", path)),
+        Err(error) => {
+            warn!("failed to export image: {}", error);
+            toasts.push(ToastLevel::Error, format!("Could not save image: {}", error));
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use std::collections::VecDeque;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::BufRead;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::Mutex;
+#[cfg(not(target_arch = "wasm32"))]
+use std::sync::mpsc::{channel, Receiver, Sender};
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread;
+use std::time::Duration;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use chess_logic::logic::{Board, GameStatus, PieceColor, PieceKind};
+use crate::board::{BoardResource, DrawAccepted, GamePhase, GameStatusRes, LocalSide, ResignRequested};
+use crate::clock::ChessClock;
+use crate::editor::EditorState;
+use crate::history::{history_moves, HistoryView, MoveHistory, replay_game, StartingPosition};
+use crate::network::NetworkSession;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::network::watch_game;
+use crate::pgn::{parse_pgn, PgnGame};
+use crate::piece::{BoardUpdate, MovePlayed, MoveRequested, NewGame, Selection};
+use crate::players::{GameMetadata, PlayerInfo};
+use crate::settings::{Settings, TimeControl};
+use crate::toast::{ToastLevel, Toasts};
+
+const ATTRACT_GAME: &str = "[White \"Paul Morphy\"]\n[Black \"Duke Karl / Count Isouard\"]\n[Result \"1-0\"]\n\n\
+    1. e4 e5 2. Nf3 d6 3. d4 Bg4 4. dxe5 Bxf3 5. Qxf3 dxe5 6. Bc4 Nf6 7. Qb3 Qe7 8. Nc3 c6 9. Bg5 b5 \
+    10. Nxb5 cxb5 11. Bxb5+ Nbd7 12. O-O-O Rd8 13. Rxd7 Rxd7 14. Rd1 Qe6 15. Bxd7+ Nxd7 16. Qb8+ Nxb8 17. Rd8# 1-0";
+const ATTRACT_MOVE_DELAY: Duration = Duration::from_millis(1200);
+const ATTRACT_RESTART_DELAY: Duration = Duration::from_secs(4);
+#[cfg(not(target_arch = "wasm32"))]
+const DEFAULT_PLAYBACK_DELAY: Duration = Duration::from_secs(1);
+
+#[derive(States, Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SpectatorState {
+    #[default]
+    Off,
+    Watching
+}
+
+pub enum StreamEvent {
+    Players {white: PlayerInfo, black: PlayerInfo, control: Option<TimeControl>},
+    Reset {start: Option<String>, moves: Vec<String>},
+    Move {text: String, clock: Option<Duration>},
+    Finished {result: Option<String>},
+    Failed(String)
+}
+
+pub trait MoveSource: Send + Sync {
+    fn poll(&mut self, delta: Duration) -> Option<StreamEvent>;
+}
+
+pub struct PgnPlayback {
+    game: PgnGame,
+    delay: Duration,
+    restart: Option<Duration>,
+    waited: Duration,
+    events: VecDeque<StreamEvent>
+}
+
+impl PgnPlayback {
+    pub fn new(game: PgnGame, delay: Duration, restart: Option<Duration>) -> Self {
+        let mut playback = PgnPlayback {game, delay, restart, waited: Duration::ZERO, events: VecDeque::new()};
+        playback.rewind();
+        playback
+    }
+
+    fn rewind(&mut self) {
+        let (white, black) = (self.game.player(PieceColor::WHITE), self.game.player(PieceColor::BLACK));
+        self.events.push_back(StreamEvent::Players {white, black, control: self.game.time_control()});
+        self.events.push_back(StreamEvent::Reset {start: self.game.start.clone(), moves: Vec::new()});
+        self.events.extend(self.game.moves.iter().map(|played| StreamEvent::Move {text: played.uci.clone(), clock: played.clock}));
+        self.events.push_back(StreamEvent::Finished {result: self.game.result.clone()});
+    }
+}
+
+impl MoveSource for PgnPlayback {
+    fn poll(&mut self, delta: Duration) -> Option<StreamEvent> {
+        let wait = match self.events.front() {
+            Some(StreamEvent::Move {..}) => self.delay,
+            Some(_) => Duration::ZERO,
+            None => self.restart?
+        };
+        self.waited += delta;
+        if self.waited < wait { return None };
+        self.waited = Duration::ZERO;
+        if self.events.is_empty() {
+            self.rewind();
+        }
+        self.events.pop_front()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub struct ChannelSource(Mutex<Receiver<StreamEvent>>);
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ChannelSource {
+    pub fn spawn(run: impl FnOnce(Sender<StreamEvent>) + Send + 'static) -> Self {
+        let (sender, receiver) = channel();
+        thread::spawn(move || run(sender));
+        ChannelSource(Mutex::new(receiver))
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl MoveSource for ChannelSource {
+    fn poll(&mut self, _: Duration) -> Option<StreamEvent> {
+        self.0.get_mut().unwrap().try_recv().ok()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn read_stdin_moves(events: Sender<StreamEvent>) {
+    let _ = events.send(StreamEvent::Reset {start: None, moves: Vec::new()});
+    for line in std::io::stdin().lock().lines() {
+        let line = match line {
+            Ok(line) => line,
+            Err(error) => { let _ = events.send(StreamEvent::Failed(format!("Could not read standard input: {}", error))); return }
+        };
+        let line = line.trim();
+        if line.is_empty() { continue };
+        let event = match line.strip_prefix("fen ") {
+            Some(fen) => StreamEvent::Reset {start: Some(fen.trim().to_string()), moves: Vec::new()},
+            None => StreamEvent::Move {text: line.to_string(), clock: None}
+        };
+        if events.send(event).is_err() { return };
+    }
+    let _ = events.send(StreamEvent::Finished {result: None});
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone)]
+enum StreamArg {
+    Playback(String),
+    Watch(String),
+    Stdin
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Clone)]
+pub struct SpectatorArgs {
+    stream: StreamArg,
+    delay: Duration
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl SpectatorArgs {
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Option<Self> {
+        let mut args = args.into_iter();
+        let (mut stream, mut delay) = (None, DEFAULT_PLAYBACK_DELAY);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--playback" => match args.next() {
+                    Some(path) => stream = Some(StreamArg::Playback(path)),
+                    None => warn!("--playback expects a PGN file")
+                },
+                "--watch" => match args.next() {
+                    Some(address) => stream = Some(StreamArg::Watch(address)),
+                    None => warn!("--watch expects an address like 192.168.1.5:5000")
+                },
+                "--stdin-moves" => stream = Some(StreamArg::Stdin),
+                "--delay" => match args.next().and_then(|millis| millis.parse().ok()) {
+                    Some(millis) => delay = Duration::from_millis(millis),
+                    None => warn!("--delay expects milliseconds")
+                },
+                _ => {}
+            }
+        }
+        stream.map(|stream| SpectatorArgs {stream, delay})
+    }
+
+    pub fn into_spectator(self) -> Spectator {
+        let mut spectator = Spectator::default();
+        match self.open() {
+            Ok((source, description)) => spectator.start(source, description),
+            Err(error) => warn!("cannot start spectating: {}", error)
+        }
+        spectator
+    }
+
+    fn open(self) -> Result<(Box<dyn MoveSource>, String), String> {
+        match self.stream {
+            StreamArg::Playback(path) => {
+                let text = std::fs::read_to_string(&path).map_err(|error| format!("{}: {}", path, error))?;
+                let game = parse_pgn(&text).map_err(|error| format!("{}: {}", path, error))?;
+                Ok((Box::new(PgnPlayback::new(game, self.delay, None)), format!("Replaying {}", path)))
+            }
+            StreamArg::Watch(address) => {
+                let description = format!("Watching the game hosted at {}", address);
+                Ok((Box::new(ChannelSource::spawn(move |events| {
+                    if let Err(error) = watch_game(&address, &events) {
+                        let _ = events.send(StreamEvent::Failed(format!("Lost the watched game: {}", error)));
+                    }
+                })), description))
+            }
+            StreamArg::Stdin => Ok((Box::new(ChannelSource::spawn(read_stdin_moves)), String::from("Following moves from standard input")))
+        }
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Spectator {
+    source: Option<Box<dyn MoveSource>>,
+    description: String,
+    ended: Option<String>,
+    attract: bool,
+    control: Option<TimeControl>,
+    queued: VecDeque<(String, Option<Duration>)>,
+    clocks: VecDeque<Option<Duration>>,
+    saved: Option<(Option<PieceColor>, GameMetadata)>
+}
+
+impl Spectator {
+    pub fn start(&mut self, source: Box<dyn MoveSource>, description: String) {
+        self.source = Some(source);
+        self.description = description;
+        self.ended = None;
+        self.attract = false;
+        self.queued.clear();
+        self.clocks.clear();
+    }
+
+    fn stop(&mut self, local_side: &mut LocalSide, metadata: &mut GameMetadata) {
+        let saved = self.saved.take();
+        *self = Spectator::default();
+        if let Some((side, names)) = saved {
+            local_side.0 = side;
+            *metadata = names;
+        }
+    }
+
+    fn end(&mut self, reason: String) {
+        self.queued.clear();
+        self.ended = Some(reason);
+    }
+}
+
+pub fn attract_playing(spectator: Res<Spectator>) -> bool {
+    spectator.attract
+}
+
+#[derive(SystemParam)]
+pub struct GameReplacer<'w> {
+    board: ResMut<'w, BoardResource>,
+    start: ResMut<'w, StartingPosition>,
+    history: ResMut<'w, MoveHistory>,
+    view: ResMut<'w, HistoryView>,
+    selection: ResMut<'w, Selection>,
+    next_phase: ResMut<'w, NextState<GamePhase>>,
+    board_update_writer: EventWriter<'w, BoardUpdate>
+}
+
+impl GameReplacer<'_> {
+    fn played_prefix(&self, start: Option<&str>, moves: &[String]) -> Option<usize> {
+        if self.start.0.as_ref().map(Board::to_fen).as_deref() != start { return None };
+        let played = history_moves(&self.history);
+        moves.starts_with(&played).then_some(played.len())
+    }
+
+    pub fn replace(&mut self, start: Option<&str>, moves: &[String]) -> Result<(), String> {
+        let (board, start, history) = replay_game(start, moves)?;
+        self.board.0 = board;
+        *self.start = start;
+        *self.history = history;
+        self.view.go_live();
+        self.selection.0 = None;
+        self.next_phase.set(GamePhase::Playing);
+        self.board_update_writer.send(BoardUpdate::resync());
+        Ok(())
+    }
+}
+
+pub fn manage_spectating(
+    mut new_game_listener: EventReader<NewGame>,
+    mut spectator: ResMut<Spectator>,
+    mut local_side: ResMut<LocalSide>,
+    mut metadata: ResMut<GameMetadata>,
+    mut next_state: ResMut<NextState<SpectatorState>>
+) {
+    if new_game_listener.read().count() > 0 && spectator.saved.is_some() {
+        spectator.stop(&mut local_side, &mut metadata);
+        next_state.set(SpectatorState::Off);
+    }
+    if spectator.source.is_none() || spectator.saved.is_some() { return };
+    spectator.saved = Some((local_side.0, metadata.clone()));
+    local_side.0 = None;
+    *metadata = GameMetadata::default();
+    next_state.set(SpectatorState::Watching);
+}
+
+pub fn follow_move_stream(
+    time: Res<Time>,
+    phase: Res<State<GamePhase>>,
+    game_status: Res<GameStatusRes>,
+    mut spectator: ResMut<Spectator>,
+    mut game: GameReplacer,
+    mut metadata: ResMut<GameMetadata>,
+    mut clock: ResMut<ChessClock>,
+    mut toasts: ResMut<Toasts>,
+    mut move_request_writer: EventWriter<MoveRequested>,
+    mut resign_writer: EventWriter<ResignRequested>,
+    mut draw_writer: EventWriter<DrawAccepted>
+) {
+    if *phase.get() == GamePhase::Loading { return };
+    if spectator.queued.is_empty() {
+        let Some(event) = spectator.source.as_mut().and_then(|source| source.poll(time.delta())) else { return };
+        match event {
+            StreamEvent::Players {white, black, control} => {
+                metadata.white = white;
+                metadata.black = black;
+                spectator.control = control;
+            }
+            StreamEvent::Reset {start, moves} => match game.played_prefix(start.as_deref(), &moves) {
+                Some(played) if played > 0 => spectator.queued.extend(moves[played..].iter().map(|text| (text.clone(), None))),
+                _ => {
+                    if let Err(error) = game.replace(start.as_deref(), &moves) {
+                        toasts.push(ToastLevel::Error, format!("Could not follow the game: {}", error));
+                        spectator.end(error);
+                        return;
+                    }
+                    *clock = ChessClock::new(spectator.control);
+                    spectator.clocks.clear();
+                    spectator.ended = None;
+                }
+            },
+            StreamEvent::Move {text, clock} => spectator.queued.push_back((text, clock)),
+            StreamEvent::Finished {result} => {
+                if game_status.status() == GameStatus::Ongoing {
+                    match result.as_deref() {
+                        Some("1-0") => { resign_writer.send(ResignRequested(PieceColor::BLACK)); }
+                        Some("0-1") => { resign_writer.send(ResignRequested(PieceColor::WHITE)); }
+                        Some("1/2-1/2") => { draw_writer.send(DrawAccepted); }
+                        _ => {}
+                    }
+                }
+                spectator.end(format!("Stream finished{}", result.map(|result| format!(" ({})", result)).unwrap_or_default()));
+            }
+            StreamEvent::Failed(reason) => {
+                toasts.push(ToastLevel::Error, reason.clone());
+                spectator.end(reason);
+            }
+        }
+    }
+    if *phase.get() != GamePhase::Playing { return };
+    let Some((text, move_clock)) = spectator.queued.pop_front() else { return };
+    let Some(candidate) = game.board.0.parse_move(&text) else {
+        toasts.push(ToastLevel::Error, format!("The stream sent an unreadable move: {}", text));
+        spectator.end(format!("Stopped at unreadable move {}", text));
+        return;
+    };
+    if let Err(error) = game.board.0.clone().try_move(candidate) {
+        toasts.push(ToastLevel::Error, format!("The stream sent an illegal move {}: {}", text, error));
+        spectator.end(format!("Stopped at illegal move {}", text));
+        return;
+    }
+    spectator.clocks.push_back(move_clock);
+    move_request_writer.send(MoveRequested {from: candidate.from, to: candidate.to, promotion: candidate.promotion.or(Some(PieceKind::QUEEN))});
+}
+
+pub fn apply_stream_clocks(mut move_played_listener: EventReader<MovePlayed>, mut spectator: ResMut<Spectator>, mut clock: ResMut<ChessClock>) {
+    for move_played in move_played_listener.read() {
+        let Some(remaining) = spectator.clocks.pop_front().flatten() else { continue };
+        clock.set_remaining(move_played.by, remaining);
+    }
+}
+
+pub fn start_attract_mode(
+    settings: Res<Settings>,
+    history: Res<MoveHistory>,
+    start: Res<StartingPosition>,
+    editor: Res<State<EditorState>>,
+    network: Option<Res<NetworkSession>>,
+    mut spectator: ResMut<Spectator>
+) {
+    if !settings.attract_mode || spectator.source.is_some() || network.is_some() { return };
+    if !history.0.is_empty() || start.0.is_some() || *editor.get() == EditorState::Open { return };
+    let game = match parse_pgn(ATTRACT_GAME) {
+        Ok(game) => game,
+        Err(error) => { warn!("attract game is unreadable: {}", error); return }
+    };
+    spectator.start(Box::new(PgnPlayback::new(game, ATTRACT_MOVE_DELAY, Some(ATTRACT_RESTART_DELAY))), String::new());
+    spectator.attract = true;
+}
+
+pub fn stop_attract_mode(
+    settings: Res<Settings>,
+    mut spectator: ResMut<Spectator>,
+    mut game: GameReplacer,
+    mut clock: ResMut<ChessClock>,
+    mut local_side: ResMut<LocalSide>,
+    mut metadata: ResMut<GameMetadata>,
+    mut next_state: ResMut<NextState<SpectatorState>>
+) {
+    if !spectator.attract { return };
+    if let Err(error) = game.replace(None, &[]) {
+        warn!("failed to clear the attract game: {}", error);
+    }
+    *clock = ChessClock::new(settings.time_control);
+    spectator.stop(&mut local_side, &mut metadata);
+    next_state.set(SpectatorState::Off);
+}
+
+#[derive(Component)]
+pub struct SpectatorBanner;
+
+pub fn spawn_spectator_banner(mut commands: Commands) {
+    commands.spawn((TextBundle {
+        text: Text::from_section("", TextStyle { font_size: 18.0, color: Color::rgb(0.6, 0.85, 1.0), ..default() }),
+        style: Style {
+            position_type: PositionType::Absolute,
+            top: Val::Px(48.0),
+            left: Val::Px(12.0),
+            padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+            ..default()
+        },
+        background_color: Color::rgba(0.0, 0.0, 0.0, 0.7).into(),
+        visibility: Visibility::Hidden,
+        z_index: ZIndex::Global(15),
+        ..default()
+    }, SpectatorBanner));
+}
+
+pub fn update_spectator_banner(spectator: Res<Spectator>, mut banner_query: Query<(&mut Text, &mut Visibility), With<SpectatorBanner>>) {
+    let shown = spectator.saved.is_some() && !spectator.attract;
+    let label = match &spectator.ended {
+        Some(reason) => format!("Spectating - {}\n{} - N starts a new game", spectator.description, reason),
+        None => format!("Spectating - {}", spectator.description)
+    };
+    let wanted = if shown { Visibility::Inherited } else { Visibility::Hidden };
+    for (mut text, mut visibility) in banner_query.iter_mut() {
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+        if shown && text.sections[0].value != label {
+            text.sections[0].value = label.clone();
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::storage::Storage;
+
+const STATS_KEY: &str = "stats.ron";
+
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug, Default)]
+#[serde(default)]
+pub struct Stats {
+    pub best_rush_streak: u32
+}
+
+impl Stats {
+    pub fn load(storage: &dyn Storage) -> Self {
+        let Some(contents) = storage.load(STATS_KEY) else { return Stats::default() };
+        ron::from_str(&contents).unwrap_or_else(|error| {
+            warn!("failed to parse {}, starting with empty stats: {}", STATS_KEY, error);
+            Stats::default()
+        })
+    }
+
+    pub fn save(&self, storage: &dyn Storage) {
+        let contents = match ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default()) {
+            Ok(contents) => contents,
+            Err(error) => { warn!("failed to serialize stats: {}", error); return }
+        };
+        if let Err(error) = storage.save(STATS_KEY, &contents) {
+            warn!("failed to save stats: {}", error);
+        }
+    }
+}
@@ -0,0 +1,93 @@
+use std::io;
+use bevy::prelude::*;
+
+pub trait Storage: Send + Sync {
+    fn load(&self, key: &str) -> Option<String>;
+    fn save(&self, key: &str, contents: &str) -> io::Result<()>;
+    fn remove(&self, key: &str) -> io::Result<()>;
+}
+
+#[derive(Resource)]
+pub struct StorageResource(pub Box<dyn Storage>);
+
+impl Default for StorageResource {
+    fn default() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        return StorageResource(Box::new(FileStorage::new(FileStorage::default_root())));
+        #[cfg(target_arch = "wasm32")]
+        return StorageResource(Box::new(LocalStorage {prefix: String::from("cheess.")}));
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub struct FileStorage {
+    root: std::path::PathBuf
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl FileStorage {
+    pub fn new(root: std::path::PathBuf) -> Self {
+        FileStorage {root}
+    }
+
+    pub fn default_root() -> std::path::PathBuf {
+        let base = std::env::var_os("XDG_DATA_HOME").map(std::path::PathBuf::from)
+            .or_else(|| std::env::var_os("APPDATA").map(std::path::PathBuf::from))
+            .or_else(|| std::env::var_os("HOME").map(|home| std::path::PathBuf::from(home).join(".local").join("share")))
+            .unwrap_or_else(|| std::path::PathBuf::from("."));
+        base.join("cheess")
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Storage for FileStorage {
+    fn load(&self, key: &str) -> Option<String> {
+        std::fs::read_to_string(self.root.join(key)).ok()
+    }
+
+    fn save(&self, key: &str, contents: &str) -> io::Result<()> {
+        let path = self.root.join(key);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, contents)
+    }
+
+    fn remove(&self, key: &str) -> io::Result<()> {
+        match std::fs::remove_file(self.root.join(key)) {
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(()),
+            result => result
+        }
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+pub struct LocalStorage {
+    prefix: String
+}
+
+#[cfg(target_arch = "wasm32")]
+impl LocalStorage {
+    fn storage() -> io::Result<web_sys::Storage> {
+        web_sys::window()
+            .and_then(|window| window.local_storage().ok().flatten())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "localStorage is not available"))
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Storage for LocalStorage {
+    fn load(&self, key: &str) -> Option<String> {
+        Self::storage().ok()?.get_item(&format!("{}{}", self.prefix, key)).ok().flatten()
+    }
+
+    fn save(&self, key: &str, contents: &str) -> io::Result<()> {
+        Self::storage()?.set_item(&format!("{}{}", self.prefix, key), contents)
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "localStorage quota exceeded"))
+    }
+
+    fn remove(&self, key: &str) -> io::Result<()> {
+        Self::storage()?.remove_item(&format!("{}{}", self.prefix, key))
+            .map_err(|_| io::Error::new(io::ErrorKind::Other, "failed to remove localStorage item"))
+    }
+}
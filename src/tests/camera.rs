@@ -0,0 +1,27 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use crate::board::{BoardCamera, WorldCursor};
+use crate::history::MoveHistory;
+use super::harness::Harness;
+
+#[test]
+fn a_second_camera_does_not_break_the_board() {
+    let mut harness = Harness::new();
+    harness.world_mut().spawn(Camera2dBundle {camera: Camera {order: 1, ..default()}, ..default()});
+    harness.frames(2);
+
+    let mut window = Window::default();
+    window.set_cursor_position(Some(Vec2::new(200.0, 200.0)));
+    harness.world_mut().spawn((window, PrimaryWindow));
+    harness.frames(2);
+
+    let board_cameras: Vec<Entity> = harness.world_mut().query_filtered::<Entity, With<BoardCamera>>().iter(harness.world()).collect();
+    assert_eq!(board_cameras.len(), 1);
+    harness.world_mut().despawn(board_cameras[0]);
+    harness.frames(2);
+
+    // Nothing here renders, so no camera can place the cursor and it stays off the board
+    assert!(harness.world().get_resource::<WorldCursor>().is_none());
+    harness.play("e4");
+    assert_eq!(harness.world().resource::<MoveHistory>().0.len(), 1);
+}
@@ -0,0 +1,22 @@
+use bevy::prelude::*;
+use chess_logic::logic::{Coordinate, PieceColor, PieceKind, STANDARD_START};
+use crate::piece::CaptureEvent;
+use super::harness::Harness;
+
+#[derive(Resource, Default)]
+struct Captures(Vec<(PieceKind, PieceColor, Coordinate, PieceColor)>);
+
+fn record_captures(mut capture_listener: EventReader<CaptureEvent>, mut captures: ResMut<Captures>) {
+    captures.0.extend(capture_listener.read().map(|capture| (capture.piece.kind, capture.piece.color, capture.square, capture.by)));
+}
+
+#[test]
+fn en_passant_reports_the_captured_pawn_square() {
+    let mut harness = Harness::new();
+    harness.app.init_resource::<Captures>().add_systems(Last, record_captures);
+    harness.start_from(STANDARD_START);
+    harness.play_all(&["e4", "a6", "e5", "d5", "exd6"]);
+    let d5 = harness.square("d5");
+    assert_eq!(harness.world().resource::<Captures>().0, [(PieceKind::PAWN, PieceColor::BLACK, d5, PieceColor::WHITE)]);
+    assert!(!harness.board().pieces.contains_key(&d5));
+}
@@ -0,0 +1,31 @@
+use bevy::prelude::*;
+use chess_logic::logic::STANDARD_START;
+use crate::config::ChessConfig;
+use crate::piece::CheckBlink;
+use super::harness::Harness;
+
+fn alpha(harness: &Harness, entity: Entity) -> f32 {
+    harness.world().get::<Sprite>(entity).unwrap().color.a()
+}
+
+#[test]
+fn blocking_a_check_restores_the_king() {
+    let mut harness = Harness::with_config(ChessConfig {blink_checked_king: true, ..ChessConfig::default()});
+    harness.start_from(STANDARD_START);
+    harness.play_all(&["d4", "e6", "Nf3", "Bb4+"]);
+    let king = harness.piece_entity(harness.square("e1"));
+    // Block while the king is dimmed, which is when a missed reset would show
+    for _ in 0..60 {
+        if alpha(&harness, king) < 1.0 { break };
+        harness.update();
+    }
+    assert!(alpha(&harness, king) < 1.0, "the checked king never blinked");
+
+    harness.play("c3");
+    harness.frames(3);
+    let king = harness.piece_entity(harness.square("e1"));
+    assert_eq!(alpha(&harness, king), 1.0);
+    assert!(harness.world().get::<CheckBlink>(king).is_none());
+    harness.frames(60);
+    assert_eq!(alpha(&harness, king), 1.0);
+}
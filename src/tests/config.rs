@@ -0,0 +1,49 @@
+use bevy::prelude::*;
+use chess_logic::logic::{Coordinate, PieceColor};
+use crate::board::{BoardOrientation, BoardTile};
+use crate::config::ChessConfig;
+use crate::piece::BoardUpdate;
+use crate::theme::BoardTheme;
+use super::harness::Harness;
+
+fn tiles(harness: &mut Harness) -> Vec<(Coordinate, Vec2, Color)> {
+    let world = harness.world_mut();
+    world.query::<(&BoardTile, &Transform, &Sprite)>().iter(world)
+        .map(|(tile, transform, sprite)| (Coordinate(tile.square.0, tile.square.1), transform.translation.truncate(), sprite.color))
+        .collect()
+}
+
+#[test]
+fn swapped_tile_colors_retint_the_board() {
+    let mut harness = Harness::new();
+    let theme = *harness.world().resource::<BoardTheme>();
+    {
+        let mut swapped = harness.world_mut().resource_mut::<BoardTheme>();
+        swapped.light = theme.dark;
+        swapped.dark = theme.light;
+    }
+    harness.world_mut().send_event(BoardUpdate::resync());
+    harness.frames(2);
+    let tiles = tiles(&mut harness);
+    assert_eq!(tiles.len(), 64);
+    for (square, _, color) in tiles {
+        let expected = if (square.0 + square.1) % 2 == 0 { theme.light } else { theme.dark };
+        assert_eq!(color, expected, "{:?} kept its old tint", square);
+    }
+}
+
+#[test]
+fn runtime_config_changes_reach_the_board() {
+    let mut harness = Harness::new();
+    {
+        let mut config = harness.world_mut().resource_mut::<ChessConfig>();
+        config.square_size = 40.0;
+        config.orientation = PieceColor::BLACK;
+    }
+    harness.world_mut().send_event(BoardUpdate::resync());
+    harness.frames(2);
+    assert_eq!(harness.world().resource::<BoardOrientation>().0, PieceColor::BLACK);
+    for (square, position, _) in tiles(&mut harness) {
+        assert_eq!(position, Vec2::new(square.0 as f32 * 40.0, square.1 as f32 * 40.0));
+    }
+}
@@ -0,0 +1,73 @@
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use crate::board::BoardResource;
+use crate::config::ChessConfig;
+use crate::history::MoveHistory;
+use crate::piece::{BoardUpdate, PhantomPiece, ShadowPiece};
+use super::harness::Harness;
+
+// Picks up the piece on `from` and carries it over `to` without letting go
+fn start_drag(harness: &mut Harness, from: &str, to: &str) -> Entity {
+    let (from, to) = (harness.square(from), harness.square(to));
+    let entity = harness.piece_entity(from);
+    harness.hover(Some(from));
+    harness.mouse(ButtonState::Pressed);
+    harness.hover(Some(to));
+    harness.update();
+    assert!(harness.piece(entity).is_dragged());
+    entity
+}
+
+fn home_of(harness: &Harness, entity: Entity, square: &str) -> bool {
+    let home = harness.world().resource::<ChessConfig>().square_to_vector(harness.square(square));
+    harness.world().get::<Transform>(entity).unwrap().translation.truncate() == home
+}
+
+#[test]
+fn dropping_a_piece_the_board_no_longer_has_cancels_the_drag() {
+    let mut harness = Harness::new();
+    let entity = start_drag(&mut harness, "e2", "e4");
+    let e2 = harness.square("e2");
+    harness.world_mut().resource_mut::<BoardResource>().0.pieces.remove(&e2);
+    harness.mouse(ButtonState::Released);
+    harness.settle();
+    assert!(!harness.piece(entity).is_dragged());
+    assert!(harness.world().resource::<MoveHistory>().0.is_empty());
+    assert!(home_of(&harness, entity, "e2"));
+}
+
+#[test]
+fn a_board_update_mid_drag_cancels_it() {
+    let mut harness = Harness::new();
+    start_drag(&mut harness, "e2", "e4");
+    harness.world_mut().send_event(BoardUpdate::resync());
+    harness.update();
+    let pawn = harness.piece_entity(harness.square("e2"));
+    assert!(!harness.piece(pawn).is_dragged());
+    assert!(home_of(&harness, pawn, "e2"));
+    harness.mouse(ButtonState::Released);
+    harness.settle();
+    assert!(harness.world().resource::<MoveHistory>().0.is_empty());
+}
+
+#[test]
+fn losing_the_cursor_mid_drag_sends_the_piece_home() {
+    let mut harness = Harness::new();
+    let entity = start_drag(&mut harness, "g1", "f3");
+    assert!(!home_of(&harness, entity, "g1"));
+    harness.hover(None);
+    harness.update();
+    assert!(!harness.piece(entity).is_dragged());
+    assert!(home_of(&harness, entity, "g1"));
+    let world = harness.world_mut();
+    let drag_sprites: Vec<Visibility> = world.query_filtered::<&Visibility, Or<(With<ShadowPiece>, With<PhantomPiece>)>>().iter(world).copied().collect();
+    assert_eq!(drag_sprites, [Visibility::Hidden; 2]);
+
+    // The release happens outside the window, and coming back must not pick the piece up again
+    harness.mouse(ButtonState::Released);
+    harness.hover(Some(harness.square("f3")));
+    harness.frames(2);
+    assert!(!harness.piece(entity).is_dragged());
+    assert!(home_of(&harness, entity, "g1"));
+    assert!(harness.world().resource::<MoveHistory>().0.is_empty());
+}
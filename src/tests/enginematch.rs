@@ -0,0 +1,27 @@
+use std::time::{Duration, Instant};
+use chess_logic::logic::PieceColor;
+use crate::board::LocalSide;
+use crate::enginematch::{EngineMatch, MatchArgs};
+use crate::history::MoveHistory;
+use crate::players::GameMetadata;
+use super::harness::Harness;
+
+#[test]
+fn a_human_plays_a_weakened_bot() {
+    let mut harness = Harness::new();
+    let args = ["--match", "human", "bot:2@300", "--seed", "7"].map(String::from);
+    harness.world_mut().insert_resource(EngineMatch::new(MatchArgs::from_args(args)));
+    harness.frames(2);
+    assert_eq!(harness.world().resource::<LocalSide>().0, Some(PieceColor::WHITE));
+    assert_eq!(harness.world().resource::<GameMetadata>().black.rating, Some(300));
+
+    harness.drag(harness.square("e2"), harness.square("e4"));
+    // The bot searches on the task pool, so give it real time rather than a frame count
+    let deadline = Instant::now() + Duration::from_secs(20);
+    while harness.world().resource::<MoveHistory>().0.len() < 2 && Instant::now() < deadline {
+        harness.update();
+        std::thread::sleep(Duration::from_millis(5));
+    }
+    assert_eq!(harness.world().resource::<MoveHistory>().0.len(), 2);
+    assert_eq!(harness.board().on_move, PieceColor::WHITE);
+}
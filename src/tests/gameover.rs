@@ -0,0 +1,64 @@
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use chess_logic::logic::{GameStatus, PieceColor, STANDARD_START};
+use crate::board::{GameOverEvent, GamePhase, GameStatusRes};
+use crate::clock::ChessClock;
+use crate::piece::Selection;
+use crate::settings::{Settings, TimeControl};
+use super::harness::Harness;
+
+#[derive(Resource, Default)]
+struct GameOverCount {
+    events: usize,
+    transitions: usize
+}
+
+fn count_game_overs(
+    mut game_over_listener: EventReader<GameOverEvent>,
+    mut transition_listener: EventReader<StateTransitionEvent<GamePhase>>,
+    mut count: ResMut<GameOverCount>
+) {
+    count.events += game_over_listener.read().count();
+    count.transitions += transition_listener.read().filter(|transition| transition.after == GamePhase::GameOver).count();
+}
+
+fn scholars_mate() -> Harness {
+    let mut harness = Harness::new();
+    harness.world_mut().resource_mut::<Settings>().time_control = Some(TimeControl {base_seconds: 300, increment_seconds: 0, delay_seconds: 0});
+    harness.app.init_resource::<GameOverCount>().add_systems(Last, count_game_overs);
+    harness.start_from(STANDARD_START);
+    harness.play_all(&["e4", "e5", "Bc4", "Nc6", "Qh5", "Nf6", "Qxf7#"]);
+    harness.frames(3);
+    harness
+}
+
+#[test]
+fn checkmate_ends_the_game_once_and_stops_the_clock() {
+    let mut harness = scholars_mate();
+    assert_eq!(harness.world().resource::<GameStatusRes>().status(), GameStatus::Checkmate {winner: PieceColor::WHITE});
+    assert_eq!(harness.phase(), GamePhase::GameOver);
+    let clock = harness.world().resource::<ChessClock>();
+    assert!(!clock.is_running());
+    let remaining = (clock.remaining(PieceColor::WHITE), clock.remaining(PieceColor::BLACK));
+    harness.frames(30);
+    let clock = harness.world().resource::<ChessClock>();
+    assert_eq!((clock.remaining(PieceColor::WHITE), clock.remaining(PieceColor::BLACK)), remaining);
+    let count = harness.world().resource::<GameOverCount>();
+    assert_eq!((count.events, count.transitions), (1, 1));
+}
+
+#[test]
+fn no_drag_begins_after_checkmate() {
+    let mut harness = scholars_mate();
+    let (d7, d5) = (harness.square("d7"), harness.square("d5"));
+    let pawn = harness.piece_entity(d7);
+    harness.hover(Some(d7));
+    harness.mouse(ButtonState::Pressed);
+    harness.hover(Some(d5));
+    harness.update();
+    assert!(!harness.piece(pawn).is_dragged());
+    harness.mouse(ButtonState::Released);
+    harness.click(d7);
+    assert_eq!(harness.world().resource::<Selection>().0, None);
+    assert_eq!(harness.board().on_move, PieceColor::BLACK);
+}
@@ -0,0 +1,178 @@
+use std::collections::HashMap;
+use std::io;
+use std::sync::Mutex;
+use std::time::Duration;
+use bevy::input::ButtonState;
+use bevy::input::mouse::MouseButtonInput;
+use bevy::prelude::*;
+use bevy::time::TimeUpdateStrategy;
+use bevy::window::ExitCondition;
+use chess_logic::logic::{Board, Coordinate};
+use crate::board::{BoardResource, GamePhase, WorldCursor};
+use crate::config::ChessConfig;
+use crate::piece::{MoveRequested, NewGame, PieceComponent, PieceEntities};
+use crate::plugin::ChessPlugin;
+use crate::settings::Settings;
+use crate::storage::{Storage, StorageResource};
+
+pub const FRAME: Duration = Duration::from_millis(16);
+
+#[derive(Default)]
+struct MemoryStorage(Mutex<HashMap<String, String>>);
+
+impl Storage for MemoryStorage {
+    fn load(&self, key: &str) -> Option<String> {
+        self.0.lock().unwrap().get(key).cloned()
+    }
+
+    fn save(&self, key: &str, contents: &str) -> io::Result<()> {
+        self.0.lock().unwrap().insert(key.to_string(), contents.to_string());
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) -> io::Result<()> {
+        self.0.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+// The whole ChessPlugin without winit, rendering or audio: frames advance by a fixed step,
+// the cursor is placed by hand and mouse buttons go through the usual input events
+pub struct Harness {
+    pub app: App
+}
+
+impl Harness {
+    pub fn new() -> Self {
+        Harness::with_config(ChessConfig::default())
+    }
+
+    pub fn with_config(config: ChessConfig) -> Self {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            bevy::input::InputPlugin,
+            WindowPlugin {primary_window: None, exit_condition: ExitCondition::DontExit, close_when_requested: false},
+            TransformPlugin,
+            HierarchyPlugin
+        ))
+            .init_asset::<Image>()
+            .init_asset::<TextureAtlasLayout>()
+            .init_asset::<Font>()
+            .init_asset::<AudioSource>()
+            .init_asset::<Shader>()
+            .init_asset::<Mesh>()
+            .init_asset::<ColorMaterial>()
+            .add_plugins(bevy::gizmos::GizmoPlugin)
+            .init_resource::<UiScale>()
+            .insert_resource(TimeUpdateStrategy::ManualDuration(FRAME))
+            .insert_resource(StorageResource(Box::new(MemoryStorage::default())))
+            .add_plugins(ChessPlugin::new(config));
+        let mut harness = Harness {app};
+        harness.update();
+        harness.app.world.resource_mut::<NextState<GamePhase>>().set(GamePhase::Playing);
+        // Finished games would otherwise land in the real archive directory, and the eval bar
+        // searches of every test app would crowd the shared compute pool the bots search on
+        let mut settings = harness.app.world.resource_mut::<Settings>();
+        settings.archive_games = false;
+        settings.engine_evaluation = false;
+        harness.update();
+        harness
+    }
+
+    pub fn update(&mut self) {
+        self.app.update();
+    }
+
+    pub fn frames(&mut self, count: usize) {
+        for _ in 0..count {
+            self.update();
+        }
+    }
+
+    // Long enough for every tween and fade to finish
+    pub fn settle(&mut self) {
+        self.frames(40);
+    }
+
+    pub fn world(&self) -> &World {
+        &self.app.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.app.world
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.app.world.resource::<BoardResource>().0
+    }
+
+    pub fn phase(&self) -> GamePhase {
+        *self.app.world.resource::<State<GamePhase>>().get()
+    }
+
+    pub fn square(&self, name: &str) -> Coordinate {
+        Coordinate::from_algebraic(name).unwrap()
+    }
+
+    pub fn piece_entity(&self, square: Coordinate) -> Entity {
+        self.app.world.resource::<PieceEntities>().0[&square]
+    }
+
+    pub fn piece(&self, entity: Entity) -> &PieceComponent {
+        self.app.world.get::<PieceComponent>(entity).unwrap()
+    }
+
+    pub fn hover(&mut self, square: Option<Coordinate>) {
+        let Some(square) = square else {
+            self.app.world.remove_resource::<WorldCursor>();
+            return;
+        };
+        let position = self.app.world.resource::<ChessConfig>().square_to_vector(square);
+        self.hover_position(position);
+    }
+
+    pub fn hover_position(&mut self, position: Vec2) {
+        let config = self.app.world.resource::<ChessConfig>().clone();
+        self.app.world.insert_resource(WorldCursor::from_position(position, &config));
+    }
+
+    pub fn mouse(&mut self, state: ButtonState) {
+        self.app.world.send_event(MouseButtonInput {button: MouseButton::Left, state, window: Entity::PLACEHOLDER});
+        self.update();
+    }
+
+    pub fn click(&mut self, square: Coordinate) {
+        self.hover(Some(square));
+        self.mouse(ButtonState::Pressed);
+        self.mouse(ButtonState::Released);
+    }
+
+    pub fn drag(&mut self, from: Coordinate, to: Coordinate) {
+        self.hover(Some(from));
+        self.mouse(ButtonState::Pressed);
+        self.hover(Some(to));
+        self.update();
+        self.mouse(ButtonState::Released);
+    }
+
+    pub fn start_from(&mut self, fen: &str) {
+        let board = Board::from_fen(fen).unwrap_or_else(|error| panic!("unreadable FEN {}: {}", fen, error));
+        self.app.world.send_event(NewGame::from_position(board));
+        self.frames(3);
+    }
+
+    // Plays a move the way the engine and network code do, then lets the move settle
+    pub fn play(&mut self, text: &str) {
+        let candidate = self.board().parse_move(text).unwrap_or_else(|| panic!("unreadable move {}", text));
+        self.app.world.send_event(MoveRequested {from: candidate.from, to: candidate.to, promotion: candidate.promotion});
+        self.frames(2);
+    }
+
+    pub fn play_all(&mut self, moves: &[&str]) {
+        for text in moves {
+            self.play(text);
+        }
+    }
+}
@@ -0,0 +1,50 @@
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use chess_logic::logic::PieceColor;
+use crate::board::{BoardOutline, GamePhase};
+use crate::config::ChessConfig;
+use crate::history::MoveHistory;
+use super::harness::Harness;
+
+#[test]
+fn plays_a_scripted_game_without_a_window() {
+    let mut harness = Harness::new();
+    assert_eq!(harness.phase(), GamePhase::Playing);
+    assert!(harness.world_mut().query_filtered::<Entity, With<PrimaryWindow>>().iter(harness.world()).next().is_none());
+    harness.play_all(&["e4", "e5", "Nf3", "Nc6"]);
+    harness.settle();
+    assert_eq!(harness.world().resource::<MoveHistory>().0.len(), 4);
+    assert_eq!(harness.board().on_move, PieceColor::WHITE);
+    let knight = harness.piece_entity(harness.square("c6"));
+    let home = harness.world().resource::<ChessConfig>().square_to_vector(harness.square("c6"));
+    assert_eq!(harness.world().get::<Transform>(knight).unwrap().translation.truncate(), home);
+}
+
+#[test]
+fn survives_a_second_window_and_a_missing_outline() {
+    let mut harness = Harness::new();
+    harness.world_mut().spawn(Window::default());
+    harness.world_mut().spawn(Window::default());
+    let outlines: Vec<Entity> = harness.world_mut().query_filtered::<Entity, With<BoardOutline>>().iter(harness.world()).collect();
+    for outline in outlines {
+        harness.world_mut().despawn(outline);
+    }
+    harness.play("e4");
+    harness.frames(3);
+    assert_eq!(harness.world().resource::<MoveHistory>().0.len(), 1);
+}
+
+#[test]
+fn moves_pieces_with_the_mouse() {
+    let mut harness = Harness::new();
+    let (e2, e4, g8, f6) = (harness.square("e2"), harness.square("e4"), harness.square("g8"), harness.square("f6"));
+    harness.drag(e2, e4);
+    harness.frames(2);
+    assert_eq!(harness.board().on_move, PieceColor::BLACK);
+    assert!(!harness.piece(harness.piece_entity(e4)).is_dragged());
+    harness.click(g8);
+    harness.click(f6);
+    harness.frames(2);
+    assert_eq!(harness.world().resource::<MoveHistory>().0.len(), 2);
+    assert!(harness.board().pieces.contains_key(&f6));
+}
@@ -0,0 +1,60 @@
+use bevy::ecs::system::RunSystemOnce;
+use bevy::prelude::*;
+use chess_logic::logic::{Coordinate, PieceColor};
+use crate::board::{BoardTile, LocalSide};
+use crate::highlight::{blend, HighlightKind, HighlightPalette, Highlights};
+use crate::theme::BoardTheme;
+use super::harness::Harness;
+
+const BEFORE_QH5: &str = "rnbqkbnr/ppppp1pp/8/5p2/4P3/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 2";
+
+fn tile_color(harness: &mut Harness, square: Coordinate) -> Color {
+    let world = harness.world_mut();
+    world.query::<(&BoardTile, &Sprite)>().iter(world)
+        .find(|(tile, _)| Coordinate(tile.square.0, tile.square.1) == square)
+        .map(|(_, sprite)| sprite.color)
+        .unwrap()
+}
+
+fn tile_base(harness: &Harness, square: Coordinate) -> Color {
+    BoardTile {square: (square.0, square.1)}.get_color(harness.world().resource::<BoardTheme>())
+}
+
+fn layered(base: Color, palette: &HighlightPalette, kinds: &[HighlightKind]) -> Color {
+    kinds.iter().fold(base, |color, kind| blend(color, palette.color(*kind)))
+}
+
+#[test]
+fn last_move_is_drawn_over_hover() {
+    let mut harness = Harness::new();
+    harness.play("e4");
+    let e4 = harness.square("e4");
+    harness.hover(Some(e4));
+    harness.frames(2);
+    let palette = *harness.world().resource::<HighlightPalette>();
+    let expected = layered(tile_base(&harness, e4), &palette, &[HighlightKind::Hover, HighlightKind::LastMove]);
+    assert_eq!(tile_color(&mut harness, e4), expected);
+}
+
+#[test]
+fn check_is_drawn_over_last_move_and_hover() {
+    let mut harness = Harness::new();
+    harness.start_from(BEFORE_QH5);
+    harness.world_mut().resource_mut::<LocalSide>().0 = Some(PieceColor::WHITE);
+    harness.play("Qh5+");
+    let e8 = harness.square("e8");
+    harness.hover(Some(e8));
+    harness.update();
+    // The king never lands on a last-move square while in check, so that layer goes on by hand
+    harness.world_mut().run_system_once(move |mut highlights: Highlights| highlights.add(e8, HighlightKind::LastMove));
+    harness.frames(2);
+    let palette = *harness.world().resource::<HighlightPalette>();
+    let expected = layered(tile_base(&harness, e8), &palette, &[HighlightKind::Hover, HighlightKind::LastMove, HighlightKind::Check]);
+    assert_eq!(tile_color(&mut harness, e8), expected);
+
+    harness.world_mut().resource_mut::<HighlightPalette>().colorblind = true;
+    harness.update();
+    let palette = *harness.world().resource::<HighlightPalette>();
+    let expected = layered(tile_base(&harness, e8), &palette, &[HighlightKind::Hover, HighlightKind::LastMove, HighlightKind::Check]);
+    assert_eq!(tile_color(&mut harness, e8), expected);
+}
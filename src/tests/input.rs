@@ -0,0 +1,77 @@
+use bevy::input::ButtonState;
+use bevy::input::mouse::MouseButtonInput;
+use bevy::prelude::*;
+use chess_logic::logic::{Coordinate, PieceColor, PieceKind, STANDARD_START};
+use crate::board::LocalSide;
+use crate::config::ChessConfig;
+use crate::history::MoveHistory;
+use crate::piece::{LegalTargets, Premove, Selection};
+use super::harness::Harness;
+
+fn sans(harness: &Harness) -> Vec<String> {
+    harness.world().resource::<MoveHistory>().0.iter().map(|played| played.san.clone()).collect()
+}
+
+fn kind_on(harness: &Harness, square: Coordinate) -> Option<PieceKind> {
+    harness.board().pieces.get(&square).map(|piece| piece.kind)
+}
+
+fn at_home(harness: &Harness, square: Coordinate) -> bool {
+    let home = harness.world().resource::<ChessConfig>().square_to_vector(square);
+    harness.world().get::<Transform>(harness.piece_entity(square)).unwrap().translation.truncate() == home
+}
+
+#[test]
+fn scripted_mouse_input_plays_the_expected_game() {
+    let mut harness = Harness::new();
+    harness.start_from(STANDARD_START);
+    let [e2, e4, e7, e5, g1, g4, f3, b8, c6, d2, d4] = ["e2", "e4", "e7", "e5", "g1", "g4", "f3", "b8", "c6", "d2", "d4"].map(|name| harness.square(name));
+
+    // Click a pawn, then its target
+    harness.click(e2);
+    assert_eq!(harness.world().resource::<Selection>().0, Some(e2));
+    assert!(harness.world().resource::<LegalTargets>().targets.contains(&e4));
+    harness.click(e4);
+    harness.frames(2);
+    assert_eq!(sans(&harness), ["e4"]);
+    assert_eq!(harness.world().resource::<Selection>().0, None);
+
+    // Drag a reply
+    harness.drag(e7, e5);
+    harness.frames(2);
+    assert_eq!(sans(&harness), ["e4", "e5"]);
+
+    // An illegal drop snaps back and leaves the turn alone
+    harness.drag(g1, g4);
+    harness.settle();
+    assert_eq!(sans(&harness).len(), 2);
+    assert_eq!(kind_on(&harness, g1), Some(PieceKind::KNIGHT));
+    assert!(at_home(&harness, g1));
+
+    // A right click mid-drag cancels it even though the drop lands on a legal square
+    harness.hover(Some(g1));
+    harness.mouse(ButtonState::Pressed);
+    harness.hover(Some(f3));
+    harness.update();
+    harness.world_mut().send_event(MouseButtonInput {button: MouseButton::Right, state: ButtonState::Pressed, window: Entity::PLACEHOLDER});
+    harness.update();
+    harness.mouse(ButtonState::Released);
+    harness.settle();
+    assert_eq!(sans(&harness).len(), 2);
+    assert!(at_home(&harness, g1));
+
+    harness.drag(g1, f3);
+    harness.frames(2);
+    assert_eq!(sans(&harness), ["e4", "e5", "Nf3"]);
+
+    // With a local side, a drag on the opponent's turn queues a premove that fires after their reply
+    harness.world_mut().resource_mut::<LocalSide>().0 = Some(PieceColor::WHITE);
+    harness.drag(d2, d4);
+    harness.settle();
+    assert_eq!(harness.world().resource::<Premove>().0, Some((d2, d4)));
+    harness.play("Nc6");
+    harness.frames(2);
+    assert_eq!(sans(&harness), ["e4", "e5", "Nf3", "Nc6", "d4"]);
+    assert_eq!(kind_on(&harness, c6), Some(PieceKind::KNIGHT));
+    assert_eq!(kind_on(&harness, b8), None);
+}
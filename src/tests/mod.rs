@@ -0,0 +1,12 @@
+mod camera;
+mod capture;
+mod check;
+mod config;
+mod drag;
+mod enginematch;
+mod gameover;
+mod harness;
+mod headless;
+mod highlight;
+mod input;
+mod promotion;
@@ -0,0 +1,24 @@
+use chess_logic::logic::{PieceColor, PieceKind};
+use crate::board::GamePhase;
+use crate::history::MoveHistory;
+use crate::piece::{PieceEntities, PromotionChosen};
+use super::harness::Harness;
+
+#[test]
+fn choosing_a_piece_finishes_the_promotion() {
+    let mut harness = Harness::new();
+    harness.start_from("k7/4P3/8/8/8/8/8/4K3 w - - 0 1");
+    let (e7, e8) = (harness.square("e7"), harness.square("e8"));
+    harness.drag(e7, e8);
+    harness.frames(2);
+    assert_eq!(harness.phase(), GamePhase::PromotionPending);
+
+    harness.world_mut().send_event(PromotionChosen(PieceKind::KNIGHT));
+    harness.frames(2);
+    assert_eq!(harness.phase(), GamePhase::Playing);
+    let knight = harness.board().pieces[&e8];
+    assert_eq!((knight.kind, knight.color), (PieceKind::KNIGHT, PieceColor::WHITE));
+    assert!(harness.world().resource::<PieceEntities>().0.contains_key(&e8));
+    assert_eq!(harness.board().on_move, PieceColor::BLACK);
+    assert_eq!(harness.world().resource::<MoveHistory>().0.len(), 1);
+}
@@ -0,0 +1,209 @@
+use bevy::prelude::*;
+use crate::assets::{PieceAssets, PieceSets};
+use crate::highlight::HighlightPalette;
+use crate::settings::Settings;
+
+#[derive(Resource, Clone, Copy, PartialEq, Debug)]
+pub struct BoardTheme {
+    pub name: &'static str,
+    pub light: Color,
+    pub dark: Color,
+    pub outline_light: Color,
+    pub outline_dark: Color
+}
+
+impl BoardTheme {
+    pub const PRESETS: [BoardTheme; 4] = [
+        BoardTheme {
+            name: "classic",
+            light: Color::rgb(0.93, 0.93, 0.82),
+            dark: Color::rgb(0.46, 0.59, 0.34),
+            outline_light: Color::rgb(0.96, 0.96, 0.9),
+            outline_dark: Color::rgb(0.2, 0.25, 0.15)
+        },
+        BoardTheme {
+            name: "blue",
+            light: Color::rgb(0.87, 0.89, 0.93),
+            dark: Color::rgb(0.55, 0.64, 0.75),
+            outline_light: Color::rgb(0.94, 0.95, 0.97),
+            outline_dark: Color::rgb(0.16, 0.2, 0.28)
+        },
+        BoardTheme {
+            name: "brown",
+            light: Color::rgb(0.94, 0.85, 0.71),
+            dark: Color::rgb(0.71, 0.53, 0.39),
+            outline_light: Color::rgb(0.97, 0.92, 0.84),
+            outline_dark: Color::rgb(0.27, 0.18, 0.12)
+        },
+        BoardTheme {
+            name: "high-contrast",
+            light: Color::WHITE,
+            dark: Color::rgb(0.42, 0.42, 0.42),
+            outline_light: Color::WHITE,
+            outline_dark: Color::BLACK
+        }
+    ];
+
+    pub fn named(name: &str) -> Option<BoardTheme> {
+        Self::PRESETS.into_iter().find(|theme| theme.name == name)
+    }
+}
+
+impl Default for BoardTheme {
+    fn default() -> Self {
+        Self::PRESETS[0]
+    }
+}
+
+pub fn luminance(color: Color) -> f32 {
+    0.299 * color.r() + 0.587 * color.g() + 0.114 * color.b()
+}
+
+pub fn apply_board_theme(settings: Res<Settings>, mut theme: ResMut<BoardTheme>) {
+    let wanted = BoardTheme::named(&settings.board_theme).unwrap_or_else(|| {
+        warn!("unknown board theme {}, using {}", settings.board_theme, BoardTheme::default().name);
+        BoardTheme::default()
+    });
+    if *theme != wanted {
+        *theme = wanted;
+    }
+}
+
+#[derive(Component)]
+pub struct ThemeButton(pub &'static str);
+
+pub fn spawn_theme_buttons(parent: &mut ChildBuilder) {
+    parent.spawn(NodeBundle {
+        style: Style {
+            width: Val::Px(360.0),
+            margin: UiRect::top(Val::Px(8.0)),
+            column_gap: Val::Px(4.0),
+            ..default()
+        },
+        ..default()
+    }).with_children(|row| {
+        for theme in BoardTheme::PRESETS {
+            row.spawn((ButtonBundle {
+                style: Style {
+                    flex_grow: 1.0,
+                    padding: UiRect::all(Val::Px(4.0)),
+                    justify_content: JustifyContent::Center,
+                    border: UiRect::bottom(Val::Px(3.0)),
+                    ..default()
+                },
+                background_color: theme.dark.into(),
+                ..default()
+            }, ThemeButton(theme.name))).with_children(|button| {
+                button.spawn(TextBundle::from_section(theme.name, TextStyle { font_size: 16.0, color: Color::WHITE, ..default() }));
+            });
+        }
+    });
+}
+
+pub fn choose_board_theme(button_query: Query<(&Interaction, &ThemeButton), Changed<Interaction>>, mut settings: ResMut<Settings>) {
+    for (interaction, ThemeButton(name)) in button_query.iter() {
+        if *interaction == Interaction::Pressed && settings.board_theme != *name {
+            settings.board_theme = name.to_string();
+        }
+    }
+}
+
+pub fn update_theme_buttons(theme: Res<BoardTheme>, mut button_query: Query<(&ThemeButton, &mut BorderColor)>) {
+    for (ThemeButton(name), mut border) in button_query.iter_mut() {
+        border.0 = if *name == theme.name { theme.light } else { Color::NONE };
+    }
+}
+
+#[derive(Component)]
+pub struct PaletteToggle;
+
+fn palette_label(colorblind: bool) -> &'static str {
+    if colorblind { "Colorblind palette: on" } else { "Colorblind palette: off" }
+}
+
+pub fn spawn_palette_toggle(parent: &mut ChildBuilder, settings: &Settings) {
+    parent.spawn((ButtonBundle {
+        style: Style {
+            width: Val::Px(360.0),
+            margin: UiRect::top(Val::Px(8.0)),
+            padding: UiRect::all(Val::Px(4.0)),
+            justify_content: JustifyContent::Center,
+            ..default()
+        },
+        background_color: Color::rgb(0.25, 0.25, 0.3).into(),
+        ..default()
+    }, PaletteToggle)).with_children(|button| {
+        button.spawn(TextBundle::from_section(palette_label(settings.colorblind_palette), TextStyle { font_size: 16.0, color: Color::WHITE, ..default() }));
+    });
+}
+
+pub fn toggle_colorblind_palette(button_query: Query<&Interaction, (Changed<Interaction>, With<PaletteToggle>)>, mut settings: ResMut<Settings>) {
+    if button_query.iter().any(|interaction| *interaction == Interaction::Pressed) {
+        settings.colorblind_palette = !settings.colorblind_palette;
+    }
+}
+
+pub fn update_palette_toggle(
+    palette: Res<HighlightPalette>,
+    button_query: Query<&Children, With<PaletteToggle>>,
+    mut text_query: Query<&mut Text>
+) {
+    for children in button_query.iter() {
+        let mut texts = text_query.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.sections[0].value = palette_label(palette.colorblind).to_string();
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct PieceSetButton(pub String);
+
+pub fn spawn_piece_set_buttons(parent: &mut ChildBuilder, sets: &PieceSets) {
+    parent.spawn(NodeBundle {
+        style: Style {
+            width: Val::Px(360.0),
+            margin: UiRect::top(Val::Px(8.0)),
+            flex_direction: FlexDirection::Column,
+            row_gap: Val::Px(4.0),
+            ..default()
+        },
+        ..default()
+    }).with_children(|column| {
+        for set in sets.0.iter() {
+            let Some(reason) = &set.unavailable else {
+                column.spawn((ButtonBundle {
+                    style: Style {
+                        padding: UiRect::all(Val::Px(4.0)),
+                        justify_content: JustifyContent::Center,
+                        border: UiRect::bottom(Val::Px(3.0)),
+                        ..default()
+                    },
+                    background_color: Color::rgb(0.25, 0.25, 0.3).into(),
+                    ..default()
+                }, PieceSetButton(set.name.clone()))).with_children(|button| {
+                    button.spawn(TextBundle::from_section(set.name.clone(), TextStyle { font_size: 16.0, color: Color::WHITE, ..default() }));
+                });
+                continue;
+            };
+            column.spawn(TextBundle::from_section(
+                format!("{} - unavailable: {}", set.name, reason),
+                TextStyle { font_size: 14.0, color: Color::GRAY, ..default() }
+            ));
+        }
+    });
+}
+
+pub fn choose_piece_set(button_query: Query<(&Interaction, &PieceSetButton), Changed<Interaction>>, mut settings: ResMut<Settings>) {
+    for (interaction, PieceSetButton(name)) in button_query.iter() {
+        if *interaction == Interaction::Pressed && settings.piece_set != *name {
+            settings.piece_set = name.clone();
+        }
+    }
+}
+
+pub fn update_piece_set_buttons(piece_assets: Res<PieceAssets>, mut button_query: Query<(&PieceSetButton, &mut BorderColor)>) {
+    for (PieceSetButton(name), mut border) in button_query.iter_mut() {
+        border.0 = if *name == piece_assets.set { Color::WHITE } else { Color::NONE };
+    }
+}
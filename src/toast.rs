@@ -0,0 +1,99 @@
+use bevy::prelude::*;
+
+const TOAST_SECONDS: f32 = 4.0;
+const ALERT_TOAST_SECONDS: f32 = 6.0;
+const MAX_TOASTS: usize = 5;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ToastLevel {
+    Info,
+    Success,
+    Warning,
+    Error
+}
+
+impl ToastLevel {
+    fn color(&self) -> Color {
+        match self {
+            ToastLevel::Info => Color::rgb(0.45, 0.65, 0.95),
+            ToastLevel::Success => Color::rgb(0.4, 0.8, 0.45),
+            ToastLevel::Warning => Color::rgb(0.95, 0.75, 0.3),
+            ToastLevel::Error => Color::rgb(0.95, 0.4, 0.35)
+        }
+    }
+
+    fn seconds(&self) -> f32 {
+        match self {
+            ToastLevel::Info | ToastLevel::Success => TOAST_SECONDS,
+            ToastLevel::Warning | ToastLevel::Error => ALERT_TOAST_SECONDS
+        }
+    }
+}
+
+struct Toast {
+    level: ToastLevel,
+    text: String,
+    timer: Timer
+}
+
+#[derive(Resource, Default)]
+pub struct Toasts {
+    queue: Vec<Toast>
+}
+
+impl Toasts {
+    pub fn push(&mut self, level: ToastLevel, text: impl Into<String>) {
+        let text = text.into();
+        self.queue.retain(|toast| toast.text != text);
+        self.queue.push(Toast {level, text, timer: Timer::from_seconds(level.seconds(), TimerMode::Once)});
+        if self.queue.len() > MAX_TOASTS {
+            self.queue.remove(0);
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct ToastArea;
+
+pub fn spawn_toast_area(mut commands: Commands) {
+    commands.spawn((NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(72.0),
+            left: Val::Px(16.0),
+            max_width: Val::Percent(45.0),
+            flex_direction: FlexDirection::Column,
+            align_items: AlignItems::FlexStart,
+            row_gap: Val::Px(4.0),
+            ..default()
+        },
+        z_index: ZIndex::Global(20),
+        ..default()
+    }, ToastArea));
+}
+
+pub fn update_toasts(time: Res<Time>, mut commands: Commands, mut toasts: ResMut<Toasts>, area_query: Query<Entity, With<ToastArea>>) {
+    if toasts.queue.is_empty() { return };
+    for toast in toasts.queue.iter_mut() {
+        toast.timer.tick(time.delta());
+    }
+    toasts.queue.retain(|toast| !toast.timer.finished());
+    let Ok(area) = area_query.get_single() else { return };
+    commands.entity(area).despawn_descendants().with_children(|area| {
+        for toast in toasts.queue.iter() {
+            let alpha = (toast.timer.remaining_secs() / 0.5).min(1.0);
+            area.spawn(NodeBundle {
+                style: Style {
+                    padding: UiRect::axes(Val::Px(10.0), Val::Px(4.0)),
+                    border: UiRect::left(Val::Px(4.0)),
+                    ..default()
+                },
+                background_color: Color::rgba(0.0, 0.0, 0.0, 0.7 * alpha).into(),
+                border_color: toast.level.color().with_a(alpha).into(),
+                ..default()
+            }).with_children(|row| {
+                row.spawn(TextBundle::from_section(toast.text.clone(), TextStyle { font_size: 20.0, color: Color::WHITE.with_a(alpha), ..default() }));
+            });
+        }
+    });
+}
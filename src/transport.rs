@@ -0,0 +1,310 @@
+use std::io;
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::{Read, Write};
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::TcpStream;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+#[cfg(target_arch = "wasm32")]
+use std::collections::VecDeque;
+#[cfg(target_arch = "wasm32")]
+use std::sync::{Arc, Mutex};
+use serde::{Deserialize, Serialize};
+use crate::network::NetMessage;
+
+#[cfg(not(target_arch = "wasm32"))]
+const READ_TIMEOUT: Duration = Duration::from_millis(1);
+// Far more than a resync of a long game needs; a peer that sends more without a newline is dropped
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_LINE_BYTES: usize = 64 * 1024;
+const RELAY_PEER_NAME: &str = "Remote player";
+
+#[derive(Debug)]
+pub enum TransportEvent {
+    Message(NetMessage),
+    RoomJoined {first: bool},
+    PeerJoined,
+    PeerLeft
+}
+
+pub trait Transport {
+    fn send(&mut self, message: &NetMessage) -> io::Result<()>;
+    fn poll(&mut self) -> io::Result<Option<TransportEvent>>;
+    fn peer(&self) -> String;
+}
+
+pub fn invalid(reason: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason.into())
+}
+
+fn encode<T: Serialize>(message: &T) -> io::Result<String> {
+    ron::to_string(message).map_err(|error| invalid(error.to_string()))
+}
+
+fn decode<T: for<'de> Deserialize<'de>>(text: &str) -> io::Result<T> {
+    ron::from_str(text).map_err(|error| invalid(format!("unreadable message: {}", error)))
+}
+
+#[derive(Serialize, Deserialize)]
+enum RelayMessage {
+    Join {room: String},
+    Joined {first: bool},
+    PeerJoined,
+    PeerLeft,
+    Relay(String),
+    Refused(String)
+}
+
+fn relay_event(text: &str) -> io::Result<Option<TransportEvent>> {
+    match decode(text)? {
+        RelayMessage::Joined {first} => Ok(Some(TransportEvent::RoomJoined {first})),
+        RelayMessage::PeerJoined => Ok(Some(TransportEvent::PeerJoined)),
+        RelayMessage::PeerLeft => Ok(Some(TransportEvent::PeerLeft)),
+        RelayMessage::Relay(payload) => decode(&payload).map(|message| Some(TransportEvent::Message(message))),
+        RelayMessage::Refused(reason) => Err(io::Error::new(io::ErrorKind::ConnectionRefused, format!("relay refused: {}", reason))),
+        RelayMessage::Join {..} => Err(invalid("relay sent a join request"))
+    }
+}
+
+fn relayed(message: &NetMessage) -> io::Result<String> {
+    encode(&RelayMessage::Relay(encode(message)?))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn would_block(error: &io::Error) -> bool {
+    matches!(error.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub struct TcpTransport {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    peer: String
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl TcpTransport {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let peer = stream.peer_addr()?.to_string();
+        Ok(TcpTransport {stream, buffer: Vec::new(), peer})
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Transport for TcpTransport {
+    fn send(&mut self, message: &NetMessage) -> io::Result<()> {
+        writeln!(self.stream, "{}", encode(message)?)
+    }
+
+    fn poll(&mut self) -> io::Result<Option<TransportEvent>> {
+        loop {
+            if let Some(end) = self.buffer.iter().position(|byte| *byte == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=end).collect();
+                let text = String::from_utf8(line).map_err(|_| invalid("message is not UTF-8"))?;
+                return decode(text.trim_end()).map(|message| Some(TransportEvent::Message(message)));
+            }
+            if self.buffer.len() > MAX_LINE_BYTES {
+                return Err(invalid(format!("message longer than {} bytes", MAX_LINE_BYTES)));
+            }
+            let mut chunk = [0; 1024];
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed")),
+                Ok(read) => self.buffer.extend_from_slice(&chunk[..read]),
+                Err(error) if would_block(&error) => return Ok(None),
+                Err(error) => return Err(error)
+            }
+        }
+    }
+
+    fn peer(&self) -> String {
+        self.peer.clone()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub struct WebSocketTransport {
+    socket: tungstenite::WebSocket<tungstenite::stream::MaybeTlsStream<TcpStream>>
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn socket_error(error: tungstenite::Error) -> io::Error {
+    match error {
+        tungstenite::Error::Io(error) => error,
+        error => io::Error::new(io::ErrorKind::ConnectionAborted, error.to_string())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl WebSocketTransport {
+    pub fn connect(url: &str, room: &str) -> io::Result<Self> {
+        // tungstenite is built without a TLS backend, so say so rather than fail somewhere in the handshake
+        if url.get(..6).is_some_and(|scheme| scheme.eq_ignore_ascii_case("wss://")) {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "secure relays (wss://) are only supported in the web build; use a ws:// relay"));
+        }
+        let (mut socket, _) = tungstenite::connect(url).map_err(socket_error)?;
+        let tungstenite::stream::MaybeTlsStream::Plain(stream) = socket.get_mut() else {
+            return Err(io::Error::new(io::ErrorKind::Unsupported, "the relay connection is not a plain TCP stream"));
+        };
+        stream.set_read_timeout(Some(READ_TIMEOUT))?;
+        let mut transport = WebSocketTransport {socket};
+        transport.write(encode(&RelayMessage::Join {room: room.to_string()})?)?;
+        Ok(transport)
+    }
+
+    fn write(&mut self, frame: String) -> io::Result<()> {
+        self.socket.send(tungstenite::Message::Text(frame)).map_err(socket_error)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Transport for WebSocketTransport {
+    fn send(&mut self, message: &NetMessage) -> io::Result<()> {
+        let frame = relayed(message)?;
+        self.write(frame)
+    }
+
+    fn poll(&mut self) -> io::Result<Option<TransportEvent>> {
+        match self.socket.read() {
+            Ok(tungstenite::Message::Text(text)) => relay_event(&text),
+            Ok(tungstenite::Message::Close(_)) => Err(io::Error::new(io::ErrorKind::UnexpectedEof, "relay closed the connection")),
+            Ok(_) => Ok(None),
+            Err(tungstenite::Error::Io(error)) if would_block(&error) => Ok(None),
+            Err(error) => Err(socket_error(error))
+        }
+    }
+
+    fn peer(&self) -> String {
+        String::from(RELAY_PEER_NAME)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+thread_local! {
+    static SOCKET: std::cell::RefCell<Option<web_sys::WebSocket>> = const { std::cell::RefCell::new(None) };
+}
+
+#[cfg(target_arch = "wasm32")]
+pub struct WebSocketTransport {
+    inbox: Arc<Mutex<VecDeque<io::Result<String>>>>,
+    pending: Vec<String>
+}
+
+#[cfg(target_arch = "wasm32")]
+impl WebSocketTransport {
+    pub fn connect(url: &str, room: &str) -> io::Result<Self> {
+        use wasm_bindgen::closure::Closure;
+        use wasm_bindgen::JsCast;
+        let socket = web_sys::WebSocket::new(url)
+            .map_err(|error| io::Error::new(io::ErrorKind::ConnectionRefused, format!("{:?}", error)))?;
+        let inbox = Arc::new(Mutex::new(VecDeque::new()));
+        let message_inbox = inbox.clone();
+        let on_message = Closure::<dyn FnMut(web_sys::MessageEvent)>::new(move |event: web_sys::MessageEvent| {
+            if let Some(text) = event.data().as_string() {
+                message_inbox.lock().unwrap().push_back(Ok(text));
+            }
+        });
+        let close_inbox = inbox.clone();
+        let on_close = Closure::<dyn FnMut(web_sys::CloseEvent)>::new(move |event: web_sys::CloseEvent| {
+            let reason = format!("relay closed the connection ({})", event.code());
+            close_inbox.lock().unwrap().push_back(Err(io::Error::new(io::ErrorKind::UnexpectedEof, reason)));
+        });
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+        on_message.forget();
+        on_close.forget();
+        SOCKET.with(|slot| *slot.borrow_mut() = Some(socket));
+        Ok(WebSocketTransport {inbox, pending: vec![encode(&RelayMessage::Join {room: room.to_string()})?]})
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        SOCKET.with(|slot| {
+            let slot = slot.borrow();
+            let Some(socket) = slot.as_ref() else { return Err(io::Error::new(io::ErrorKind::NotConnected, "no relay connection")) };
+            if socket.ready_state() != web_sys::WebSocket::OPEN { return Ok(()) };
+            for frame in self.pending.drain(..) {
+                socket.send_with_str(&frame).map_err(|error| io::Error::new(io::ErrorKind::BrokenPipe, format!("{:?}", error)))?;
+            }
+            Ok(())
+        })
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl Transport for WebSocketTransport {
+    fn send(&mut self, message: &NetMessage) -> io::Result<()> {
+        self.pending.push(relayed(message)?);
+        self.flush()
+    }
+
+    fn poll(&mut self) -> io::Result<Option<TransportEvent>> {
+        self.flush()?;
+        let next = self.inbox.lock().unwrap().pop_front();
+        match next {
+            Some(Ok(text)) => relay_event(&text),
+            Some(Err(error)) => Err(error),
+            None => Ok(None)
+        }
+    }
+
+    fn peer(&self) -> String {
+        String::from(RELAY_PEER_NAME)
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod tests {
+    use super::*;
+
+    use std::net::TcpListener;
+    use std::time::Instant;
+
+    fn connected_pair() -> (TcpStream, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        (client, server)
+    }
+
+    fn next_event(transport: &mut TcpTransport) -> io::Result<TransportEvent> {
+        let deadline = Instant::now() + Duration::from_secs(2);
+        loop {
+            if let Some(event) = transport.poll()? { return Ok(event) };
+            assert!(Instant::now() < deadline, "nothing arrived");
+        }
+    }
+
+    #[test]
+    fn messages_arrive_one_line_at_a_time() {
+        let (client, server) = connected_pair();
+        let (mut sender, mut receiver) = (TcpTransport::new(client).unwrap(), TcpTransport::new(server).unwrap());
+        sender.send(&NetMessage::Hello {version: 3, white: Some(true)}).unwrap();
+        sender.send(&NetMessage::Resign).unwrap();
+        assert!(matches!(next_event(&mut receiver).unwrap(), TransportEvent::Message(NetMessage::Hello {version: 3, white: Some(true)})));
+        assert!(matches!(next_event(&mut receiver).unwrap(), TransportEvent::Message(NetMessage::Resign)));
+        assert!(receiver.poll().unwrap().is_none());
+    }
+
+    #[test]
+    fn an_endless_line_drops_the_connection() {
+        let (mut client, server) = connected_pair();
+        let mut receiver = TcpTransport::new(server).unwrap();
+        client.write_all(&vec![b'a'; MAX_LINE_BYTES + 4096]).unwrap();
+        let error = next_event(&mut receiver).err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn unreadable_lines_are_errors() {
+        let (mut client, server) = connected_pair();
+        let mut receiver = TcpTransport::new(server).unwrap();
+        client.write_all(b"not a message\n").unwrap();
+        assert_eq!(next_event(&mut receiver).err().unwrap().kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn secure_relays_are_refused_up_front() {
+        let error = WebSocketTransport::connect("WSS://relay.example.org/chess", "room").err().unwrap();
+        assert_eq!(error.kind(), io::ErrorKind::Unsupported);
+    }
+}